@@ -0,0 +1,211 @@
+//! Vertical alignment of consecutive items on a token, such as `=` or `:`.
+//!
+//! Alignment pads the part of each item before the token so the tokens
+//! line up in one column. A maximum distance keeps one long item from
+//! pushing the others far to the right: items are then aligned in separate
+//! runs instead.
+
+/// Aligns the parts of consecutive items around a token.
+///
+/// Widths are counted in characters. Runs of items are aligned together as
+/// long as the longest and shortest part before the token differ by at
+/// most the maximum distance, if one is set.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::align::Alignment;
+///
+/// let lines = ["a = 1", "long_name = 2", "// no token", "bc=3"];
+/// assert_eq!(
+///     Alignment::new("=").align_lines(&lines),
+///     ["a         = 1", "long_name = 2", "// no token", "bc = 3"]
+/// );
+///
+/// let aligned = Alignment::new(":")
+///     .with_spacing("", " ")
+///     .with_padding_after_token(true)
+///     .with_max_distance(4)
+///     .align_pairs(&[("x", "1"), ("yy", "2"), ("much_longer", "3")]);
+/// assert_eq!(aligned, ["x:  1", "yy: 2", "much_longer: 3"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alignment {
+    token: String,
+    before: String,
+    after: String,
+    pad_after_token: bool,
+    max_distance: Option<usize>,
+}
+
+impl Alignment {
+    /// Create an alignment on `token`, with a space on each side of it.
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            before: " ".to_string(),
+            after: " ".to_string(),
+            pad_after_token: false,
+            max_distance: None,
+        }
+    }
+
+    /// Set the text put before and after the token.
+    #[must_use]
+    pub fn with_spacing(mut self, before: &str, after: &str) -> Self {
+        self.before = before.to_string();
+        self.after = after.to_string();
+        self
+    }
+
+    /// Pad after the token instead of before it, as in `key:   value`.
+    #[must_use]
+    pub fn with_padding_after_token(mut self, after: bool) -> Self {
+        self.pad_after_token = after;
+        self
+    }
+
+    /// Limit how much padding alignment may add to an item.
+    ///
+    /// Items whose parts before the token differ in width by more than
+    /// `distance` are aligned in separate runs.
+    #[must_use]
+    pub fn with_max_distance(mut self, distance: usize) -> Self {
+        self.max_distance = Some(distance);
+        self
+    }
+
+    /// Compute the width each part before the token is padded to.
+    ///
+    /// # Arguments
+    /// * `lefts` - Text of consecutive items before the token
+    ///
+    /// # Returns
+    /// One width per item, shared by the items of a run
+    pub fn widths<S: AsRef<str>>(&self, lefts: &[S]) -> Vec<usize> {
+        let lengths: Vec<usize> = lefts
+            .iter()
+            .map(|left| left.as_ref().trim_end().chars().count())
+            .collect();
+        let mut widths = Vec::with_capacity(lengths.len());
+        let mut start = 0;
+        while start < lengths.len() {
+            let (mut min, mut max) = (lengths[start], lengths[start]);
+            let mut end = start + 1;
+            while end < lengths.len() {
+                let (next_min, next_max) = (min.min(lengths[end]), max.max(lengths[end]));
+                if self
+                    .max_distance
+                    .is_some_and(|distance| next_max - next_min > distance)
+                {
+                    break;
+                }
+                (min, max) = (next_min, next_max);
+                end += 1;
+            }
+            widths.extend(std::iter::repeat_n(max, end - start));
+            start = end;
+        }
+        widths
+    }
+
+    /// Align consecutive items given as the text before and after the token.
+    ///
+    /// # Returns
+    /// The aligned items, without line endings
+    pub fn align_pairs<L: AsRef<str>, R: AsRef<str>>(&self, pairs: &[(L, R)]) -> Vec<String> {
+        let lefts: Vec<&str> = pairs.iter().map(|(left, _)| left.as_ref()).collect();
+        pairs
+            .iter()
+            .zip(self.widths(&lefts))
+            .map(|((left, right), width)| self.join(left.as_ref(), right.as_ref(), width))
+            .collect()
+    }
+
+    /// Align consecutive lines on the first occurrence of the token.
+    ///
+    /// Lines without the token are kept as they are and end the run of
+    /// lines aligned together. Indentation is kept and counts towards the
+    /// width, so lines should share it.
+    pub fn align_lines<S: AsRef<str>>(&self, lines: &[S]) -> Vec<String> {
+        let mut aligned = Vec::with_capacity(lines.len());
+        let mut run: Vec<(&str, &str)> = Vec::new();
+        for line in lines {
+            let line = line.as_ref();
+            match line.split_once(self.token.as_str()) {
+                Some(pair) => run.push(pair),
+                None => {
+                    aligned.extend(self.align_pairs(&run));
+                    run.clear();
+                    aligned.push(line.to_string());
+                }
+            }
+        }
+        aligned.extend(self.align_pairs(&run));
+        aligned
+    }
+
+    /// Join the parts of one item, padding the left one to `width` characters.
+    fn join(&self, left: &str, right: &str, width: usize) -> String {
+        let left = left.trim_end();
+        let padding = " ".repeat(width.saturating_sub(left.chars().count()));
+        let (before, after) = if self.pad_after_token {
+            ("", padding.as_str())
+        } else {
+            (padding.as_str(), "")
+        };
+        format!(
+            "{left}{before}{}{}{}{after}{}",
+            self.before,
+            self.token,
+            self.after,
+            right.trim_start()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(None, &[6, 6, 6, 6, 6])]
+    #[case(Some(5), &[6, 6, 6, 6, 6])]
+    #[case(Some(3), &[3, 3, 3, 3, 6])]
+    #[case(Some(1), &[3, 3, 1, 1, 6])]
+    fn test_widths(#[case] max_distance: Option<usize>, #[case] expected: &[usize]) {
+        let mut alignment = Alignment::new("=");
+        if let Some(distance) = max_distance {
+            alignment = alignment.with_max_distance(distance);
+        }
+        let lefts = ["abc", "def ", "g", "h", "ijklmn"];
+        assert_eq!(alignment.widths(&lefts), expected);
+    }
+
+    #[test]
+    fn test_align_lines_keeps_indentation() {
+        let lines = ["    a: 1", "    bbb :2"];
+        let alignment = Alignment::new(":").with_spacing("", " ");
+        assert_eq!(alignment.align_lines(&lines), ["    a  : 1", "    bbb: 2"]);
+        assert_eq!(
+            alignment.with_padding_after_token(true).align_lines(&lines),
+            ["    a:   1", "    bbb: 2"]
+        );
+    }
+
+    #[test]
+    fn test_alignment_counts_characters() {
+        let pairs = [("é", "1"), ("ab", "2")];
+        assert_eq!(
+            Alignment::new("=").align_pairs(&pairs),
+            ["é  = 1", "ab = 2"]
+        );
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let alignment = Alignment::new("=");
+        assert!(alignment.align_lines::<&str>(&[]).is_empty());
+        assert!(alignment.widths::<&str>(&[]).is_empty());
+    }
+}
@@ -1,24 +1,41 @@
-use crate::cli::handler::handle_cli;
+use crate::cli::cli_entry::{build_embedded_commands, CliCommand};
+use crate::cli::commands::{
+    ConfigError, ConfigFlag, ConfigMigrator, ConfigPrompt, Preset, Reporter,
+};
+use crate::cli::custom_command::find_command;
+use crate::cli::custom_command::CustomCommand;
+use crate::cli::error::{CliResult, ExitStatus};
+use crate::cli::handler::{dispatch, handle_cli, try_handle_cli};
+use crate::cli::settings::CliSettings;
 use crate::parser::LanguageProvider;
-use crate::pipeline::{Pass, Pipeline};
+use crate::pipeline::{ConflictPolicy, ConsistencyPass, LinePass, Pass, Pipeline, ProjectPass};
+use clap::{Arg, ArgMatches, Command};
 use serde::{de::DeserializeOwned, Serialize};
+use std::env;
+use std::ffi::OsString;
 use std::marker::PhantomData;
 
 /// Builder for CLI runner with fluent interface
 ///
 /// Add passes one by one using `add_pass` method
+///
+/// The CLI formats the files of a single language. It does not take a
+/// `LanguageRegistry`: binaries formatting several languages collect
+/// files with `FileCollector::collect_registered` and format them with
+/// `LanguageRegistry::format_files` themselves.
 pub struct CliBuilder<Language, Config>
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
     pipeline: Pipeline<Config>,
+    settings: CliSettings,
     _language_marker: PhantomData<Language>,
 }
 
 impl<Language, Config> CliBuilder<Language, Config>
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
     /// Create new CLI builder
@@ -26,6 +43,7 @@ where
     pub fn new() -> Self {
         Self {
             pipeline: Pipeline::new(),
+            settings: CliSettings::default(),
             _language_marker: PhantomData,
         }
     }
@@ -34,12 +52,66 @@ where
     #[must_use]
     pub fn add_pass<P>(mut self, pass: P) -> Self
     where
-        P: Pass<Config = Config> + 'static,
+        P: Pass<Config = Config> + Send + Sync + 'static,
     {
         self.pipeline.add_pass(pass);
         self
     }
 
+    /// Add line-based pass to the pipeline
+    #[must_use]
+    pub fn add_line_pass<P>(mut self, pass: P) -> Self
+    where
+        P: LinePass<Config = Config> + Send + Sync + 'static,
+    {
+        self.pipeline.add_line_pass(pass);
+        self
+    }
+
+    /// Add pass to the pipeline with a priority; lower priorities run first
+    #[must_use]
+    pub fn add_pass_with_priority<P>(mut self, pass: P, priority: i32) -> Self
+    where
+        P: Pass<Config = Config> + Send + Sync + 'static,
+    {
+        self.pipeline.add_pass_with_priority(pass, priority);
+        self
+    }
+
+    /// Add consistency pass to the run-level stage of the pipeline
+    #[must_use]
+    pub fn add_consistency_pass<P>(mut self, pass: P) -> Self
+    where
+        P: ConsistencyPass<Config = Config> + Send + Sync + 'static,
+    {
+        self.pipeline.add_consistency_pass(pass);
+        self
+    }
+
+    /// Add project pass to the run-level stage of the pipeline
+    #[must_use]
+    pub fn add_project_pass<P>(mut self, pass: P) -> Self
+    where
+        P: ProjectPass<Config = Config> + Send + Sync + 'static,
+    {
+        self.pipeline.add_project_pass(pass);
+        self
+    }
+
+    /// Add the passes of a pipeline as a named phase
+    #[must_use]
+    pub fn add_phase(mut self, name: &'static str, phase: Pipeline<Config>) -> Self {
+        self.pipeline.add_phase(name, phase);
+        self
+    }
+
+    /// Set how the edits of different passes are combined
+    #[must_use]
+    pub fn conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.pipeline.set_conflict_policy(policy);
+        self
+    }
+
     /// Set the pipeline to use
     #[must_use]
     pub fn with_pipeline(mut self, pipeline: Pipeline<Config>) -> Self {
@@ -47,15 +119,313 @@ where
         self
     }
 
+    /// Add a custom subcommand next to the built-in `init` and `format`
+    ///
+    /// The name must not clash with a built-in subcommand.
+    ///
+    /// # Arguments
+    /// * `name` - Subcommand name
+    /// * `command` - Clap definition of the subcommand's arguments and help
+    /// * `handler` - Function run with the subcommand's matches
+    #[must_use]
+    pub fn add_command<F>(mut self, name: &'static str, command: Command, handler: F) -> Self
+    where
+        F: Fn(&ArgMatches) -> CliResult<()> + 'static,
+    {
+        self.settings
+            .custom_commands
+            .push(CustomCommand::new(name, command, handler));
+        self
+    }
+
+    /// Add a global command line argument for experiment or feature flags
+    ///
+    /// Values passed on the command line are handed to every pass through
+    /// `Pass::configure` under the argument id before formatting starts.
+    ///
+    /// # Arguments
+    /// * `arg` - Clap definition of the argument
+    #[must_use]
+    pub fn add_arg(mut self, arg: Arg) -> Self {
+        self.settings.extra_args.push(arg);
+        self
+    }
+
+    /// Set the binary name shown in help output
+    ///
+    /// Defaults to the file name of the running executable.
+    #[must_use]
+    pub fn name(mut self, name: &'static str) -> Self {
+        self.settings.name = Some(name);
+        self
+    }
+
+    /// Set the version shown by `--version`
+    ///
+    /// Typically `env!("CARGO_PKG_VERSION")` of the formatter crate.
+    #[must_use]
+    pub fn version(mut self, version: &'static str) -> Self {
+        self.settings.version = Some(version);
+        self
+    }
+
+    /// Set the description shown in help output
+    #[must_use]
+    pub fn about(mut self, about: &'static str) -> Self {
+        self.settings.about = Some(about);
+        self
+    }
+
+    /// Set the default configuration file path
+    ///
+    /// Defaults to `<name>.yml`.
+    #[must_use]
+    pub fn default_config_path(mut self, path: &'static str) -> Self {
+        self.settings.default_config_path = Some(path);
+        self
+    }
+
+    /// Fail when the config file does not exist instead of using the defaults
+    ///
+    /// Makes `--require-config` the behavior of every run, so a mistyped
+    /// `--config` path cannot go unnoticed.
+    #[must_use]
+    pub fn require_config(mut self) -> Self {
+        self.settings.require_config = true;
+        self
+    }
+
+    /// Expose the top-level config fields as flags of `format`
+    ///
+    /// Every field gets a flag named after it in kebab case, so
+    /// `indent_size` can be tried out with `--indent-size 2` instead of
+    /// editing the config. Values are parsed as YAML and applied over the
+    /// loaded config like `--set`, which takes precedence over them. Fields
+    /// holding mappings, and fields named like a built-in flag, get no flag.
+    ///
+    /// # Panics
+    /// Panics if the default config cannot be serialized to YAML
+    #[must_use]
+    pub fn config_flags(mut self) -> Self {
+        self.settings.config_flags = ConfigFlag::from_config::<Config>();
+        self
+    }
+
+    /// Only load the project config, ignoring the system and user configs
+    ///
+    /// By default, `/etc/<name>/config.yml` and
+    /// `$XDG_CONFIG_HOME/<name>/config.yml` are merged under the project
+    /// config, lowest priority first.
+    #[must_use]
+    pub fn ignore_config_layers(mut self) -> Self {
+        self.settings.ignore_config_layers = true;
+        self
+    }
+
+    /// Add a file name marking the project root, on top of `.git`
+    ///
+    /// The default config file and relative cache directories are resolved
+    /// against the nearest directory containing a marker, so running from a
+    /// subdirectory behaves like running from the root.
+    ///
+    /// # Arguments
+    /// * `marker` - File or directory name, e.g. "Cargo.toml"
+    #[must_use]
+    pub fn root_marker(mut self, marker: &'static str) -> Self {
+        self.settings.root_markers.push(marker);
+        self
+    }
+
+    /// Set the field descriptions used by `init --interactive`
+    ///
+    /// Descriptions are shown while prompting and written as comments
+    /// into the generated config file.
+    #[must_use]
+    pub fn config_prompt<P>(mut self, prompt: P) -> Self
+    where
+        P: ConfigPrompt + 'static,
+    {
+        self.settings.config_prompt = Some(Box::new(prompt));
+        self
+    }
+
+    /// Register a named config that `init --preset <name>` writes instead of the defaults
+    ///
+    /// # Arguments
+    /// * `name` - Preset name used on the command line (e.g. "compact")
+    /// * `config` - The preset config
+    ///
+    /// # Panics
+    /// Panics if the config cannot be serialized to YAML
+    #[must_use]
+    pub fn add_preset(mut self, name: &'static str, config: Config) -> Self {
+        let config = serde_yaml::to_value(&config).expect("Preset config should serialize to YAML");
+        self.settings.presets.push(Preset { name, config });
+        self
+    }
+
+    /// Register a migration of config files written for an older schema
+    ///
+    /// Config files record their schema in a top-level `version` key. Files
+    /// older than a migration's version are migrated when loaded, and
+    /// `init --update` rewrites them to the latest version.
+    ///
+    /// # Arguments
+    /// * `migrator` - Upgrade of configs to the migrator's version
+    #[must_use]
+    pub fn add_migration<M>(mut self, migrator: M) -> Self
+    where
+        M: ConfigMigrator + 'static,
+    {
+        self.settings.migrations.add(migrator);
+        self
+    }
+
+    /// Register a check of invariants serde cannot express, e.g. `indent_size > 0`
+    ///
+    /// The validator runs on the final config of every command, after
+    /// migrations, nested configs and `--set` overrides. Its errors fail the
+    /// command like other config errors, and `validate` lists them.
+    ///
+    /// # Arguments
+    /// * `validator` - Returns every problem found in the config
+    #[must_use]
+    pub fn validate_config<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&Config) -> Result<(), Vec<ConfigError>> + Send + Sync + 'static,
+    {
+        self.settings.validators.add(validator);
+        self
+    }
+
+    /// Set the reporter presenting the results of `format`
+    ///
+    /// `factory` is called once per run and its reporter replaces the one
+    /// selected by `--output`, e.g. to send results to an editor or a
+    /// review bot.
+    ///
+    /// # Arguments
+    /// * `factory` - Creates the reporter of a run
+    #[must_use]
+    pub fn reporter<F, R>(mut self, factory: F) -> Self
+    where
+        F: Fn() -> R + 'static,
+        R: Reporter + 'static,
+    {
+        self.settings.reporter = Some(Box::new(move || Box::new(factory())));
+        self
+    }
+
     /// Run the CLI
+    ///
+    /// Initializes a logger (unless one is already installed) and exits the
+    /// process with the run's exit code when it is not successful.
     pub fn run(self) {
-        handle_cli::<Language, Config>(self.pipeline);
+        handle_cli::<Language, Config>(self.pipeline, self.settings);
+    }
+
+    /// Run the CLI on the process arguments and return its outcome.
+    ///
+    /// Never exits the process or initializes a logger, so the CLI can be
+    /// embedded in other programs.
+    ///
+    /// # Returns
+    /// The exit status of the command, or the error that stopped it
+    ///
+    /// # Errors
+    /// Returns the error of a failed command; `CliError::exit_code` gives
+    /// the matching process exit code
+    pub fn try_run(self) -> CliResult<ExitStatus> {
+        self.try_run_from(env::args_os())
+    }
+
+    /// Run the CLI on the given arguments and return its outcome.
+    ///
+    /// # Arguments
+    /// * `args` - Command line arguments, starting with the binary name
+    ///
+    /// # Returns
+    /// The exit status of the command, or the error that stopped it
+    ///
+    /// # Errors
+    /// Returns the error of a failed command
+    pub fn try_run_from<I, T>(self, args: I) -> CliResult<ExitStatus>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        try_handle_cli::<Language, Config, _, _>(self.pipeline, &self.settings, args)
+    }
+
+    /// Split the CLI into its subcommands and a dispatcher running them.
+    ///
+    /// The commands can be mounted under a host application's own command;
+    /// rename them with `Command::visible_alias` (e.g. `fmt`) so the
+    /// dispatcher still recognizes them. The name and default config path
+    /// from `name` and `default_config_path` still apply; without `name`,
+    /// the host's binary name selects the default config and the system
+    /// and user config directories.
+    ///
+    /// # Returns
+    /// The `init`, `validate`, `format`, `explain`, `list-passes` and
+    /// `config` subcommands followed by the custom ones, and the dispatcher
+    /// to hand the host's matches to
+    #[must_use]
+    pub fn into_commands(self) -> (Vec<Command>, CliDispatcher<Language, Config>) {
+        let commands = build_embedded_commands(&self.settings);
+        let dispatcher = CliDispatcher {
+            pipeline: self.pipeline,
+            settings: self.settings,
+            _language_marker: PhantomData,
+        };
+        (commands, dispatcher)
+    }
+}
+
+/// Runs the subcommands returned by `CliBuilder::into_commands`.
+pub struct CliDispatcher<Language, Config>
+where
+    Config: Serialize + DeserializeOwned + Default + Sync,
+    Language: LanguageProvider,
+{
+    pipeline: Pipeline<Config>,
+    settings: CliSettings,
+    _language_marker: PhantomData<Language>,
+}
+
+impl<Language, Config> CliDispatcher<Language, Config>
+where
+    Config: Serialize + DeserializeOwned + Default + Sync,
+    Language: LanguageProvider,
+{
+    /// Check whether `name` is one of the dispatched subcommands.
+    #[must_use]
+    pub fn handles(&self, name: &str) -> bool {
+        CliCommand::ALL
+            .iter()
+            .any(|command| command.as_str() == name)
+            || find_command(&self.settings.custom_commands, name).is_some()
+    }
+
+    /// Run the subcommand selected in the host's matches.
+    ///
+    /// # Arguments
+    /// * `matches` - Matches of the host command the subcommands are mounted on
+    ///
+    /// # Returns
+    /// The exit status of the subcommand, or the error that stopped it
+    ///
+    /// # Errors
+    /// Returns the error of a failed command, or `CliError::UnknownCommand`
+    /// if the selected subcommand is not one of ours
+    pub fn dispatch(self, matches: &ArgMatches) -> CliResult<ExitStatus> {
+        dispatch::<Language, Config>(self.pipeline, &self.settings, matches)
     }
 }
 
 impl<Language, Config> Default for CliBuilder<Language, Config>
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
     fn default() -> Self {
@@ -67,8 +437,526 @@ where
 #[must_use]
 pub fn cli_builder<Language, Config>() -> CliBuilder<Language, Config>
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
     CliBuilder::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::commands::{FileRecord, RunStatus};
+    use crate::cli::error::CliError;
+    use crate::pipeline::{Edit, ProjectEdit, ProjectFile};
+    use crate::supported_extension::SupportedExtension;
+    use serde::Deserialize;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+    use tempfile::TempDir;
+    use tree_sitter::Node;
+
+    struct JsonLanguage;
+
+    impl LanguageProvider for JsonLanguage {
+        fn language() -> tree_sitter::Language {
+            tree_sitter_json::LANGUAGE.into()
+        }
+
+        fn supported_extension() -> &'static SupportedExtension {
+            static JSON_EXTENSIONS: SupportedExtension = SupportedExtension::new(&["json"]);
+            &JSON_EXTENSIONS
+        }
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct TestConfig {
+        width: usize,
+    }
+
+    /// Appends a newline to sources that do not end with one.
+    struct FinalNewlinePass;
+
+    impl Pass for FinalNewlinePass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &TestConfig, _root: &Node, source: &str) -> Vec<Edit> {
+            if source.ends_with('\n') {
+                return Vec::new();
+            }
+            vec![Edit {
+                range: (source.len(), source.len()),
+                content: "\n".to_string(),
+            }]
+        }
+    }
+
+    /// Panics on sources containing `boom`.
+    struct BoomPass;
+
+    impl Pass for BoomPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &TestConfig, _root: &Node, source: &str) -> Vec<Edit> {
+            assert!(!source.contains("boom"), "boom in source");
+            Vec::new()
+        }
+    }
+
+    /// Runs after a pass that is never added.
+    struct OrphanPass;
+
+    impl Pass for OrphanPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &TestConfig, _root: &Node, _source: &str) -> Vec<Edit> {
+            Vec::new()
+        }
+
+        fn depends_on(&self) -> &'static [&'static str] {
+            &["MissingPass"]
+        }
+    }
+
+    /// Rewrites every source to the number of files the pass saw, e.g. `[3]`.
+    struct FileCountPass;
+
+    impl ConsistencyPass for FileCountPass {
+        type Config = TestConfig;
+        type Summary = ();
+        type Project = usize;
+
+        fn summarize(&self, _config: &TestConfig, _root: &Node, _source: &str) {}
+
+        fn combine(&self, _config: &TestConfig, summaries: Vec<()>) -> usize {
+            summaries.len()
+        }
+
+        fn run(
+            &self,
+            _config: &TestConfig,
+            count: &usize,
+            _root: &Node,
+            source: &str,
+        ) -> Vec<Edit> {
+            vec![Edit {
+                range: (0, source.len()),
+                content: format!("[{count}]\n"),
+            }]
+        }
+    }
+
+    /// Rewrites every file to the number of files the pass received, e.g. `[3]`.
+    struct FileListPass;
+
+    impl ProjectPass for FileListPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &TestConfig, files: &[ProjectFile<TestConfig>]) -> Vec<ProjectEdit> {
+            files
+                .iter()
+                .enumerate()
+                .map(|(file, project_file)| ProjectEdit {
+                    file,
+                    edit: Edit {
+                        range: (0, project_file.source.len()),
+                        content: format!("[{}]\n", files.len()),
+                    },
+                })
+                .collect()
+        }
+    }
+
+    /// Rewrites every file to the number of files and the width of its config, e.g. `[3, 1]`.
+    struct FileWidthPass;
+
+    impl ProjectPass for FileWidthPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &TestConfig, files: &[ProjectFile<TestConfig>]) -> Vec<ProjectEdit> {
+            files
+                .iter()
+                .enumerate()
+                .map(|(file, project_file)| ProjectEdit {
+                    file,
+                    edit: Edit {
+                        range: (0, project_file.source.len()),
+                        content: format!("[{}, {}]\n", files.len(), project_file.config.width),
+                    },
+                })
+                .collect()
+        }
+    }
+
+    /// Write `count` JSON files into `dir` and return their paths.
+    fn write_files(dir: &Path, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| {
+                let file = dir.join(format!("{i}.json"));
+                fs::write(&file, "[]\n").unwrap();
+                file.display().to_string()
+            })
+            .collect()
+    }
+
+    fn run_with(dir: &TempDir, args: &[&str]) -> CliResult<ExitStatus> {
+        let config = dir.path().join("config.yaml");
+        let config = config.to_str().unwrap();
+        let args = ["jfmt"].iter().chain(args).map(|arg| {
+            arg.replace("CONFIG", config)
+                .replace("DIR", dir.path().to_str().unwrap())
+        });
+
+        cli_builder::<JsonLanguage, TestConfig>()
+            .add_pass(FinalNewlinePass)
+            .try_run_from(args)
+    }
+
+    #[test]
+    fn test_try_run_from_reports_status() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.json"), "{}").unwrap();
+
+        let status = run_with(&dir, &["format", "-c", "CONFIG", "DIR/a.json"]).unwrap();
+        assert_eq!(status, ExitStatus::NeedsFormatting);
+
+        let status = run_with(
+            &dir,
+            &["format", "-c", "CONFIG", "-m", "write", "DIR/a.json"],
+        );
+        assert_eq!(status.unwrap(), ExitStatus::Success);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.json")).unwrap(),
+            "{}\n"
+        );
+
+        let status = run_with(&dir, &["format", "-c", "CONFIG", "DIR/a.json"]).unwrap();
+        assert_eq!(status, ExitStatus::Success);
+    }
+
+    #[test]
+    fn test_try_run_from_returns_errors() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("config.yaml"), "width: wide\n").unwrap();
+
+        let err = run_with(&dir, &["validate", "-c", "CONFIG"]).unwrap_err();
+        assert!(matches!(err, CliError::InvalidConfig { count: 1, .. }));
+        assert_eq!(err.exit_code(), 2);
+
+        let err = run_with(&dir, &["explain", "FMT999"]).unwrap_err();
+        assert!(matches!(err, CliError::UnknownRule { .. }));
+        assert_eq!(err.exit_code(), 2);
+
+        let err = run_with(&dir, &["format", "--no-such-flag"]).unwrap_err();
+        assert!(matches!(err, CliError::ArgumentError { .. }));
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_fail_fast_stops_at_panicking_pass() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.yaml");
+        let boom = dir.path().join("a.json");
+        let unformatted = dir.path().join("b.json");
+        fs::write(&boom, "{\"boom\": 1}\n").unwrap();
+        fs::write(&unformatted, "{}").unwrap();
+
+        let err = cli_builder::<JsonLanguage, TestConfig>()
+            .add_pass(BoomPass)
+            .add_pass(FinalNewlinePass)
+            .try_run_from([
+                "jfmt",
+                "format",
+                "--fail-fast",
+                "-c",
+                config.to_str().unwrap(),
+                boom.to_str().unwrap(),
+                unformatted.to_str().unwrap(),
+            ])
+            .unwrap_err();
+
+        assert!(matches!(err, CliError::FilesFailed { count: 1 }));
+        assert_eq!(err.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_lines_fails_file_with_panicking_pass() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.yaml");
+        let boom = dir.path().join("a.json");
+        fs::write(&boom, "{\"boom\": 1}\n").unwrap();
+
+        let err = cli_builder::<JsonLanguage, TestConfig>()
+            .add_pass(BoomPass)
+            .try_run_from([
+                "jfmt",
+                "format",
+                "--lines",
+                "1:1",
+                "-c",
+                config.to_str().unwrap(),
+                boom.to_str().unwrap(),
+            ])
+            .unwrap_err();
+
+        assert!(matches!(err, CliError::FilesFailed { count: 1 }));
+        assert_eq!(err.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_invalid_pipeline_is_a_usage_error() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.json"), "{}\n").unwrap();
+        let config = dir.path().join("config.yaml");
+        let file = dir.path().join("a.json");
+
+        let err = cli_builder::<JsonLanguage, TestConfig>()
+            .add_pass(OrphanPass)
+            .try_run_from([
+                "jfmt",
+                "format",
+                "-c",
+                config.to_str().unwrap(),
+                file.to_str().unwrap(),
+            ])
+            .unwrap_err();
+
+        assert!(matches!(err, CliError::InvalidPipeline { .. }));
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_config_flags_override_config() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.json"), "{}\n").unwrap();
+        let run = |args: &[&str]| {
+            let args = ["jfmt", "format", "-c"]
+                .into_iter()
+                .map(str::to_string)
+                .chain([dir.path().join("config.yaml").display().to_string()])
+                .chain(args.iter().map(|arg| arg.to_string()))
+                .chain([dir.path().join("a.json").display().to_string()]);
+            cli_builder::<JsonLanguage, TestConfig>()
+                .add_pass(FinalNewlinePass)
+                .config_flags()
+                .try_run_from(args)
+        };
+
+        assert_eq!(run(&["--width", "2"]).unwrap(), ExitStatus::Success);
+        let err = run(&["--width", "wide"]).unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_consistency_pass_sees_files_of_every_batch() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.yaml");
+        fs::write(&config, "width: 0\nrunner:\n  batch_size: 2\n").unwrap();
+        let files = write_files(dir.path(), 5);
+
+        let args = ["jfmt", "format", "-m", "write", "-c"]
+            .into_iter()
+            .map(str::to_string)
+            .chain([config.display().to_string()])
+            .chain(files.iter().cloned());
+        let status = cli_builder::<JsonLanguage, TestConfig>()
+            .add_consistency_pass(FileCountPass)
+            .try_run_from(args);
+
+        assert_eq!(status.unwrap(), ExitStatus::Success);
+        for file in &files {
+            assert_eq!(fs::read_to_string(file).unwrap(), "[5]\n");
+        }
+    }
+
+    #[test]
+    fn test_consistency_pass_sees_every_config_group() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.yaml");
+        fs::write(&config, "width: 0\nrunner:\n  batch_size: 1\n").unwrap();
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        fs::write(nested.join("config.yaml"), "width: 1\n").unwrap();
+        let root_files = write_files(dir.path(), 3);
+        let nested_files = write_files(&nested, 2);
+
+        let args = ["jfmt", "format", "-m", "write", "-c"]
+            .into_iter()
+            .map(str::to_string)
+            .chain([config.display().to_string()])
+            .chain(root_files.iter().chain(&nested_files).cloned());
+        let status = cli_builder::<JsonLanguage, TestConfig>()
+            .add_consistency_pass(FileCountPass)
+            .try_run_from(args);
+
+        assert_eq!(status.unwrap(), ExitStatus::Success);
+        for file in root_files.iter().chain(&nested_files) {
+            assert_eq!(fs::read_to_string(file).unwrap(), "[5]\n");
+        }
+    }
+
+    #[test]
+    fn test_project_pass_sees_config_of_each_file() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.yaml");
+        fs::write(&config, "width: 0\n").unwrap();
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        fs::write(nested.join("config.toml"), "width = 1\n").unwrap();
+        let root_files = write_files(dir.path(), 2);
+        let nested_files = write_files(&nested, 1);
+
+        let args = ["jfmt", "format", "-m", "write", "-c"]
+            .into_iter()
+            .map(str::to_string)
+            .chain([config.display().to_string()])
+            .chain(root_files.iter().chain(&nested_files).cloned());
+        let status = cli_builder::<JsonLanguage, TestConfig>()
+            .add_project_pass(FileWidthPass)
+            .try_run_from(args);
+
+        assert_eq!(status.unwrap(), ExitStatus::Success);
+        for file in &root_files {
+            assert_eq!(fs::read_to_string(file).unwrap(), "[3, 0]\n");
+        }
+        for file in &nested_files {
+            assert_eq!(fs::read_to_string(file).unwrap(), "[3, 1]\n");
+        }
+    }
+
+    #[test]
+    fn test_project_pass_receives_files_of_every_batch() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.yaml");
+        fs::write(&config, "width: 0\nrunner:\n  batch_size: 2\n").unwrap();
+        let files = write_files(dir.path(), 5);
+
+        let args = ["jfmt", "format", "-m", "write", "-c"]
+            .into_iter()
+            .map(str::to_string)
+            .chain([config.display().to_string()])
+            .chain(files.iter().cloned());
+        let status = cli_builder::<JsonLanguage, TestConfig>()
+            .add_project_pass(FileListPass)
+            .try_run_from(args);
+
+        assert_eq!(status.unwrap(), ExitStatus::Success);
+        for file in &files {
+            assert_eq!(fs::read_to_string(file).unwrap(), "[5]\n");
+        }
+    }
+
+    #[test]
+    fn test_reporter_receives_results() {
+        #[derive(Default)]
+        struct Collected {
+            changed: Vec<PathBuf>,
+            exit_code: Option<i32>,
+            version: Option<&'static str>,
+        }
+
+        struct CollectReporter(Rc<RefCell<Collected>>);
+
+        impl Reporter for CollectReporter {
+            fn on_file(&mut self, record: &FileRecord<'_>) -> CliResult<()> {
+                if record.changed {
+                    self.0.borrow_mut().changed.push(record.path.to_path_buf());
+                }
+                Ok(())
+            }
+
+            fn on_status(&mut self, status: &RunStatus) -> CliResult<()> {
+                let mut collected = self.0.borrow_mut();
+                collected.exit_code = Some(status.exit_code);
+                collected.version = Some(status.version);
+                Ok(())
+            }
+        }
+
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("a.json");
+        fs::write(&file, "{}").unwrap();
+        let config = dir.path().join("config.yaml");
+        let collected = Rc::new(RefCell::new(Collected::default()));
+
+        let reporter = Rc::clone(&collected);
+        let status = cli_builder::<JsonLanguage, TestConfig>()
+            .add_pass(FinalNewlinePass)
+            .version("9.9.9")
+            .reporter(move || CollectReporter(Rc::clone(&reporter)))
+            .try_run_from([
+                "jfmt",
+                "format",
+                "-c",
+                config.to_str().unwrap(),
+                file.to_str().unwrap(),
+            ]);
+
+        assert_eq!(status.unwrap(), ExitStatus::NeedsFormatting);
+        let collected = collected.borrow();
+        assert_eq!(collected.changed, [file]);
+        assert_eq!(collected.exit_code, Some(1));
+        assert_eq!(collected.version, Some("9.9.9"));
+    }
+
+    #[test]
+    fn test_into_commands_default_to_host_binary_name() {
+        let (commands, _) = cli_builder::<JsonLanguage, TestConfig>().into_commands();
+        let format = commands
+            .iter()
+            .find(|command| command.get_name() == "format")
+            .unwrap();
+        let config = format
+            .get_arguments()
+            .find(|arg| arg.get_id() == "config_path")
+            .unwrap();
+
+        let host = env::args_os().next().unwrap();
+        let host = Path::new(&host).file_name().unwrap().to_str().unwrap();
+        assert_eq!(
+            config.get_default_values(),
+            [format!("{host}.yml").as_str()]
+        );
+    }
+
+    #[test]
+    fn test_into_commands_mounts_under_host() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("a.json");
+        let config = dir.path().join("config.yaml");
+        fs::write(&file, "{}").unwrap();
+
+        let (commands, dispatcher) = cli_builder::<JsonLanguage, TestConfig>()
+            .add_pass(FinalNewlinePass)
+            .into_commands();
+        let host = Command::new("lang")
+            .subcommand(Command::new("build"))
+            .subcommands(commands.into_iter().map(|command| {
+                if command.get_name() == "format" {
+                    command.visible_alias("fmt")
+                } else {
+                    command
+                }
+            }));
+
+        let matches = host
+            .try_get_matches_from([
+                "lang",
+                "fmt",
+                "-c",
+                config.to_str().unwrap(),
+                "-m",
+                "write",
+                file.to_str().unwrap(),
+            ])
+            .unwrap();
+        let (name, _) = matches.subcommand().unwrap();
+        assert!(dispatcher.handles(name));
+        assert!(!dispatcher.handles("build"));
+
+        assert_eq!(dispatcher.dispatch(&matches).unwrap(), ExitStatus::Success);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "{}\n");
+    }
+}
@@ -1,4 +1,8 @@
-use clap::{Arg, Command};
+use crate::cli::commands::ConfigFlag;
+use crate::cli::settings::CliSettings;
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, Command};
+use std::path::Path;
 
 /// Format modes for the formatter.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -22,6 +26,36 @@ impl FormatMode {
     }
 }
 
+/// Output formats for command results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable log messages
+    Human,
+    /// Machine-readable JSON lines on stdout
+    Json,
+    /// A SARIF log on stdout, for code scanning tools
+    Sarif,
+    /// No output; the exit code tells the outcome
+    Quiet,
+}
+
+impl OutputFormat {
+    const HUMAN: &'static str = "human";
+    const JSON: &'static str = "json";
+    const SARIF: &'static str = "sarif";
+    const QUIET: &'static str = "quiet";
+
+    /// Get the string representation of the output format.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputFormat::Human => Self::HUMAN,
+            OutputFormat::Json => Self::JSON,
+            OutputFormat::Sarif => Self::SARIF,
+            OutputFormat::Quiet => Self::QUIET,
+        }
+    }
+}
+
 /// Available CLI commands.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CliCommand {
@@ -29,21 +63,50 @@ pub enum CliCommand {
     Init,
     /// Format source files
     Format,
+    /// Validate a configuration file
+    Validate,
+    /// Describe a rule by its code
+    Explain,
+    /// List the passes of the formatter
+    ListPasses,
+    /// Inspect the effective configuration
+    Config,
 }
 
 impl CliCommand {
     const INIT: &'static str = "init";
     const FORMAT: &'static str = "format";
+    const VALIDATE: &'static str = "validate";
+    const EXPLAIN: &'static str = "explain";
+    const LIST_PASSES: &'static str = "list-passes";
+    const CONFIG: &'static str = "config";
+
+    /// All built-in commands.
+    pub const ALL: [CliCommand; 6] = [
+        CliCommand::Init,
+        CliCommand::Validate,
+        CliCommand::Format,
+        CliCommand::Explain,
+        CliCommand::ListPasses,
+        CliCommand::Config,
+    ];
 
     /// Get the string representation of the CLI command.
     pub fn as_str(self) -> &'static str {
         match self {
             CliCommand::Init => Self::INIT,
             CliCommand::Format => Self::FORMAT,
+            CliCommand::Validate => Self::VALIDATE,
+            CliCommand::Explain => Self::EXPLAIN,
+            CliCommand::ListPasses => Self::LIST_PASSES,
+            CliCommand::Config => Self::CONFIG,
         }
     }
 }
 
+/// Name of the `config` subcommand printing the effective config.
+pub const CONFIG_SHOW: &str = "show";
+
 /// Get config filename by binary name.
 ///
 /// # Arguments
@@ -55,61 +118,398 @@ fn default_config_name(bin_name: &str) -> String {
     format!("{bin_name}.yml")
 }
 
+/// Get the name of a formatter whose subcommands are mounted under a host application.
+///
+/// The name set with `CliBuilder::name` comes first, then the name of the
+/// host's binary, like the standalone CLI uses its own.
+pub(crate) fn embedded_name(settings: &CliSettings) -> String {
+    settings
+        .name
+        .map(str::to_string)
+        .or_else(|| {
+            let arg = std::env::args_os().next()?;
+            let name = Path::new(&arg).file_name()?.to_str()?;
+            Some(name.to_string())
+        })
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string())
+}
+
 /// Create a config argument with a default value.
 ///
 /// # Arguments
 /// * `default` - The default config filename
-fn config_arg(default: &'static str) -> Arg {
+fn config_arg(default: &str) -> Arg {
     Arg::new("config_path")
         .short('c')
         .long("config")
         .value_name("FILENAME")
-        .default_value(default)
-        .help("Path to the configuration file")
+        .default_value(default.to_string())
+        .help("Path to the configuration file; the default is looked up at the project root")
+}
+
+/// Create an argument failing the command when the config file is missing.
+fn require_config_arg() -> Arg {
+    Arg::new("require_config")
+        .long("require-config")
+        .action(ArgAction::SetTrue)
+        .help("Fail if the configuration file does not exist instead of using the defaults")
+}
+
+/// Create an output format argument accepting human and JSON output.
+fn output_arg() -> Arg {
+    Arg::new("output")
+        .short('o')
+        .long("output")
+        .value_name("FORMAT")
+        .default_value(OutputFormat::Human.as_str())
+        .value_parser([OutputFormat::Human.as_str(), OutputFormat::Json.as_str()])
+        .help(format!(
+            "Output format: '{}' for log messages, '{}' for JSON lines",
+            OutputFormat::Human.as_str(),
+            OutputFormat::Json.as_str()
+        ))
+}
+
+/// Create the output format argument of `format`, which also accepts SARIF and quiet output.
+fn report_output_arg() -> Arg {
+    output_arg()
+        .value_parser([
+            OutputFormat::Human.as_str(),
+            OutputFormat::Json.as_str(),
+            OutputFormat::Sarif.as_str(),
+            OutputFormat::Quiet.as_str(),
+        ])
+        .help(format!(
+            "Output format: '{}' for log messages, '{}' for JSON lines, '{}' for a SARIF log, '{}' for none",
+            OutputFormat::Human.as_str(),
+            OutputFormat::Json.as_str(),
+            OutputFormat::Sarif.as_str(),
+            OutputFormat::Quiet.as_str()
+        ))
 }
 
 /// Build CLI with dynamic binary and config names.
 ///
+/// Values set in `settings` take precedence over the ones derived from
+/// `bin_name`.
+///
 /// # Arguments
-/// * `bin_name` - The name of the binary (used for help text and defaults)
+/// * `bin_name` - The name of the binary from argv (used for help text and defaults)
+/// * `settings` - Customizations from `CliBuilder`
 ///
 /// # Returns
 /// A configured `Command` ready to parse arguments
-pub fn build_cli(bin_name: &str) -> Command {
-    let bin_name_leaked: &'static str = Box::leak(bin_name.to_string().into_boxed_str());
-    let config_leaked: &'static str = Box::leak(default_config_name(bin_name).into_boxed_str());
-
-    Command::new(bin_name_leaked)
-        .about("Formatter tool")
-        .version(env!("CARGO_PKG_VERSION"))
-        .subcommand(
-            Command::new(CliCommand::Init.as_str())
-                .about("Create a new configuration file")
-                .arg(config_arg(config_leaked)),
+pub fn build_cli(bin_name: &str, settings: &CliSettings) -> Command {
+    let bin_name = settings.name.unwrap_or(bin_name);
+
+    Command::new(bin_name.to_string())
+        .about(settings.about.unwrap_or("Formatter tool"))
+        .version(settings.version.unwrap_or(env!("CARGO_PKG_VERSION")))
+        .args(
+            settings
+                .extra_args
+                .iter()
+                .map(|arg| arg.clone().global(true)),
         )
-        .subcommand(
-            Command::new(CliCommand::Format.as_str())
-                .about("Format specified files")
-                .arg(config_arg(config_leaked))
-                .arg(
-                    Arg::new("files_path")
-                        .value_name("FILES")
-                        .default_value(".")
-                        .num_args(1..)
-                        .help("Files or directories to format"),
+        .subcommands(build_subcommands(bin_name, settings))
+}
+
+/// Build the subcommands for mounting under a host application's command.
+///
+/// Extra arguments are attached to each built-in subcommand, since there is
+/// no root command of our own to hold them.
+///
+/// # Arguments
+/// * `settings` - Customizations from `CliBuilder`
+///
+/// # Returns
+/// The `init`, `validate`, `format`, `explain`, `list-passes` and `config`
+/// subcommands followed by the custom ones
+pub fn build_embedded_commands(settings: &CliSettings) -> Vec<Command> {
+    build_subcommands(&embedded_name(settings), settings)
+        .into_iter()
+        .map(|command| {
+            if CliCommand::ALL
+                .iter()
+                .any(|c| c.as_str() == command.get_name())
+            {
+                command.args(
+                    settings
+                        .extra_args
+                        .iter()
+                        .map(|arg| arg.clone().global(true)),
                 )
-                .arg(
-                    Arg::new("mode")
-                        .short('m')
-                        .long("mode")
-                        .value_name("MODE")
-                        .default_value(FormatMode::Check.as_str())
-                        .value_parser([FormatMode::Check.as_str(), FormatMode::Write.as_str()])
-                        .help(format!(
-                            "Format mode: '{}' to only verify formatting, '{}' to apply changes",
-                            FormatMode::Check.as_str(),
-                            FormatMode::Write.as_str()
-                        )),
-                ),
-        )
+            } else {
+                command
+            }
+        })
+        .collect()
+}
+
+/// Get the config flags of `settings` that do not clash with an extra argument.
+fn config_flags(settings: &CliSettings) -> Vec<ConfigFlag> {
+    settings
+        .config_flags
+        .iter()
+        .filter(|flag| {
+            !settings
+                .extra_args
+                .iter()
+                .any(|arg| arg.get_long() == Some(flag.long()))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Build the built-in and custom subcommands.
+///
+/// # Arguments
+/// * `bin_name` - The binary name the default config filename is derived from
+/// * `settings` - Customizations from `CliBuilder`
+fn build_subcommands(bin_name: &str, settings: &CliSettings) -> Vec<Command> {
+    let default_config = settings
+        .default_config_path
+        .map_or_else(|| default_config_name(bin_name), str::to_string);
+
+    vec![
+        Command::new(CliCommand::Init.as_str())
+            .about("Create a new configuration file")
+            .arg(config_arg(&default_config))
+            .arg(
+                Arg::new("force")
+                    .long("force")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("update")
+                    .help("Overwrite an existing configuration file with the defaults"),
+            )
+            .arg(
+                Arg::new("update")
+                    .long("update")
+                    .action(ArgAction::SetTrue)
+                    .help("Add missing fields with their defaults to an existing configuration file"),
+            )
+            .arg(
+                Arg::new("interactive")
+                    .short('i')
+                    .long("interactive")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("update")
+                    .help("Prompt for every configuration field"),
+            )
+            .arg(
+                Arg::new("preset")
+                    .long("preset")
+                    .value_name("NAME")
+                    .value_parser(PossibleValuesParser::new(
+                        settings.presets.iter().map(|preset| preset.name),
+                    ))
+                    .conflicts_with("update")
+                    .hide(settings.presets.is_empty())
+                    .help("Write a preset configuration instead of the defaults"),
+            ),
+        Command::new(CliCommand::Validate.as_str())
+            .about("Check a configuration file for syntax errors, invalid values, and unknown keys")
+            .arg(config_arg(&default_config))
+            .arg(output_arg()),
+        Command::new(CliCommand::Format.as_str())
+            .about("Format specified files")
+            .arg(config_arg(&default_config))
+            .arg(require_config_arg())
+            .arg(
+                Arg::new("files_path")
+                    .value_name("FILES")
+                    .default_value(".")
+                    .num_args(1..)
+                    .help("Files or directories to format"),
+            )
+            .arg(
+                Arg::new("files_from")
+                    .long("files-from")
+                    .value_name("FILE")
+                    .conflicts_with("files_path")
+                    .help("Read the files to format from FILE ('-' for stdin), one per line, instead of traversing FILES"),
+            )
+            .arg(
+                Arg::new("null")
+                    .short('0')
+                    .long("null")
+                    .action(ArgAction::SetTrue)
+                    .requires("files_from")
+                    .help("Paths in --files-from are separated by NUL characters instead of newlines"),
+            )
+            .arg(
+                Arg::new("mode")
+                    .short('m')
+                    .long("mode")
+                    .value_name("MODE")
+                    .default_value(FormatMode::Check.as_str())
+                    .value_parser([FormatMode::Check.as_str(), FormatMode::Write.as_str()])
+                    .help(format!(
+                        "Format mode: '{}' to only verify formatting, '{}' to apply changes",
+                        FormatMode::Check.as_str(),
+                        FormatMode::Write.as_str()
+                    )),
+            )
+            .arg(report_output_arg())
+            .arg(
+                Arg::new("source_map")
+                    .long("source-map")
+                    .value_name("FILENAME")
+                    .help("Write a JSON source map of original to formatted byte ranges for changed files"),
+            )
+            .arg(
+                Arg::new("profile_io")
+                    .long("profile-io")
+                    .action(ArgAction::SetTrue)
+                    .help("Report read and write times per file"),
+            )
+            .arg(
+                Arg::new("timings")
+                    .long("timings")
+                    .action(ArgAction::SetTrue)
+                    .help("Report the time spent in each pass and the parse metrics of each file"),
+            )
+            .arg(
+                Arg::new("trace_edits")
+                    .long("trace-edits")
+                    .value_name("FILE")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_missing_value("-")
+                    .help("Log every edit made by each pass, with the text it replaces, to FILE [default: stderr]"),
+            )
+            .arg(
+                Arg::new("jobs")
+                    .short('j')
+                    .long("jobs")
+                    .value_name("N")
+                    .default_value("1")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Number of files to format in parallel (0 = one per CPU)"),
+            )
+            .arg(
+                Arg::new("max_file_size")
+                    .long("max-file-size")
+                    .value_name("BYTES")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Skip files larger than this many bytes with a warning"),
+            )
+            .arg(
+                Arg::new("set")
+                    .long("set")
+                    .value_name("KEY=VALUE")
+                    .action(ArgAction::Append)
+                    .help("Override a config field by its dotted path, e.g. --set indent.width=4 (repeatable)"),
+            )
+            .arg(
+                Arg::new("fail_fast")
+                    .long("fail-fast")
+                    .action(ArgAction::SetTrue)
+                    .help("Stop at the first file that needs formatting (check mode only)"),
+            )
+            .arg(
+                Arg::new("lines")
+                    .long("lines")
+                    .value_name("START:END")
+                    .conflicts_with("source_map")
+                    .help("Only format lines START to END (inclusive) of a single file"),
+            )
+            .arg(
+                Arg::new("cache_dir")
+                    .long("cache-dir")
+                    .value_name("DIR")
+                    .help("Remember which files are already formatted in DIR and skip them on later runs; relative to the project root"),
+            )
+            .arg(
+                Arg::new("backup")
+                    .long("backup")
+                    .value_name("SUFFIX")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_missing_value(".bak")
+                    .help("Keep the original of every written file, with SUFFIX appended to its name [default: .bak]"),
+            ),
+        Command::new(CliCommand::Explain.as_str())
+            .about("Describe the rule with a diagnostic code")
+            .arg(
+                Arg::new("code")
+                    .value_name("CODE")
+                    .required(true)
+                    .help("Code of the rule, e.g. FMT001"),
+            ),
+        Command::new(CliCommand::ListPasses.as_str())
+            .about("List the passes of the formatter and whether the config enables them")
+            .arg(config_arg(&default_config))
+            .arg(require_config_arg())
+            .arg(output_arg()),
+        Command::new(CliCommand::Config.as_str())
+            .about("Inspect the effective configuration")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new(CONFIG_SHOW)
+                    .about("Print the project config merged over the user and system configs")
+                    .arg(config_arg(&default_config))
+                    .arg(
+                        Arg::new("origin")
+                            .long("origin")
+                            .action(ArgAction::SetTrue)
+                            .help("Show the file or default each value comes from"),
+                    ),
+            ),
+    ]
+    .into_iter()
+    .map(|command| {
+        if command.get_name() == CliCommand::Format.as_str() {
+            ConfigFlag::add_to(command, &config_flags(settings))
+        } else {
+            command
+        }
+    })
+    .chain(
+        settings
+            .custom_commands
+            .iter()
+            .map(|c| c.command().clone()),
+    )
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_cli_defaults_from_bin_name() {
+        let cli = build_cli("myfmt", &CliSettings::default());
+        let matches = cli.try_get_matches_from(["myfmt", "format"]).unwrap();
+        let (_, sub_matches) = matches.subcommand().unwrap();
+
+        assert_eq!(
+            sub_matches.get_one::<String>("config_path").unwrap(),
+            "myfmt.yml"
+        );
+    }
+
+    #[test]
+    fn test_build_cli_uses_settings() {
+        let settings = CliSettings {
+            name: Some("prettyfmt"),
+            version: Some("9.9.9"),
+            about: Some("Pretty formatter"),
+            default_config_path: Some(".prettyfmt.yaml"),
+            ..CliSettings::default()
+        };
+        let cli = build_cli("argv-name", &settings);
+
+        assert_eq!(cli.get_name(), "prettyfmt");
+        assert_eq!(cli.get_version(), Some("9.9.9"));
+        assert_eq!(cli.get_about().unwrap().to_string(), "Pretty formatter");
+
+        let matches = cli.try_get_matches_from(["prettyfmt", "init"]).unwrap();
+        let (_, sub_matches) = matches.subcommand().unwrap();
+        assert_eq!(
+            sub_matches.get_one::<String>("config_path").unwrap(),
+            ".prettyfmt.yaml"
+        );
+    }
 }
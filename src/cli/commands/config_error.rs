@@ -0,0 +1,225 @@
+use crate::cli::commands::config_format::error_location;
+use crate::cli::error::CliError;
+use std::path::Path;
+
+/// Lines shown above the offending line in a code frame.
+const CONTEXT_LINES: usize = 2;
+
+/// Attach the location of a config loading error and an excerpt of the file to it.
+///
+/// # Arguments
+/// * `err` - Error returned while reading `content`
+/// * `path` - Path of the config file
+/// * `content` - Content of the config file
+///
+/// # Returns
+/// `CliError::InvalidConfigSyntax` if the error has a location, `err` otherwise
+pub fn locate(err: CliError, path: &Path, content: &str) -> CliError {
+    let Some((line, column)) = error_location(&err, content) else {
+        return err;
+    };
+    CliError::InvalidConfigSyntax {
+        path: path.to_path_buf(),
+        line,
+        column,
+        message: describe(&err),
+        frame: code_frame(content, line, column),
+        source: Box::new(err),
+    }
+}
+
+/// Describe a config loading error without its location, with guidance for common mistakes.
+pub fn describe(err: &CliError) -> String {
+    let message = match err {
+        CliError::YamlError { source } => strip_location(&source.to_string()),
+        CliError::TomlError { source } => source.message().to_string(),
+        CliError::JsonParseError { source } => strip_location(&source.to_string()),
+        CliError::Json5Error { message, .. } => message.clone(),
+        CliError::InvalidConfigSyntax { message, .. } => return message.clone(),
+        err => return err.to_string(),
+    };
+    guidance(&message)
+}
+
+/// Render the lines up to `line` with a caret under `column`, both 1-based.
+pub fn code_frame(content: &str, line: usize, column: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let first = line.saturating_sub(CONTEXT_LINES).max(1);
+    let width = line.to_string().len();
+
+    let mut frame = String::new();
+    for number in first..=line {
+        let text = lines.get(number - 1).copied().unwrap_or_default();
+        frame.push_str(&format!("{number:>width$} | {text}\n"));
+    }
+    // Keep tabs before the column so the caret lines up with the text
+    let text = lines.get(line - 1).copied().unwrap_or_default();
+    let padding: String = text
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    frame.push_str(&format!("{:>width$} | {padding}^", ""));
+    frame
+}
+
+/// Remove the ` at line L column C` that serde errors give their location with.
+fn strip_location(message: &str) -> String {
+    const AT_LINE: &str = " at line ";
+    const COLUMN: &str = " column ";
+    let digits =
+        |text: &str| text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    let Some(start) = message.rfind(AT_LINE) else {
+        return message.to_string();
+    };
+    let rest = &message[start + AT_LINE.len()..];
+    let line_len = digits(rest);
+    let Some(after_line) = rest[line_len..].strip_prefix(COLUMN) else {
+        return message.to_string();
+    };
+    let column_len = digits(after_line);
+    if line_len == 0 || column_len == 0 {
+        return message.to_string();
+    }
+    format!("{}{}", &message[..start], &after_line[column_len..])
+}
+
+/// Rewrite common serde and YAML messages into plainer ones with a hint.
+fn guidance(message: &str) -> String {
+    if let Some((prefix, rest)) = message.split_once("invalid type: ") {
+        let Some((found, expected)) = rest.split_once(", expected ") else {
+            return message.to_string();
+        };
+        let hint = if expected.contains("bool") {
+            " (write true or false)"
+        } else if found.starts_with("string") && is_number(expected) {
+            " (write the number without quotes)"
+        } else if expected.contains("sequence") {
+            " (write a list, e.g. [a, b])"
+        } else if expected.contains("map") || expected.starts_with("struct") {
+            " (write the nested keys indented below this one)"
+        } else {
+            ""
+        };
+        return format!("{prefix}expected {expected}, found {found}{hint}");
+    }
+
+    if let Some((prefix, rest)) = message.split_once("unknown variant ") {
+        let (variant, allowed) = rest.split_once(", expected ").unwrap_or((rest, ""));
+        let allowed = allowed
+            .trim_start_matches("one of ")
+            .replace('`', "")
+            .replace(" or ", ", ");
+        return format!(
+            "{prefix}{} is not an allowed value, use one of: {allowed}",
+            variant.replace('`', "'")
+        );
+    }
+
+    if let Some((prefix, field)) = message.split_once("missing field ") {
+        return format!("{prefix}missing required key {}", field.replace('`', "'"));
+    }
+
+    let hint = if message.contains("found character that cannot start any token") {
+        " (YAML is indented with spaces, not tabs)"
+    } else if message.contains("mapping values are not allowed in this context") {
+        " (check the indentation, and quote values containing ': ')"
+    } else if message.contains("did not find expected key") {
+        " (check that the keys of this block are indented alike)"
+    } else {
+        ""
+    };
+    format!("{message}{hint}")
+}
+
+/// Check whether a serde `expected` description names a number type.
+fn is_number(expected: &str) -> bool {
+    [
+        "integer", "float", "number", "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32",
+        "i64", "isize", "f32", "f64",
+    ]
+    .iter()
+    .any(|name| expected.contains(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::commands::config_format::ConfigFormat;
+    use rstest::rstest;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestConfig {
+        width: usize,
+        style: Style,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Style {
+        tabs: bool,
+        quote: Quote,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Quote {
+        Single,
+        Double,
+    }
+
+    #[test]
+    fn test_code_frame() {
+        let content = "width: 80\nstyle:\n  tabs: maybe\n  quote: single\n";
+
+        assert_eq!(
+            code_frame(content, 3, 9),
+            "1 | width: 80\n2 | style:\n3 |   tabs: maybe\n  |         ^"
+        );
+        assert_eq!(code_frame("\ta: [\n", 1, 3), "1 | \ta: [\n  | \t ^");
+    }
+
+    #[rstest]
+    #[case(
+        "width: 80\nstyle:\n  tabs: maybe\n  quote: single\n",
+        "style.tabs: expected a boolean, found string \"maybe\" (write true or false)"
+    )]
+    #[case(
+        "width: '80'\nstyle:\n  tabs: true\n  quote: single\n",
+        "width: expected usize, found string \"80\" (write the number without quotes)"
+    )]
+    #[case(
+        "width: 80\nstyle:\n  tabs: true\n  quote: curly\n",
+        "style.quote: 'curly' is not an allowed value, use one of: single, double"
+    )]
+    #[case(
+        "width: 80\nstyle:\n  quote: single\n",
+        "style: missing required key 'tabs'"
+    )]
+    #[case(
+        "width: 80\nstyle:\n\ttabs: true\n",
+        "found character that cannot start any token, while scanning for the next token (YAML is indented with spaces, not tabs)"
+    )]
+    fn test_describe(#[case] content: &str, #[case] expected: &str) {
+        let err = ConfigFormat::Yaml.parse::<TestConfig>(content).unwrap_err();
+
+        let located = locate(err, Path::new("fmt.yml"), content);
+
+        assert!(matches!(located, CliError::InvalidConfigSyntax { .. }));
+        assert_eq!(describe(&located), expected);
+    }
+
+    #[test]
+    fn test_locate_display() {
+        let content = "width = 80\n[style]\ntabs = 1\nquote = \"single\"\n";
+        let err = ConfigFormat::Toml.parse::<TestConfig>(content).unwrap_err();
+
+        let located = locate(err, Path::new("fmt.toml"), content);
+
+        assert_eq!(
+            located.to_string(),
+            "Invalid config file 'fmt.toml' at line 3, column 8: expected a boolean, found integer `1` (write true or false)\n1 | width = 80\n2 | [style]\n3 | tabs = 1\n  |        ^"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::cli::commands::config_migration::VERSION_KEY;
+use crate::cli::commands::runner_config::RUNNER_SECTION;
+use crate::cli::commands::ConfigOverride;
+use crate::cli::error::{CliError, CliResult};
+use clap::{Arg, ArgMatches, Command};
+use log::debug;
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// A top-level config field exposed as a `--<field>` flag of `format`.
+///
+/// The flag's value is parsed as YAML and applied like `--set field=value`,
+/// on top of the loaded config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFlag {
+    /// Key of the field in the config file
+    field: String,
+    /// Long name of the flag, the field name in kebab case
+    long: String,
+    /// Id of the flag's argument, distinct from the ids of built-in arguments
+    id: String,
+}
+
+impl ConfigFlag {
+    /// Create flags for the top-level fields of `Config`.
+    ///
+    /// Fields holding mappings are left to `--set`, as are the config
+    /// version and the `runner` section.
+    ///
+    /// # Panics
+    /// Panics if the default config cannot be serialized to YAML
+    pub fn from_config<Config>() -> Vec<Self>
+    where
+        Config: Serialize + Default,
+    {
+        let defaults =
+            serde_yaml::to_value(Config::default()).expect("Config should serialize to YAML");
+        let Value::Mapping(fields) = defaults else {
+            return Vec::new();
+        };
+
+        fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_mapping())
+            .filter_map(|(key, _)| key.as_str().map(str::to_string))
+            .filter(|field| field != VERSION_KEY && field != RUNNER_SECTION)
+            .map(|field| Self {
+                long: field.replace('_', "-"),
+                id: format!("config.{field}"),
+                field,
+            })
+            .collect()
+    }
+
+    /// Long name of the flag, without the leading dashes
+    pub fn long(&self) -> &str {
+        &self.long
+    }
+
+    /// Create the argument of the flag.
+    pub fn arg(&self) -> Arg {
+        Arg::new(self.id.clone())
+            .long(self.long.clone())
+            .value_name("VALUE")
+            .help(format!("Override `{}` of the config", self.field))
+    }
+
+    /// Add `flags` to `command`, skipping those clashing with one of its flags.
+    pub fn add_to(command: Command, flags: &[ConfigFlag]) -> Command {
+        let mut command = command;
+        for flag in flags {
+            let taken = command
+                .get_arguments()
+                .any(|arg| arg.get_long() == Some(flag.long()));
+            if taken {
+                debug!(
+                    "Not adding --{} for the config field {}, the argument already exists",
+                    flag.long, flag.field
+                );
+                continue;
+            }
+            command = command.arg(flag.arg());
+        }
+        command
+    }
+
+    /// Get the override given with this flag, if any.
+    ///
+    /// # Arguments
+    /// * `matches` - Matches of the command the flag was added to
+    ///
+    /// # Returns
+    /// The override, `None` if the flag was not given, or an error if its
+    /// value is not valid YAML
+    pub fn override_from(&self, matches: &ArgMatches) -> CliResult<Option<ConfigOverride>> {
+        let Ok(Some(value)) = matches.try_get_one::<String>(&self.id) else {
+            return Ok(None);
+        };
+
+        ConfigOverride::parse(&format!("{}={value}", self.field))
+            .map(Some)
+            .ok_or_else(|| CliError::InvalidArgument {
+                arg: self.long.clone(),
+                value: value.clone(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct TestConfig {
+        indent_size: usize,
+        use_tabs: bool,
+        tags: Vec<String>,
+        style: Style,
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Style {
+        quote: String,
+    }
+
+    #[test]
+    fn test_from_config_skips_mappings() {
+        let flags = ConfigFlag::from_config::<TestConfig>();
+
+        let longs: Vec<_> = flags.iter().map(ConfigFlag::long).collect();
+        assert_eq!(longs, ["indent-size", "use-tabs", "tags"]);
+        assert_eq!(flags[0].field, "indent_size");
+    }
+
+    #[test]
+    fn test_override_from_matches() {
+        let flags = ConfigFlag::from_config::<TestConfig>();
+        let command = Command::new("format").arg(Arg::new("tags").long("tags"));
+        let command = ConfigFlag::add_to(command, &flags);
+
+        let matches = command
+            .try_get_matches_from(["format", "--indent-size", "2", "--tags", "a"])
+            .unwrap();
+
+        let indent = flags[0].override_from(&matches).unwrap().unwrap();
+        assert_eq!(indent.key(), "indent_size");
+        assert_eq!(flags[1].override_from(&matches).unwrap(), None);
+        // `--tags` belongs to the command, not to the config flag
+        assert_eq!(flags[2].override_from(&matches).unwrap(), None);
+    }
+}
@@ -0,0 +1,176 @@
+use super::json5;
+use crate::cli::error::{CliError, CliResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+/// File format of a config file, chosen by its extension.
+///
+/// Every format is read into the same values, so overrides, validation and
+/// `init --update` work alike for all of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ConfigFormat {
+    /// `.yml` and `.yaml` files
+    #[default]
+    Yaml,
+    /// `.toml` files
+    Toml,
+    /// `.json` files
+    Json,
+    /// `.json5` files, written as plain JSON
+    Json5,
+}
+
+impl ConfigFormat {
+    const YAML: &'static str = "yaml";
+    const TOML: &'static str = "toml";
+    const JSON: &'static str = "json";
+    const JSON5: &'static str = "json5";
+
+    /// Get the string representation of the format
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => Self::YAML,
+            ConfigFormat::Toml => Self::TOML,
+            ConfigFormat::Json => Self::JSON,
+            ConfigFormat::Json5 => Self::JSON5,
+        }
+    }
+
+    /// Get the format of the config file at `path` from its extension, ignoring case.
+    ///
+    /// # Returns
+    /// The format, or `None` if the extension is not a config format
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "yml" | "yaml" => Some(ConfigFormat::Yaml),
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            "json5" => Some(ConfigFormat::Json5),
+            _ => None,
+        }
+    }
+
+    /// Deserialize a config, or any other value, from `content` in this format.
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> CliResult<T> {
+        match self {
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(CliError::from),
+            ConfigFormat::Toml => toml::from_str(content).map_err(CliError::from),
+            ConfigFormat::Json => {
+                serde_json::from_str(content).map_err(|source| CliError::JsonParseError { source })
+            }
+            ConfigFormat::Json5 => serde_json::from_str(&json5::to_json(content)?)
+                .map_err(|source| CliError::JsonParseError { source }),
+        }
+    }
+
+    /// Serialize `value` into the content of a config file in this format.
+    ///
+    /// JSON and JSON5 files are both pretty-printed as JSON, which is valid JSON5.
+    pub fn render<T: Serialize>(self, value: &T) -> CliResult<String> {
+        match self {
+            ConfigFormat::Yaml => serde_yaml::to_string(value).map_err(CliError::from),
+            ConfigFormat::Toml => toml::to_string_pretty(value).map_err(CliError::from),
+            ConfigFormat::Json | ConfigFormat::Json5 => {
+                let content = serde_json::to_string_pretty(value)?;
+                Ok(content + "\n")
+            }
+        }
+    }
+}
+
+/// Get the 1-based line and column of the problem a config loading error points at, if known.
+///
+/// # Arguments
+/// * `err` - Error returned while reading `content`
+/// * `content` - Content of the config file
+pub(crate) fn error_location(err: &CliError, content: &str) -> Option<(usize, usize)> {
+    match err {
+        CliError::YamlError { source } => {
+            let location = source.location()?;
+            Some((location.line(), location.column()))
+        }
+        CliError::TomlError { source } => {
+            let offset = source.span()?.start.min(content.len());
+            let before = &content[..offset];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            Some((
+                before.matches('\n').count() + 1,
+                before[line_start..].chars().count() + 1,
+            ))
+        }
+        CliError::JsonParseError { source } if source.line() > 0 => {
+            Some((source.line(), source.column()))
+        }
+        CliError::Json5Error { line, column, .. } => Some((*line, *column)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+    struct TestConfig {
+        width: usize,
+        style: Style,
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+    struct Style {
+        tabs: bool,
+    }
+
+    #[rstest]
+    #[case("fmt.yml", Some(ConfigFormat::Yaml))]
+    #[case("fmt.YAML", Some(ConfigFormat::Yaml))]
+    #[case("fmt.toml", Some(ConfigFormat::Toml))]
+    #[case("fmt.json", Some(ConfigFormat::Json))]
+    #[case("fmt.Json5", Some(ConfigFormat::Json5))]
+    #[case("fmt.txt", None)]
+    #[case("fmt", None)]
+    fn test_from_path(#[case] path: &str, #[case] expected: Option<ConfigFormat>) {
+        assert_eq!(ConfigFormat::from_path(Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case(ConfigFormat::Yaml, "width: 80\nstyle:\n  tabs: true\n")]
+    #[case(ConfigFormat::Toml, "width = 80\n\n[style]\ntabs = true\n")]
+    #[case(
+        ConfigFormat::Json,
+        "{\n  \"width\": 80,\n  \"style\": {\n    \"tabs\": true\n  }\n}\n"
+    )]
+    #[case(
+        ConfigFormat::Json5,
+        "{\n  \"width\": 80,\n  \"style\": {\n    \"tabs\": true\n  }\n}\n"
+    )]
+    fn test_round_trip(#[case] format: ConfigFormat, #[case] content: &str) {
+        let config = TestConfig {
+            width: 80,
+            style: Style { tabs: true },
+        };
+
+        assert_eq!(format.render(&config).unwrap(), content);
+        assert_eq!(format.parse::<TestConfig>(content).unwrap(), config);
+        let value: serde_yaml::Value = format.parse(content).unwrap();
+        assert_eq!(value, serde_yaml::to_value(&config).unwrap());
+    }
+
+    #[rstest]
+    #[case(ConfigFormat::Yaml, "width: 80\nstyle:\n  tabs: maybe\n", (3, 9))]
+    #[case(ConfigFormat::Toml, "width = 80\n[style]\ntabs = maybe\n", (3, 8))]
+    #[case(ConfigFormat::Json, "{\n  \"width\": 80,\n  \"style\": {\"tabs\": 1}\n}", (3, 21))]
+    #[case(ConfigFormat::Json5, "{\n  width: 80,\n  style: {tabs: maybe}\n}", (3, 17))]
+    fn test_error_location(
+        #[case] format: ConfigFormat,
+        #[case] content: &str,
+        #[case] expected: (usize, usize),
+    ) {
+        let err = format.parse::<TestConfig>(content).unwrap_err();
+        assert_eq!(error_location(&err, content), Some(expected));
+    }
+}
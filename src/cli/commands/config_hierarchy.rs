@@ -0,0 +1,219 @@
+use crate::supported_extension::CONFIG_EXTENSIONS;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Files sharing the same nested config files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigGroup {
+    /// Nested config files applying to `files`, outermost first
+    pub configs: Vec<PathBuf>,
+    /// Files to format with these configs, in collection order
+    pub files: Vec<PathBuf>,
+}
+
+/// Finds the nested config files that apply to each formatted file.
+///
+/// A config file named like the root config, placed in a subdirectory of
+/// the root config's directory, applies to every file below that
+/// subdirectory. Its extension may be that of any config format, so a
+/// nested `fmt.toml` applies under a root `fmt.yml`; when a directory holds
+/// several, the one with the root config's extension is used, then the
+/// first in the order of `CONFIG_EXTENSIONS`. Only the keys it sets
+/// override the configs of its ancestor directories.
+pub struct ConfigHierarchy {
+    /// Directory of the root config
+    root: PathBuf,
+    /// File names a nested config may have, in order of preference
+    file_names: Vec<PathBuf>,
+    /// Whether each candidate nested config path exists, cached across files
+    found: HashMap<PathBuf, bool>,
+}
+
+impl ConfigHierarchy {
+    /// Create a hierarchy rooted at the config file at `config_path`.
+    pub fn new(config_path: &Path) -> Self {
+        let root = match config_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        Self {
+            root: canonical(&root),
+            file_names: nested_file_names(config_path),
+            found: HashMap::new(),
+        }
+    }
+
+    /// Get the nested config files applying to `file`, outermost first.
+    ///
+    /// Files outside the root config's directory have no nested configs.
+    pub fn nested_configs(&mut self, file: &Path) -> Vec<PathBuf> {
+        let Some(parent) = file.parent() else {
+            return Vec::new();
+        };
+        let dir = canonical(if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        });
+        if self.file_names.is_empty() || !dir.starts_with(&self.root) {
+            return Vec::new();
+        }
+
+        let found = &mut self.found;
+        let mut configs: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|ancestor| *ancestor != self.root)
+            .filter_map(|ancestor| {
+                self.file_names
+                    .iter()
+                    .map(|file_name| ancestor.join(file_name))
+                    .find(|config| {
+                        *found
+                            .entry(config.clone())
+                            .or_insert_with(|| config.is_file())
+                    })
+            })
+            .collect();
+        configs.reverse();
+        configs
+    }
+
+    /// Split `files` into groups sharing the same nested configs.
+    ///
+    /// Groups are ordered by their first file, and keep the order of their files.
+    pub fn group(&mut self, files: &[PathBuf]) -> Vec<ConfigGroup> {
+        let mut groups: Vec<ConfigGroup> = Vec::new();
+        for file in files {
+            let configs = self.nested_configs(file);
+            match groups.iter_mut().find(|group| group.configs == configs) {
+                Some(group) => group.files.push(file.clone()),
+                None => groups.push(ConfigGroup {
+                    configs,
+                    files: vec![file.clone()],
+                }),
+            }
+        }
+        groups
+    }
+}
+
+/// Get the file names a nested config of the root config at `config_path` may have.
+///
+/// The root config's own name comes first, followed by its stem with each
+/// other config extension. A root config without a config extension is
+/// only matched by its exact name.
+fn nested_file_names(config_path: &Path) -> Vec<PathBuf> {
+    let Some(file_name) = config_path.file_name() else {
+        return Vec::new();
+    };
+    let mut file_names = vec![PathBuf::from(file_name)];
+    if !CONFIG_EXTENSIONS.matches(config_path) {
+        return file_names;
+    }
+    let (Some(stem), Some(extension)) = (config_path.file_stem(), config_path.extension()) else {
+        return file_names;
+    };
+    let extension = extension.to_string_lossy().to_ascii_lowercase();
+    file_names.extend(
+        CONFIG_EXTENSIONS
+            .extensions()
+            .iter()
+            .filter(|other| **other != extension)
+            .map(|other| {
+                let mut file_name = stem.to_os_string();
+                file_name.push(".");
+                file_name.push(other);
+                PathBuf::from(file_name)
+            }),
+    );
+    file_names
+}
+
+/// Get the canonical form of `path`, or `path` itself if it cannot be resolved.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_group_by_nested_configs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+        fs::write(root.join("fmt.yml"), "").unwrap();
+        fs::write(root.join("a/fmt.yml"), "").unwrap();
+        fs::write(root.join("a/b/fmt.yml"), "").unwrap();
+        fs::write(root.join("c/other.yml"), "").unwrap();
+
+        let files = [
+            root.join("top.json"),
+            root.join("a/b/deep.json"),
+            root.join("a/mid.json"),
+            root.join("c/plain.json"),
+        ];
+        let mut hierarchy = ConfigHierarchy::new(&root.join("fmt.yml"));
+        let groups = hierarchy.group(&files);
+
+        let canonical_root = canonical(root);
+        let configs: Vec<_> = groups.iter().map(|group| group.configs.clone()).collect();
+        assert_eq!(
+            configs,
+            vec![
+                vec![],
+                vec![
+                    canonical_root.join("a/fmt.yml"),
+                    canonical_root.join("a/b/fmt.yml"),
+                ],
+                vec![canonical_root.join("a/fmt.yml")],
+            ]
+        );
+        assert_eq!(
+            groups[0].files,
+            vec![root.join("top.json"), root.join("c/plain.json")]
+        );
+    }
+
+    #[test]
+    fn test_nested_configs_of_any_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("fmt.yml"), "").unwrap();
+        fs::write(root.join("a/fmt.toml"), "").unwrap();
+        fs::write(root.join("a/b/fmt.json"), "").unwrap();
+        fs::write(root.join("a/b/fmt.yml"), "").unwrap();
+        fs::write(root.join("a/b/other.toml"), "").unwrap();
+
+        let mut hierarchy = ConfigHierarchy::new(&root.join("fmt.yml"));
+
+        let canonical_root = canonical(root);
+        assert_eq!(
+            hierarchy.nested_configs(&root.join("a/b/file.json")),
+            vec![
+                canonical_root.join("a/fmt.toml"),
+                canonical_root.join("a/b/fmt.yml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_files_outside_root_have_no_nested_configs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("project")).unwrap();
+        fs::create_dir_all(root.join("elsewhere")).unwrap();
+        fs::write(root.join("elsewhere/fmt.yml"), "").unwrap();
+
+        let mut hierarchy = ConfigHierarchy::new(&root.join("project/fmt.yml"));
+
+        assert!(hierarchy
+            .nested_configs(&root.join("elsewhere/file.json"))
+            .is_empty());
+    }
+}
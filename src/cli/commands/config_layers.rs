@@ -0,0 +1,149 @@
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// File name of the system and user configs, in the formatter's directory.
+const LAYER_FILE_NAME: &str = "config.yml";
+
+/// Directory holding the system-wide configs of every formatter.
+const SYSTEM_CONFIG_DIR: &str = "/etc";
+
+/// Where a value of the effective config comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// The default of the config type
+    Default,
+    /// The system-wide config file
+    System(PathBuf),
+    /// The config file of the user
+    User(PathBuf),
+    /// The config file of the project
+    Project(PathBuf),
+}
+
+impl ConfigOrigin {
+    /// Get the path of the config file the value comes from, if any
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ConfigOrigin::System(path) | ConfigOrigin::User(path) | ConfigOrigin::Project(path) => {
+                Some(path)
+            }
+            ConfigOrigin::Default => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigOrigin::Default => write!(f, "default"),
+            ConfigOrigin::System(path) => write!(f, "system {}", path.display()),
+            ConfigOrigin::User(path) => write!(f, "user {}", path.display()),
+            ConfigOrigin::Project(path) => write!(f, "project {}", path.display()),
+        }
+    }
+}
+
+/// Config files merged under the project config, lowest priority first.
+///
+/// The system-wide config is `/etc/<name>/config.yml` and the user config
+/// `$XDG_CONFIG_HOME/<name>/config.yml`, falling back to
+/// `~/.config/<name>/config.yml`. Each layer only overrides the keys it sets,
+/// like nested configs do; the project config wins over both.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigLayers {
+    /// Existing layer files, lowest priority first
+    layers: Vec<ConfigOrigin>,
+}
+
+impl ConfigLayers {
+    /// Create an empty set of layers, so only the project config is loaded
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Find the system and user configs of the formatter named `name`.
+    ///
+    /// Only files that exist are kept.
+    pub fn discover(name: &str) -> Self {
+        let user_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        Self::from_dirs(
+            Some(Path::new(SYSTEM_CONFIG_DIR)),
+            user_dir.as_deref(),
+            name,
+        )
+    }
+
+    /// Find the layer files of `name` in the given system and user config directories.
+    pub(crate) fn from_dirs(
+        system_dir: Option<&Path>,
+        user_dir: Option<&Path>,
+        name: &str,
+    ) -> Self {
+        let file = |dir: Option<&Path>| {
+            dir.map(|dir| dir.join(name).join(LAYER_FILE_NAME))
+                .filter(|path| path.is_file())
+        };
+        let layers = [
+            file(system_dir).map(ConfigOrigin::System),
+            file(user_dir).map(ConfigOrigin::User),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        Self { layers }
+    }
+
+    /// Returns true if no layer file exists
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Get the origins of the existing layer files, lowest priority first
+    pub fn origins(&self) -> &[ConfigOrigin] {
+        &self.layers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_from_dirs_keeps_existing_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let system = temp_dir.path().join("etc");
+        let user = temp_dir.path().join("home");
+        fs::create_dir_all(system.join("jfmt")).unwrap();
+        fs::create_dir_all(user.join("jfmt")).unwrap();
+        fs::write(user.join("jfmt/config.yml"), "width: 2\n").unwrap();
+
+        let layers = ConfigLayers::from_dirs(Some(&system), Some(&user), "jfmt");
+        let files: Vec<_> = layers
+            .origins()
+            .iter()
+            .filter_map(ConfigOrigin::path)
+            .collect();
+        assert_eq!(files, [user.join("jfmt/config.yml")]);
+
+        fs::write(system.join("jfmt/config.yml"), "width: 1\n").unwrap();
+        let layers = ConfigLayers::from_dirs(Some(&system), Some(&user), "jfmt");
+        assert_eq!(
+            layers.origins(),
+            [
+                ConfigOrigin::System(system.join("jfmt/config.yml")),
+                ConfigOrigin::User(user.join("jfmt/config.yml")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_dirs_have_no_layers() {
+        assert!(ConfigLayers::from_dirs(None, None, "jfmt").is_empty());
+    }
+}
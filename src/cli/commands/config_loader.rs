@@ -1,31 +1,62 @@
+use crate::cli::commands::config_error;
+use crate::cli::commands::config_format::ConfigFormat;
+use crate::cli::commands::config_layers::{ConfigLayers, ConfigOrigin};
+use crate::cli::commands::config_migration::ConfigMigrations;
+use crate::cli::commands::config_override::ConfigOverride;
+use crate::cli::commands::config_validation::ConfigValidators;
+use crate::cli::commands::runner_config::{RunnerConfig, RUNNER_SECTION};
+use crate::cli::commands::unknown_keys::find_unknown_keys;
 use crate::cli::error::{CliError, CliResult};
+use crate::core::FileWriter;
 use crate::supported_extension::CONFIG_EXTENSIONS;
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Configuration loader responsible for loading and validating config files.
+///
+/// The format of a config file follows its extension; see `ConfigFormat`.
 pub struct ConfigLoader;
 
 impl ConfigLoader {
-    /// Load config or create default when missing.
+    /// Load config or defaults when missing, migrate it to the latest version, then apply overrides.
+    ///
+    /// The file itself is left unchanged; `init --update` writes the migrated config.
     ///
     /// # Arguments
     /// * `config_path` - Path to the config file
+    /// * `overrides` - Overrides applied in order on top of the loaded config
+    /// * `migrations` - Migrations applied to files of older versions
     ///
     /// # Returns
-    /// The loaded or default config
-    pub fn load<Config>(config_path: &Path) -> CliResult<Config>
+    /// The loaded config with migrations and overrides applied
+    pub fn load_migrated<Config>(
+        config_path: &Path,
+        overrides: &[ConfigOverride],
+        migrations: &ConfigMigrations,
+    ) -> CliResult<Config>
     where
         Config: Serialize + DeserializeOwned + Default,
     {
         info!("Loading config from {}...", config_path.display());
 
         let config = if Self::exists(config_path)? {
-            Self::validate_config::<Config>(config_path)?;
-            Self::from_file(config_path)?
+            let mut value: serde_yaml::Value = Self::from_file(config_path)?;
+            let version = migrations.migrate(&mut value)?;
+            Self::warn_unknown_keys::<Config>(config_path, &value)?;
+            if version < migrations.latest() {
+                info!(
+                    "Migrated config {} from version {version} to {}, run init --update to save it",
+                    config_path.display(),
+                    migrations.latest()
+                );
+                serde_yaml::from_value(value)?
+            } else {
+                // Deserialize from the file again to report errors with their location
+                Self::from_file(config_path)?
+            }
         } else {
             Self::check_extension(config_path)?;
             debug!(
@@ -35,19 +66,197 @@ impl ConfigLoader {
             Config::default()
         };
 
-        Ok(config)
+        if overrides.is_empty() {
+            return Ok(config);
+        }
+
+        let mut value = serde_yaml::to_value(config)?;
+        for config_override in overrides {
+            debug!("Overriding config field {}", config_override.key());
+            config_override.apply(&mut value);
+        }
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    /// Merge nested config files over `base`, then apply `--set` overrides again.
+    ///
+    /// Each nested config only overrides the keys it sets, mappings being
+    /// merged key by key; later files take precedence. Overrides still win
+    /// over every file.
+    ///
+    /// # Arguments
+    /// * `base` - Config of the root config file, overrides included
+    /// * `nested` - Nested config files, outermost first
+    /// * `overrides` - Overrides applied in order on top of the merged config
+    /// * `migrations` - Migrations applied to nested files of older versions
+    ///
+    /// # Returns
+    /// The merged config
+    pub fn load_nested<Config>(
+        base: &Config,
+        nested: &[PathBuf],
+        overrides: &[ConfigOverride],
+        migrations: &ConfigMigrations,
+    ) -> CliResult<Config>
+    where
+        Config: Serialize + DeserializeOwned + Default,
+    {
+        let mut value = serde_yaml::to_value(base)?;
+        for path in nested {
+            debug!("Merging nested config {}", path.display());
+            let mut overlay: serde_yaml::Value = Self::from_file(path)?;
+            migrations.migrate(&mut overlay)?;
+            Self::warn_unknown_keys::<Config>(path, &overlay)?;
+            merge_over(&mut value, overlay);
+        }
+        for config_override in overrides {
+            config_override.apply(&mut value);
+        }
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    /// Load config merged over the system and user configs, then apply overrides.
+    ///
+    /// Without layer files, this loads the project config like `load_migrated`.
+    ///
+    /// # Arguments
+    /// * `config_path` - Path to the project config file, which may not exist
+    /// * `layers` - System and user configs the project config is merged over
+    /// * `overrides` - Overrides applied in order on top of the merged config
+    /// * `migrations` - Migrations applied to files of older versions
+    ///
+    /// # Returns
+    /// The merged config with overrides applied
+    pub fn load_layered<Config>(
+        config_path: &Path,
+        layers: &ConfigLayers,
+        overrides: &[ConfigOverride],
+        migrations: &ConfigMigrations,
+    ) -> CliResult<Config>
+    where
+        Config: Serialize + DeserializeOwned + Default,
+    {
+        if layers.is_empty() {
+            return Self::load_migrated(config_path, overrides, migrations);
+        }
+
+        info!("Loading config from {}...", config_path.display());
+        let mut value = serde_yaml::Value::Null;
+        for (_, layer) in Self::layered_values::<Config>(config_path, layers, migrations)? {
+            merge_over(&mut value, layer);
+        }
+        for config_override in overrides {
+            debug!("Overriding config field {}", config_override.key());
+            config_override.apply(&mut value);
+        }
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    /// Read the defaults of `Config`, the layer files and the project config.
+    ///
+    /// Files are migrated to the latest version and their unknown keys are
+    /// reported. Merging the values in order gives the effective config.
+    ///
+    /// # Arguments
+    /// * `config_path` - Path to the project config file, which may not exist
+    /// * `layers` - System and user configs under the project config
+    /// * `migrations` - Migrations applied to files of older versions
+    ///
+    /// # Returns
+    /// The value of every source with its origin, lowest priority first
+    pub fn layered_values<Config>(
+        config_path: &Path,
+        layers: &ConfigLayers,
+        migrations: &ConfigMigrations,
+    ) -> CliResult<Vec<(ConfigOrigin, serde_yaml::Value)>>
+    where
+        Config: Serialize + Default,
+    {
+        let mut values = vec![(
+            ConfigOrigin::Default,
+            serde_yaml::to_value(Config::default())?,
+        )];
+        let project = if Self::exists(config_path)? {
+            Some(ConfigOrigin::Project(config_path.to_path_buf()))
+        } else {
+            Self::check_extension(config_path)?;
+            None
+        };
+        for origin in layers.origins().iter().chain(&project) {
+            let Some(path) = origin.path() else {
+                continue;
+            };
+            debug!("Merging config layer {origin}");
+            let mut value: serde_yaml::Value = Self::from_file(path)?;
+            migrations.migrate(&mut value)?;
+            Self::warn_unknown_keys::<Config>(path, &value)?;
+            values.push((origin.clone(), value));
+        }
+        Ok(values)
+    }
+
+    /// Load fmt-runner's own settings from the `runner` section of the config file.
+    ///
+    /// The `runner` sections of the layer files are merged under the project's.
+    ///
+    /// # Arguments
+    /// * `config_path` - Path to the config file
+    /// * `layers` - System and user configs the config file is merged over
+    /// * `overrides` - `--set` overrides applied in order on top of the merged configs
+    ///
+    /// # Returns
+    /// The runner settings, or defaults when no file sets the section
+    pub fn load_runner_config(
+        config_path: &Path,
+        layers: &ConfigLayers,
+        overrides: &[ConfigOverride],
+    ) -> CliResult<RunnerConfig> {
+        let mut value = serde_yaml::Value::Null;
+        for path in layers.origins().iter().filter_map(ConfigOrigin::path) {
+            merge_over(&mut value, Self::from_file(path)?);
+        }
+        if Self::exists(config_path)? {
+            merge_over(&mut value, Self::from_file(config_path)?);
+        }
+        for config_override in overrides {
+            config_override.apply(&mut value);
+        }
+
+        match value.get(RUNNER_SECTION) {
+            Some(section) => Ok(serde_yaml::from_value(section.clone())?),
+            None => Ok(RunnerConfig::default()),
+        }
     }
 
-    /// Write a default config file (creates parent directories if needed).
+    /// Write a default config file at the latest version (creates parent directories if needed).
     ///
     /// # Arguments
     /// * `path` - Path where the config file should be created
+    /// * `migrations` - Registered migrations, giving the latest version
     ///
     /// # Returns
     /// `Ok(())` on success, or an error
-    pub fn create_default_file<Config: Serialize + Default>(path: &Path) -> CliResult<()> {
-        let default_config = Config::default();
-        let yaml = serde_yaml::to_string(&default_config)?;
+    pub fn create_default_file<Config: Serialize + Default>(
+        path: &Path,
+        migrations: &ConfigMigrations,
+    ) -> CliResult<()> {
+        let mut config = serde_yaml::to_value(Config::default())?;
+        migrations.stamp(&mut config);
+        Self::create_file(path, &config)
+    }
+
+    /// Write a config file with the given contents (creates parent directories if needed).
+    ///
+    /// The config is written in the format matching the extension of `path`.
+    ///
+    /// # Arguments
+    /// * `path` - Path where the config file should be created
+    /// * `config` - Config to serialize into the file
+    ///
+    /// # Returns
+    /// `Ok(())` on success, or an error
+    pub fn create_file<Config: Serialize>(path: &Path, config: &Config) -> CliResult<()> {
+        let content = Self::format_of(path)?.render(config)?;
 
         if let Some(parent) = path.parent() {
             if !parent.exists() {
@@ -55,10 +264,49 @@ impl ConfigLoader {
             }
         }
 
-        fs::write(path, yaml)?;
+        fs::write(path, content)?;
         Ok(())
     }
 
+    /// Add config fields missing from an existing file, with their default values.
+    ///
+    /// User values, unknown sections, and the order of existing keys are kept;
+    /// missing keys are appended to their mapping. Comments are not preserved.
+    /// A file of an older version is migrated to the latest version first.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the existing config file
+    /// * `migrations` - Migrations applied if the file is of an older version
+    ///
+    /// # Returns
+    /// The dotted paths of the added keys
+    pub fn update_file<Config: Serialize + DeserializeOwned + Default>(
+        path: &Path,
+        migrations: &ConfigMigrations,
+    ) -> CliResult<Vec<String>> {
+        let mut value: serde_yaml::Value = Self::from_file(path)?;
+        let version = migrations.migrate(&mut value)?;
+        let migrated = version < migrations.latest();
+        if migrated {
+            info!(
+                "Migrating {} from version {version} to {}...",
+                path.display(),
+                migrations.latest()
+            );
+        }
+        let defaults = serde_yaml::to_value(Config::default())?;
+
+        let mut added = Vec::new();
+        merge_missing(&mut value, defaults, "", &mut added);
+        serde_yaml::from_value::<Config>(value.clone())?;
+
+        if migrated || !added.is_empty() {
+            let content = Self::format_of(path)?.render(&value)?;
+            FileWriter::new().write(path, &content)?;
+        }
+        Ok(added)
+    }
+
     /// Check if a valid config file exists at the given path.
     ///
     /// # Arguments
@@ -78,18 +326,42 @@ impl ConfigLoader {
         }
     }
 
+    /// Check that the config file exists, for runs that must not fall back to the defaults.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the config file
+    ///
+    /// # Returns
+    /// `Ok(())` if the file exists, `CliError::ConfigNotFound` otherwise
+    pub fn require(path: &Path) -> CliResult<()> {
+        if Self::exists(path)? {
+            Ok(())
+        } else {
+            Err(CliError::ConfigNotFound {
+                path: path.to_path_buf(),
+            })
+        }
+    }
+
     /// Validate config file by attempting to load it.
     ///
     /// # Arguments
     /// * `path` - Path to the config file
+    /// * `migrations` - Migrations applied if the file is of an older version
+    /// * `validators` - Checks run on the loaded config
     ///
     /// # Returns
     /// `Ok(())` if config is valid, error otherwise
-    pub fn validate<Config>(path: &Path) -> CliResult<()>
+    pub fn validate<Config>(
+        path: &Path,
+        migrations: &ConfigMigrations,
+        validators: &ConfigValidators,
+    ) -> CliResult<()>
     where
         Config: Serialize + DeserializeOwned + Default,
     {
-        Self::load::<Config>(path)?;
+        let config = Self::load_migrated::<Config>(path, &[], migrations)?;
+        validators.check(&config, path)?;
         Ok(())
     }
 
@@ -101,21 +373,18 @@ impl ConfigLoader {
     /// # Returns
     /// `Ok(())` if extension is supported, error otherwise
     pub fn check_extension(path: &Path) -> CliResult<()> {
-        if !CONFIG_EXTENSIONS.matches(path) {
-            return Err(CliError::UnsupportedConfigExtension);
-        }
-        Ok(())
+        Self::format_of(path).map(|_| ())
     }
 
-    /// Deserialize a config from YAML string.
-    ///
-    /// # Arguments
-    /// * `yaml` - YAML string to deserialize
+    /// Get the format of the config file at `path` from its extension.
     ///
     /// # Returns
-    /// The deserialized config or a YAML error
-    fn from_str<Config: DeserializeOwned>(yaml: &str) -> CliResult<Config> {
-        serde_yaml::from_str(yaml).map_err(CliError::from)
+    /// The format, or `CliError::UnsupportedConfigExtension`
+    pub fn format_of(path: &Path) -> CliResult<ConfigFormat> {
+        if !CONFIG_EXTENSIONS.matches(path) {
+            return Err(CliError::UnsupportedConfigExtension);
+        }
+        ConfigFormat::from_path(path).ok_or(CliError::UnsupportedConfigExtension)
     }
 
     /// Load config from a file path.
@@ -126,26 +395,94 @@ impl ConfigLoader {
     /// # Returns
     /// The loaded config or an error
     fn from_file<Config: DeserializeOwned>(config_path: &Path) -> CliResult<Config> {
+        let format = Self::format_of(config_path)?;
+        debug!(
+            "Reading {} config from {}",
+            format.as_str(),
+            config_path.display()
+        );
         let config_content = fs::read_to_string(config_path)?;
-        Self::from_str(&config_content)
+        format
+            .parse(&config_content)
+            .map_err(|err| config_error::locate(err, config_path, &config_content))
     }
 
-    /// Validate config content by deserializing it (private helper).
+    /// Log a warning for each key of the config file `value` that `Config` does not define.
     ///
-    /// # Arguments
-    /// * `path` - Path to the config file
-    ///
-    /// # Returns
-    /// `Ok(())` if config is valid, error otherwise
-    fn validate_config<Config: DeserializeOwned>(path: &Path) -> CliResult<()> {
-        Self::from_file::<Config>(path)?;
+    /// Such keys are otherwise ignored, so a typo would silently leave a
+    /// setting at its default.
+    fn warn_unknown_keys<Config: Serialize + Default>(
+        path: &Path,
+        value: &serde_yaml::Value,
+    ) -> CliResult<()> {
+        for unknown in find_unknown_keys::<Config>(value)? {
+            warn!("{}: {}", path.display(), unknown.message());
+        }
         Ok(())
     }
 }
 
+/// Recursively replace values of `value` by those set in `overlay`.
+///
+/// Mappings are merged key by key; any other value replaces the old one.
+pub(crate) fn merge_over(value: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    match (value.as_mapping_mut(), overlay) {
+        (Some(mapping), serde_yaml::Value::Mapping(overlay_mapping)) => {
+            for (key, overlay_value) in overlay_mapping {
+                match mapping.get_mut(&key) {
+                    Some(existing) => merge_over(existing, overlay_value),
+                    None => {
+                        mapping.insert(key, overlay_value);
+                    }
+                }
+            }
+        }
+        // An empty file sets nothing
+        (_, serde_yaml::Value::Null) => {}
+        (_, overlay) => *value = overlay,
+    }
+}
+
+/// Recursively insert keys of `defaults` that are missing from `value`.
+///
+/// # Arguments
+/// * `value` - The user's config value, updated in place
+/// * `defaults` - The default config value
+/// * `prefix` - Dotted path of `value`, used to report added keys
+/// * `added` - Collects the dotted paths of the added keys
+fn merge_missing(
+    value: &mut serde_yaml::Value,
+    defaults: serde_yaml::Value,
+    prefix: &str,
+    added: &mut Vec<String>,
+) {
+    let (Some(mapping), serde_yaml::Value::Mapping(default_mapping)) =
+        (value.as_mapping_mut(), defaults)
+    else {
+        return;
+    };
+
+    for (key, default) in default_mapping {
+        let name = match key.as_str() {
+            Some(name) if prefix.is_empty() => name.to_string(),
+            Some(name) => format!("{prefix}.{name}"),
+            None => prefix.to_string(),
+        };
+        match mapping.get_mut(&key) {
+            Some(existing) => merge_missing(existing, default, &name, added),
+            None => {
+                added.push(name);
+                mapping.insert(key, default);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli::commands::ConfigMigrator;
+    use crate::core::{ParseErrorPolicy, RuleLevel};
     use rstest::{fixture, rstest};
     use serde::{Deserialize, Serialize};
     use std::fs;
@@ -168,6 +505,20 @@ mod tests {
         }
     }
 
+    fn load<Config>(path: &Path) -> CliResult<Config>
+    where
+        Config: Serialize + DeserializeOwned + Default,
+    {
+        load_with_overrides(path, &[])
+    }
+
+    fn load_with_overrides<Config>(path: &Path, overrides: &[ConfigOverride]) -> CliResult<Config>
+    where
+        Config: Serialize + DeserializeOwned + Default,
+    {
+        ConfigLoader::load_migrated(path, overrides, &ConfigMigrations::new())
+    }
+
     #[fixture]
     fn temp_dir() -> TempDir {
         TempDir::new().expect("Failed to create temp directory")
@@ -184,14 +535,14 @@ mod tests {
         let yaml = serde_yaml::to_string(&expected).unwrap();
         fs::write(&path, yaml).unwrap();
 
-        let loaded: TestConfig = ConfigLoader::load(&path).unwrap();
+        let loaded: TestConfig = load(&path).unwrap();
         assert_eq!(loaded, expected);
     }
 
     #[rstest]
     fn test_load_missing_config_creates_default(temp_dir: TempDir) {
         let path = config_path(&temp_dir, "missing.yaml");
-        let config: TestConfig = ConfigLoader::load(&path).unwrap();
+        let config: TestConfig = load(&path).unwrap();
         assert_eq!(config, TestConfig::default());
     }
 
@@ -200,15 +551,19 @@ mod tests {
         let path = config_path(&temp_dir, "invalid.yaml");
         fs::write(&path, "invalid: yaml: content: [").unwrap();
 
-        let result = ConfigLoader::load::<TestConfig>(&path);
+        let result = load::<TestConfig>(&path);
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), CliError::YamlError { .. }));
+        assert!(matches!(
+            result.unwrap_err(),
+            CliError::InvalidConfigSyntax { line: 1, source, .. }
+                if matches!(*source, CliError::YamlError { .. })
+        ));
     }
 
     #[rstest]
     fn test_create_default_file(temp_dir: TempDir) {
         let path = config_path(&temp_dir, "new_config.yaml");
-        ConfigLoader::create_default_file::<TestConfig>(&path).unwrap();
+        ConfigLoader::create_default_file::<TestConfig>(&path, &ConfigMigrations::new()).unwrap();
 
         assert!(path.exists());
         let content = fs::read_to_string(&path).unwrap();
@@ -216,6 +571,16 @@ mod tests {
         assert_eq!(loaded, TestConfig::default());
     }
 
+    #[rstest]
+    fn test_create_file_writes_given_config(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "preset.yaml");
+        let preset = TestConfig::new("compact", 2, true);
+        ConfigLoader::create_file(&path, &preset).unwrap();
+
+        let loaded: TestConfig = load(&path).unwrap();
+        assert_eq!(loaded, preset);
+    }
+
     #[rstest]
     fn test_create_default_file_creates_parent_dirs(temp_dir: TempDir) {
         let path = temp_dir
@@ -223,7 +588,7 @@ mod tests {
             .join("nested")
             .join("dirs")
             .join("config.yaml");
-        ConfigLoader::create_default_file::<TestConfig>(&path).unwrap();
+        ConfigLoader::create_default_file::<TestConfig>(&path, &ConfigMigrations::new()).unwrap();
 
         assert!(path.exists());
         assert!(path.parent().unwrap().exists());
@@ -262,6 +627,9 @@ mod tests {
     #[case("config.yml")]
     #[case("CONFIG.YAML")]
     #[case("Config.YML")]
+    #[case("config.toml")]
+    #[case("config.json")]
+    #[case("config.json5")]
     fn test_check_extension_valid(#[case] filename: &str) {
         let path = Path::new(filename);
         let result = ConfigLoader::check_extension(path);
@@ -270,9 +638,8 @@ mod tests {
 
     #[rstest]
     #[case("config.txt")]
-    #[case("config.json")]
+    #[case("config.jsonc")]
     #[case("config")]
-    #[case("config.toml")]
     fn test_check_extension_invalid(#[case] filename: &str) {
         let path = Path::new(filename);
         let result = ConfigLoader::check_extension(path);
@@ -290,7 +657,11 @@ mod tests {
         let yaml = serde_yaml::to_string(&config).unwrap();
         fs::write(&path, yaml).unwrap();
 
-        let result = ConfigLoader::validate::<TestConfig>(&path);
+        let result = ConfigLoader::validate::<TestConfig>(
+            &path,
+            &ConfigMigrations::new(),
+            &ConfigValidators::default(),
+        );
         assert!(result.is_ok());
     }
 
@@ -299,10 +670,371 @@ mod tests {
         let path = config_path(&temp_dir, "invalid.yaml");
         fs::write(&path, "name: test\nvalue: not_a_number\n").unwrap();
 
-        let result = ConfigLoader::validate::<TestConfig>(&path);
+        let result = ConfigLoader::validate::<TestConfig>(
+            &path,
+            &ConfigMigrations::new(),
+            &ConfigValidators::default(),
+        );
         assert!(result.is_err());
     }
 
+    #[rstest]
+    fn test_load_runner_config_section(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "runner.yaml");
+        let yaml = "name: test\nvalue: 1\nenabled: true\nrunner:\n  reader:\n    buffer_size: 1024\n  on_parse_error: skip\n  batch_size: 16\n  rules:\n    FMT001: off\n";
+        fs::write(&path, yaml).unwrap();
+
+        let runner = ConfigLoader::load_runner_config(&path, &ConfigLayers::new(), &[]).unwrap();
+        assert_eq!(runner.reader.buffer_size, 1024);
+        assert_eq!(runner.on_parse_error, ParseErrorPolicy::Skip);
+        assert_eq!(runner.batch_size, 16);
+        assert_eq!(runner.rules.get("FMT001"), Some(&RuleLevel::Off));
+        assert_eq!(
+            runner.reader.max_in_memory_size,
+            RunnerConfig::default().reader.max_in_memory_size
+        );
+
+        let config: TestConfig = load(&path).unwrap();
+        assert_eq!(config, TestConfig::new("test", 1, true));
+    }
+
+    #[rstest]
+    fn test_load_layered_merges_project_over_layers(temp_dir: TempDir) {
+        let system = temp_dir.path().join("etc");
+        let user = temp_dir.path().join("home");
+        fs::create_dir_all(system.join("fmt")).unwrap();
+        fs::create_dir_all(user.join("fmt")).unwrap();
+        fs::write(
+            system.join("fmt/config.yml"),
+            "name: system\nvalue: 1\nrunner:\n  batch_size: 8\n",
+        )
+        .unwrap();
+        fs::write(user.join("fmt/config.yml"), "value: 2\nenabled: true\n").unwrap();
+        let project = config_path(&temp_dir, "fmt.yml");
+        fs::write(&project, "value: 3\n").unwrap();
+        let layers = ConfigLayers::from_dirs(Some(&system), Some(&user), "fmt");
+
+        let config: TestConfig =
+            ConfigLoader::load_layered(&project, &layers, &[], &ConfigMigrations::new()).unwrap();
+        assert_eq!(config, TestConfig::new("system", 3, true));
+
+        let runner = ConfigLoader::load_runner_config(&project, &layers, &[]).unwrap();
+        assert_eq!(runner.batch_size, 8);
+
+        let missing = config_path(&temp_dir, "missing.yml");
+        let config: TestConfig =
+            ConfigLoader::load_layered(&missing, &layers, &[], &ConfigMigrations::new()).unwrap();
+        assert_eq!(config, TestConfig::new("system", 2, true));
+    }
+
+    #[rstest]
+    fn test_load_runner_config_defaults(temp_dir: TempDir) {
+        let missing = config_path(&temp_dir, "missing.yaml");
+        assert_eq!(
+            ConfigLoader::load_runner_config(&missing, &ConfigLayers::new(), &[]).unwrap(),
+            RunnerConfig::default()
+        );
+
+        let without_section = config_path(&temp_dir, "plain.yaml");
+        fs::write(&without_section, "name: test\nvalue: 1\nenabled: true\n").unwrap();
+        assert_eq!(
+            ConfigLoader::load_runner_config(&without_section, &ConfigLayers::new(), &[]).unwrap(),
+            RunnerConfig::default()
+        );
+    }
+
+    #[rstest]
+    fn test_load_with_overrides(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "overrides.yaml");
+        fs::write(&path, "name: test\nvalue: 1\nenabled: false\n").unwrap();
+        let overrides = [
+            ConfigOverride::parse("value=7").unwrap(),
+            ConfigOverride::parse("enabled=true").unwrap(),
+            ConfigOverride::parse("runner.reader.buffer_size=1024").unwrap(),
+        ];
+
+        let config: TestConfig = load_with_overrides(&path, &overrides).unwrap();
+        assert_eq!(config, TestConfig::new("test", 7, true));
+
+        let runner =
+            ConfigLoader::load_runner_config(&path, &ConfigLayers::new(), &overrides).unwrap();
+        assert_eq!(runner.reader.buffer_size, 1024);
+
+        let missing = config_path(&temp_dir, "missing.yaml");
+        let config: TestConfig = load_with_overrides(&missing, &overrides).unwrap();
+        assert_eq!(config, TestConfig::new("", 7, true));
+    }
+
+    #[rstest]
+    fn test_load_with_invalid_override(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "overrides.yaml");
+        fs::write(&path, "name: test\nvalue: 1\nenabled: false\n").unwrap();
+        let overrides = [ConfigOverride::parse("value=many").unwrap()];
+
+        let result = load_with_overrides::<TestConfig>(&path, &overrides);
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    fn test_update_file_adds_missing_fields(temp_dir: TempDir) {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct NewConfig {
+            name: String,
+            value: i32,
+            enabled: bool,
+            inner: NewInner,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct NewInner {
+            first: i32,
+            second: i32,
+        }
+
+        impl Default for NewConfig {
+            fn default() -> Self {
+                Self {
+                    name: "default".to_string(),
+                    value: 7,
+                    enabled: true,
+                    inner: NewInner {
+                        first: 1,
+                        second: 2,
+                    },
+                }
+            }
+        }
+
+        let path = config_path(&temp_dir, "old.yaml");
+        fs::write(
+            &path,
+            "value: 42\nname: mine\ninner:\n  first: 10\nrunner:\n  reader:\n    buffer_size: 1024\n",
+        )
+        .unwrap();
+
+        let added =
+            ConfigLoader::update_file::<NewConfig>(&path, &ConfigMigrations::new()).unwrap();
+        assert_eq!(added, vec!["enabled", "inner.second"]);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("value: 42\nname: mine\n"));
+        assert!(content.contains("runner:"));
+
+        let loaded: NewConfig = load(&path).unwrap();
+        assert_eq!(loaded.name, "mine");
+        assert_eq!(loaded.value, 42);
+        assert!(loaded.enabled);
+        assert_eq!(
+            loaded.inner,
+            NewInner {
+                first: 10,
+                second: 2
+            }
+        );
+
+        assert!(
+            ConfigLoader::update_file::<NewConfig>(&path, &ConfigMigrations::new())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[rstest]
+    fn test_update_file_rejects_invalid_values(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "invalid.yaml");
+        fs::write(&path, "name: test\nvalue: not_a_number\n").unwrap();
+
+        let result = ConfigLoader::update_file::<TestConfig>(&path, &ConfigMigrations::new());
+        assert!(matches!(result, Err(CliError::YamlError { .. })));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "name: test\nvalue: not_a_number\n"
+        );
+    }
+
+    #[rstest]
+    fn test_toml_config(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "config.toml");
+        ConfigLoader::create_file(&path, &TestConfig::new("toml", 3, true)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "name = \"toml\"\nvalue = 3\nenabled = true\n"
+        );
+
+        fs::write(
+            &path,
+            "name = \"toml\"\nvalue = 3\nenabled = true\n\n[runner]\nbatch_size = 16\n",
+        )
+        .unwrap();
+        let overrides = [ConfigOverride::parse("value=4").unwrap()];
+        let config: TestConfig = load_with_overrides(&path, &overrides).unwrap();
+        assert_eq!(config, TestConfig::new("toml", 4, true));
+        let runner = ConfigLoader::load_runner_config(&path, &ConfigLayers::new(), &[]).unwrap();
+        assert_eq!(runner.batch_size, 16);
+
+        fs::write(&path, "name = \"toml\"\nvalue = \"three\"\n").unwrap();
+        assert!(matches!(
+            load::<TestConfig>(&path),
+            Err(CliError::InvalidConfigSyntax { line: 2, source, .. })
+                if matches!(*source, CliError::TomlError { .. })
+        ));
+    }
+
+    #[rstest]
+    #[case("config.json")]
+    #[case("config.json5")]
+    fn test_json_config(temp_dir: TempDir, #[case] filename: &str) {
+        let path = config_path(&temp_dir, filename);
+        ConfigLoader::create_file(&path, &TestConfig::new("json", 3, true)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\n  \"name\": \"json\",\n  \"value\": 3,\n  \"enabled\": true\n}\n"
+        );
+
+        let overrides = [ConfigOverride::parse("value=4").unwrap()];
+        let config: TestConfig = load_with_overrides(&path, &overrides).unwrap();
+        assert_eq!(config, TestConfig::new("json", 4, true));
+
+        fs::write(&path, "{\"name\": \"json\", \"value\": \"three\"}").unwrap();
+        assert!(matches!(
+            load::<TestConfig>(&path),
+            Err(CliError::InvalidConfigSyntax { line: 1, source, .. })
+                if matches!(*source, CliError::JsonParseError { .. })
+        ));
+    }
+
+    #[rstest]
+    fn test_json5_config(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "config.json5");
+        fs::write(
+            &path,
+            "// Shared settings\n{\n  name: 'five',\n  value: 0x10,\n  enabled: true,\n}\n",
+        )
+        .unwrap();
+
+        let config: TestConfig = load(&path).unwrap();
+        assert_eq!(config, TestConfig::new("five", 16, true));
+    }
+
+    #[rstest]
+    fn test_load_nested(temp_dir: TempDir) {
+        let outer = config_path(&temp_dir, "outer.yml");
+        let inner = config_path(&temp_dir, "inner.toml");
+        fs::write(&outer, "value: 7\n").unwrap();
+        fs::write(&inner, "enabled = true\nvalue = 8\n").unwrap();
+        let base = TestConfig::new("root", 1, false);
+
+        let config: TestConfig = ConfigLoader::load_nested(
+            &base,
+            &[outer.clone(), inner.clone()],
+            &[],
+            &ConfigMigrations::new(),
+        )
+        .unwrap();
+        assert_eq!(config, TestConfig::new("root", 8, true));
+
+        let overrides = [ConfigOverride::parse("value=9").unwrap()];
+        let config: TestConfig =
+            ConfigLoader::load_nested(&base, &[outer], &overrides, &ConfigMigrations::new())
+                .unwrap();
+        assert_eq!(config, TestConfig::new("root", 9, false));
+    }
+
+    #[test]
+    fn test_merge_over_keeps_unset_keys() {
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str("style:\n  tabs: true\n  width: 80\nlist: [1, 2]\n").unwrap();
+        let overlay = serde_yaml::from_str("style:\n  width: 100\nlist: [3]\n").unwrap();
+
+        merge_over(&mut value, overlay);
+
+        let expected: serde_yaml::Value =
+            serde_yaml::from_str("style:\n  tabs: true\n  width: 100\nlist: [3]\n").unwrap();
+        assert_eq!(value, expected);
+    }
+
+    /// Renames the `count` field of version 0 configs to `value`.
+    struct RenameCount;
+
+    impl ConfigMigrator for RenameCount {
+        fn version(&self) -> u64 {
+            1
+        }
+
+        fn migrate(&self, config: &mut serde_yaml::Value) -> Result<(), String> {
+            let mapping = config.as_mapping_mut().ok_or("not a mapping")?;
+            if let Some(count) = mapping.remove("count") {
+                mapping.insert("value".into(), count);
+            }
+            Ok(())
+        }
+    }
+
+    fn rename_count() -> ConfigMigrations {
+        let mut migrations = ConfigMigrations::new();
+        migrations.add(RenameCount);
+        migrations
+    }
+
+    #[rstest]
+    fn test_require(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "fmt.yml");
+        assert!(matches!(
+            ConfigLoader::require(&path),
+            Err(CliError::ConfigNotFound { .. })
+        ));
+
+        fs::write(&path, "name: test\n").unwrap();
+        assert!(ConfigLoader::require(&path).is_ok());
+    }
+
+    #[rstest]
+    fn test_load_and_update_migrated_file(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "old.yml");
+        let content = "name: old\ncount: 5\nenabled: true\n";
+        fs::write(&path, content).unwrap();
+
+        let config: TestConfig = ConfigLoader::load_migrated(&path, &[], &rename_count()).unwrap();
+        assert_eq!(config, TestConfig::new("old", 5, true));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        let added = ConfigLoader::update_file::<TestConfig>(&path, &rename_count()).unwrap();
+        assert!(added.is_empty());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "version: 1\nname: old\nenabled: true\nvalue: 5\n"
+        );
+    }
+
+    #[rstest]
+    fn test_create_default_file_at_latest_version(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "new.yml");
+
+        ConfigLoader::create_default_file::<TestConfig>(&path, &rename_count()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "version: 1\nname: ''\nvalue: 0\nenabled: false\n"
+        );
+        let config: TestConfig = ConfigLoader::load_migrated(&path, &[], &rename_count()).unwrap();
+        assert_eq!(config, TestConfig::default());
+    }
+
+    #[rstest]
+    fn test_update_toml_file(temp_dir: TempDir) {
+        let path = config_path(&temp_dir, "old.toml");
+        fs::write(&path, "value = 42\nname = \"mine\"\n").unwrap();
+
+        let added =
+            ConfigLoader::update_file::<TestConfig>(&path, &ConfigMigrations::new()).unwrap();
+
+        assert_eq!(added, vec!["enabled"]);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "value = 42\nname = \"mine\"\nenabled = false\n"
+        );
+    }
+
     #[rstest]
     fn test_load_with_nested_structure(temp_dir: TempDir) {
         #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -320,7 +1052,7 @@ mod tests {
         let yaml = "outer: test\ninner:\n  field: 42\n";
         fs::write(&path, yaml).unwrap();
 
-        let loaded: NestedConfig = ConfigLoader::load(&path).unwrap();
+        let loaded: NestedConfig = load(&path).unwrap();
         assert_eq!(loaded.outer, "test");
         assert_eq!(loaded.inner.field, 42);
     }
@@ -0,0 +1,239 @@
+use crate::cli::error::{CliError, CliResult};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::sync::Arc;
+
+/// Top-level key holding the schema version of a config file.
+///
+/// Files without it are version 0, i.e. older than every migration.
+pub const VERSION_KEY: &str = "version";
+
+/// Upgrades config files written for an older schema of the formatter's config.
+///
+/// Register implementations with `CliBuilder::add_migration`. When a config
+/// file's `version` is older than a migration's, the migration is applied to
+/// the raw file content before it is deserialized; `init --update` writes the
+/// result back.
+///
+/// # Examples
+/// ```ignore
+/// struct RenameIndent;
+///
+/// impl ConfigMigrator for RenameIndent {
+///     fn version(&self) -> u64 {
+///         1
+///     }
+///
+///     fn migrate(&self, config: &mut serde_yaml::Value) -> Result<(), String> {
+///         if let Some(mapping) = config.as_mapping_mut() {
+///             if let Some(indent) = mapping.remove("indent") {
+///                 mapping.insert("indent_width".into(), indent);
+///             }
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait ConfigMigrator: Send + Sync {
+    /// Version of the schema this migration upgrades configs to.
+    fn version(&self) -> u64;
+
+    /// Rewrite a config of the previous version in place.
+    ///
+    /// # Returns
+    /// `Ok(())`, or a description of why the config cannot be migrated
+    fn migrate(&self, config: &mut Value) -> Result<(), String>;
+}
+
+/// The migrations registered by the formatter binary, ordered by version.
+#[derive(Clone, Default)]
+pub struct ConfigMigrations {
+    migrators: Vec<Arc<dyn ConfigMigrator>>,
+}
+
+impl fmt::Debug for ConfigMigrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let versions: Vec<u64> = self.migrators.iter().map(|m| m.version()).collect();
+        f.debug_struct("ConfigMigrations")
+            .field("versions", &versions)
+            .finish()
+    }
+}
+
+impl ConfigMigrations {
+    /// Create an empty set of migrations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a migration; migrations to the same version run in registration order.
+    pub fn add<M: ConfigMigrator + 'static>(&mut self, migrator: M) {
+        let index = self
+            .migrators
+            .partition_point(|existing| existing.version() <= migrator.version());
+        self.migrators.insert(index, Arc::new(migrator));
+    }
+
+    /// Check whether no migrations are registered.
+    pub fn is_empty(&self) -> bool {
+        self.migrators.is_empty()
+    }
+
+    /// Get the latest schema version, 0 without migrations.
+    pub fn latest(&self) -> u64 {
+        self.migrators.last().map_or(0, |m| m.version())
+    }
+
+    /// Get the schema version of a config file `value`.
+    ///
+    /// # Returns
+    /// The version, or `CliError::InvalidConfigVersion` if it is not a non-negative integer
+    pub fn version_of(value: &Value) -> CliResult<u64> {
+        match value.get(VERSION_KEY) {
+            None | Some(Value::Null) => Ok(0),
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| CliError::InvalidConfigVersion {
+                    version: serde_yaml::to_string(version)
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string(),
+                }),
+        }
+    }
+
+    /// Apply the migrations newer than the version of `value`, then set its version to the latest.
+    ///
+    /// Without migrations, `value` is left alone and its `version` key is not
+    /// read, so a config may use that key for its own purposes.
+    ///
+    /// # Returns
+    /// The version `value` had before, or an error if it is newer than the
+    /// latest version or a migration fails
+    pub fn migrate(&self, value: &mut Value) -> CliResult<u64> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+        let version = Self::version_of(value)?;
+        let latest = self.latest();
+        if version > latest {
+            return Err(CliError::UnsupportedConfigVersion { version, latest });
+        }
+
+        for migrator in &self.migrators {
+            if migrator.version() > version {
+                migrator
+                    .migrate(value)
+                    .map_err(|message| CliError::ConfigMigrationFailed {
+                        version: migrator.version(),
+                        message,
+                    })?;
+            }
+        }
+        if version < latest {
+            self.stamp(value);
+        }
+        Ok(version)
+    }
+
+    /// Set the version of a config `value` to the latest, as the first key.
+    ///
+    /// Nothing is written without migrations, so formatters that never
+    /// register one keep their config files unchanged.
+    pub fn stamp(&self, value: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        let Value::Mapping(mapping) = value else {
+            return;
+        };
+        let mut stamped = Mapping::new();
+        stamped.insert(Value::from(VERSION_KEY), Value::from(self.latest()));
+        for (key, child) in std::mem::take(mapping) {
+            if key.as_str() != Some(VERSION_KEY) {
+                stamped.insert(key, child);
+            }
+        }
+        *mapping = stamped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Renames a top-level key.
+    struct Rename {
+        version: u64,
+        from: &'static str,
+        to: &'static str,
+    }
+
+    impl ConfigMigrator for Rename {
+        fn version(&self) -> u64 {
+            self.version
+        }
+
+        fn migrate(&self, config: &mut Value) -> Result<(), String> {
+            let mapping = config.as_mapping_mut().ok_or("config is not a mapping")?;
+            if let Some(value) = mapping.remove(self.from) {
+                mapping.insert(Value::from(self.to), value);
+            }
+            Ok(())
+        }
+    }
+
+    fn migrations() -> ConfigMigrations {
+        let mut migrations = ConfigMigrations::new();
+        migrations.add(Rename {
+            version: 2,
+            from: "indent_width",
+            to: "indent_size",
+        });
+        migrations.add(Rename {
+            version: 1,
+            from: "indent",
+            to: "indent_width",
+        });
+        migrations
+    }
+
+    #[rstest]
+    #[case("indent: 4\n", 0, "version: 2\nindent_size: 4\n")]
+    #[case("version: 1\nindent_width: 4\n", 1, "version: 2\nindent_size: 4\n")]
+    #[case("version: 2\nindent_size: 4\n", 2, "version: 2\nindent_size: 4\n")]
+    fn test_migrate(#[case] content: &str, #[case] from: u64, #[case] expected: &str) {
+        let mut value: Value = serde_yaml::from_str(content).unwrap();
+
+        assert_eq!(migrations().migrate(&mut value).unwrap(), from);
+        assert_eq!(serde_yaml::to_string(&value).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        "version: 3\n",
+        "Config version 3 is newer than the latest supported version 2"
+    )]
+    #[case("version: two\n", "Config version 'two' is not a non-negative integer")]
+    #[case(
+        "[1, 2]\n",
+        "Config migration to version 1 failed: config is not a mapping"
+    )]
+    fn test_migrate_errors(#[case] content: &str, #[case] message: &str) {
+        let mut value: Value = serde_yaml::from_str(content).unwrap();
+
+        let err = migrations().migrate(&mut value).unwrap_err();
+
+        assert!(err.to_string().starts_with(message), "{err}");
+    }
+
+    #[test]
+    fn test_stamp_without_migrations_keeps_config() {
+        let mut value: Value = serde_yaml::from_str("indent: 4\n").unwrap();
+
+        ConfigMigrations::new().stamp(&mut value);
+
+        assert_eq!(serde_yaml::to_string(&value).unwrap(), "indent: 4\n");
+    }
+}
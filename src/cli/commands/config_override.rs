@@ -0,0 +1,95 @@
+use serde_yaml::{Mapping, Value};
+
+/// A `--set dotted.path=value` override applied on top of the loaded config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    /// Keys leading from the root mapping to the overridden field
+    keys: Vec<String>,
+    /// The new value, parsed as YAML
+    value: Value,
+}
+
+impl ConfigOverride {
+    /// Parse an override from `dotted.path=value`.
+    ///
+    /// The value is parsed as YAML, so `true`, `4` and `[a, b]` keep their types.
+    ///
+    /// # Returns
+    /// `Some(ConfigOverride)` if the text has a non-empty key, `None` otherwise
+    pub fn parse(text: &str) -> Option<Self> {
+        let (key, value) = text.split_once('=')?;
+        let keys: Vec<String> = key.trim().split('.').map(str::to_string).collect();
+        if keys.iter().any(String::is_empty) {
+            return None;
+        }
+
+        let value = serde_yaml::from_str(value.trim()).ok()?;
+        Some(Self { keys, value })
+    }
+
+    /// Dotted path of the overridden field.
+    pub fn key(&self) -> String {
+        self.keys.join(".")
+    }
+
+    /// Replace the field in `config`, creating intermediate mappings as needed.
+    pub fn apply(&self, config: &mut Value) {
+        let mut current = config;
+        for key in &self.keys {
+            if !current.is_mapping() {
+                *current = Value::Mapping(Mapping::new());
+            }
+            let Value::Mapping(mapping) = current else {
+                return;
+            };
+            current = mapping
+                .entry(Value::from(key.as_str()))
+                .or_insert(Value::Null);
+        }
+        *current = self.value.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("indent=4", "indent", Value::from(4))]
+    #[case("style.tabs=true", "style.tabs", Value::from(true))]
+    #[case("quote = single", "quote", Value::from("single"))]
+    #[case("empty=", "empty", Value::Null)]
+    fn test_parse(#[case] text: &str, #[case] key: &str, #[case] value: Value) {
+        let parsed = ConfigOverride::parse(text).unwrap();
+        assert_eq!(parsed.key(), key);
+        assert_eq!(parsed.value, value);
+    }
+
+    #[rstest]
+    #[case("indent")]
+    #[case("=4")]
+    #[case("style..tabs=true")]
+    #[case("tags=[a")]
+    fn test_parse_invalid(#[case] text: &str) {
+        assert_eq!(ConfigOverride::parse(text), None);
+    }
+
+    #[test]
+    fn test_apply_replaces_nested_field() {
+        let mut config: Value = serde_yaml::from_str("width: 80\nstyle:\n  tabs: false\n").unwrap();
+
+        ConfigOverride::parse("style.tabs=true")
+            .unwrap()
+            .apply(&mut config);
+        ConfigOverride::parse("runner.reader.buffer_size=1024")
+            .unwrap()
+            .apply(&mut config);
+
+        let expected: Value = serde_yaml::from_str(
+            "width: 80\nstyle:\n  tabs: true\nrunner:\n  reader:\n    buffer_size: 1024\n",
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+    }
+}
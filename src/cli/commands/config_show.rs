@@ -0,0 +1,136 @@
+use crate::cli::commands::config_layers::{ConfigLayers, ConfigOrigin};
+use crate::cli::commands::config_loader::merge_over;
+use crate::cli::commands::{ConfigLoader, ConfigMigrations};
+use crate::cli::error::CliResult;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::Value;
+use std::path::Path;
+
+/// Execute the config show command: print the effective config.
+///
+/// The effective config merges the project config over the user and system
+/// configs and the defaults. It is printed as YAML, or with `origin` as one
+/// `key: value  # origin` line per field naming the source of the value.
+///
+/// # Arguments
+/// * `config_path` - Path to the project config file; the defaults are used if it does not exist
+/// * `layers` - System and user configs under the project config
+/// * `migrations` - Migrations applied to files of older versions
+/// * `origin` - Print where each value comes from
+///
+/// # Returns
+/// `Ok(())` on success, or an error if a config cannot be loaded
+pub fn execute<Config>(
+    config_path: &Path,
+    layers: &ConfigLayers,
+    migrations: &ConfigMigrations,
+    origin: bool,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    let values = ConfigLoader::layered_values::<Config>(config_path, layers, migrations)?;
+    let mut effective = Value::Null;
+    for (_, value) in &values {
+        merge_over(&mut effective, value.clone());
+    }
+    // Fail on values the config type rejects, like other commands would
+    serde_yaml::from_value::<Config>(effective.clone())?;
+
+    if origin {
+        for line in origin_lines(&effective, &values)? {
+            println!("{line}");
+        }
+    } else {
+        print!("{}", serde_yaml::to_string(&effective)?);
+    }
+    Ok(())
+}
+
+/// Render every field of `effective` with the last source in `values` setting it.
+fn origin_lines(effective: &Value, values: &[(ConfigOrigin, Value)]) -> CliResult<Vec<String>> {
+    let mut fields = Vec::new();
+    collect_fields(effective, &mut Vec::new(), &mut fields);
+
+    fields
+        .into_iter()
+        .map(|(keys, value)| {
+            let origin = values
+                .iter()
+                .rev()
+                .find(|(_, source)| lookup(source, &keys).is_some())
+                .map_or(&ConfigOrigin::Default, |(origin, _)| origin);
+            Ok(format!(
+                "{}: {}  # {origin}",
+                keys.join("."),
+                serde_json::to_string(value)?
+            ))
+        })
+        .collect()
+}
+
+/// Collect the dotted keys of the fields of `value` that are not mappings, in order.
+fn collect_fields<'a>(
+    value: &'a Value,
+    prefix: &mut Vec<String>,
+    fields: &mut Vec<(Vec<String>, &'a Value)>,
+) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (key, child) in mapping {
+                let key = key.as_str().map_or_else(
+                    || serde_json::to_string(key).unwrap_or_default(),
+                    str::to_string,
+                );
+                prefix.push(key);
+                collect_fields(child, prefix, fields);
+                prefix.pop();
+            }
+        }
+        _ => fields.push((prefix.clone(), value)),
+    }
+}
+
+/// Get the value at `keys` in `value`, if it is set.
+fn lookup<'a>(value: &'a Value, keys: &[String]) -> Option<&'a Value> {
+    keys.iter()
+        .try_fold(value, |current, key| current.get(key.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_origin_lines_name_last_source() {
+        let user = PathBuf::from("/home/me/.config/jfmt/config.yml");
+        let project = PathBuf::from("jfmt.yml");
+        let values: Vec<(ConfigOrigin, Value)> = vec![
+            (
+                ConfigOrigin::Default,
+                serde_yaml::from_str("width: 80\nstyle:\n  tabs: false\n").unwrap(),
+            ),
+            (
+                ConfigOrigin::User(user),
+                serde_yaml::from_str("width: 100\nstyle:\n  tabs: true\n").unwrap(),
+            ),
+            (
+                ConfigOrigin::Project(project),
+                serde_yaml::from_str("width: 120\n").unwrap(),
+            ),
+        ];
+        let mut effective = Value::Null;
+        for (_, value) in &values {
+            merge_over(&mut effective, value.clone());
+        }
+
+        assert_eq!(
+            origin_lines(&effective, &values).unwrap(),
+            [
+                "width: 120  # project jfmt.yml",
+                "style.tabs: true  # user /home/me/.config/jfmt/config.yml",
+            ]
+        );
+    }
+}
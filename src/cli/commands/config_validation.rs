@@ -0,0 +1,193 @@
+use crate::cli::error::{CliError, CliResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A violated invariant of a deserialized config, reported by a config validator.
+///
+/// # Examples
+/// ```ignore
+/// fn check(config: &MyConfig) -> Result<(), Vec<ConfigError>> {
+///     if config.indent_size == 0 {
+///         return Err(vec![ConfigError::for_key("indent_size", "must be greater than 0")]);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    key: Option<String>,
+    message: String,
+}
+
+impl ConfigError {
+    /// Create an error about the config as a whole, e.g. mutually exclusive options.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            key: None,
+            message: message.into(),
+        }
+    }
+
+    /// Create an error about the field at the dotted path `key`.
+    pub fn for_key(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: Some(key.into()),
+            message: message.into(),
+        }
+    }
+
+    /// Get the dotted path of the offending field, if the error is about one.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Get the description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key {
+            Some(key) => write!(f, "{key}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Checks a config, given as its serialized value.
+type Validator = dyn Fn(&Value) -> Result<(), Vec<ConfigError>> + Send + Sync;
+
+/// The config validators registered by the formatter binary.
+///
+/// Validators run on the final config, after migrations, nested configs and
+/// `--set` overrides are applied.
+#[derive(Clone, Default)]
+pub struct ConfigValidators {
+    validators: Vec<Arc<Validator>>,
+}
+
+impl fmt::Debug for ConfigValidators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigValidators")
+            .field("count", &self.validators.len())
+            .finish()
+    }
+}
+
+impl ConfigValidators {
+    /// Register a validator of `Config`.
+    pub fn add<Config, F>(&mut self, validator: F)
+    where
+        Config: DeserializeOwned,
+        F: Fn(&Config) -> Result<(), Vec<ConfigError>> + Send + Sync + 'static,
+    {
+        self.validators.push(Arc::new(move |value: &Value| {
+            let config: Config = serde_yaml::from_value(value.clone())
+                .map_err(|err| vec![ConfigError::new(err.to_string())])?;
+            validator(&config)
+        }));
+    }
+
+    /// Run every validator on `config`.
+    ///
+    /// # Returns
+    /// The errors of all validators, in registration order
+    pub fn errors<Config: Serialize>(&self, config: &Config) -> CliResult<Vec<ConfigError>> {
+        if self.validators.is_empty() {
+            return Ok(Vec::new());
+        }
+        let value = serde_yaml::to_value(config)?;
+        Ok(self
+            .validators
+            .iter()
+            .filter_map(|validator| validator(&value).err())
+            .flatten()
+            .collect())
+    }
+
+    /// Run every validator on the config loaded from `path`.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `CliError::ConfigRejected` with the errors of all validators
+    pub fn check<Config: Serialize>(&self, config: &Config, path: &Path) -> CliResult<()> {
+        let errors = self.errors(config)?;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CliError::ConfigRejected {
+                path: path.to_path_buf(),
+                errors,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct TestConfig {
+        indent_size: usize,
+        tabs: bool,
+        spaces: bool,
+    }
+
+    fn validators() -> ConfigValidators {
+        let mut validators = ConfigValidators::default();
+        validators.add(|config: &TestConfig| {
+            if config.indent_size == 0 {
+                return Err(vec![ConfigError::for_key(
+                    "indent_size",
+                    "must be greater than 0",
+                )]);
+            }
+            Ok(())
+        });
+        validators.add(|config: &TestConfig| {
+            if config.tabs && config.spaces {
+                return Err(vec![ConfigError::new("tabs and spaces cannot both be set")]);
+            }
+            Ok(())
+        });
+        validators
+    }
+
+    #[test]
+    fn test_check_collects_all_errors() {
+        let config = TestConfig {
+            indent_size: 0,
+            tabs: true,
+            spaces: true,
+        };
+
+        let err = validators()
+            .check(&config, Path::new("fmt.yml"))
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Config file 'fmt.yml' is invalid: indent_size: must be greater than 0; tabs and spaces cannot both be set"
+        );
+    }
+
+    #[test]
+    fn test_check_accepts_valid_config() {
+        let config = TestConfig {
+            indent_size: 2,
+            ..TestConfig::default()
+        };
+
+        assert!(validators().check(&config, Path::new("fmt.yml")).is_ok());
+        assert!(ConfigValidators::default()
+            .check(&TestConfig::default(), Path::new("fmt.yml"))
+            .is_ok());
+    }
+}
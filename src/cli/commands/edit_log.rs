@@ -0,0 +1,89 @@
+use crate::cli::error::CliResult;
+use crate::core::TracedEdit;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Destination meaning stderr for `--trace-edits`.
+pub const STDERR_TRACE: &str = "-";
+
+/// Maximum number of characters shown of the text before and after an edit.
+const SNIPPET_CHARS: usize = 60;
+
+/// Write traced edits, one per line, to stderr or to the file at `destination`.
+///
+/// # Arguments
+/// * `edits` - Edits recorded by the engine, indexed like `files`
+/// * `files` - Paths of the formatted files
+/// * `destination` - Trace file path, or `-` for stderr
+pub fn write_edit_trace(
+    edits: &[TracedEdit],
+    files: &[PathBuf],
+    destination: &Path,
+) -> CliResult<()> {
+    if destination == Path::new(STDERR_TRACE) {
+        write_lines(edits, files, &mut io::stderr().lock())
+    } else {
+        let mut writer = BufWriter::new(File::create(destination)?);
+        write_lines(edits, files, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Write one line per edit: path, pass, byte range, and the replaced and inserted text.
+fn write_lines(edits: &[TracedEdit], files: &[PathBuf], out: &mut impl Write) -> CliResult<()> {
+    for edit in edits {
+        let path = files
+            .get(edit.file)
+            .map_or_else(|| format!("#{}", edit.file), |p| p.display().to_string());
+        writeln!(
+            out,
+            "{path}: {} {}..{} {:?} -> {:?}",
+            edit.pass,
+            edit.range.0,
+            edit.range.1,
+            snippet(&edit.before),
+            snippet(&edit.after)
+        )?;
+    }
+    Ok(())
+}
+
+/// Shorten `text` to `SNIPPET_CHARS` characters, marking the cut with `...`.
+fn snippet(text: &str) -> String {
+    match text.char_indices().nth(SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_lines() {
+        let edits = vec![TracedEdit {
+            file: 0,
+            pass: "ColonPass",
+            range: (4, 6),
+            before: " :".to_string(),
+            after: ":\n ".to_string(),
+        }];
+        let mut out = Vec::new();
+        write_lines(&edits, &[PathBuf::from("x.json")], &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "x.json: ColonPass 4..6 \" :\" -> \":\\n \"\n"
+        );
+    }
+
+    #[test]
+    fn test_snippet_is_shortened() {
+        let long = "é".repeat(SNIPPET_CHARS + 5);
+        assert_eq!(snippet(&long), format!("{}...", "é".repeat(SNIPPET_CHARS)));
+        assert_eq!(snippet("short"), "short");
+    }
+}
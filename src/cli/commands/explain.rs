@@ -0,0 +1,49 @@
+use crate::cli::error::{CliError, CliResult};
+use crate::pipeline::Pipeline;
+
+/// Print the description of the rule with `code`.
+///
+/// # Arguments
+/// * `pipeline` - The pipeline whose passes register the rules
+/// * `code` - Code of the rule, e.g. `FMT001`
+///
+/// # Returns
+/// `Ok(())` if a pass registers the rule, `CliError::UnknownRule` otherwise
+pub fn execute<Config>(pipeline: &Pipeline<Config>, code: &str) -> CliResult<()> {
+    let (pass, rule) = pipeline.rule(code).ok_or_else(|| CliError::UnknownRule {
+        code: code.to_string(),
+    })?;
+    println!("{}", explanation(pass, rule.code, rule.description));
+    Ok(())
+}
+
+/// Build the text printed for a rule.
+fn explanation(pass: &str, code: &str, description: &str) -> String {
+    format!("{code} ({pass}): {description}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explanation() {
+        assert_eq!(
+            explanation(
+                "WidthPass",
+                "FMT001",
+                "Lines must not exceed the maximum line length."
+            ),
+            "FMT001 (WidthPass): Lines must not exceed the maximum line length."
+        );
+    }
+
+    #[test]
+    fn test_unknown_rule() {
+        let pipeline = Pipeline::<()>::new();
+        assert!(matches!(
+            execute(&pipeline, "FMT999"),
+            Err(CliError::UnknownRule { code }) if code == "FMT999"
+        ));
+    }
+}
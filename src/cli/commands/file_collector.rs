@@ -1,16 +1,60 @@
+use crate::cli::commands::{CollectorConfig, SymlinkPolicy, WorkspaceRoot};
+use crate::cli::error::CliResult;
+use crate::core::{FileError, LanguageRegistry};
 use crate::parser::LanguageProvider;
-use crate::supported_extension::SupportedExtension;
+use crate::supported_extension::matches_pattern;
+use log::{debug, warn};
 use std::collections::HashSet;
 use std::fs;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
+/// Manifest path that stands for standard input.
+const STDIN_MANIFEST: &str = "-";
+
 /// File collector responsible for gathering supported files from the filesystem.
-pub struct FileCollector;
+pub struct FileCollector {
+    /// Sort collected files by path (default: true)
+    sort: bool,
+    /// Whether traversal follows symlinks (default: follow)
+    symlinks: SymlinkPolicy,
+    /// Glob patterns of files collected on top of the supported ones
+    associations: Vec<String>,
+    /// Project root that association patterns with a `/` are matched from
+    root: Option<WorkspaceRoot>,
+}
+
+impl Default for FileCollector {
+    fn default() -> Self {
+        Self::from_config(&CollectorConfig::default())
+    }
+}
 
 impl FileCollector {
-    /// Collect unique supported files from multiple paths.
+    /// Create a collector using the settings from the runner config.
     ///
-    /// This function deduplicates files and returns them in sorted order.
+    /// # Arguments
+    /// * `config` - Collector section of the runner config
+    pub fn from_config(config: &CollectorConfig) -> Self {
+        Self {
+            sort: config.sort,
+            symlinks: config.symlinks,
+            associations: config.associations.clone(),
+            root: None,
+        }
+    }
+
+    /// Match association patterns containing a `/` against paths relative to `root`.
+    ///
+    /// Such patterns then match the same files whichever subdirectory of
+    /// the project the run starts from.
+    #[must_use]
+    pub fn with_root(mut self, root: WorkspaceRoot) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    /// Collect unique supported files from multiple paths with the default settings.
     ///
     /// # Arguments
     /// * `paths` - Array of paths to search
@@ -18,52 +62,246 @@ impl FileCollector {
     /// # Returns
     /// Sorted vector of unique file paths
     pub fn collect_all<Language: LanguageProvider>(paths: &[PathBuf]) -> Vec<PathBuf> {
+        Self::default().collect::<Language>(paths)
+    }
+
+    /// Collect unique supported files from multiple paths.
+    ///
+    /// Files are sorted by path, comparing components byte-wise regardless
+    /// of locale, unless sorting is disabled; then they are returned in
+    /// traversal order, which depends on the filesystem.
+    ///
+    /// Files reached through several paths (such as a symlink and its
+    /// target) are collected once, under the first path in that order. Every
+    /// directory is traversed at most once, so symlink cycles end.
+    ///
+    /// # Arguments
+    /// * `paths` - Array of paths to search
+    ///
+    /// # Returns
+    /// Vector of unique file paths
+    pub fn collect<Language: LanguageProvider>(&self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let (files, errors) = self.collect_with_errors::<Language>(paths);
+        for error in &errors {
+            warn!(
+                "Could not traverse {}: {}",
+                error.path.display(),
+                error.message
+            );
+        }
+        files
+    }
+
+    /// Collect unique supported files from multiple paths, keeping traversal errors.
+    ///
+    /// Works like `collect`, but paths that cannot be inspected, directories
+    /// that cannot be listed and entries that cannot be read are returned
+    /// with their error instead of being logged. Traversal continues past
+    /// every error.
+    ///
+    /// # Arguments
+    /// * `paths` - Array of paths to search
+    ///
+    /// # Returns
+    /// The unique file paths and the errors met during traversal, in traversal order
+    pub fn collect_with_errors<Language: LanguageProvider>(
+        &self,
+        paths: &[PathBuf],
+    ) -> (Vec<PathBuf>, Vec<FileError>) {
+        self.collect_matching(paths, &|path| self.supports::<Language>(path))
+    }
+
+    /// Collect unique files of any language of `registry` from multiple paths.
+    ///
+    /// Works like `collect_with_errors`, for binaries formatting several
+    /// languages. Associations are ignored, since they do not say which
+    /// language formats the files.
+    ///
+    /// # Arguments
+    /// * `registry` - Languages whose files are collected
+    /// * `paths` - Array of paths to search
+    ///
+    /// # Returns
+    /// The unique file paths and the errors met during traversal, in traversal order
+    pub fn collect_registered<Config>(
+        &self,
+        registry: &LanguageRegistry<Config>,
+        paths: &[PathBuf],
+    ) -> (Vec<PathBuf>, Vec<FileError>) {
+        self.collect_matching(paths, &|path| registry.matches(path))
+    }
+
+    /// Collect unique files for which `supported` returns true.
+    fn collect_matching(
+        &self,
+        paths: &[PathBuf],
+        supported: &dyn Fn(&Path) -> bool,
+    ) -> (Vec<PathBuf>, Vec<FileError>) {
+        let mut visited = HashSet::new();
+        let mut errors = Vec::new();
+        let mut files_vec: Vec<PathBuf> = paths
+            .iter()
+            .flat_map(|path| self.collect_from_path(path, supported, &mut visited, &mut errors))
+            .collect();
+        if self.sort {
+            files_vec.sort();
+        }
+
         let mut files_set = HashSet::new();
-        let mut files_vec = Vec::new();
+        files_vec.retain(|file| {
+            let identity = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+            files_set.insert(identity)
+        });
+        (files_vec, errors)
+    }
 
-        for path in paths {
-            for file in Self::collect_from_path::<Language>(path) {
-                if files_set.insert(file.clone()) {
-                    files_vec.push(file);
-                }
-            }
+    /// Collect unique supported files listed in a manifest, without traversal.
+    ///
+    /// Listed paths that are not existing files (deleted files, directories)
+    /// or are not supported are ignored.
+    ///
+    /// # Arguments
+    /// * `manifest` - Path of the manifest file, or `-` for standard input
+    /// * `nul_delimited` - Paths are separated by NUL instead of newlines
+    ///
+    /// # Returns
+    /// Unique supported file paths in manifest order
+    pub fn collect_listed<Language: LanguageProvider>(
+        &self,
+        manifest: &Path,
+        nul_delimited: bool,
+    ) -> CliResult<Vec<PathBuf>> {
+        let mut content = String::new();
+        if manifest.as_os_str() == STDIN_MANIFEST {
+            io::stdin().read_to_string(&mut content)?;
+        } else {
+            fs::File::open(manifest)?.read_to_string(&mut content)?;
         }
 
-        files_vec
+        let mut files_set = HashSet::new();
+
+        Ok(Self::parse_manifest(&content, nul_delimited)
+            .into_iter()
+            .filter(|path| path.is_file() && self.supports::<Language>(path))
+            .filter(|path| files_set.insert(path.clone()))
+            .collect())
+    }
+
+    /// Returns true if the language supports `path` or an association matches it.
+    fn supports<Language: LanguageProvider>(&self, path: &Path) -> bool {
+        Language::supported_extension().matches_file(path) || self.is_associated(path)
+    }
+
+    /// Returns true if an association pattern matches `path`.
+    fn is_associated(&self, path: &Path) -> bool {
+        let mut relative = None;
+        self.associations.iter().any(|pattern| {
+            if matches_pattern(pattern, path) {
+                return true;
+            }
+            let Some(root) = self.root.as_ref().filter(|_| pattern.contains('/')) else {
+                return false;
+            };
+            relative
+                .get_or_insert_with(|| root.relative(path))
+                .as_deref()
+                .is_some_and(|relative| matches_pattern(pattern, relative))
+        })
+    }
+
+    /// Split manifest content into paths, skipping empty entries.
+    fn parse_manifest(content: &str, nul_delimited: bool) -> Vec<PathBuf> {
+        let entries: Vec<&str> = if nul_delimited {
+            content.split('\0').collect()
+        } else {
+            content.lines().collect()
+        };
+
+        entries
+            .into_iter()
+            .filter(|entry| !entry.is_empty())
+            .map(PathBuf::from)
+            .collect()
     }
 
     /// Collect supported files from path (file or directory).
     ///
     /// # Arguments
     /// * `root` - Root path to search from
+    /// * `supported` - Returns true for the files to collect
+    /// * `visited` - Canonical paths of the directories traversed so far
+    /// * `errors` - Errors met during traversal
     ///
     /// # Returns
     /// Vector of supported file paths
-    fn collect_from_path<Language: LanguageProvider>(root: &Path) -> Vec<PathBuf> {
+    fn collect_from_path(
+        &self,
+        root: &Path,
+        supported: &dyn Fn(&Path) -> bool,
+        visited: &mut HashSet<PathBuf>,
+        errors: &mut Vec<FileError>,
+    ) -> Vec<PathBuf> {
         let mut files = Vec::new();
-        let supported = Language::supported_extension();
 
-        if root.is_file() {
-            if supported.matches(root) {
-                files.push(root.to_path_buf());
+        match fs::metadata(root) {
+            Ok(metadata) if metadata.is_file() => {
+                if supported(root) {
+                    files.push(root.to_path_buf());
+                }
+            }
+            Ok(metadata) if metadata.is_dir() => {
+                self.collect_recursive(root, supported, &mut files, visited, errors);
             }
-        } else if root.is_dir() {
-            Self::collect_recursive(root, supported, &mut files);
+            Ok(_) => {}
+            Err(err) => errors.push(FileError::new(root, err)),
         }
 
         files
     }
 
     /// Helper: recursively walk directory and push supported files.
-    fn collect_recursive(dir: &Path, supported: &SupportedExtension, files: &mut Vec<PathBuf>) {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    Self::collect_recursive(&path, supported, files);
-                } else if supported.matches(&path) {
-                    files.push(path);
+    ///
+    /// Directories already in `visited` are not traversed again. Directories
+    /// and entries that cannot be read are added to `errors` and skipped.
+    fn collect_recursive(
+        &self,
+        dir: &Path,
+        supported: &dyn Fn(&Path) -> bool,
+        files: &mut Vec<PathBuf>,
+        visited: &mut HashSet<PathBuf>,
+        errors: &mut Vec<FileError>,
+    ) {
+        let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if !visited.insert(canonical) {
+            debug!("Skipping {}, which was already traversed", dir.display());
+            return;
+        }
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                errors.push(FileError::new(dir, err));
+                return;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    errors.push(FileError::new(dir, err));
+                    continue;
                 }
+            };
+            let path = entry.path();
+            let is_symlink = entry.file_type().is_ok_and(|kind| kind.is_symlink());
+            if is_symlink && self.symlinks == SymlinkPolicy::Skip {
+                debug!("Skipping symlink {}", path.display());
+                continue;
+            }
+            if path.is_dir() {
+                self.collect_recursive(&path, supported, files, visited, errors);
+            } else if supported(&path) {
+                files.push(path);
             }
         }
     }
@@ -72,7 +310,10 @@ impl FileCollector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::Engine;
     use crate::parser::LanguageProvider;
+    use crate::pipeline::Pipeline;
+    use crate::supported_extension::SupportedExtension;
     use rstest::{fixture, rstest};
     use std::fs;
     use tempfile::TempDir;
@@ -157,6 +398,89 @@ mod tests {
         assert_eq!(files[0], file_path);
     }
 
+    /// A language parsed as JSON, for engines of a registry.
+    struct MockJson;
+
+    impl LanguageProvider for MockJson {
+        fn language() -> Language {
+            tree_sitter_json::LANGUAGE.into()
+        }
+
+        fn supported_extension() -> &'static SupportedExtension {
+            static EXTENSIONS: SupportedExtension = SupportedExtension::new(&["mock"]);
+            &EXTENSIONS
+        }
+    }
+
+    /// Another language of a registry, for text files.
+    struct Text;
+
+    impl LanguageProvider for Text {
+        fn language() -> Language {
+            tree_sitter_json::LANGUAGE.into()
+        }
+
+        fn supported_extension() -> &'static SupportedExtension {
+            static EXTENSIONS: SupportedExtension = SupportedExtension::new(&["txt"]);
+            &EXTENSIONS
+        }
+    }
+
+    #[rstest]
+    fn test_collect_registered_collects_every_language(test_files_structure: TempDir) {
+        let mut registry = LanguageRegistry::<()>::new();
+        registry
+            .register("mock", Engine::<MockJson, ()>::new(Pipeline::new()))
+            .register("text", Engine::<Text, ()>::new(Pipeline::new()));
+
+        let paths = vec![test_files_structure.path().to_path_buf()];
+        let (files, errors) = FileCollector::default().collect_registered(&registry, &paths);
+
+        assert!(errors.is_empty());
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| file.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            ["file1.mock", "file3.txt", "deep1.mock", "nested1.mock"]
+        );
+    }
+
+    #[rstest]
+    fn test_collect_associated_files(test_files_structure: TempDir) {
+        let base = test_files_structure.path();
+        fs::write(base.join("Jenkinsfile"), "pipeline").unwrap();
+        let config = CollectorConfig {
+            associations: vec!["*.xml".to_string(), "Jenkinsfile".to_string()],
+            ..CollectorConfig::default()
+        };
+        let collector = FileCollector::from_config(&config);
+
+        let files = collector.collect::<MockLanguage>(&[base.to_path_buf()]);
+
+        assert_eq!(files.len(), 7);
+        assert!(files.iter().any(|f| f.ends_with("Jenkinsfile")));
+        assert!(files.iter().any(|f| f.ends_with("nested/unsupported.xml")));
+        assert!(!files.iter().any(|f| f.ends_with("file3.txt")));
+    }
+
+    #[rstest]
+    fn test_collect_associated_files_from_root(test_files_structure: TempDir) {
+        let base = test_files_structure.path();
+        fs::write(base.join(".git"), "").unwrap();
+        let config = CollectorConfig {
+            associations: vec!["/nested/*.xml".to_string()],
+            ..CollectorConfig::default()
+        };
+        let root = WorkspaceRoot::discover(base, &[]).unwrap();
+        let collector = FileCollector::from_config(&config).with_root(root);
+
+        let files = collector.collect::<MockLanguage>(&[base.join("nested")]);
+        assert!(files.iter().any(|f| f.ends_with("nested/unsupported.xml")));
+        assert!(!files.iter().any(|f| f.ends_with("file3.txt")));
+    }
+
     #[rstest]
     fn test_collect_from_unsupported_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -186,6 +510,39 @@ mod tests {
         assert_eq!(files.len(), 0);
     }
 
+    #[rstest]
+    fn test_collect_with_errors_reports_nonexistent_path() {
+        let paths = vec![PathBuf::from("/nonexistent/path")];
+        let (files, errors) = FileCollector::default().collect_with_errors::<MockLanguage>(&paths);
+
+        assert!(files.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, paths[0]);
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_collect_with_errors_reports_unreadable_directory(test_files_structure: TempDir) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let base = test_files_structure.path();
+        let locked = base.join("nested/deep");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        let readable = fs::read_dir(&locked).is_ok();
+        let (files, errors) =
+            FileCollector::default().collect_with_errors::<MockLanguage>(&[base.to_path_buf()]);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        if readable {
+            // Privileged users can list the directory anyway.
+            return;
+        }
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, locked);
+        assert!(files.iter().any(|f| f.ends_with("nested1.mock")));
+        assert!(!files.iter().any(|f| f.ends_with("deep1.mock")));
+    }
+
     #[rstest]
     fn test_collect_recursive_depth(test_files_structure: TempDir) {
         let nested_path = test_files_structure.path().join("nested");
@@ -228,6 +585,54 @@ mod tests {
         assert_eq!(files.len(), 3);
     }
 
+    #[rstest]
+    #[case("a.mock\nb.test\n\n", false, &["a.mock", "b.test"])]
+    #[case("a.mock\r\nb c.test", false, &["a.mock", "b c.test"])]
+    #[case("a.mock\0with\nnewline.test\0", true, &["a.mock", "with\nnewline.test"])]
+    fn test_parse_manifest(
+        #[case] content: &str,
+        #[case] nul_delimited: bool,
+        #[case] expected: &[&str],
+    ) {
+        let expected: Vec<PathBuf> = expected.iter().map(PathBuf::from).collect();
+        assert_eq!(
+            FileCollector::parse_manifest(content, nul_delimited),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_collect_listed_filters_and_deduplicates(test_files_structure: TempDir) {
+        let base = test_files_structure.path();
+        let manifest = base.join("manifest");
+        let listed = [
+            base.join("file1.mock"),
+            base.join("file3.txt"),
+            base.join("nested"),
+            base.join("deleted.mock"),
+            base.join("nested/deep/deep1.mock"),
+            base.join("file1.mock"),
+        ];
+        let content: Vec<String> = listed.iter().map(|p| p.display().to_string()).collect();
+        fs::write(&manifest, content.join("\n")).unwrap();
+
+        let files = FileCollector::default()
+            .collect_listed::<MockLanguage>(&manifest, false)
+            .unwrap();
+
+        assert_eq!(
+            files,
+            vec![base.join("file1.mock"), base.join("nested/deep/deep1.mock")]
+        );
+    }
+
+    #[rstest]
+    fn test_collect_listed_missing_manifest() {
+        let result = FileCollector::default()
+            .collect_listed::<MockLanguage>(Path::new("/nonexistent"), false);
+        assert!(result.is_err());
+    }
+
     #[rstest]
     fn test_collect_empty_paths_array() {
         let paths: Vec<PathBuf> = vec![];
@@ -235,4 +640,78 @@ mod tests {
 
         assert_eq!(files.len(), 0);
     }
+
+    #[rstest]
+    fn test_collect_sorts_files_by_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        for name in ["b.mock", "a.b.mock", "a/z.mock", "B.mock", "a/c/d.mock"] {
+            let path = base.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "content").unwrap();
+        }
+
+        let paths = vec![base.join("b.mock"), base.to_path_buf()];
+        let files = FileCollector::collect_all::<MockLanguage>(&paths);
+
+        let expected: Vec<PathBuf> = ["B.mock", "a/c/d.mock", "a/z.mock", "a.b.mock", "b.mock"]
+            .iter()
+            .map(|name| base.join(name))
+            .collect();
+        assert_eq!(files, expected);
+    }
+
+    #[rstest]
+    fn test_collect_without_sorting_keeps_argument_order(test_files_structure: TempDir) {
+        let base = test_files_structure.path();
+        let collector = FileCollector::from_config(&CollectorConfig {
+            sort: false,
+            ..CollectorConfig::default()
+        });
+
+        let paths = vec![base.join("file2.test"), base.join("file1.mock")];
+        let files = collector.collect::<MockLanguage>(&paths);
+
+        assert_eq!(files, paths);
+    }
+
+    #[cfg(unix)]
+    fn symlinked_structure() -> TempDir {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir(base.join("real")).unwrap();
+        fs::write(base.join("real/a.mock"), "content").unwrap();
+        symlink(base.join("real"), base.join("real/loop")).unwrap();
+        symlink(base.join("real/a.mock"), base.join("link.mock")).unwrap();
+        symlink(base.join("real"), base.join("linked_dir")).unwrap();
+        temp_dir
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_collect_follows_symlinks_once() {
+        let temp_dir = symlinked_structure();
+        let base = temp_dir.path();
+
+        let files = FileCollector::collect_all::<MockLanguage>(&[base.to_path_buf()]);
+
+        assert_eq!(files, vec![base.join("link.mock")]);
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_collect_skips_symlinks() {
+        let temp_dir = symlinked_structure();
+        let base = temp_dir.path();
+        let collector = FileCollector::from_config(&CollectorConfig {
+            symlinks: SymlinkPolicy::Skip,
+            ..CollectorConfig::default()
+        });
+
+        let files = collector.collect::<MockLanguage>(&[base.to_path_buf()]);
+
+        assert_eq!(files, vec![base.join("real/a.mock")]);
+    }
 }
@@ -1,8 +1,76 @@
-use crate::cli::error::CliResult;
-use log::debug;
-use std::fs::{read_to_string, File};
+use crate::cli::commands::ReaderConfig;
+use crate::cli::error::{CliError, CliResult};
+use crate::core::Encoding;
+use log::info;
+use std::fs::{self, File};
 use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// A file mapped into memory, for parsing huge files without reading them into a `String`.
+///
+/// Formatting needs the text in memory because edits are applied to it;
+/// mapped files serve hosts that only need the tree, such as syntax checks.
+/// Changes to the file by other processes while it is mapped are visible
+/// through the mapping, and truncating it makes reads fail, so only map
+/// files that are not written concurrently.
+///
+/// # Examples
+/// ```no_run
+/// use fmt_runner::{MappedFile, Parser};
+/// # use fmt_runner::{LanguageProvider, SupportedExtension};
+/// # struct Json;
+/// # impl LanguageProvider for Json {
+/// #     fn language() -> tree_sitter::Language { unimplemented!() }
+/// #     fn supported_extension() -> &'static SupportedExtension { unimplemented!() }
+/// # }
+///
+/// let mapped = MappedFile::open("huge.json".as_ref()).unwrap();
+/// let tree = Parser::<Json>::new().parse_bytes(mapped.as_bytes());
+/// ```
+#[cfg(feature = "mmap")]
+pub struct MappedFile {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedFile {
+    /// Map the file at `path` into memory.
+    pub fn open(path: &Path) -> CliResult<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only; the type documents that the file
+        // must not be modified while it is mapped.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self { map })
+    }
+
+    /// Get the bytes of the file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+/// Outcome of reading a single file with a size limit.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadOutcome {
+    /// The file content, which was UTF-8 without a byte order mark
+    Read(String),
+    /// The file content, decoded from another encoding
+    Decoded {
+        /// The decoded content, without byte order mark
+        content: String,
+        /// The encoding of the file, to write it back in
+        encoding: Encoding,
+    },
+    /// The file looks like binary data and was not decoded
+    Binary,
+    /// The file exceeded the maximum file size and was not read
+    Skipped {
+        /// Size of the file in bytes
+        size: usize,
+        /// The limit that was exceeded, in bytes
+        limit: usize,
+    },
+}
 
 /// File reader with optimizations for large files.
 pub struct FileReader {
@@ -10,18 +78,62 @@ pub struct FileReader {
     buffer_size: usize,
     /// Maximum file size for in-memory reading (default: 10MB)
     max_in_memory_size: usize,
+    /// Files above this size are skipped by `read` (default: no limit)
+    max_file_size: Option<usize>,
+    /// Decode files that are not valid UTF-8 as Latin-1 (default: false)
+    latin1_fallback: bool,
 }
 
 impl Default for FileReader {
     fn default() -> Self {
-        Self {
-            buffer_size: 8 * 1024,                // 8KB buffer
-            max_in_memory_size: 10 * 1024 * 1024, // 10MB
-        }
+        Self::from_config(&ReaderConfig::default())
     }
 }
 
 impl FileReader {
+    /// Create a reader using the thresholds from the runner config.
+    ///
+    /// # Arguments
+    /// * `config` - Reader section of the runner config
+    pub fn from_config(config: &ReaderConfig) -> Self {
+        Self {
+            buffer_size: config.buffer_size.max(1),
+            max_in_memory_size: config.max_in_memory_size,
+            max_file_size: config.max_file_size,
+            latin1_fallback: config.latin1_fallback,
+        }
+    }
+
+    /// Read a single file unless it exceeds the maximum file size or is binary.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to read
+    ///
+    /// # Returns
+    /// The file content, `ReadOutcome::Skipped` if the file is too large, or
+    /// `ReadOutcome::Binary` if it looks like binary data
+    ///
+    /// # Errors
+    /// Returns `CliError::UnsupportedEncoding` if the file cannot be decoded.
+    pub fn read(&self, file_path: &Path) -> CliResult<ReadOutcome> {
+        let file_size = std::fs::metadata(file_path)?.len() as usize;
+        if let Some(limit) = self.max_file_size.filter(|&limit| file_size > limit) {
+            return Ok(ReadOutcome::Skipped {
+                size: file_size,
+                limit,
+            });
+        }
+
+        let bytes = self.read_bytes(file_path, file_size)?;
+        if Encoding::is_binary(&bytes) {
+            return Ok(ReadOutcome::Binary);
+        }
+        match self.decode(file_path, bytes)? {
+            (content, Encoding::Utf8) => Ok(ReadOutcome::Read(content)),
+            (content, encoding) => Ok(ReadOutcome::Decoded { content, encoding }),
+        }
+    }
+
     /// Read given files into strings with optimization for large files.
     ///
     /// # Arguments
@@ -46,23 +158,35 @@ impl FileReader {
     /// * `file_path` - Path to the file to read
     ///
     /// # Returns
-    /// File content as a string
-    fn read_file(&self, file_path: &PathBuf) -> CliResult<String> {
+    /// File content as a string, decoded and without byte order mark
+    pub fn read_file(&self, file_path: &Path) -> CliResult<String> {
         let metadata = std::fs::metadata(file_path)?;
-        let file_size = metadata.len() as usize;
+        let bytes = self.read_bytes(file_path, metadata.len() as usize)?;
+        Ok(self.decode(file_path, bytes)?.0)
+    }
 
+    /// Read a file of known size, choosing the buffered path for large files.
+    fn read_bytes(&self, file_path: &Path, file_size: usize) -> CliResult<Vec<u8>> {
         if file_size > self.max_in_memory_size {
-            debug!(
-                "Reading large file ({} bytes) with buffering: {}",
+            info!(
+                "Reading large file ({} bytes, above the {} byte in-memory threshold) with buffering: {}",
                 file_size,
+                self.max_in_memory_size,
                 file_path.display()
             );
             self.read_large_file(file_path, file_size)
         } else {
-            Ok(read_to_string(file_path)?)
+            Ok(fs::read(file_path)?)
         }
     }
 
+    /// Decode the bytes read from `file_path`.
+    fn decode(&self, file_path: &Path, bytes: Vec<u8>) -> CliResult<(String, Encoding)> {
+        Encoding::decode(bytes, self.latin1_fallback).ok_or_else(|| CliError::UnsupportedEncoding {
+            path: file_path.to_path_buf(),
+        })
+    }
+
     /// Read a large file with buffering.
     ///
     /// # Arguments
@@ -70,13 +194,13 @@ impl FileReader {
     /// * `file_size` - Size of the file in bytes
     ///
     /// # Returns
-    /// File content as a string
-    fn read_large_file(&self, file_path: &PathBuf, file_size: usize) -> CliResult<String> {
+    /// File content as bytes
+    fn read_large_file(&self, file_path: &Path, file_size: usize) -> CliResult<Vec<u8>> {
         let file = File::open(file_path)?;
         let mut reader = BufReader::with_capacity(self.buffer_size, file);
-        let mut content = String::with_capacity(file_size);
+        let mut content = Vec::with_capacity(file_size);
 
-        reader.read_to_string(&mut content)?;
+        reader.read_to_end(&mut content)?;
         Ok(content)
     }
 }
@@ -276,6 +400,56 @@ mod tests {
         assert!(result[0].chars().all(|c| c == 'a'));
     }
 
+    #[rstest]
+    fn test_from_config_uses_thresholds(temp_dir: TempDir) {
+        let config = ReaderConfig {
+            buffer_size: 16,
+            max_in_memory_size: 64,
+            max_file_size: None,
+            latin1_fallback: false,
+        };
+        let reader = FileReader::from_config(&config);
+        let path = create_sized_file(&temp_dir, "slow_path.txt", 1024);
+
+        assert_eq!(reader.buffer_size, 16);
+        assert_eq!(reader.max_in_memory_size, 64);
+        assert_eq!(reader.read_file(&path).unwrap().len(), 1024);
+    }
+
+    #[rstest]
+    fn test_read_skips_files_above_max_file_size(temp_dir: TempDir) {
+        let config = ReaderConfig {
+            max_file_size: Some(100),
+            ..ReaderConfig::default()
+        };
+        let reader = FileReader::from_config(&config);
+        let small = create_sized_file(&temp_dir, "small.txt", 100);
+        let large = create_sized_file(&temp_dir, "large.txt", 101);
+
+        assert_eq!(
+            reader.read(&small).unwrap(),
+            ReadOutcome::Read("a".repeat(100))
+        );
+        assert_eq!(
+            reader.read(&large).unwrap(),
+            ReadOutcome::Skipped {
+                size: 101,
+                limit: 100
+            }
+        );
+    }
+
+    #[rstest]
+    fn test_read_without_limit_reads_everything(temp_dir: TempDir) {
+        let path = create_sized_file(&temp_dir, "any.txt", 4096);
+        let reader = FileReader::default();
+
+        assert_eq!(
+            reader.read(&path).unwrap(),
+            ReadOutcome::Read("a".repeat(4096))
+        );
+    }
+
     #[rstest]
     fn test_read_file_at_boundary_size(temp_dir: TempDir) {
         let size = 10 * 1024 * 1024; // 10MB
@@ -287,4 +461,74 @@ mod tests {
 
         assert_eq!(result[0].len(), size);
     }
+
+    #[rstest]
+    #[case(b"\xEF\xBB\xBF{}".to_vec(), Encoding::Utf8Bom)]
+    #[case(b"\xFF\xFE{\0}\0".to_vec(), Encoding::Utf16Le)]
+    #[case(b"\xFE\xFF\0{\0}".to_vec(), Encoding::Utf16Be)]
+    fn test_read_decodes_byte_order_marks(
+        temp_dir: TempDir,
+        #[case] bytes: Vec<u8>,
+        #[case] encoding: Encoding,
+    ) {
+        let path = temp_dir.path().join("marked.json");
+        fs::write(&path, bytes).unwrap();
+
+        assert_eq!(
+            FileReader::default().read(&path).unwrap(),
+            ReadOutcome::Decoded {
+                content: "{}".to_string(),
+                encoding
+            }
+        );
+    }
+
+    #[cfg(feature = "mmap")]
+    #[rstest]
+    fn test_mapped_file_exposes_file_bytes(temp_dir: TempDir) {
+        let path = create_test_file(&temp_dir, "mapped.json", "{\"a\": 1}");
+
+        let mapped = MappedFile::open(&path).unwrap();
+
+        assert_eq!(mapped.as_bytes(), b"{\"a\": 1}");
+    }
+
+    #[rstest]
+    fn test_read_detects_binary_files(temp_dir: TempDir) {
+        let path = temp_dir.path().join("image.json");
+        fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        assert_eq!(
+            FileReader::default().read(&path).unwrap(),
+            ReadOutcome::Binary
+        );
+    }
+
+    #[rstest]
+    fn test_read_rejects_unsupported_encoding(temp_dir: TempDir) {
+        let path = temp_dir.path().join("latin1.txt");
+        fs::write(&path, b"caf\xE9").unwrap();
+
+        let err = FileReader::default().read(&path).unwrap_err();
+
+        assert!(matches!(err, CliError::UnsupportedEncoding { .. }));
+    }
+
+    #[rstest]
+    fn test_read_falls_back_to_latin1(temp_dir: TempDir) {
+        let path = temp_dir.path().join("latin1.txt");
+        fs::write(&path, b"caf\xE9").unwrap();
+        let config = ReaderConfig {
+            latin1_fallback: true,
+            ..ReaderConfig::default()
+        };
+
+        assert_eq!(
+            FileReader::from_config(&config).read(&path).unwrap(),
+            ReadOutcome::Decoded {
+                content: "café".to_string(),
+                encoding: Encoding::Latin1
+            }
+        );
+    }
 }
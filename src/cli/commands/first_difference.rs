@@ -0,0 +1,62 @@
+use serde::Serialize;
+use std::fmt;
+
+/// A 1-based line and column in a text; the column counts characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TextPosition {
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+}
+
+impl fmt::Display for TextPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Find where `formatted` first diverges from `original`.
+///
+/// # Returns
+/// The position in `original` of the first differing character, or `None`
+/// if the texts are equal
+pub fn first_difference(original: &str, formatted: &str) -> Option<TextPosition> {
+    let mut position = TextPosition { line: 1, column: 1 };
+    let mut formatted_chars = formatted.chars();
+
+    for ch in original.chars() {
+        if formatted_chars.next() != Some(ch) {
+            return Some(position);
+        }
+        if ch == '\n' {
+            position.line += 1;
+            position.column = 1;
+        } else {
+            position.column += 1;
+        }
+    }
+
+    formatted_chars.next().map(|_| position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("a\nb :1\n", "a\nb: 1\n", Some((2, 2)))]
+    #[case("é :1", "é: 1", Some((1, 2)))]
+    #[case("abc", "abc\n", Some((1, 4)))]
+    #[case("abc\n", "abc", Some((1, 4)))]
+    #[case("same", "same", None)]
+    fn test_first_difference(
+        #[case] original: &str,
+        #[case] formatted: &str,
+        #[case] expected: Option<(usize, usize)>,
+    ) {
+        let expected = expected.map(|(line, column)| TextPosition { line, column });
+        assert_eq!(first_difference(original, formatted), expected);
+    }
+}
@@ -1,40 +1,191 @@
-use crate::cli::cli_entry::FormatMode;
-use crate::cli::commands::{ConfigLoader, FileCollector, FileReader};
-use crate::cli::error::CliResult;
-use crate::core::Engine;
+use crate::cli::cli_entry::{FormatMode, OutputFormat};
+use crate::cli::commands::config_hierarchy::ConfigHierarchy;
+use crate::cli::commands::edit_log::write_edit_trace;
+use crate::cli::commands::first_difference::{first_difference, TextPosition};
+use crate::cli::commands::io_profile::IoProfile;
+use crate::cli::commands::parse_profile::ParseProfile;
+use crate::cli::commands::pass_profile::PassProfile;
+use crate::cli::commands::run_status::{config_hash, print_json_line, FileRecord, RunStatus};
+use crate::cli::commands::{
+    ConfigLayers, ConfigLoader, ConfigMigrations, ConfigOverride, ConfigValidators, FileCollector,
+    FileReader, ReadOutcome, Reporter, WorkspaceRoot,
+};
+use crate::cli::error::{CliError, CliResult};
+use crate::core::{
+    Encoding, Engine, EngineCache, FileError, FileWriter, FirstStop, LineRange, PassTiming,
+    RunMetrics, RunReport, Severity, TracedEdit,
+};
 use crate::parser::LanguageProvider;
-use crate::pipeline::Pipeline;
-use log::{info, warn};
+use crate::pipeline::{Pipeline, SourceMap, SourceMapSegment};
+use log::{debug, error, info, warn};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Options controlling a format run.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Format mode (check or write)
+    pub mode: FormatMode,
+    /// Output format of the profiles; results go to the reporter
+    pub output: OutputFormat,
+    /// Where to write source maps of changed files, if requested
+    pub source_map: Option<PathBuf>,
+    /// Report read/write times per file
+    pub profile_io: bool,
+    /// Report the time spent in each pass and the parse metrics of each file
+    pub timings: bool,
+    /// Log every edit to this file (`-` for stderr), if requested
+    pub trace_edits: Option<PathBuf>,
+    /// Number of files to format in parallel (0 = one per CPU)
+    pub jobs: usize,
+    /// Skip files larger than this many bytes, overriding the config
+    pub max_file_size: Option<usize>,
+    /// Stop at the first file that needs formatting (check mode only)
+    pub fail_fast: bool,
+    /// Only format these lines of a single file
+    pub lines: Option<LineRange>,
+    /// Keep originals of written files with this suffix appended
+    pub backup: Option<String>,
+    /// Read the files to format from this manifest (`-` for stdin) instead of traversing
+    pub files_from: Option<PathBuf>,
+    /// Paths in the manifest are NUL-delimited
+    pub nul_delimited: bool,
+    /// Fail if the config file does not exist instead of using the defaults
+    pub require_config: bool,
+    /// `--set` overrides applied on top of the loaded config
+    pub overrides: Vec<ConfigOverride>,
+    /// Directory caching which files are already formatted, if requested
+    pub cache_dir: Option<PathBuf>,
+    /// Project root that association patterns are matched from, if found
+    pub root: Option<WorkspaceRoot>,
+    /// System and user configs the config is merged over
+    pub layers: ConfigLayers,
+    /// Migrations applied to config files of older versions
+    pub migrations: ConfigMigrations,
+    /// Checks run on the config of every group of files
+    pub validators: ConfigValidators,
+    /// Version of the formatter, reported in the final status
+    pub version: &'static str,
+}
+
+/// Source map of a single file as written to the source map file.
+#[derive(Serialize)]
+struct SourceMapRecord<'a> {
+    path: &'a Path,
+    segments: &'a [SourceMapSegment],
+}
+
+/// Top-level document of the source map file.
+#[derive(Serialize)]
+struct SourceMapDocument<'a> {
+    files: Vec<SourceMapRecord<'a>>,
+}
 
 /// Execute the format command with improved architecture and performance.
 ///
 /// This function coordinates:
-/// 1. Configuration loading via ConfigLoader
+/// 1. Configuration loading via ConfigLoader, with nested configs resolved
+///    per file by ConfigHierarchy
 /// 2. File collection via FileCollector
 /// 3. File reading via FileReader (optimized for large files)
 /// 4. Formatting via Engine
 ///
+/// Results are handed to `reporter`, which also receives a final status
+/// when the run fails. In check mode, files that need formatting make the
+/// run fail with `CliError::NeedsFormatting`.
+///
+/// Files that cannot be read, formatted, or written are reported and the
+/// remaining files are still processed; the run then fails with
+/// `CliError::FilesFailed`.
+///
 /// # Arguments
 /// * `config_path` - Path to the configuration file
 /// * `files_path` - Paths to files or directories to format
 /// * `pipeline` - The formatting pipeline to apply
-/// * `mode` - Format mode (check or write)
+/// * `options` - Options controlling the run
+/// * `reporter` - Receives the results of the run
 pub fn execute<Language, Config>(
     config_path: &Path,
     files_path: &[PathBuf],
     pipeline: Pipeline<Config>,
-    mode: FormatMode,
+    options: &FormatOptions,
+    reporter: &mut dyn Reporter,
 ) -> CliResult<()>
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
-    let config = ConfigLoader::load::<Config>(config_path)?;
+    let started = Instant::now();
+    let mut status = RunStatus::new(options.version);
+
+    let result = run::<Language, Config>(
+        config_path,
+        files_path,
+        pipeline,
+        options,
+        reporter,
+        &mut status,
+    );
+
+    if let Err(err) = &result {
+        status.exit_code = err.exit_code();
+        status.error = Some(err.to_string());
+    }
+    status.duration_ms = started.elapsed().as_millis();
+    reporter.on_status(&status)?;
+
+    result
+}
 
-    let files = FileCollector::collect_all::<Language>(files_path);
+/// Run the format command, recording counts into `status`.
+fn run<Language, Config>(
+    config_path: &Path,
+    files_path: &[PathBuf],
+    pipeline: Pipeline<Config>,
+    options: &FormatOptions,
+    reporter: &mut dyn Reporter,
+    status: &mut RunStatus,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default + Sync,
+    Language: LanguageProvider,
+{
+    if options.require_config {
+        ConfigLoader::require(config_path)?;
+    }
+    let config = ConfigLoader::load_layered::<Config>(
+        config_path,
+        &options.layers,
+        &options.overrides,
+        &options.migrations,
+    )?;
+    options.validators.check(&config, config_path)?;
+    let runner_config =
+        ConfigLoader::load_runner_config(config_path, &options.layers, &options.overrides)?;
+    status.config_hash = Some(config_hash(&config)?);
+
+    let mut collector = FileCollector::from_config(&runner_config.collector);
+    if let Some(root) = &options.root {
+        collector = collector.with_root(root.clone());
+    }
+    let files = match &options.files_from {
+        Some(manifest) => collector.collect_listed::<Language>(manifest, options.nul_delimited)?,
+        None => {
+            let (files, traversal_errors) = collector.collect_with_errors::<Language>(files_path);
+            for failed in &traversal_errors {
+                warn!(
+                    "Could not traverse {}: {}",
+                    failed.path.display(),
+                    failed.message
+                );
+            }
+            status.traversal_errors = traversal_errors.len();
+            files
+        }
+    };
 
     if files.is_empty() {
         info!("No supported files found to format.");
@@ -43,68 +194,696 @@ where
 
     info!("Found {} file(s) to process", files.len());
 
-    let reader = FileReader::default();
-    let file_contents = reader.read_files(&files)?;
+    if options.lines.is_some() && files.len() != 1 {
+        return Err(CliError::LinesRequireSingleFile { count: files.len() });
+    }
 
-    let mut engine = Engine::<Language, Config>::new(pipeline);
+    let mut reader_config = runner_config.reader;
+    if options.max_file_size.is_some() {
+        reader_config.max_file_size = options.max_file_size;
+    }
+    let reader = FileReader::from_config(&reader_config);
+    let mut profile = options.profile_io.then(IoProfile::new);
 
-    match mode {
-        FormatMode::Check => execute_check_mode(&mut engine, &config, &file_contents, &files),
-        FormatMode::Write => execute_write_mode(&mut engine, &config, &file_contents, &files)?,
+    for code in runner_config.rules.keys() {
+        if pipeline.rule(code).is_none() {
+            warn!("No pass checks rule {code}, which is set in runner.rules");
+        }
+    }
+
+    let run_level_passes = pipeline.has_run_level_passes();
+
+    let mut engine = Engine::<Language, Config>::try_new(pipeline)?
+        .with_jobs(options.jobs)
+        .with_parse_error_policy(runner_config.on_parse_error)
+        .with_line_endings(runner_config.line_endings)
+        .with_rule_levels(runner_config.rules)
+        .with_generated_markers(runner_config.generated_markers);
+    if let Some(timeout) = runner_config.parse_timeout_ms {
+        engine = engine.with_parse_timeout(Duration::from_millis(timeout));
+    }
+    if options.timings {
+        engine = engine.with_timings();
+    }
+    if options.trace_edits.is_some() {
+        engine = engine.with_edit_trace();
+    }
+    if options.source_map.is_some() {
+        engine = engine.with_source_maps();
+    }
+    if let Some(cache_dir) = &options.cache_dir {
+        engine = engine.with_cache(EngineCache::open(cache_dir)?);
+    }
+    let writer = (options.mode == FormatMode::Write).then(|| match &options.backup {
+        Some(suffix) => FileWriter::new().with_backup(suffix),
+        None => FileWriter::new(),
+    });
+
+    status.files_total = files.len();
+    let fail_fast =
+        options.fail_fast && options.mode == FormatMode::Check && options.lines.is_none();
+    let batch_size = match runner_config.batch_size {
+        0 => files.len(),
+        size => size,
+    };
+
+    let groups = ConfigHierarchy::new(config_path).group(&files);
+    let mut group_configs = Vec::with_capacity(groups.len());
+    for group in &groups {
+        group_configs.push(load_group_config(
+            &config,
+            config_path,
+            &group.configs,
+            options,
+        )?);
+    }
+    let config_of = |group: usize| group_configs[group].as_ref().unwrap_or(&config);
+    let group_of: HashMap<&PathBuf, usize> = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(i, group)| group.files.iter().map(move |file| (file, i)))
+        .collect();
+    // Run-level passes see every file at once, each with the config of its
+    // group; otherwise groups are formatted one after the other
+    let batches: Vec<(&[PathBuf], Option<usize>)> = if run_level_passes {
+        vec![(&files, None)]
+    } else {
+        groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, group)| {
+                group
+                    .files
+                    .chunks(batch_size)
+                    .map(move |chunk| (chunk, Some(i)))
+            })
+            .collect()
+    };
+
+    let mut totals = RunTotals::default();
+    for (chunk, group) in batches {
+        let batch = read_files(&reader, chunk, profile.as_mut(), &mut totals.errors);
+        status.files_skipped += batch.skipped.len();
+        for file in &batch.skipped {
+            reporter.on_file(&FileRecord::skipped(file))?;
+        }
+        let (batch_config, file_configs): (&Config, Vec<&Config>) = match group {
+            Some(group) => (config_of(group), Vec::new()),
+            None => (
+                &config,
+                batch
+                    .files
+                    .iter()
+                    .map(|file| config_of(group_of[file]))
+                    .collect(),
+            ),
+        };
+        let configs = BatchConfigs {
+            run: batch_config,
+            files: &file_configs,
+        };
+
+        if fail_fast {
+            // A file that could not be read stops the run before any check
+            let stop = if totals.errors.is_empty() {
+                execute_fail_fast_check(&mut engine, configs, &batch, reporter)?
+            } else {
+                None
+            };
+            let unformatted = match stop {
+                Some(FirstStop::Unformatted(path)) => Some(path),
+                Some(FirstStop::Failed(error)) => {
+                    totals.errors.push(error);
+                    None
+                }
+                None => None,
+            };
+            if unformatted.is_some() || !totals.errors.is_empty() {
+                status.files_changed = usize::from(unformatted.is_some());
+                status.files_failed = totals.errors.len();
+                report_failures(&totals.errors, reporter)?;
+                reporter.on_finish(FormatMode::Check)?;
+                check_failures(&totals.errors)?;
+            }
+            if let Some(path) = unformatted {
+                return Err(CliError::FailFast { path });
+            }
+            continue;
+        }
+
+        format_batch(
+            &mut engine,
+            configs,
+            batch,
+            writer.as_ref(),
+            options,
+            reporter,
+            profile.as_mut(),
+            &mut totals,
+        )?;
+    }
+
+    if fail_fast {
+        status.files_failed = totals.errors.len();
+        report_failures(&totals.errors, reporter)?;
+        reporter.on_finish(FormatMode::Check)?;
+        return check_failures(&totals.errors);
+    }
+
+    engine.save_cache()?;
+    if let Some(destination) = &options.trace_edits {
+        write_edit_trace(&totals.edits, &totals.files, destination)?;
+    }
+    if let Some(source_map_path) = &options.source_map {
+        write_source_maps(&totals.source_maps, source_map_path)?;
+    }
+
+    status.files_changed = totals.changed_files.len();
+    status.files_ignored = totals.ignored_files;
+    status.files_failed = totals.errors.len();
+    status.diagnostic_errors = totals.diagnostic_errors;
+
+    report_failures(&totals.errors, reporter)?;
+    reporter.on_finish(options.mode)?;
+
+    if let Some(profile) = &profile {
+        report_io_profile(profile, options.output)?;
+    }
+
+    if options.timings {
+        let pass_profile = PassProfile::new(std::mem::take(&mut totals.timings), &totals.files);
+        report_pass_profile(&pass_profile, options.output)?;
+        report_parse_profile(&totals.parses, options.output)?;
+    }
+
+    check_failures(&totals.errors)?;
+    if options.mode == FormatMode::Check && !totals.changed_files.is_empty() {
+        return Err(CliError::NeedsFormatting {
+            count: totals.changed_files.len(),
+        });
+    }
+    if totals.diagnostic_errors > 0 {
+        return Err(CliError::DiagnosticErrors {
+            count: totals.diagnostic_errors,
+        });
     }
 
     Ok(())
 }
 
-/// Execute check mode - verify if files need formatting.
-fn execute_check_mode<Language, Config>(
-    engine: &mut Engine<Language, Config>,
+/// Results of the batches processed so far.
+///
+/// Only paths and per-file metadata are kept; the contents of a batch are
+/// dropped once it has been formatted and written.
+#[derive(Default)]
+struct RunTotals {
+    /// Files that were formatted, indexing `timings` and `edits`
+    files: Vec<PathBuf>,
+    /// Files that need or received formatting
+    changed_files: Vec<PathBuf>,
+    /// Files that could not be read, formatted, or written
+    errors: Vec<FileError>,
+    /// Pass timings, indexed like `files`
+    timings: Vec<PassTiming>,
+    /// Measurements of the trees parsed from formatted files
+    parses: ParseProfile,
+    /// Traced edits, indexed like `files`
+    edits: Vec<TracedEdit>,
+    /// Source maps of changed files
+    source_maps: Vec<(PathBuf, SourceMap)>,
+    /// Number of error diagnostics reported about formatted files
+    diagnostic_errors: usize,
+    /// Number of files left unformatted because they disable formatting
+    ignored_files: usize,
+}
+
+/// Configurations a batch is formatted with.
+struct BatchConfigs<'a, Config> {
+    /// Configuration of the batch
+    run: &'a Config,
+    /// Configuration of each file, when files of the batch differ in
+    /// configuration; empty when every file uses `run`
+    files: &'a [&'a Config],
+}
+
+impl<Config> Clone for BatchConfigs<'_, Config> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Config> Copy for BatchConfigs<'_, Config> {}
+
+impl<'a, Config> BatchConfigs<'a, Config> {
+    /// Configuration of the `file`th file of the batch.
+    fn of(&self, file: usize) -> &'a Config {
+        self.files.get(file).copied().unwrap_or(self.run)
+    }
+}
+
+/// Files of one batch that were read, with what reading found out about them.
+struct ReadBatch {
+    /// Files that were read
+    files: Vec<PathBuf>,
+    /// Contents of `files`
+    contents: Vec<String>,
+    /// Files that were skipped because of their size or binary content
+    skipped: Vec<PathBuf>,
+    /// Encodings of files that were not plain UTF-8
+    encodings: HashMap<PathBuf, Encoding>,
+}
+
+/// Load the config of a group of files under `nested` configs, if it has any.
+///
+/// The nested configs are layered over `config` and the result is
+/// validated against the innermost of them.
+fn load_group_config<Config>(
     config: &Config,
-    file_contents: &[String],
-    files: &[PathBuf],
-) where
+    config_path: &Path,
+    nested: &[PathBuf],
+    options: &FormatOptions,
+) -> CliResult<Option<Config>>
+where
     Config: Serialize + DeserializeOwned + Default,
+{
+    if nested.is_empty() {
+        return Ok(None);
+    }
+    let nested_config =
+        ConfigLoader::load_nested(config, nested, &options.overrides, &options.migrations)?;
+    let innermost = nested.last().map_or(config_path, PathBuf::as_path);
+    options.validators.check(&nested_config, innermost)?;
+    Ok(Some(nested_config))
+}
+
+/// Format, check or write one batch and add its results to `totals`.
+///
+/// Files of the batch are written by `writer` in write mode and compared to
+/// their formatted text otherwise. Files are reported as soon as the batch
+/// is done.
+#[allow(clippy::too_many_arguments)]
+fn format_batch<Language, Config>(
+    engine: &mut Engine<Language, Config>,
+    configs: BatchConfigs<Config>,
+    batch: ReadBatch,
+    writer: Option<&FileWriter>,
+    options: &FormatOptions,
+    reporter: &mut dyn Reporter,
+    profile: Option<&mut IoProfile>,
+    totals: &mut RunTotals,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
-    info!("Running in check mode...");
-    let changed_files = engine.check(config, file_contents, files);
+    let mut report = format_codes(
+        engine,
+        configs,
+        &batch.contents,
+        &batch.files,
+        options.lines,
+    );
+    let offset = totals.files.len();
+    totals
+        .timings
+        .extend(report.metrics.timings.drain(..).map(|mut timing| {
+            timing.file += offset;
+            timing
+        }));
+    if options.timings {
+        for outcome in &report.outcomes {
+            if let Some(parse) = &outcome.parse {
+                totals.parses.record(&outcome.path, parse);
+            }
+        }
+    }
+    if options.trace_edits.is_some() {
+        totals
+            .edits
+            .extend(engine.take_edit_trace().into_iter().map(|mut edit| {
+                edit.file += offset;
+                edit
+            }));
+    }
 
-    if changed_files.is_empty() {
-        info!("✓ All files are formatted correctly!");
-    } else {
-        warn!(
-            "✗ The following {} file(s) need formatting:",
-            changed_files.len()
+    let differences = match writer {
+        Some(writer) => {
+            execute_write_mode(writer, &mut report, &batch.encodings, profile);
+            HashMap::new()
+        }
+        None => execute_check_mode(&batch.contents, &report),
+    };
+    let changed_files = report.changed_paths();
+
+    for outcome in report.outcomes.iter_mut().filter(|o| o.changed) {
+        if let Some(map) = outcome.source_map.take() {
+            totals.source_maps.push((outcome.path.clone(), map));
+        }
+    }
+
+    for outcome in report.outcomes.iter().filter(|o| o.ignored) {
+        info!(
+            "Ignoring {}, which disables formatting",
+            outcome.path.display()
         );
-        for file in &changed_files {
-            warn!("  - {}", file.display());
+    }
+    for outcome in report.outcomes.iter().filter(|o| o.formatted.is_ok()) {
+        let file = &outcome.path;
+        let record = if outcome.ignored {
+            FileRecord::ignored(file)
+        } else {
+            FileRecord::new(file, changed_files.contains(file))
+                .with_position(differences.get(file).copied())
+                .with_diagnostics(&outcome.diagnostics)
+        };
+        reporter.on_file(&record)?;
+    }
+
+    totals.ignored_files += report.outcomes.iter().filter(|o| o.ignored).count();
+    totals.diagnostic_errors += report
+        .outcomes
+        .iter()
+        .filter(|o| o.formatted.is_ok())
+        .flat_map(|o| &o.diagnostics)
+        .filter(|diagnostic| diagnostic.is_error())
+        .count();
+    totals.errors.append(&mut report.errors);
+    totals.changed_files.extend(changed_files);
+    totals.files.extend(batch.files);
+    Ok(())
+}
+
+/// Report failed files to `reporter`.
+fn report_failures(failed_files: &[FileError], reporter: &mut dyn Reporter) -> CliResult<()> {
+    for failed in failed_files {
+        reporter.on_file(&FileRecord::failed(&failed.path, &failed.message))?;
+    }
+    Ok(())
+}
+
+/// Fail the run if any file failed, after the others were processed.
+fn check_failures(failed_files: &[FileError]) -> CliResult<()> {
+    if failed_files.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::FilesFailed {
+            count: failed_files.len(),
+        })
+    }
+}
+
+/// Read all files, skipping binary files and those above the size limit.
+///
+/// Files that cannot be read are added to `failed_files` and the rest are
+/// still read. Read times are recorded when profiling.
+fn read_files(
+    reader: &FileReader,
+    files: &[PathBuf],
+    mut profile: Option<&mut IoProfile>,
+    failed_files: &mut Vec<FileError>,
+) -> ReadBatch {
+    let mut read_files = Vec::with_capacity(files.len());
+    let mut contents = Vec::with_capacity(files.len());
+    let mut skipped_files = Vec::new();
+    let mut encodings = HashMap::new();
+
+    for file in files {
+        let started = Instant::now();
+        let content = match reader.read(file) {
+            Ok(ReadOutcome::Read(content)) => content,
+            Ok(ReadOutcome::Decoded { content, encoding }) => {
+                debug!("Decoded {} from {}", file.display(), encoding.as_str());
+                encodings.insert(file.clone(), encoding);
+                content
+            }
+            Ok(ReadOutcome::Skipped { size, limit }) => {
+                warn!(
+                    "Skipping {} ({} bytes exceeds the maximum file size of {} bytes)",
+                    file.display(),
+                    size,
+                    limit
+                );
+                skipped_files.push(file.clone());
+                continue;
+            }
+            Ok(ReadOutcome::Binary) => {
+                warn!(
+                    "Skipping {}, which looks like a binary file",
+                    file.display()
+                );
+                skipped_files.push(file.clone());
+                continue;
+            }
+            Err(err) => {
+                failed_files.push(FileError::new(file, err));
+                continue;
+            }
+        };
+        if let Some(profile) = profile.as_deref_mut() {
+            profile.record_read(file, content.len(), started.elapsed());
         }
-        info!("\nRun with --mode write to apply formatting.");
+        read_files.push(file.clone());
+        contents.push(content);
+    }
+
+    ReadBatch {
+        files: read_files,
+        contents,
+        skipped: skipped_files,
+        encodings,
     }
 }
 
-/// Execute write mode - format and write files.
-fn execute_write_mode<Language, Config>(
+/// Format all file contents, restricted to `lines` if given.
+///
+/// A pass panicking on one file only fails that file. Diagnostics of the
+/// other files are logged with their severity.
+fn format_codes<Language, Config>(
     engine: &mut Engine<Language, Config>,
-    config: &Config,
+    configs: BatchConfigs<Config>,
     file_contents: &[String],
     files: &[PathBuf],
-) -> CliResult<()>
+    lines: Option<LineRange>,
+) -> RunReport
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
+    Language: LanguageProvider,
+{
+    let report = match lines {
+        Some(lines) => {
+            info!("Formatting lines {}:{}", lines.start(), lines.end());
+            let started = Instant::now();
+            let outcomes = files
+                .iter()
+                .zip(file_contents)
+                .enumerate()
+                .map(|(i, (file, code))| {
+                    engine.format_file_range(configs.of(i), file, code, lines.byte_range(code))
+                })
+                .collect();
+            let metrics = RunMetrics {
+                timings: engine.take_timings(),
+                ..RunMetrics::default()
+            };
+            RunReport::new(outcomes, metrics, started.elapsed())
+        }
+        None if configs.files.is_empty() => engine.format_report(configs.run, file_contents, files),
+        None => engine.format_report_with_configs(configs.run, configs.files, file_contents, files),
+    };
+
+    for outcome in report.outcomes.iter().filter(|o| o.formatted.is_ok()) {
+        for diagnostic in &outcome.diagnostics {
+            match diagnostic.severity {
+                Severity::Error => error!("{}: {diagnostic}", outcome.path.display()),
+                Severity::Warning => warn!("{}: {diagnostic}", outcome.path.display()),
+            }
+        }
+    }
+    report
+}
+
+/// Execute check mode - verify if files need formatting.
+///
+/// # Arguments
+/// * `file_contents` - Contents of the formatted files, in the order of the report
+/// * `report` - The report of formatting the files
+///
+/// # Returns
+/// The files that need formatting, with the position of their first difference
+fn execute_check_mode(
+    file_contents: &[String],
+    report: &RunReport,
+) -> HashMap<PathBuf, TextPosition> {
+    info!("Running in check mode...");
+    report
+        .outcomes
+        .iter()
+        .zip(file_contents)
+        .filter_map(|(outcome, code)| {
+            let formatted_code = outcome.formatted.as_ref().ok()?;
+            Some((
+                outcome.path.clone(),
+                first_difference(code, formatted_code)?,
+            ))
+        })
+        .collect()
+}
+
+/// Execute check mode on one batch, stopping at the first file that needs formatting or fails.
+///
+/// The file that needs formatting is reported, a failed one is left to
+/// the caller; the files of a batch that is formatted are reported as
+/// unchanged.
+///
+/// # Returns
+/// Why the check stopped at a file of the batch, if it did
+fn execute_fail_fast_check<Language, Config>(
+    engine: &mut Engine<Language, Config>,
+    configs: BatchConfigs<Config>,
+    batch: &ReadBatch,
+    reporter: &mut dyn Reporter,
+) -> CliResult<Option<FirstStop>>
+where
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
+    info!("Running in check mode (fail fast)...");
+    let (files, file_contents) = (&batch.files, &batch.contents);
+    if !configs.files.is_empty() {
+        // Files with configurations of their own are formatted together
+        // for their run-level passes, so the whole report is available
+        let report =
+            engine.format_report_with_configs(configs.run, configs.files, file_contents, files);
+        let stop =
+            report.outcomes.into_iter().zip(file_contents).find_map(
+                |(outcome, code)| match outcome.formatted {
+                    Err(err) => Some(Err(FileError::new(&outcome.path, err))),
+                    Ok(formatted) if outcome.changed => {
+                        Some(Ok((outcome.path, first_difference(code, &formatted))))
+                    }
+                    Ok(_) => None,
+                },
+            );
+        return match stop {
+            Some(Err(error)) => Ok(Some(FirstStop::Failed(error))),
+            Some(Ok((path, position))) => {
+                reporter.on_file(&FileRecord::new(&path, true).with_position(position))?;
+                Ok(Some(FirstStop::Unformatted(path)))
+            }
+            None => {
+                for file in files {
+                    reporter.on_file(&FileRecord::new(file, false))?;
+                }
+                Ok(None)
+            }
+        };
+    }
+
+    let config = configs.run;
+    let path = match engine.try_find_first_unformatted(config, file_contents, files) {
+        Some(FirstStop::Unformatted(path)) => path,
+        Some(failed) => return Ok(Some(failed)),
+        None => {
+            for file in files {
+                reporter.on_file(&FileRecord::new(file, false))?;
+            }
+            return Ok(None);
+        }
+    };
+
+    // Only the first file is reformatted to locate its difference
+    let position = files.iter().position(|file| *file == path).and_then(|i| {
+        let formatted = engine.format(config, &file_contents[i]);
+        first_difference(&file_contents[i], &formatted)
+    });
+
+    reporter.on_file(&FileRecord::new(&path, true).with_position(position))?;
+    Ok(Some(FirstStop::Unformatted(path)))
+}
+
+/// Execute write mode - atomically write changed files, recording write times when profiling.
+///
+/// Files are written back in the encoding they were read in. Files that
+/// cannot be written are added to the errors of `report` and the rest are
+/// still written.
+fn execute_write_mode(
+    writer: &FileWriter,
+    report: &mut RunReport,
+    encodings: &HashMap<PathBuf, Encoding>,
+    mut profile: Option<&mut IoProfile>,
+) {
     info!("Running in write mode...");
-    let changed_files = engine.format_and_write(config, file_contents, files)?;
 
-    if changed_files.is_empty() {
-        info!("✓ No files needed formatting!");
-    } else {
-        info!("✓ Successfully formatted {} file(s):", changed_files.len());
-        for file in &changed_files {
-            info!("  - {}", file.display());
+    for outcome in &report.outcomes {
+        let (true, Ok(formatted_code)) = (outcome.changed, &outcome.formatted) else {
+            continue;
+        };
+
+        let file = &outcome.path;
+        let started = Instant::now();
+        let encoding = encodings.get(file).copied().unwrap_or_default();
+        if let Err(err) = writer.write_encoded(file, formatted_code, encoding) {
+            report.errors.push(FileError::new(file, err));
+            continue;
+        }
+        if let Some(profile) = profile.as_deref_mut() {
+            profile.record_write(file, started.elapsed());
         }
     }
+}
+
+/// Write source maps as a JSON document to `path`.
+fn write_source_maps(maps: &[(PathBuf, SourceMap)], path: &Path) -> CliResult<()> {
+    let document = SourceMapDocument {
+        files: maps
+            .iter()
+            .map(|(file, map)| SourceMapRecord {
+                path: file,
+                segments: map.segments(),
+            })
+            .collect(),
+    };
 
+    std::fs::write(path, serde_json::to_string_pretty(&document)?)?;
+    info!("Source map written to {}", path.display());
+    Ok(())
+}
+
+/// Report per-file IO timings as a table or JSON lines.
+fn report_io_profile(profile: &IoProfile, output: OutputFormat) -> CliResult<()> {
+    match output {
+        OutputFormat::Human | OutputFormat::Sarif | OutputFormat::Quiet => profile.print_table(),
+        OutputFormat::Json => {
+            for timing in profile.timings() {
+                print_json_line(timing)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Report per-pass timings as a table or JSON lines.
+fn report_pass_profile(profile: &PassProfile, output: OutputFormat) -> CliResult<()> {
+    match output {
+        OutputFormat::Human | OutputFormat::Sarif | OutputFormat::Quiet => profile.print_table(),
+        OutputFormat::Json => {
+            for record in profile.records() {
+                print_json_line(&record)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Report per-file parse measurements as a table or JSON lines.
+fn report_parse_profile(profile: &ParseProfile, output: OutputFormat) -> CliResult<()> {
+    match output {
+        OutputFormat::Human | OutputFormat::Sarif | OutputFormat::Quiet => profile.print_table(),
+        OutputFormat::Json => {
+            for record in profile.records() {
+                print_json_line(record)?;
+            }
+        }
+    }
     Ok(())
 }
@@ -1,8 +1,40 @@
-use crate::cli::commands::ConfigLoader;
-use crate::cli::error::CliResult;
+use crate::cli::commands::config_format::ConfigFormat;
+use crate::cli::commands::config_migration::{ConfigMigrations, VERSION_KEY};
+use crate::cli::commands::init_wizard::{self, ConfigPrompt};
+use crate::cli::commands::{ConfigLoader, ConfigValidators};
+use crate::cli::error::{CliError, CliResult};
+use crate::core::FileWriter;
 use log::info;
 use serde::{de::DeserializeOwned, Serialize};
-use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A named config registered by the formatter binary, selectable with `init --preset`.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    /// Name used on the command line
+    pub name: &'static str,
+    /// The preset config, serialized to YAML
+    pub config: serde_yaml::Value,
+}
+
+/// Options controlling the init command.
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    /// Overwrite an existing config file with the defaults
+    pub force: bool,
+    /// Add fields missing from an existing config file, keeping user values
+    pub update: bool,
+    /// Prompt for every field instead of writing the defaults
+    pub interactive: bool,
+    /// Write this preset instead of the defaults
+    pub preset: Option<Preset>,
+    /// Migrations of older config files; new files get the latest version
+    pub migrations: ConfigMigrations,
+    /// Checks run on existing files and presets
+    pub validators: ConfigValidators,
+}
 
 /// Execute the init command to create or validate a configuration file.
 ///
@@ -10,32 +42,136 @@ use std::path::PathBuf;
 /// 1. Check if config file exists and validate it
 /// 2. Create a default config file if it doesn't exist
 ///
+/// With `force`, an existing file is replaced by the defaults; with
+/// `update`, fields added to the config since the file was written are
+/// merged into it. With `interactive`, the user is asked for every field.
+/// With `preset`, the preset takes the place of the defaults. New files are
+/// written in the format matching the extension of `config_path`. Existing
+/// files of an older version are migrated by `update`.
+///
 /// # Arguments
 /// * `config_path` - Path where the config file should be created or validated
+/// * `options` - Options controlling how an existing file is treated
+/// * `prompt` - Field descriptions for interactive mode, if registered
 ///
 /// # Returns
 /// `Ok(())` on success, or an error if validation or creation fails
-pub fn execute<Config>(config_path: PathBuf) -> CliResult<()>
+pub fn execute<Config>(
+    config_path: PathBuf,
+    options: &InitOptions,
+    prompt: Option<&dyn ConfigPrompt>,
+) -> CliResult<()>
 where
     Config: Serialize + DeserializeOwned + Default,
 {
-    if ConfigLoader::exists(&config_path)? {
-        info!("Config file already exists, validating...");
-        ConfigLoader::validate::<Config>(&config_path)?;
-        info!("✓ Config at {} is valid.", config_path.display());
+    let exists = ConfigLoader::exists(&config_path)?;
+    let replaces_file = options.interactive || options.preset.is_some();
+    if exists && replaces_file && !options.force {
+        return Err(CliError::ConfigAlreadyExists { path: config_path });
+    }
+
+    if let Some(preset) = &options.preset {
+        info!("Using preset '{}'", preset.name);
+        let config = serde_yaml::from_value::<Config>(preset.config.clone())?;
+        options.validators.check(&config, &config_path)?;
+    }
+
+    if options.interactive {
+        let initial = match &options.preset {
+            Some(preset) => preset.config.clone(),
+            None => serde_yaml::to_value(Config::default())?,
+        };
+        return execute_interactive::<Config>(&config_path, initial, prompt, &options.migrations);
+    }
+
+    if exists {
+        if options.force {
+            info!("Config file already exists, overwriting...");
+            create_initial_file::<Config>(&config_path, options)?;
+            info!("✓ Configuration written to {}", config_path.display());
+        } else if options.update {
+            info!("Config file already exists, adding missing fields...");
+            let added = ConfigLoader::update_file::<Config>(&config_path, &options.migrations)?;
+            if added.is_empty() {
+                info!("✓ Config at {} is up to date.", config_path.display());
+            } else {
+                info!(
+                    "✓ Added {} field(s) to {}: {}",
+                    added.len(),
+                    config_path.display(),
+                    added.join(", ")
+                );
+            }
+        } else {
+            info!("Config file already exists, validating...");
+            ConfigLoader::validate::<Config>(
+                &config_path,
+                &options.migrations,
+                &options.validators,
+            )?;
+            info!("✓ Config at {} is valid.", config_path.display());
+        }
     } else {
         ConfigLoader::check_extension(&config_path)?;
         info!(
-            "Config file not found. Creating default at {}...",
-            config_path.display()
-        );
-        ConfigLoader::create_default_file::<Config>(&config_path)?;
-        info!(
-            "✓ Default configuration created at {}",
+            "Config file not found. Creating it at {}...",
             config_path.display()
         );
+        create_initial_file::<Config>(&config_path, options)?;
+        info!("✓ Configuration created at {}", config_path.display());
     }
 
     info!("✓ Configuration available at: {}", config_path.display());
     Ok(())
 }
+
+/// Write the preset, or the default config if no preset is chosen, at the latest version.
+fn create_initial_file<Config>(config_path: &Path, options: &InitOptions) -> CliResult<()>
+where
+    Config: Serialize + Default,
+{
+    match &options.preset {
+        Some(preset) => {
+            let mut config = preset.config.clone();
+            options.migrations.stamp(&mut config);
+            ConfigLoader::create_file(config_path, &config)
+        }
+        None => ConfigLoader::create_default_file::<Config>(config_path, &options.migrations),
+    }
+}
+
+/// Ask for every config field, starting from `initial`, and write the answers to `config_path`.
+fn execute_interactive<Config>(
+    config_path: &Path,
+    initial: serde_yaml::Value,
+    prompt: Option<&dyn ConfigPrompt>,
+    migrations: &ConfigMigrations,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    let format = ConfigLoader::format_of(config_path)?;
+
+    let yaml =
+        init_wizard::run::<Config>(initial, &mut io::stdin().lock(), &mut io::stdout(), prompt)?;
+    // The field descriptions are YAML comments, so other formats go without them
+    let content = match format {
+        ConfigFormat::Yaml if migrations.is_empty() => yaml,
+        ConfigFormat::Yaml => format!("{VERSION_KEY}: {}\n{yaml}", migrations.latest()),
+        format => {
+            let mut config = serde_yaml::from_str::<serde_yaml::Value>(&yaml)?;
+            migrations.stamp(&mut config);
+            format.render(&config)?
+        }
+    };
+
+    if let Some(parent) = config_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    FileWriter::new().write(config_path, &content)?;
+
+    info!("✓ Configuration written to {}", config_path.display());
+    Ok(())
+}
@@ -0,0 +1,293 @@
+use crate::cli::error::CliResult;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::Value;
+use std::io::{BufRead, Write};
+
+/// Describes config fields for `init --interactive`.
+///
+/// Register an implementation with `CliBuilder::config_prompt`. Fields are
+/// addressed by their dotted path (e.g. `indent.width`).
+///
+/// # Examples
+/// ```ignore
+/// struct MyPrompt;
+///
+/// impl ConfigPrompt for MyPrompt {
+///     fn description(&self, field: &str) -> Option<String> {
+///         match field {
+///             "indent" => Some("Number of spaces per indentation level".to_string()),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait ConfigPrompt {
+    /// Describe a field; shown while prompting and written as a comment above it.
+    fn description(&self, _field: &str) -> Option<String> {
+        None
+    }
+}
+
+/// A config field the wizard prompts for.
+struct Field {
+    /// Keys leading from the root mapping to the field
+    keys: Vec<Value>,
+    /// Dotted path of the field
+    name: String,
+    /// Default value of the field
+    default: Value,
+}
+
+/// Prompt for every config field and render the answers as annotated YAML.
+///
+/// Each prompt shows the field's default; an empty answer keeps it. Answers
+/// are parsed as YAML and re-asked if the config would no longer load. When
+/// `input` ends, the remaining fields keep their defaults.
+///
+/// # Arguments
+/// * `initial` - Values shown as defaults, e.g. `Config::default()` or a preset
+/// * `input` - Where answers are read from
+/// * `output` - Where prompts are written to
+/// * `prompt` - Field descriptions, if registered
+///
+/// # Returns
+/// The YAML config file content
+pub fn run<Config>(
+    initial: Value,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    prompt: Option<&dyn ConfigPrompt>,
+) -> CliResult<String>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    let mut value = initial;
+    let mut fields = Vec::new();
+    collect_fields(&value, &mut Vec::new(), &mut fields);
+
+    'fields: for field in fields {
+        if let Some(description) = prompt.and_then(|p| p.description(&field.name)) {
+            writeln!(output, "# {description}")?;
+        }
+
+        loop {
+            write!(
+                output,
+                "{} [{}]: ",
+                field.name,
+                serde_json::to_string(&field.default)?
+            )?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                break 'fields;
+            }
+
+            let answer = line.trim();
+            if answer.is_empty() {
+                break;
+            }
+
+            let mut candidate = value.clone();
+            let parsed = serde_yaml::from_str::<Value>(answer).and_then(|new_value| {
+                set_field(&mut candidate, &field.keys, new_value);
+                serde_yaml::from_value::<Config>(candidate.clone())
+            });
+            match parsed {
+                Ok(_) => {
+                    value = candidate;
+                    break;
+                }
+                Err(err) => writeln!(output, "Invalid value: {err}")?,
+            }
+        }
+    }
+
+    let mut yaml = String::new();
+    render(&value, prompt, "", 0, &mut yaml)?;
+    Ok(yaml)
+}
+
+/// Collect the leaf fields of a config value, depth first.
+fn collect_fields(value: &Value, keys: &mut Vec<Value>, fields: &mut Vec<Field>) {
+    match value.as_mapping() {
+        Some(mapping) if !mapping.is_empty() => {
+            for (key, child) in mapping {
+                keys.push(key.clone());
+                collect_fields(child, keys, fields);
+                keys.pop();
+            }
+        }
+        _ if !keys.is_empty() => fields.push(Field {
+            keys: keys.clone(),
+            name: dotted_name(keys),
+            default: value.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Replace the field at `keys` with `new_value`.
+fn set_field(value: &mut Value, keys: &[Value], new_value: Value) {
+    match keys.split_first() {
+        Some((key, rest)) => {
+            if let Some(child) = value.get_mut(key) {
+                set_field(child, rest, new_value);
+            }
+        }
+        None => *value = new_value,
+    }
+}
+
+/// Join keys into a dotted path.
+fn dotted_name(keys: &[Value]) -> String {
+    keys.iter().map(key_text).collect::<Vec<_>>().join(".")
+}
+
+/// Render a mapping key as YAML text.
+fn key_text(key: &Value) -> String {
+    match key.as_str() {
+        Some(key) => key.to_string(),
+        None => serde_yaml::to_string(key)
+            .map(|text| text.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Render a config value as block YAML with description comments.
+fn render(
+    value: &Value,
+    prompt: Option<&dyn ConfigPrompt>,
+    prefix: &str,
+    depth: usize,
+    out: &mut String,
+) -> CliResult<()> {
+    let Some(mapping) = value.as_mapping().filter(|mapping| !mapping.is_empty()) else {
+        out.push_str(&serde_yaml::to_string(value)?);
+        return Ok(());
+    };
+
+    let indent = "  ".repeat(depth);
+    for (key, child) in mapping {
+        let key = key_text(key);
+        let name = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        if let Some(description) = prompt.and_then(|p| p.description(&name)) {
+            for line in description.lines() {
+                out.push_str(&format!("{indent}# {line}\n"));
+            }
+        }
+
+        if child.as_mapping().is_some_and(|m| !m.is_empty()) {
+            out.push_str(&format!("{indent}{key}:\n"));
+            render(child, prompt, &name, depth + 1, out)?;
+            continue;
+        }
+
+        let rendered = serde_yaml::to_string(child)?;
+        let rendered = rendered.trim_end();
+        let is_block = child.as_sequence().is_some_and(|seq| !seq.is_empty());
+        if is_block || rendered.contains('\n') {
+            out.push_str(&format!("{indent}{key}:\n"));
+            for line in rendered.lines() {
+                out.push_str(&format!("{indent}  {line}\n"));
+            }
+        } else {
+            out.push_str(&format!("{indent}{key}: {rendered}\n"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestConfig {
+        width: usize,
+        style: Style,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Style {
+        tabs: bool,
+        quote: String,
+    }
+
+    impl Default for TestConfig {
+        fn default() -> Self {
+            Self {
+                width: 80,
+                style: Style {
+                    tabs: false,
+                    quote: "double".to_string(),
+                },
+                tags: vec!["a".to_string(), "b".to_string()],
+            }
+        }
+    }
+
+    struct TestPrompt;
+
+    impl ConfigPrompt for TestPrompt {
+        fn description(&self, field: &str) -> Option<String> {
+            (field == "style.tabs").then(|| "Indent with tabs".to_string())
+        }
+    }
+
+    fn run_with(input: &str) -> (String, String) {
+        let mut output = Vec::new();
+        let initial = serde_yaml::to_value(TestConfig::default()).unwrap();
+        let yaml = run::<TestConfig>(
+            initial,
+            &mut input.as_bytes(),
+            &mut output,
+            Some(&TestPrompt),
+        )
+        .unwrap();
+        (yaml, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_answers_and_defaults() {
+        let (yaml, output) = run_with("100\ntrue\n\n[x]\n");
+
+        assert!(output.contains("width [80]: "));
+        assert!(output.contains("# Indent with tabs\nstyle.tabs [false]: "));
+        assert_eq!(
+            yaml,
+            "width: 100\nstyle:\n  # Indent with tabs\n  tabs: true\n  quote: double\ntags:\n  - x\n"
+        );
+
+        let config: TestConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config.width, 100);
+        assert!(config.style.tabs);
+        assert_eq!(config.tags, vec!["x"]);
+    }
+
+    #[test]
+    fn test_invalid_answer_is_asked_again() {
+        let (yaml, output) = run_with("wide\n120\n");
+
+        assert!(output.contains("Invalid value"));
+        assert_eq!(output.matches("width [80]: ").count(), 2);
+        let config: TestConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config.width, 120);
+    }
+
+    #[test]
+    fn test_end_of_input_keeps_defaults() {
+        let (yaml, _) = run_with("");
+        let config: TestConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config, TestConfig::default());
+    }
+}
@@ -0,0 +1,77 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Read and write timings of a single file.
+#[derive(Debug, Clone, Serialize)]
+pub struct IoTiming {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Path of the file
+    pub path: PathBuf,
+    /// Size of the file as read, in bytes
+    pub bytes: usize,
+    /// Time spent reading the file, in microseconds
+    pub read_us: u128,
+    /// Time spent writing the file, in microseconds, if it was written
+    pub write_us: Option<u128>,
+}
+
+/// Per-file IO timings collected for `--profile-io`.
+#[derive(Debug, Default)]
+pub struct IoProfile {
+    timings: Vec<IoTiming>,
+}
+
+impl IoProfile {
+    /// Create an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the time spent reading a file.
+    pub fn record_read(&mut self, path: &Path, bytes: usize, duration: Duration) {
+        self.timings.push(IoTiming {
+            kind: "io_profile",
+            path: path.to_path_buf(),
+            bytes,
+            read_us: duration.as_micros(),
+            write_us: None,
+        });
+    }
+
+    /// Record the time spent writing a previously read file.
+    pub fn record_write(&mut self, path: &Path, duration: Duration) {
+        if let Some(timing) = self.timings.iter_mut().find(|t| t.path == path) {
+            timing.write_us = Some(duration.as_micros());
+        }
+    }
+
+    /// Get the recorded timings in read order.
+    pub fn timings(&self) -> &[IoTiming] {
+        &self.timings
+    }
+
+    /// Print the profile as a table on stderr, slowest files first.
+    pub fn print_table(&self) {
+        let mut rows: Vec<&IoTiming> = self.timings.iter().collect();
+        rows.sort_by_key(|t| std::cmp::Reverse(t.read_us + t.write_us.unwrap_or(0)));
+
+        eprintln!(
+            "{:>12} {:>12} {:>12}  path",
+            "bytes", "read (us)", "write (us)"
+        );
+        for t in rows {
+            let write = t
+                .write_us
+                .map_or_else(|| "-".to_string(), |w| w.to_string());
+            eprintln!(
+                "{:>12} {:>12} {:>12}  {}",
+                t.bytes,
+                t.read_us,
+                write,
+                t.path.display()
+            );
+        }
+    }
+}
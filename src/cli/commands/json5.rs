@@ -0,0 +1,337 @@
+//! Conversion of JSON5 config files to JSON.
+//!
+//! JSON5 adds comments, trailing commas, unquoted keys, single-quoted
+//! strings and more lenient numbers to JSON. The text is rewritten to
+//! plain JSON for `serde_json`, keeping every value on its line so errors
+//! reported on the JSON point at the right line of the JSON5 file.
+
+use crate::cli::error::{CliError, CliResult};
+
+/// Convert JSON5 `source` to JSON.
+///
+/// `Infinity` and `NaN` have no JSON equivalent and are rejected.
+///
+/// # Returns
+/// The JSON text, or `CliError::Json5Error` locating the first problem
+pub(crate) fn to_json(source: &str) -> CliResult<String> {
+    Converter {
+        chars: source.chars().collect(),
+        pos: 0,
+        out: String::with_capacity(source.len()),
+    }
+    .convert()
+}
+
+struct Converter {
+    chars: Vec<char>,
+    pos: usize,
+    out: String,
+}
+
+impl Converter {
+    fn convert(mut self) -> CliResult<String> {
+        while let Some(c) = self.peek(0) {
+            match c {
+                '/' if matches!(self.peek(1), Some('/' | '*')) => self.comment()?,
+                '"' | '\'' => self.string(c)?,
+                ',' => {
+                    self.pos += 1;
+                    let closes = matches!(self.next_significant(), Some(']' | '}'));
+                    self.out.push(if closes { ' ' } else { ',' });
+                }
+                c if c.is_ascii_digit() || matches!(c, '+' | '-' | '.') => self.number()?,
+                c if c.is_alphabetic() || c == '_' || c == '$' => self.identifier()?,
+                c => {
+                    self.out.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(self.out)
+    }
+
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.pos + ahead).copied()
+    }
+
+    /// Get the next character that is not whitespace or part of a comment.
+    fn next_significant(&self) -> Option<char> {
+        let mut i = self.pos;
+        loop {
+            match (self.chars.get(i)?, self.chars.get(i + 1)) {
+                (c, _) if c.is_whitespace() => i += 1,
+                ('/', Some('/')) => {
+                    while self.chars.get(i).is_some_and(|&c| c != '\n') {
+                        i += 1;
+                    }
+                }
+                ('/', Some('*')) => {
+                    i += 2;
+                    while !(self.chars.get(i)? == &'*' && self.chars.get(i + 1) == Some(&'/')) {
+                        i += 1;
+                    }
+                    i += 2;
+                }
+                (&c, _) => return Some(c),
+            }
+        }
+    }
+
+    /// Replace a comment by spaces, keeping its line breaks.
+    fn comment(&mut self) -> CliResult<()> {
+        let start = self.pos;
+        let block = self.peek(1) == Some('*');
+        self.pos += 2;
+        self.out.push_str("  ");
+        loop {
+            match self.peek(0) {
+                None if block => return Err(self.error_at(start, "unterminated comment")),
+                None => return Ok(()),
+                Some('\n') if !block => return Ok(()),
+                Some('*') if block && self.peek(1) == Some('/') => {
+                    self.pos += 2;
+                    self.out.push_str("  ");
+                    return Ok(());
+                }
+                Some(c) => {
+                    self.out.push(if c == '\n' { '\n' } else { ' ' });
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// Copy a string quoted with `quote` as a double-quoted JSON string.
+    fn string(&mut self, quote: char) -> CliResult<()> {
+        let start = self.pos;
+        self.pos += 1;
+        self.out.push('"');
+        loop {
+            let Some(c) = self.peek(0) else {
+                return Err(self.error_at(start, "unterminated string"));
+            };
+            self.pos += 1;
+            match c {
+                c if c == quote => break,
+                '\n' => return Err(self.error_at(start, "unterminated string")),
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.escape(start)?,
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+        Ok(())
+    }
+
+    /// Translate the escape sequence after a backslash to JSON.
+    fn escape(&mut self, start: usize) -> CliResult<()> {
+        let Some(c) = self.peek(0) else {
+            return Err(self.error_at(start, "unterminated string"));
+        };
+        self.pos += 1;
+        match c {
+            '\'' => self.out.push('\''),
+            // A line continuation
+            '\n' => {}
+            '\r' => {
+                if self.peek(0) == Some('\n') {
+                    self.pos += 1;
+                }
+            }
+            '0' => self.out.push_str("\\u0000"),
+            'v' => self.out.push_str("\\u000b"),
+            'x' => {
+                let digits: String = self.chars.iter().skip(self.pos).take(2).collect();
+                let code = u8::from_str_radix(&digits, 16)
+                    .map_err(|_| self.error_at(self.pos - 2, "invalid \\x escape"))?;
+                self.pos += 2;
+                self.out.push_str(&format!("\\u{code:04x}"));
+            }
+            '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u' => {
+                self.out.push('\\');
+                self.out.push(c);
+            }
+            c => self.out.push(c),
+        }
+        Ok(())
+    }
+
+    /// Copy a number, rewriting the forms JSON lacks.
+    fn number(&mut self) -> CliResult<()> {
+        let start = self.pos;
+        let mut sign = "";
+        if let Some(c @ ('+' | '-')) = self.peek(0) {
+            sign = if c == '-' { "-" } else { "" };
+            self.pos += 1;
+        }
+        if self.peek(0).is_some_and(char::is_alphabetic) {
+            return self.identifier().map_err(|_| {
+                self.error_at(start, "Infinity and NaN cannot be used in config files")
+            });
+        }
+
+        let hex = self.peek(0) == Some('0') && matches!(self.peek(1), Some('x' | 'X'));
+        if hex {
+            self.pos += 2;
+        }
+        let mut text = String::new();
+        while let Some(c) = self.peek(0) {
+            let exponent_sign =
+                matches!(c, '+' | '-') && !hex && matches!(text.chars().last(), Some('e' | 'E'));
+            if !(c.is_ascii_hexdigit() || c == '.' || exponent_sign)
+                || (!hex && c.is_alphabetic() && !matches!(c, 'e' | 'E'))
+            {
+                break;
+            }
+            text.push(c);
+            self.pos += 1;
+        }
+
+        if hex {
+            let value = u64::from_str_radix(&text, 16)
+                .map_err(|_| self.error_at(start, "invalid hexadecimal number"))?;
+            self.out.push_str(&format!("{sign}{value}"));
+            return Ok(());
+        }
+        if text.is_empty() {
+            return Err(self.error_at(start, "invalid number"));
+        }
+        if text.starts_with('.') {
+            text.insert(0, '0');
+        }
+        if let Some(dot) = text.find('.') {
+            if !text[dot + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+                text.insert(dot + 1, '0');
+            }
+        }
+        self.out.push_str(sign);
+        self.out.push_str(&text);
+        Ok(())
+    }
+
+    /// Copy a literal, or quote an unquoted key.
+    ///
+    /// Any identifier followed by a colon is a key, even one spelled like a
+    /// literal, such as `null` in `{ null: 1 }`.
+    fn identifier(&mut self) -> CliResult<()> {
+        let start = self.pos;
+        while self
+            .peek(0)
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        match name.as_str() {
+            _ if self.next_significant() == Some(':') => {
+                self.out.push('"');
+                self.out.push_str(&name);
+                self.out.push('"');
+            }
+            "true" | "false" | "null" => self.out.push_str(&name),
+            "Infinity" | "NaN" => {
+                return Err(self.error_at(start, "Infinity and NaN cannot be used in config files"))
+            }
+            _ => return Err(self.error_at(start, &format!("unexpected identifier '{name}'"))),
+        }
+        Ok(())
+    }
+
+    fn error_at(&self, pos: usize, message: &str) -> CliError {
+        let before = &self.chars[..pos.min(self.chars.len())];
+        let line_start = before.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+        CliError::Json5Error {
+            message: message.to_string(),
+            line: before.iter().filter(|&&c| c == '\n').count() + 1,
+            column: pos - line_start + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_to_json() {
+        let source = r#"// Formatter settings
+{
+  width: 80, /* columns */
+  'name': 'it\'s "fine"',
+  $id: 0x1F,
+  ratio: .5,
+  scale: +2.,
+  big: 1e+3,
+  list: [1, 2, 3,],
+  escaped: '\x41\
+B',
+}
+"#;
+
+        let json = to_json(source).unwrap();
+
+        assert_eq!(json.lines().count(), source.lines().count() - 1);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "width": 80,
+                "name": "it's \"fine\"",
+                "$id": 31,
+                "ratio": 0.5,
+                "scale": 2.0,
+                "big": 1000.0,
+                "list": [1, 2, 3],
+                "escaped": "AB",
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_json_quotes_keys_spelled_like_literals() {
+        let json = to_json("{ null: 1, true: 2, false /* key */ : 3, Infinity: null, NaN: true }")
+            .unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "null": 1,
+                "true": 2,
+                "false": 3,
+                "Infinity": null,
+                "NaN": true,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "{\n  a: Infinity\n}",
+        "Infinity and NaN cannot be used in config files",
+        2,
+        6
+    )]
+    #[case(
+        "{\n  a: -NaN\n}",
+        "Infinity and NaN cannot be used in config files",
+        2,
+        6
+    )]
+    #[case("{\n  a: yes\n}", "unexpected identifier 'yes'", 2, 6)]
+    #[case("{ a: 'open\n}", "unterminated string", 1, 6)]
+    #[case("{ /* a: 1 }", "unterminated comment", 1, 3)]
+    fn test_to_json_errors(
+        #[case] source: &str,
+        #[case] message: &str,
+        #[case] line: usize,
+        #[case] column: usize,
+    ) {
+        let err = to_json(source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("JSON5 parsing error: {message} at line {line} column {column}")
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::cli::cli_entry::OutputFormat;
+use crate::cli::commands::run_status::print_json_line;
+use crate::cli::commands::{ConfigLayers, ConfigLoader, ConfigMigrations};
+use crate::cli::error::CliResult;
+use crate::pipeline::Pipeline;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+
+/// Stages of a run, as listed in `PassListing::stage`.
+const STAGE_FILE: &str = "file";
+const STAGE_CONSISTENCY: &str = "consistency";
+const STAGE_PROJECT: &str = "project";
+
+/// A pass of the pipeline, as listed by `list-passes`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PassListing {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Name of the pass
+    pub name: &'static str,
+    /// When the pass runs: `file`, `consistency` or `project`
+    pub stage: &'static str,
+    /// Name of the phase of the pass, if it belongs to a named one
+    pub phase: Option<&'static str>,
+    /// Whether the config enables the pass
+    pub enabled: bool,
+    /// One-line description of the pass, if any
+    pub description: Option<&'static str>,
+    /// Keys of the config the pass reads
+    pub config_keys: &'static [&'static str],
+    /// Codes of the rules the pass checks
+    pub rules: Vec<&'static str>,
+}
+
+impl PassListing {
+    /// Create a listing of an enabled pass without config keys or rules.
+    fn new(name: &'static str, stage: &'static str, description: Option<&'static str>) -> Self {
+        Self {
+            kind: "pass",
+            name,
+            stage,
+            phase: None,
+            enabled: true,
+            description,
+            config_keys: &[],
+            rules: Vec::new(),
+        }
+    }
+
+    /// Render the listing for humans, one line for the pass and one per detail.
+    fn lines(&self) -> Vec<String> {
+        let mut notes = vec![if self.enabled { "enabled" } else { "disabled" }.to_string()];
+        if let Some(phase) = self.phase {
+            notes.push(format!("phase {phase}"));
+        }
+        if self.stage != STAGE_FILE {
+            notes.push(self.stage.to_string());
+        }
+        let mut header = format!("{} [{}]", self.name, notes.join(", "));
+        if let Some(description) = self.description {
+            header.push_str(": ");
+            header.push_str(description);
+        }
+
+        let mut lines = vec![header];
+        if !self.config_keys.is_empty() {
+            lines.push(format!("    config: {}", self.config_keys.join(", ")));
+        }
+        if !self.rules.is_empty() {
+            lines.push(format!("    rules: {}", self.rules.join(", ")));
+        }
+        lines
+    }
+}
+
+/// Execute the list-passes command: print the passes of the pipeline in run order.
+///
+/// # Arguments
+/// * `pipeline` - The pipeline of the formatter, with its order resolved
+/// * `config_path` - Path to the config deciding which passes are enabled;
+///   the defaults are used if it does not exist, unless `require_config` is set
+/// * `layers` - System and user configs the config is merged over
+/// * `output` - Output format of the listing
+/// * `require_config` - Fail if the config file does not exist
+///
+/// # Returns
+/// `Ok(())` on success, or an error if the config cannot be loaded
+pub fn execute<Config>(
+    pipeline: &Pipeline<Config>,
+    config_path: &Path,
+    layers: &ConfigLayers,
+    output: OutputFormat,
+    require_config: bool,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    if require_config {
+        ConfigLoader::require(config_path)?;
+    }
+    let config =
+        ConfigLoader::load_layered::<Config>(config_path, layers, &[], &ConfigMigrations::new())?;
+    for listing in list(pipeline, &config) {
+        // Only `format` accepts SARIF and quiet output
+        match output {
+            OutputFormat::Human | OutputFormat::Sarif | OutputFormat::Quiet => {
+                for line in listing.lines() {
+                    println!("{line}");
+                }
+            }
+            OutputFormat::Json => print_json_line(&listing)?,
+        }
+    }
+    Ok(())
+}
+
+/// List every pass of `pipeline`, per-file passes first, as they run.
+fn list<Config>(pipeline: &Pipeline<Config>, config: &Config) -> Vec<PassListing> {
+    let mut listings = Vec::new();
+    for phase in pipeline.phases() {
+        for pass in phase.passes() {
+            listings.push(PassListing {
+                phase: phase.name(),
+                enabled: pass.enabled(config),
+                config_keys: pass.config_keys(),
+                rules: pass.rules().iter().map(|rule| rule.code).collect(),
+                ..PassListing::new(pass.name(), STAGE_FILE, pass.description())
+            });
+        }
+    }
+    listings.extend(
+        pipeline
+            .consistency_passes()
+            .iter()
+            .map(|pass| PassListing::new(pass.name(), STAGE_CONSISTENCY, pass.description())),
+    );
+    listings.extend(
+        pipeline
+            .project_passes()
+            .iter()
+            .map(|pass| PassListing::new(pass.name(), STAGE_PROJECT, pass.description())),
+    );
+    listings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{
+        BlankLinesPass, FinalNewlinePass, TrailingWhitespacePass, WhitespaceConfig,
+    };
+
+    #[test]
+    fn test_list_shows_enabled_passes_and_keys() {
+        let mut whitespace = Pipeline::new();
+        whitespace
+            .add_pass(TrailingWhitespacePass::new())
+            .add_pass(BlankLinesPass::new());
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(FinalNewlinePass::new())
+            .add_phase("whitespace", whitespace);
+        let config = WhitespaceConfig {
+            max_blank_lines: None,
+            ..WhitespaceConfig::default()
+        };
+
+        let lines: Vec<String> = list(&pipeline, &config)
+            .iter()
+            .flat_map(PassListing::lines)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "FinalNewlinePass [enabled]: End files with exactly one newline",
+                "    config: whitespace.final_newline",
+                "TrailingWhitespacePass [enabled, phase whitespace]: Remove spaces and tabs at the end of lines",
+                "    config: whitespace.trim_trailing_whitespace",
+                "BlankLinesPass [disabled, phase whitespace]: Collapse runs of blank lines",
+                "    config: whitespace.max_blank_lines",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_listing_json() {
+        let listing = PassListing::new("Sorter", STAGE_PROJECT, None);
+        assert_eq!(
+            serde_json::to_string(&listing).unwrap(),
+            r#"{"type":"pass","name":"Sorter","stage":"project","phase":null,"enabled":true,"description":null,"config_keys":[],"rules":[]}"#
+        );
+    }
+}
@@ -1,11 +1,53 @@
+mod config_error;
+mod config_flags;
+mod config_format;
+mod config_hierarchy;
+mod config_layers;
 mod config_loader;
+mod config_migration;
+mod config_override;
+mod config_show;
+mod config_validation;
+mod edit_log;
+mod explain;
 mod file_collector;
 mod file_reader;
+mod first_difference;
 mod format;
 mod init;
+mod init_wizard;
+mod io_profile;
+mod json5;
+mod list_passes;
+mod parse_profile;
+mod pass_profile;
+mod reporter;
+mod run_status;
+mod runner_config;
+mod sarif;
+mod unknown_keys;
+mod validate;
+mod workspace_root;
 
+pub use config_flags::ConfigFlag;
+pub use config_layers::ConfigLayers;
 pub use config_loader::ConfigLoader;
+pub use config_migration::{ConfigMigrations, ConfigMigrator};
+pub use config_override::ConfigOverride;
+pub use config_show::execute as config_show;
+pub use config_validation::{ConfigError, ConfigValidators};
+pub use explain::execute as explain;
 pub use file_collector::FileCollector;
-pub use file_reader::FileReader;
-pub use format::execute as format;
-pub use init::execute as init;
+#[cfg(feature = "mmap")]
+pub use file_reader::MappedFile;
+pub use file_reader::{FileReader, ReadOutcome};
+pub use format::{execute as format, FormatOptions};
+pub use init::{execute as init, InitOptions, Preset};
+pub use init_wizard::ConfigPrompt;
+pub use list_passes::execute as list_passes;
+pub use reporter::{reporter_for, HumanReporter, JsonReporter, QuietReporter, Reporter};
+pub use run_status::{FileRecord, RunStatus};
+pub use runner_config::{CollectorConfig, ReaderConfig, SymlinkPolicy};
+pub use sarif::SarifReporter;
+pub use validate::execute as validate;
+pub use workspace_root::WorkspaceRoot;
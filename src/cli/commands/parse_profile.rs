@@ -0,0 +1,66 @@
+use crate::core::ParseMetrics;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Measurements of the tree parsed from a single file.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseRecord {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Path of the file
+    pub path: PathBuf,
+    /// Time spent parsing the file, in microseconds
+    pub us: u128,
+    /// Number of levels of the tree
+    pub depth: usize,
+    /// Number of nodes of the tree
+    pub nodes: usize,
+    /// Number of error and missing nodes of the tree
+    pub errors: usize,
+}
+
+/// Per-file parse measurements collected for `--timings`.
+#[derive(Debug, Default)]
+pub struct ParseProfile {
+    records: Vec<ParseRecord>,
+}
+
+impl ParseProfile {
+    /// Record the measurements of the tree parsed from a file.
+    pub fn record(&mut self, path: &Path, metrics: &ParseMetrics) {
+        self.records.push(ParseRecord {
+            kind: "parse_metrics",
+            path: path.to_path_buf(),
+            us: metrics.duration.as_micros(),
+            depth: metrics.depth,
+            nodes: metrics.nodes,
+            errors: metrics.errors,
+        });
+    }
+
+    /// Get the recorded measurements in formatting order.
+    pub fn records(&self) -> &[ParseRecord] {
+        &self.records
+    }
+
+    /// Print the profile as a table on stderr, slowest files first.
+    pub fn print_table(&self) {
+        let mut rows: Vec<&ParseRecord> = self.records.iter().collect();
+        rows.sort_by_key(|r| std::cmp::Reverse(r.us));
+
+        eprintln!(
+            "{:>12} {:>8} {:>10} {:>8}  path",
+            "parse (us)", "depth", "nodes", "errors"
+        );
+        for r in rows {
+            eprintln!(
+                "{:>12} {:>8} {:>10} {:>8}  {}",
+                r.us,
+                r.depth,
+                r.nodes,
+                r.errors,
+                r.path.display()
+            );
+        }
+    }
+}
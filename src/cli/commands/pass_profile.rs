@@ -0,0 +1,154 @@
+use crate::core::PassTiming;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Time spent in one pass on one file, as printed for `--timings --output json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PassTimingRecord<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Path of the file
+    pub path: &'a Path,
+    /// Name of the pass
+    pub pass: &'static str,
+    /// Time spent in the pass, in microseconds
+    pub us: u128,
+}
+
+/// Time spent in one pass across all files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PassSummary<'a> {
+    pass: &'static str,
+    files: usize,
+    total: Duration,
+    max: Duration,
+    slowest: &'a Path,
+}
+
+/// Per-pass timings collected for `--timings`.
+pub struct PassProfile<'a> {
+    timings: Vec<PassTiming>,
+    files: &'a [PathBuf],
+}
+
+impl<'a> PassProfile<'a> {
+    /// Create a profile from engine timings of `files`, indexed like the engine input.
+    pub fn new(timings: Vec<PassTiming>, files: &'a [PathBuf]) -> Self {
+        Self { timings, files }
+    }
+
+    /// Get one record per file and pass, in file order.
+    pub fn records(&self) -> Vec<PassTimingRecord<'_>> {
+        self.timings
+            .iter()
+            .filter_map(|timing| {
+                Some(PassTimingRecord {
+                    kind: "pass_timing",
+                    path: self.files.get(timing.file)?,
+                    pass: timing.pass,
+                    us: timing.duration.as_micros(),
+                })
+            })
+            .collect()
+    }
+
+    /// Sum the timings of each pass, slowest pass first.
+    fn summaries(&self) -> Vec<PassSummary<'_>> {
+        let mut summaries: Vec<PassSummary> = Vec::new();
+        for timing in &self.timings {
+            let Some(path) = self.files.get(timing.file) else {
+                continue;
+            };
+            let duration = timing.duration;
+            match summaries.iter_mut().find(|s| s.pass == timing.pass) {
+                Some(summary) => {
+                    summary.files += 1;
+                    summary.total += duration;
+                    if duration > summary.max {
+                        summary.max = duration;
+                        summary.slowest = path;
+                    }
+                }
+                None => summaries.push(PassSummary {
+                    pass: timing.pass,
+                    files: 1,
+                    total: duration,
+                    max: duration,
+                    slowest: path,
+                }),
+            }
+        }
+        summaries.sort_by_key(|s| std::cmp::Reverse(s.total));
+        summaries
+    }
+
+    /// Print the time spent in each pass as a table on stderr, slowest pass first.
+    pub fn print_table(&self) {
+        eprintln!(
+            "{:>12} {:>12} {:>12} {:>6}  {:<24} slowest file",
+            "total (us)", "mean (us)", "max (us)", "files", "pass"
+        );
+        for summary in self.summaries() {
+            let mean = summary.total / summary.files as u32;
+            eprintln!(
+                "{:>12} {:>12} {:>12} {:>6}  {:<24} {}",
+                summary.total.as_micros(),
+                mean.as_micros(),
+                summary.max.as_micros(),
+                summary.files,
+                summary.pass,
+                summary.slowest.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(file: usize, pass: &'static str, us: u64) -> PassTiming {
+        PassTiming {
+            file,
+            pass,
+            duration: Duration::from_micros(us),
+        }
+    }
+
+    #[test]
+    fn test_summaries_per_pass() {
+        let files = vec![PathBuf::from("a.json"), PathBuf::from("b.json")];
+        let profile = PassProfile::new(
+            vec![
+                timing(0, "Indent", 10),
+                timing(0, "Spacing", 40),
+                timing(1, "Indent", 30),
+                timing(1, "Spacing", 5),
+            ],
+            &files,
+        );
+
+        let summaries = profile.summaries();
+        assert_eq!(
+            summaries,
+            vec![
+                PassSummary {
+                    pass: "Spacing",
+                    files: 2,
+                    total: Duration::from_micros(45),
+                    max: Duration::from_micros(40),
+                    slowest: Path::new("a.json"),
+                },
+                PassSummary {
+                    pass: "Indent",
+                    files: 2,
+                    total: Duration::from_micros(40),
+                    max: Duration::from_micros(30),
+                    slowest: Path::new("b.json"),
+                },
+            ]
+        );
+        assert_eq!(profile.records()[1].path, Path::new("a.json"));
+    }
+}
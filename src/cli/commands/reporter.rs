@@ -0,0 +1,192 @@
+use crate::cli::cli_entry::{FormatMode, OutputFormat};
+use crate::cli::commands::first_difference::TextPosition;
+use crate::cli::commands::run_status::{print_json_line, FileRecord, RunStatus};
+use crate::cli::commands::sarif::SarifReporter;
+use crate::cli::error::CliResult;
+use log::{error, info, warn};
+use std::path::PathBuf;
+
+/// Receives the results of a `format` run and presents them.
+///
+/// `format` calls `on_file` for every file, then `on_finish` once all
+/// files were processed and finally `on_status`, which is called even when
+/// the run stopped on an error. Files that failed are reported after the
+/// others. Every method does nothing by default.
+///
+/// The reporter is chosen with `--output`, or set for every run with
+/// `CliBuilder::reporter`.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::{CliResult, FileRecord, Reporter, RunStatus};
+///
+/// #[derive(Default)]
+/// struct CountReporter {
+///     changed: usize,
+/// }
+///
+/// impl Reporter for CountReporter {
+///     fn on_file(&mut self, record: &FileRecord<'_>) -> CliResult<()> {
+///         self.changed += usize::from(record.changed);
+///         Ok(())
+///     }
+///
+///     fn on_status(&mut self, status: &RunStatus) -> CliResult<()> {
+///         println!("{} of {} file(s) changed", self.changed, status.files_total);
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Reporter {
+    /// Called with the outcome of a file: processed, skipped, ignored or failed.
+    fn on_file(&mut self, _record: &FileRecord<'_>) -> CliResult<()> {
+        Ok(())
+    }
+
+    /// Called once every file was reported, unless the run stopped on an error.
+    ///
+    /// With `--fail-fast`, `mode` is check mode and only the files checked
+    /// before the first unformatted one were reported.
+    fn on_finish(&mut self, _mode: FormatMode) -> CliResult<()> {
+        Ok(())
+    }
+
+    /// Called last with the final status of the run, also when it failed.
+    fn on_status(&mut self, _status: &RunStatus) -> CliResult<()> {
+        Ok(())
+    }
+}
+
+/// Create the reporter for `output`.
+///
+/// # Arguments
+/// * `output` - Output format selected with `--output`
+/// * `tool_name` - Name of the formatter, used in SARIF logs
+pub fn reporter_for(output: OutputFormat, tool_name: &str) -> Box<dyn Reporter> {
+    match output {
+        OutputFormat::Human => Box::new(HumanReporter::default()),
+        OutputFormat::Json => Box::new(JsonReporter),
+        OutputFormat::Sarif => Box::new(SarifReporter::new(tool_name)),
+        OutputFormat::Quiet => Box::new(QuietReporter),
+    }
+}
+
+/// Reports results as log messages and a `summary:` line on stdout.
+///
+/// Files that need or received formatting and failed files are listed once
+/// the run finished.
+#[derive(Debug, Default)]
+pub struct HumanReporter {
+    /// Files that need or received formatting, with their first difference
+    changed: Vec<(PathBuf, Option<TextPosition>)>,
+    /// Files that failed, with their error
+    failed: Vec<(PathBuf, String)>,
+}
+
+impl Reporter for HumanReporter {
+    fn on_file(&mut self, record: &FileRecord<'_>) -> CliResult<()> {
+        if let Some(message) = record.error {
+            self.failed
+                .push((record.path.to_path_buf(), message.to_string()));
+        } else if record.changed {
+            let position = record
+                .line
+                .zip(record.column)
+                .map(|(line, column)| TextPosition { line, column });
+            self.changed.push((record.path.to_path_buf(), position));
+        }
+        Ok(())
+    }
+
+    fn on_finish(&mut self, mode: FormatMode) -> CliResult<()> {
+        match mode {
+            FormatMode::Check => {
+                if self.changed.is_empty() {
+                    info!("✓ All files are formatted correctly!");
+                } else {
+                    warn!(
+                        "✗ The following {} file(s) need formatting:",
+                        self.changed.len()
+                    );
+                    for (file, position) in &self.changed {
+                        match position {
+                            Some(position) => warn!("  - {}:{position}", file.display()),
+                            None => warn!("  - {}", file.display()),
+                        }
+                    }
+                    info!("\nRun with --mode write to apply formatting.");
+                }
+            }
+            FormatMode::Write => {
+                if self.changed.is_empty() {
+                    info!("✓ No files needed formatting!");
+                } else {
+                    info!("✓ Successfully formatted {} file(s):", self.changed.len());
+                    for (file, _) in &self.changed {
+                        info!("  - {}", file.display());
+                    }
+                }
+            }
+        }
+        for (file, message) in &self.failed {
+            error!("✗ {}: {}", file.display(), message);
+        }
+        Ok(())
+    }
+
+    fn on_status(&mut self, status: &RunStatus) -> CliResult<()> {
+        println!("{}", status.summary_line());
+        Ok(())
+    }
+}
+
+/// Reports every file and the final status as JSON lines on stdout.
+#[derive(Debug, Default)]
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn on_file(&mut self, record: &FileRecord<'_>) -> CliResult<()> {
+        print_json_line(record)
+    }
+
+    fn on_status(&mut self, status: &RunStatus) -> CliResult<()> {
+        print_json_line(status)
+    }
+}
+
+/// Reports nothing; the exit code tells the outcome.
+#[derive(Debug, Default)]
+pub struct QuietReporter;
+
+impl Reporter for QuietReporter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_human_reporter_collects_changed_and_failed_files() {
+        let mut reporter = HumanReporter::default();
+        let position = TextPosition { line: 2, column: 5 };
+
+        reporter
+            .on_file(&FileRecord::new(Path::new("a.json"), true).with_position(Some(position)))
+            .unwrap();
+        reporter
+            .on_file(&FileRecord::new(Path::new("b.json"), false))
+            .unwrap();
+        reporter
+            .on_file(&FileRecord::failed(Path::new("c.json"), "unreadable"))
+            .unwrap();
+
+        assert_eq!(
+            reporter.changed,
+            [(PathBuf::from("a.json"), Some(position))]
+        );
+        assert_eq!(
+            reporter.failed,
+            [(PathBuf::from("c.json"), "unreadable".to_string())]
+        );
+    }
+}
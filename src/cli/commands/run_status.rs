@@ -0,0 +1,246 @@
+use crate::cli::commands::first_difference::TextPosition;
+use crate::cli::error::{
+    CliResult, INTERNAL_EXIT_CODE, SUCCESS_EXIT_CODE, UNFORMATTED_EXIT_CODE, USAGE_EXIT_CODE,
+};
+use crate::core::{fnv1a_64, Diagnostic};
+use serde::Serialize;
+use std::path::Path;
+
+/// Outcome of a single file, as reported to a `Reporter` and emitted in JSON output.
+#[derive(Debug, Serialize)]
+pub struct FileRecord<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Path of the file
+    pub path: &'a Path,
+    /// `true` if the file needs (check mode) or received (write mode) formatting
+    pub changed: bool,
+    /// `true` if the file was not processed
+    pub skipped: bool,
+    /// `true` if the file was left unformatted because it disables formatting
+    pub ignored: bool,
+    /// Error that stopped the file from being processed
+    pub error: Option<&'a str>,
+    /// Line of the first difference, if the file needs formatting
+    pub line: Option<usize>,
+    /// Column of the first difference, if the file needs formatting
+    pub column: Option<usize>,
+    /// Diagnostics reported about the file
+    pub diagnostics: &'a [Diagnostic],
+}
+
+impl<'a> FileRecord<'a> {
+    /// Create a record for a processed file.
+    pub fn new(path: &'a Path, changed: bool) -> Self {
+        Self {
+            kind: "file",
+            path,
+            changed,
+            skipped: false,
+            ignored: false,
+            error: None,
+            line: None,
+            column: None,
+            diagnostics: &[],
+        }
+    }
+
+    /// Create a record for a skipped file.
+    pub fn skipped(path: &'a Path) -> Self {
+        Self {
+            kind: "file",
+            path,
+            changed: false,
+            skipped: true,
+            ignored: false,
+            error: None,
+            line: None,
+            column: None,
+            diagnostics: &[],
+        }
+    }
+
+    /// Create a record for a file left unformatted because it disables formatting.
+    pub fn ignored(path: &'a Path) -> Self {
+        Self {
+            ignored: true,
+            ..Self::new(path, false)
+        }
+    }
+
+    /// Create a record for a file that failed with `error`.
+    pub fn failed(path: &'a Path, error: &'a str) -> Self {
+        Self {
+            kind: "file",
+            path,
+            changed: false,
+            skipped: false,
+            ignored: false,
+            error: Some(error),
+            line: None,
+            column: None,
+            diagnostics: &[],
+        }
+    }
+
+    /// Set the position of the first difference between the file and its formatted text.
+    #[must_use]
+    pub fn with_position(mut self, position: Option<TextPosition>) -> Self {
+        self.line = position.map(|p| p.line);
+        self.column = position.map(|p| p.column);
+        self
+    }
+
+    /// Set the diagnostics reported about the file.
+    #[must_use]
+    pub fn with_diagnostics(mut self, diagnostics: &'a [Diagnostic]) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+}
+
+/// Final machine-readable status object emitted at the end of a JSON run.
+///
+/// Wrapper tools can rely on this object alone instead of combining
+/// stdout parsing with the process exit code.
+#[derive(Debug, Serialize)]
+pub struct RunStatus {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Exit code the process will use
+    pub exit_code: i32,
+    /// Number of files processed
+    pub files_total: usize,
+    /// Number of files that need or received formatting
+    pub files_changed: usize,
+    /// Number of files that were skipped
+    pub files_skipped: usize,
+    /// Number of files left unformatted because they disable formatting
+    pub files_ignored: usize,
+    /// Number of files that failed to be read, formatted, or written
+    pub files_failed: usize,
+    /// Number of paths and directories that could not be traversed while collecting files
+    pub traversal_errors: usize,
+    /// Number of error diagnostics reported about formatted files
+    pub diagnostic_errors: usize,
+    /// Wall time of the run in milliseconds
+    pub duration_ms: u128,
+    /// Version of the formatter
+    pub version: &'static str,
+    /// Stable hash of the effective configuration, if it was loaded
+    pub config_hash: Option<String>,
+    /// Error message if the run failed
+    pub error: Option<String>,
+}
+
+impl RunStatus {
+    /// Create an empty status for the formatter at `version`.
+    pub fn new(version: &'static str) -> Self {
+        Self {
+            kind: "status",
+            exit_code: 0,
+            files_total: 0,
+            files_changed: 0,
+            files_skipped: 0,
+            files_ignored: 0,
+            files_failed: 0,
+            traversal_errors: 0,
+            diagnostic_errors: 0,
+            duration_ms: 0,
+            version,
+            config_hash: None,
+            error: None,
+        }
+    }
+}
+
+impl RunStatus {
+    /// Render the status as a single `key=value` line for human output.
+    ///
+    /// The line starts with `summary:` so scripts can find it with `grep`.
+    pub fn summary_line(&self) -> String {
+        let outcome = match self.exit_code {
+            SUCCESS_EXIT_CODE => "ok",
+            UNFORMATTED_EXIT_CODE => "needs-formatting",
+            USAGE_EXIT_CODE => "usage-error",
+            INTERNAL_EXIT_CODE => "internal-error",
+            _ => "error",
+        };
+        format!(
+            "summary: status={outcome} exit_code={} files={} changed={} skipped={} ignored={} failed={} traversal_errors={} diagnostic_errors={} duration_ms={}",
+            self.exit_code,
+            self.files_total,
+            self.files_changed,
+            self.files_skipped,
+            self.files_ignored,
+            self.files_failed,
+            self.traversal_errors,
+            self.diagnostic_errors,
+            self.duration_ms
+        )
+    }
+}
+
+/// Print a serializable record as a single JSON line on stdout.
+pub fn print_json_line<T: Serialize>(record: &T) -> CliResult<()> {
+    println!("{}", serde_json::to_string(record)?);
+    Ok(())
+}
+
+/// Compute a stable hash of a configuration.
+///
+/// The config is serialized to YAML and hashed with 64-bit FNV-1a, so the
+/// result does not depend on the Rust version or process.
+///
+/// # Returns
+/// The hash as a 16-character lowercase hex string
+pub fn config_hash<Config: Serialize>(config: &Config) -> CliResult<String> {
+    let yaml = serde_yaml::to_string(config)?;
+    Ok(format!("{:016x}", fnv1a_64(yaml.as_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct TestConfig {
+        indent: usize,
+    }
+
+    #[test]
+    fn test_config_hash_is_stable_and_distinct() {
+        let a = config_hash(&TestConfig { indent: 2 }).unwrap();
+        let b = config_hash(&TestConfig { indent: 2 }).unwrap();
+        let c = config_hash(&TestConfig { indent: 4 }).unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.len(), 16);
+    }
+
+    #[test]
+    fn test_status_serializes_with_type_tag() {
+        let status = RunStatus::new("9.9.9");
+        let value = serde_json::to_value(&status).unwrap();
+
+        assert_eq!(value["type"], "status");
+        assert_eq!(value["exit_code"], 0);
+        assert_eq!(value["version"], "9.9.9");
+    }
+
+    #[test]
+    fn test_summary_line() {
+        let mut status = RunStatus::new("1.0.0");
+        status.exit_code = UNFORMATTED_EXIT_CODE;
+        status.files_total = 3;
+        status.files_changed = 1;
+        status.duration_ms = 12;
+
+        assert_eq!(
+            status.summary_line(),
+            "summary: status=needs-formatting exit_code=1 files=3 changed=1 skipped=0 ignored=0 failed=0 traversal_errors=0 diagnostic_errors=0 duration_ms=12"
+        );
+    }
+}
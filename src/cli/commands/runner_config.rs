@@ -0,0 +1,146 @@
+use crate::core::{LineEndings, ParseErrorPolicy, RuleLevel};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Key of the section holding fmt-runner's own settings in the config file.
+pub const RUNNER_SECTION: &str = "runner";
+
+/// Settings of fmt-runner itself, read from the `runner` section of the config file.
+///
+/// The section lives next to the formatter's own config fields and is
+/// ignored when deserializing the formatter config.
+///
+/// # Examples
+/// ```yaml
+/// runner:
+///   collector:
+///     sort: false
+///     symlinks: skip
+///     associations: ["*.tpl", "Jenkinsfile"]
+///   reader:
+///     buffer_size: 65536
+///     max_in_memory_size: 1048576
+///     max_file_size: 5242880
+///   on_parse_error: skip
+///   parse_timeout_ms: 5000
+///   line_endings: lf
+///   batch_size: 64
+///   rules:
+///     FMT001: warn
+///   generated_markers:
+///     - "@generated"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunnerConfig {
+    /// File collection settings
+    pub collector: CollectorConfig,
+    /// File reading settings
+    pub reader: ReaderConfig,
+    /// What to do with files that have syntax errors before formatting
+    pub on_parse_error: ParseErrorPolicy,
+    /// Files taking longer than this to parse are left unchanged and
+    /// reported as errors, in milliseconds; no limit if unset
+    pub parse_timeout_ms: Option<u64>,
+    /// Line endings of formatted files
+    pub line_endings: LineEndings,
+    /// Number of files read, formatted and written before the next ones are
+    /// read, bounding the memory of a run; `0` processes all files at once.
+    /// Ignored when the pipeline has consistency or project passes, which
+    /// need every file of the run at once
+    pub batch_size: usize,
+    /// Levels overriding the severity of diagnostics by rule code
+    pub rules: BTreeMap<String, RuleLevel>,
+    /// Texts marking generated files, which are left unformatted when
+    /// found in their first lines
+    pub generated_markers: Vec<String>,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            collector: CollectorConfig::default(),
+            reader: ReaderConfig::default(),
+            on_parse_error: ParseErrorPolicy::default(),
+            parse_timeout_ms: None,
+            line_endings: LineEndings::default(),
+            batch_size: 256,
+            rules: BTreeMap::new(),
+            generated_markers: Vec::new(),
+        }
+    }
+}
+
+/// Settings for `FileCollector`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollectorConfig {
+    /// Sort files found by traversal by path, so runs are reproducible across filesystems
+    pub sort: bool,
+    /// Whether traversal follows symlinked files and directories
+    pub symlinks: SymlinkPolicy,
+    /// Glob patterns of more files to format with the formatter's language,
+    /// on top of the ones it supports; see `FileMatcher::with_patterns`
+    pub associations: Vec<String>,
+}
+
+impl Default for CollectorConfig {
+    fn default() -> Self {
+        Self {
+            sort: true,
+            symlinks: SymlinkPolicy::default(),
+            associations: Vec::new(),
+        }
+    }
+}
+
+/// What `FileCollector` does with symlinks it finds while traversing directories.
+///
+/// Paths given explicitly are always followed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Collect symlinked files and traverse symlinked directories, each directory only once
+    #[default]
+    Follow,
+    /// Ignore symlinks
+    Skip,
+}
+
+impl SymlinkPolicy {
+    const FOLLOW: &'static str = "follow";
+    const SKIP: &'static str = "skip";
+
+    /// Get the string representation of the policy
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SymlinkPolicy::Follow => Self::FOLLOW,
+            SymlinkPolicy::Skip => Self::SKIP,
+        }
+    }
+}
+
+/// Settings for `FileReader`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReaderConfig {
+    /// Buffer size for reading large files, in bytes
+    pub buffer_size: usize,
+    /// Files above this size are read through the buffered slow path, in bytes
+    pub max_in_memory_size: usize,
+    /// Files above this size are skipped with a warning, in bytes
+    pub max_file_size: Option<usize>,
+    /// Read files that are not valid UTF-8 as Latin-1 instead of failing them
+    pub latin1_fallback: bool,
+}
+
+impl Default for ReaderConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 8 * 1024,                // 8KB buffer
+            max_in_memory_size: 10 * 1024 * 1024, // 10MB
+            max_file_size: None,
+            latin1_fallback: false,
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::cli::cli_entry::FormatMode;
+use crate::cli::commands::reporter::Reporter;
+use crate::cli::commands::run_status::{FileRecord, RunStatus};
+use crate::cli::error::{CliResult, SUCCESS_EXIT_CODE, UNFORMATTED_EXIT_CODE};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Version of the SARIF format written by `SarifReporter`.
+const SARIF_VERSION: &str = "2.1.0";
+
+/// Schema of the SARIF format written by `SarifReporter`.
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Rule id of results for files that need formatting.
+const UNFORMATTED_RULE: &str = "unformatted";
+
+/// Rule id of results for files that could not be processed.
+const FILE_ERROR_RULE: &str = "file-error";
+
+/// Rule id of diagnostics that have neither a code nor a pass.
+const DIAGNOSTIC_RULE: &str = "diagnostic";
+
+/// Reports diagnostics, unformatted and failed files as a SARIF log on stdout.
+///
+/// The log is written when the run ends, so code scanning tools can read it
+/// as a single JSON document. In write mode, files that were formatted are
+/// not reported.
+#[derive(Debug)]
+pub struct SarifReporter {
+    /// Name of the tool in the log
+    tool_name: String,
+    /// Results for diagnostics and failed files
+    results: Vec<Value>,
+    /// Results for files that need formatting
+    unformatted: Vec<Value>,
+    /// Mode of the run, once it finished
+    mode: Option<FormatMode>,
+}
+
+impl SarifReporter {
+    /// Create a reporter naming `tool_name` as the tool of the log.
+    pub fn new(tool_name: &str) -> Self {
+        Self {
+            tool_name: tool_name.to_string(),
+            results: Vec::new(),
+            unformatted: Vec::new(),
+            mode: None,
+        }
+    }
+
+    /// Build the SARIF log of the run.
+    fn log(&self, status: &RunStatus) -> Value {
+        let mut results = self.results.clone();
+        if self.mode != Some(FormatMode::Write) {
+            results.extend(self.unformatted.iter().cloned());
+        }
+        let notifications: Vec<Value> = status
+            .error
+            .iter()
+            .map(|message| json!({ "level": "error", "message": { "text": message } }))
+            .collect();
+
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": SARIF_VERSION,
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": self.tool_name,
+                        "version": status.version,
+                    }
+                },
+                "invocations": [{
+                    "executionSuccessful": status.exit_code == SUCCESS_EXIT_CODE
+                        || status.exit_code == UNFORMATTED_EXIT_CODE,
+                    "exitCode": status.exit_code,
+                    "toolExecutionNotifications": notifications,
+                }],
+                "results": results,
+            }]
+        })
+    }
+}
+
+impl Reporter for SarifReporter {
+    fn on_file(&mut self, record: &FileRecord<'_>) -> CliResult<()> {
+        if let Some(message) = record.error {
+            self.results.push(result(
+                FILE_ERROR_RULE,
+                "error",
+                message,
+                location(record.path, None),
+            ));
+        }
+        if record.changed {
+            let region = record
+                .line
+                .zip(record.column)
+                .map(|(line, column)| json!({ "startLine": line, "startColumn": column }));
+            self.unformatted.push(result(
+                UNFORMATTED_RULE,
+                "warning",
+                "File is not formatted",
+                location(record.path, region),
+            ));
+        }
+        for diagnostic in record.diagnostics {
+            let region = diagnostic.range.map(|(start, end)| {
+                json!({ "byteOffset": start, "byteLength": end.saturating_sub(start) })
+            });
+            self.results.push(result(
+                diagnostic
+                    .code
+                    .or(diagnostic.pass)
+                    .unwrap_or(DIAGNOSTIC_RULE),
+                diagnostic.severity.as_str(),
+                &diagnostic.message,
+                location(record.path, region),
+            ));
+        }
+        Ok(())
+    }
+
+    fn on_finish(&mut self, mode: FormatMode) -> CliResult<()> {
+        self.mode = Some(mode);
+        Ok(())
+    }
+
+    fn on_status(&mut self, status: &RunStatus) -> CliResult<()> {
+        println!("{}", serde_json::to_string_pretty(&self.log(status))?);
+        Ok(())
+    }
+}
+
+/// Build a SARIF result.
+fn result(rule: &str, level: &str, message: &str, location: Value) -> Value {
+    json!({
+        "ruleId": rule,
+        "level": level,
+        "message": { "text": message },
+        "locations": [location],
+    })
+}
+
+/// Build a SARIF location in the file at `path`, with an optional region.
+fn location(path: &Path, region: Option<Value>) -> Value {
+    let uri = path.to_string_lossy().replace('\\', "/");
+    let mut physical = json!({ "artifactLocation": { "uri": uri } });
+    if let Some(region) = region {
+        physical["region"] = region;
+    }
+    json!({ "physicalLocation": physical })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::commands::first_difference::TextPosition;
+    use crate::core::Diagnostic;
+
+    #[test]
+    fn test_log_reports_diagnostics_and_unformatted_files() {
+        let mut reporter = SarifReporter::new("jfmt");
+        let mut diagnostic = Diagnostic::warning("Line too long");
+        diagnostic.code = Some("FMT001");
+        diagnostic.range = Some((4, 10));
+        let diagnostics = [diagnostic];
+
+        reporter
+            .on_file(
+                &FileRecord::new(Path::new("src/a.json"), true)
+                    .with_position(Some(TextPosition { line: 3, column: 1 }))
+                    .with_diagnostics(&diagnostics),
+            )
+            .unwrap();
+        reporter
+            .on_file(&FileRecord::failed(Path::new("b.json"), "unreadable"))
+            .unwrap();
+        reporter.on_finish(FormatMode::Check).unwrap();
+
+        let mut status = RunStatus::new("1.0.0");
+        status.exit_code = UNFORMATTED_EXIT_CODE;
+        let log = reporter.log(&status);
+
+        assert_eq!(log["version"], SARIF_VERSION);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "jfmt");
+        assert_eq!(run["invocations"][0]["executionSuccessful"], true);
+        let rules: Vec<_> = run["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["ruleId"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, ["FMT001", FILE_ERROR_RULE, UNFORMATTED_RULE]);
+        let region = &run["results"][0]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["byteOffset"], 4);
+        assert_eq!(region["byteLength"], 6);
+    }
+
+    #[test]
+    fn test_write_mode_omits_formatted_files() {
+        let mut reporter = SarifReporter::new("jfmt");
+        reporter
+            .on_file(&FileRecord::new(Path::new("a.json"), true))
+            .unwrap();
+        reporter.on_finish(FormatMode::Write).unwrap();
+
+        let log = reporter.log(&RunStatus::new("1.0.0"));
+        assert_eq!(log["runs"][0]["results"], json!([]));
+    }
+}
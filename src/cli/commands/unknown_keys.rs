@@ -0,0 +1,195 @@
+use crate::cli::commands::config_migration::VERSION_KEY;
+use crate::cli::commands::runner_config::{RunnerConfig, RUNNER_SECTION};
+use crate::cli::error::CliResult;
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// A key of a config file that the config does not define.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// Path of the key, one segment per mapping level
+    pub path: Vec<String>,
+    /// Dotted path of the closest defined key at the same level, if one is close enough
+    pub suggestion: Option<String>,
+}
+
+impl UnknownKey {
+    /// Get the dotted path of the key.
+    pub fn key(&self) -> String {
+        self.path.join(".")
+    }
+
+    /// Describe the key, with its suggestion if any.
+    pub fn message(&self) -> String {
+        match &self.suggestion {
+            Some(suggestion) => {
+                format!("unknown key '{}', did you mean '{suggestion}'?", self.key())
+            }
+            None => format!("unknown key '{}'", self.key()),
+        }
+    }
+}
+
+/// Find the keys of a config file `value` that `Config`, the runner settings
+/// and the `version` convention do not define.
+///
+/// Keys are compared with those of the default config, so every field must
+/// be serialized by `Config::default()`. Mappings whose default is empty
+/// (e.g. map-typed fields) accept any key.
+///
+/// # Returns
+/// The unknown keys in file order
+pub fn find_unknown_keys<Config>(value: &Value) -> CliResult<Vec<UnknownKey>>
+where
+    Config: Serialize + Default,
+{
+    let mut known = serde_yaml::to_value(Config::default())?;
+    if let Some(mapping) = known.as_mapping_mut() {
+        mapping.insert(
+            Value::from(RUNNER_SECTION),
+            serde_yaml::to_value(RunnerConfig::default())?,
+        );
+        let version = Value::from(VERSION_KEY);
+        if !mapping.contains_key(&version) {
+            mapping.insert(version, Value::from(0));
+        }
+    }
+
+    let mut unknown = Vec::new();
+    collect_unknown_keys(value, &known, &mut Vec::new(), &mut unknown);
+    Ok(unknown)
+}
+
+/// Collect keys of `value` that do not appear in `known`.
+fn collect_unknown_keys(
+    value: &Value,
+    known: &Value,
+    path: &mut Vec<String>,
+    unknown: &mut Vec<UnknownKey>,
+) {
+    let (Some(mapping), Some(known_mapping)) = (value.as_mapping(), known.as_mapping()) else {
+        return;
+    };
+    if known_mapping.is_empty() {
+        return;
+    }
+
+    for (key, child) in mapping {
+        let name = key
+            .as_str()
+            .map_or_else(|| format!("{key:?}"), str::to_string);
+        match known_mapping.get(key) {
+            Some(known_child) => {
+                path.push(name);
+                collect_unknown_keys(child, known_child, path, unknown);
+                path.pop();
+            }
+            None => {
+                let candidates = known_mapping.keys().filter_map(Value::as_str);
+                let suggestion = closest_key(&name, candidates).map(|closest| {
+                    path.iter()
+                        .map(String::as_str)
+                        .chain([closest])
+                        .collect::<Vec<_>>()
+                        .join(".")
+                });
+                let mut key_path = path.clone();
+                key_path.push(name);
+                unknown.push(UnknownKey {
+                    path: key_path,
+                    suggestion,
+                });
+            }
+        }
+    }
+}
+
+/// Get the candidate closest to `name`, if it is a plausible typo of it.
+///
+/// A candidate is plausible when at most a third of `name` has to change,
+/// and at least one edit is always allowed. Ties go to the first candidate.
+fn closest_key<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Count the insertions, deletions, substitutions and swaps of adjacent
+/// characters turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows of the distance table for the two previous and the current prefix of `a`
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[derive(Default, Serialize)]
+    struct TestConfig {
+        indent_size: usize,
+        style: Style,
+    }
+
+    #[derive(Default, Serialize)]
+    struct Style {
+        tabs: bool,
+        quote: String,
+    }
+
+    #[rstest]
+    #[case("indent_size", "indent_size", 0)]
+    #[case("indnet_size", "indent_size", 1)]
+    #[case("indent", "indent_size", 5)]
+    #[case("tabz", "tabs", 1)]
+    #[case("", "abc", 3)]
+    fn test_edit_distance(#[case] a: &str, #[case] b: &str, #[case] expected: usize) {
+        assert_eq!(edit_distance(a, b), expected);
+        assert_eq!(edit_distance(b, a), expected);
+    }
+
+    #[test]
+    fn test_find_unknown_keys_with_suggestions() {
+        let value: Value = serde_yaml::from_str(
+            "version: 1\nindnet_size: 2\nstyle:\n  tabz: true\n  colour: red\nrunnr:\n  jobs: 2\nrunner:\n  batch_sise: 4\n",
+        )
+        .unwrap();
+
+        let unknown = find_unknown_keys::<TestConfig>(&value).unwrap();
+
+        let messages: Vec<_> = unknown.iter().map(UnknownKey::message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unknown key 'indnet_size', did you mean 'indent_size'?",
+                "unknown key 'style.tabz', did you mean 'style.tabs'?",
+                "unknown key 'style.colour'",
+                "unknown key 'runnr', did you mean 'runner'?",
+                "unknown key 'runner.batch_sise', did you mean 'runner.batch_size'?",
+            ]
+        );
+    }
+}
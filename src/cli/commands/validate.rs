@@ -0,0 +1,379 @@
+use crate::cli::cli_entry::OutputFormat;
+use crate::cli::commands::config_error;
+use crate::cli::commands::config_format::{error_location, ConfigFormat};
+use crate::cli::commands::config_migration::ConfigMigrations;
+use crate::cli::commands::run_status::print_json_line;
+use crate::cli::commands::runner_config::{RunnerConfig, RUNNER_SECTION};
+use crate::cli::commands::unknown_keys::find_unknown_keys;
+use crate::cli::commands::{ConfigError, ConfigLoader, ConfigValidators};
+use crate::cli::error::{CliError, CliResult};
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+
+/// A problem found in a config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Description of the problem
+    pub message: String,
+    /// Dotted path of the offending key, if known
+    pub key: Option<String>,
+    /// 1-based line of the problem, if known
+    pub line: Option<usize>,
+    /// 1-based column of the problem, if known
+    pub column: Option<usize>,
+}
+
+impl ConfigIssue {
+    /// Create an issue without a position.
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            kind: "config_issue",
+            message: message.into(),
+            key: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Create an issue from an error reading `content`, keeping its location.
+    fn from_error(err: &CliError, content: &str) -> Self {
+        let mut issue = Self::new(config_error::describe(err));
+        if let Some((line, column)) = error_location(err, content) {
+            issue.line = Some(line);
+            issue.column = Some(column);
+        }
+        issue
+    }
+
+    /// Create an issue from an error of a config validator, locating its key in `content`.
+    fn from_config_error(err: &ConfigError, content: &str) -> Self {
+        match err.key() {
+            Some(key) => {
+                let path: Vec<String> = key.split('.').map(str::to_string).collect();
+                Self::for_key(err.to_string(), &path, content)
+            }
+            None => Self::new(err.message()),
+        }
+    }
+
+    /// Create an issue for a key, locating it in `content`.
+    fn for_key(message: impl Into<String>, key: &[String], content: &str) -> Self {
+        let mut issue = Self::new(message);
+        issue.line = find_key_line(content, key);
+        issue.column = issue.line.and_then(|line| {
+            let text = content.lines().nth(line - 1)?;
+            Some(text.len() - text.trim_start().len() + 1)
+        });
+        issue.key = Some(key.join("."));
+        issue
+    }
+}
+
+/// Execute the validate command: check a config file without formatting anything.
+///
+/// Files of an older version are checked after migrating them. Configs
+/// that load are also checked by the registered validators.
+///
+/// # Arguments
+/// * `config_path` - Path to the config file
+/// * `output` - Output format for the issues
+/// * `migrations` - Migrations applied to files of older versions
+/// * `validators` - Checks of invariants of the deserialized config
+///
+/// # Returns
+/// `Ok(())` if the config is valid, `CliError::InvalidConfig` otherwise
+pub fn execute<Config>(
+    config_path: &Path,
+    output: OutputFormat,
+    migrations: &ConfigMigrations,
+    validators: &ConfigValidators,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    ConfigLoader::require(config_path)?;
+
+    let format = ConfigLoader::format_of(config_path)?;
+    let content = fs::read_to_string(config_path)?;
+    let mut issues = validate_content::<Config>(&content, format, migrations)?;
+    if issues.is_empty() {
+        let config = ConfigLoader::load_migrated::<Config>(config_path, &[], migrations)?;
+        issues.extend(
+            validators
+                .errors(&config)?
+                .iter()
+                .map(|err| ConfigIssue::from_config_error(err, &content)),
+        );
+    }
+
+    for issue in &issues {
+        // Only `format` accepts SARIF and quiet output
+        match output {
+            OutputFormat::Human | OutputFormat::Sarif | OutputFormat::Quiet => eprintln!(
+                "{}:{}:{}: {}",
+                config_path.display(),
+                issue.line.unwrap_or(1),
+                issue.column.unwrap_or(1),
+                issue.message
+            ),
+            OutputFormat::Json => print_json_line(issue)?,
+        }
+    }
+
+    if issues.is_empty() {
+        info!("✓ Config at {} is valid.", config_path.display());
+        Ok(())
+    } else {
+        Err(CliError::InvalidConfig {
+            path: config_path.to_path_buf(),
+            count: issues.len(),
+        })
+    }
+}
+
+/// Check config file content for syntax errors, invalid values, and unknown keys.
+///
+/// Files of an older version are migrated first; a file that cannot be
+/// migrated is reported as a single issue.
+///
+/// # Arguments
+/// * `content` - Content of the config file
+/// * `format` - Format of the config file
+/// * `migrations` - Migrations applied to files of older versions
+///
+/// # Returns
+/// The issues found, in file order where possible
+pub fn validate_content<Config>(
+    content: &str,
+    format: ConfigFormat,
+    migrations: &ConfigMigrations,
+) -> CliResult<Vec<ConfigIssue>>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    let mut value: Value = match format.parse(content) {
+        Ok(value) => value,
+        Err(err) => return Ok(vec![ConfigIssue::from_error(&err, content)]),
+    };
+    let version = match migrations.migrate(&mut value) {
+        Ok(version) => version,
+        Err(err) => return Ok(vec![ConfigIssue::from_error(&err, content)]),
+    };
+
+    let mut issues = Vec::new();
+    let parsed = if version < migrations.latest() {
+        serde_yaml::from_value::<Config>(value.clone()).map_err(CliError::from)
+    } else {
+        format.parse::<Config>(content)
+    };
+    if let Err(err) = parsed {
+        issues.push(ConfigIssue::from_error(&err, content));
+    }
+
+    if let Some(section) = value.get(RUNNER_SECTION) {
+        if let Err(err) = serde_yaml::from_value::<RunnerConfig>(section.clone()) {
+            issues.push(ConfigIssue::for_key(
+                format!("invalid runner settings: {err}"),
+                &[RUNNER_SECTION.to_string()],
+                content,
+            ));
+        }
+    }
+
+    issues.extend(
+        find_unknown_keys::<Config>(&value)?
+            .iter()
+            .map(|unknown| ConfigIssue::for_key(unknown.message(), &unknown.path, content)),
+    );
+
+    issues.sort_by_key(|issue| issue.line.unwrap_or(0));
+    Ok(issues)
+}
+
+/// Find the 1-based line on which the dotted `key` is defined.
+///
+/// Each segment is searched for after the line of its parent, as a YAML
+/// key (`key:`), a TOML key (`key =`) or the last part of a TOML table
+/// header (`[parent.key]`).
+fn find_key_line(content: &str, key: &[String]) -> Option<usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut start = 0;
+    let mut found = None;
+
+    for segment in key {
+        let offset = lines[start..].iter().position(|line| {
+            let line = line.trim_start().trim_start_matches("- ");
+            let quoted = [
+                segment.clone(),
+                format!("\"{segment}\""),
+                format!("'{segment}'"),
+            ];
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header.trim_start_matches('[');
+                let name = header.split(']').next().unwrap_or_default();
+                let last = name.rsplit('.').next().unwrap_or_default().trim();
+                return quoted.iter().any(|quoted| last == quoted);
+            }
+            quoted.iter().any(|quoted| {
+                line.strip_prefix(quoted.as_str()).is_some_and(|rest| {
+                    let rest = rest.trim_start();
+                    rest.starts_with(':') || rest.starts_with('=')
+                })
+            })
+        })?;
+        found = Some(start + offset + 1);
+        start += offset + 1;
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct TestConfig {
+        width: usize,
+        style: Style,
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Style {
+        tabs: bool,
+    }
+
+    #[test]
+    fn test_valid_config_has_no_issues() {
+        let content =
+            "width: 80\nstyle:\n  tabs: true\nrunner:\n  reader:\n    buffer_size: 1024\n";
+        assert!(validate_content::<TestConfig>(
+            content,
+            ConfigFormat::Yaml,
+            &ConfigMigrations::new()
+        )
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn test_syntax_error_has_location() {
+        let issues = validate_content::<TestConfig>(
+            "width: 80\nstyle: [\n",
+            ConfigFormat::Yaml,
+            &ConfigMigrations::new(),
+        )
+        .unwrap();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].line.is_some());
+    }
+
+    #[test]
+    fn test_invalid_value_has_location() {
+        let issues = validate_content::<TestConfig>(
+            "width: 80\nstyle:\n  tabs: maybe\n",
+            ConfigFormat::Yaml,
+            &ConfigMigrations::new(),
+        )
+        .unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(3));
+    }
+
+    #[test]
+    fn test_unknown_keys_are_reported_with_lines() {
+        let content = "width: 80\nstyle:\n  tabs: true\n  tabz: true\ncolour: red\n";
+        let issues =
+            validate_content::<TestConfig>(content, ConfigFormat::Yaml, &ConfigMigrations::new())
+                .unwrap();
+
+        let keys: Vec<_> = issues.iter().map(|i| (i.key.clone(), i.line)).collect();
+        assert_eq!(
+            keys,
+            vec![
+                (Some("style.tabz".to_string()), Some(4)),
+                (Some("colour".to_string()), Some(5)),
+            ]
+        );
+        assert_eq!(issues[0].column, Some(3));
+        assert_eq!(
+            issues[0].message,
+            "unknown key 'style.tabz', did you mean 'style.tabs'?"
+        );
+        assert_eq!(issues[1].message, "unknown key 'colour'");
+    }
+
+    #[test]
+    fn test_invalid_runner_section() {
+        let content = "width: 80\nstyle:\n  tabs: true\nrunner:\n  reader:\n    buffer_size: big\n";
+        let issues =
+            validate_content::<TestConfig>(content, ConfigFormat::Yaml, &ConfigMigrations::new())
+                .unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key.as_deref(), Some("runner"));
+        assert_eq!(issues[0].line, Some(4));
+    }
+
+    #[test]
+    fn test_toml_issues_have_locations() {
+        let content = "width = 80\ncolour = \"red\"\n\n[style]\ntabs = true\ntabz = true\n";
+        let issues =
+            validate_content::<TestConfig>(content, ConfigFormat::Toml, &ConfigMigrations::new())
+                .unwrap();
+        let keys: Vec<_> = issues.iter().map(|i| (i.key.clone(), i.line)).collect();
+        assert_eq!(
+            keys,
+            vec![
+                (Some("colour".to_string()), Some(2)),
+                (Some("style.tabz".to_string()), Some(6)),
+            ]
+        );
+
+        let issues = validate_content::<TestConfig>(
+            "width = 80\n[style]\ntabs = 1\n",
+            ConfigFormat::Toml,
+            &ConfigMigrations::new(),
+        )
+        .unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].line, issues[0].column), (Some(3), Some(8)));
+
+        let issues = validate_content::<TestConfig>(
+            "width = [\n",
+            ConfigFormat::Toml,
+            &ConfigMigrations::new(),
+        )
+        .unwrap();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].line.is_some());
+    }
+
+    #[rstest]
+    #[case(
+        ConfigFormat::Json,
+        "{\n  \"width\": 80,\n  \"colour\": \"red\",\n  \"style\": {\n    \"tabz\": true,\n    \"tabs\": true\n  }\n}\n"
+    )]
+    #[case(
+        ConfigFormat::Json5,
+        "{\n  width: 80,\n  colour: 'red', // typo\n  style: {\n    tabz: true,\n    tabs: true,\n  },\n}\n"
+    )]
+    fn test_json_issues_have_locations(#[case] format: ConfigFormat, #[case] content: &str) {
+        let issues =
+            validate_content::<TestConfig>(content, format, &ConfigMigrations::new()).unwrap();
+        let keys: Vec<_> = issues.iter().map(|i| (i.key.clone(), i.line)).collect();
+        assert_eq!(
+            keys,
+            vec![
+                (Some("colour".to_string()), Some(3)),
+                (Some("style.tabz".to_string()), Some(5)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+/// Entry marking the root of a git repository, always looked for.
+pub const GIT_MARKER: &str = ".git";
+
+/// Root directory of the project being formatted.
+///
+/// The root is the nearest directory, starting from the working directory,
+/// that contains `.git` or one of the configured marker files. Relative
+/// paths that belong to the project, such as the default config file and
+/// the cache directory, are resolved against it, so running from a
+/// subdirectory behaves like running from the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+    /// Canonical path of the root directory
+    path: PathBuf,
+}
+
+impl WorkspaceRoot {
+    /// Find the root of the project containing `start`.
+    ///
+    /// # Arguments
+    /// * `start` - Directory the search starts from, usually the working directory
+    /// * `markers` - File names marking the root, on top of `.git`
+    ///
+    /// # Returns
+    /// The nearest ancestor of `start` (or `start` itself) containing a
+    /// marker, or `None` if there is none
+    pub fn discover(start: &Path, markers: &[&str]) -> Option<Self> {
+        let start = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+        start
+            .ancestors()
+            .find(|dir| {
+                std::iter::once(GIT_MARKER)
+                    .chain(markers.iter().copied())
+                    .any(|marker| dir.join(marker).exists())
+            })
+            .map(|dir| Self {
+                path: dir.to_path_buf(),
+            })
+    }
+
+    /// Get the path of the root directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Resolve `path` against the root; absolute paths are returned unchanged.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.path.join(path)
+        }
+    }
+
+    /// Get `path` relative to the root.
+    ///
+    /// Relative paths are taken as relative to the working directory.
+    ///
+    /// # Returns
+    /// The path below the root, or `None` if `path` is outside of it
+    pub fn relative(&self, path: &Path) -> Option<PathBuf> {
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir().ok()?.join(path)
+        };
+        // Canonicalize the directory only, so symlinked files keep their own name
+        let absolute = match (absolute.parent(), absolute.file_name()) {
+            (Some(parent), Some(name)) => parent
+                .canonicalize()
+                .map_or_else(|_| absolute.clone(), |parent| parent.join(name)),
+            _ => absolute,
+        };
+        absolute
+            .strip_prefix(&self.path)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_discover_finds_nearest_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("crates/inner/src")).unwrap();
+        fs::write(root.join("crates/inner/Cargo.toml"), "").unwrap();
+
+        let found = WorkspaceRoot::discover(&root.join("crates/inner/src"), &[]).unwrap();
+        assert_eq!(found.path(), root);
+
+        let found =
+            WorkspaceRoot::discover(&root.join("crates/inner/src"), &["Cargo.toml"]).unwrap();
+        assert_eq!(found.path(), root.join("crates/inner"));
+    }
+
+    #[test]
+    fn test_resolve_and_relative() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("marker"), "").unwrap();
+        let workspace = WorkspaceRoot::discover(&root.join("sub"), &["marker"]).unwrap();
+
+        assert_eq!(
+            workspace.resolve(Path::new("fmt.yml")),
+            root.join("fmt.yml")
+        );
+        assert_eq!(
+            workspace.resolve(Path::new("/abs/fmt.yml")),
+            Path::new("/abs/fmt.yml")
+        );
+        assert_eq!(
+            workspace.relative(&root.join("sub/file.json")),
+            Some(PathBuf::from("sub/file.json"))
+        );
+        assert_eq!(workspace.relative(Path::new("/elsewhere/file.json")), None);
+    }
+}
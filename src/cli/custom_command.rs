@@ -0,0 +1,85 @@
+use crate::cli::error::CliResult;
+use clap::{ArgMatches, Command};
+
+/// Handler invoked with the matches of a custom subcommand.
+pub type CommandHandler = Box<dyn Fn(&ArgMatches) -> CliResult<()>>;
+
+/// A subcommand registered by the formatter binary next to the built-in ones.
+pub struct CustomCommand {
+    command: Command,
+    handler: CommandHandler,
+}
+
+impl CustomCommand {
+    /// Create a custom command named `name`.
+    ///
+    /// # Arguments
+    /// * `name` - Subcommand name; overrides the name of `command`
+    /// * `command` - Clap definition of the subcommand's arguments and help
+    /// * `handler` - Function run when the subcommand is invoked
+    pub fn new<F>(name: &'static str, command: Command, handler: F) -> Self
+    where
+        F: Fn(&ArgMatches) -> CliResult<()> + 'static,
+    {
+        Self {
+            command: command.name(name),
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Get the subcommand name.
+    pub fn name(&self) -> &str {
+        self.command.get_name()
+    }
+
+    /// Get the clap definition of the subcommand.
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Run the handler with the subcommand's matches.
+    pub fn run(&self, matches: &ArgMatches) -> CliResult<()> {
+        (self.handler)(matches)
+    }
+}
+
+/// Find the custom command named `name`.
+pub fn find_command<'a>(commands: &'a [CustomCommand], name: &str) -> Option<&'a CustomCommand> {
+    commands.iter().find(|command| command.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_custom_command_dispatch() {
+        let seen = Rc::new(Cell::new(false));
+        let seen_in_handler = Rc::clone(&seen);
+        let commands = vec![CustomCommand::new(
+            "stats",
+            Command::new("ignored").arg(Arg::new("verbose").long("verbose").num_args(0)),
+            move |matches| {
+                seen_in_handler.set(matches.contains_id("verbose"));
+                Ok(())
+            },
+        )];
+
+        let matches = Command::new("fmt")
+            .subcommands(commands.iter().map(|c| c.command().clone()))
+            .try_get_matches_from(["fmt", "stats", "--verbose"])
+            .unwrap();
+        let (name, sub_matches) = matches.subcommand().unwrap();
+
+        assert_eq!(name, "stats");
+        find_command(&commands, name)
+            .unwrap()
+            .run(sub_matches)
+            .unwrap();
+        assert!(seen.get());
+        assert!(find_command(&commands, "lint").is_none());
+    }
+}
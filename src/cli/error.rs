@@ -16,6 +16,9 @@ pub enum CliError {
     #[error("Unknown command '{command}'")]
     UnknownCommand { command: String },
 
+    #[error("Unknown rule '{code}'")]
+    UnknownRule { code: String },
+
     #[error("Failed to get binary name from command line arguments")]
     BinaryNameError,
 
@@ -25,15 +28,116 @@ pub enum CliError {
     #[error("Config path points to a directory, but a file was expected")]
     ConfigPathIsDirectory,
 
+    #[error("Config file '{}' not found", path.display())]
+    ConfigNotFound { path: std::path::PathBuf },
+
+    #[error("Config file '{}' has {count} issue(s)", path.display())]
+    InvalidConfig {
+        path: std::path::PathBuf,
+        count: usize,
+    },
+
+    #[error("Config file '{}' already exists, use --force to overwrite it", path.display())]
+    ConfigAlreadyExists { path: std::path::PathBuf },
+
+    #[error("Invalid config file '{}' at line {line}, column {column}: {message}\n{frame}", path.display())]
+    InvalidConfigSyntax {
+        path: std::path::PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+        frame: String,
+        source: Box<CliError>,
+    },
+
+    #[error("Config file '{}' is invalid: {}", path.display(), join_config_errors(errors))]
+    ConfigRejected {
+        path: std::path::PathBuf,
+        errors: Vec<crate::cli::commands::ConfigError>,
+    },
+
+    #[error("Config version '{version}' is not a non-negative integer")]
+    InvalidConfigVersion { version: String },
+
+    #[error("Config version {version} is newer than the latest supported version {latest}, upgrade the formatter to use it")]
+    UnsupportedConfigVersion { version: u64, latest: u64 },
+
+    #[error("Config migration to version {version} failed: {message}")]
+    ConfigMigrationFailed { version: u64, message: String },
+
     #[error("Invalid argument '{arg}' with value '{value}'")]
     InvalidArgument { arg: String, value: String },
 
+    #[error("{count} file(s) could not be processed")]
+    FilesFailed { count: usize },
+
+    #[error("{count} file(s) need formatting")]
+    NeedsFormatting { count: usize },
+
+    #[error("{count} error diagnostic(s) reported")]
+    DiagnosticErrors { count: usize },
+
+    #[error("File '{}' needs formatting (stopped at the first one due to --fail-fast)", path.display())]
+    FailFast { path: std::path::PathBuf },
+
+    #[error("File '{}' is not valid UTF-8 and has no UTF-8 or UTF-16 byte order mark (set runner.reader.latin1_fallback to read it as Latin-1)", path.display())]
+    UnsupportedEncoding { path: std::path::PathBuf },
+
+    #[error("--lines requires exactly one file, but {count} were found")]
+    LinesRequireSingleFile { count: usize },
+
+    #[error("Invalid pipeline: {source}")]
+    InvalidPipeline {
+        #[from]
+        source: crate::pipeline::PipelineError,
+    },
+
+    #[error("Cannot load the grammar: {source}")]
+    IncompatibleGrammar {
+        #[from]
+        source: crate::parser::GrammarError,
+    },
+
+    #[error("{source}")]
+    ArgumentError {
+        #[from]
+        source: clap::Error,
+    },
+
     #[error("YAML parsing error: {source}")]
     YamlError {
         #[from]
         source: serde_yaml::Error,
     },
 
+    #[error("TOML parsing error: {source}")]
+    TomlError {
+        #[from]
+        source: toml::de::Error,
+    },
+
+    #[error("TOML serialization error: {source}")]
+    TomlSerializationError {
+        #[from]
+        source: toml::ser::Error,
+    },
+
+    #[error("JSON parsing error: {source}")]
+    JsonParseError { source: serde_json::Error },
+
+    #[error("JSON5 parsing error: {message} at line {line} column {column}")]
+    Json5Error {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+
+    #[error("JSON serialization error: {source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+    },
+
     #[error("IO error: {source}")]
     IoError {
         #[from]
@@ -44,12 +148,152 @@ pub enum CliError {
 /// Result type for CLI operations
 pub type CliResult<T> = Result<T, CliError>;
 
+/// Exit code of a successful run
+pub const SUCCESS_EXIT_CODE: i32 = 0;
+
+/// Exit code when files need formatting
+pub const UNFORMATTED_EXIT_CODE: i32 = 1;
+
+/// Exit code of usage and configuration errors
+pub const USAGE_EXIT_CODE: i32 = 2;
+
+/// Exit code of internal, IO and parse errors
+pub const INTERNAL_EXIT_CODE: i32 = 3;
+
+impl CliError {
+    /// Get the process exit code for this error.
+    ///
+    /// # Returns
+    /// `UNFORMATTED_EXIT_CODE` if files need formatting or have error
+    /// diagnostics, `USAGE_EXIT_CODE` for
+    /// invalid arguments, configs or pipelines, `INTERNAL_EXIT_CODE` otherwise
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NeedsFormatting { .. }
+            | Self::FailFast { .. }
+            | Self::DiagnosticErrors { .. } => UNFORMATTED_EXIT_CODE,
+            Self::ConfigPathMissing
+            | Self::FilesPathMissing
+            | Self::NoValidSubcommand
+            | Self::UnknownCommand { .. }
+            | Self::UnknownRule { .. }
+            | Self::UnsupportedConfigExtension
+            | Self::ConfigPathIsDirectory
+            | Self::ConfigNotFound { .. }
+            | Self::InvalidConfig { .. }
+            | Self::ConfigAlreadyExists { .. }
+            | Self::InvalidConfigSyntax { .. }
+            | Self::ConfigRejected { .. }
+            | Self::InvalidConfigVersion { .. }
+            | Self::UnsupportedConfigVersion { .. }
+            | Self::ConfigMigrationFailed { .. }
+            | Self::InvalidArgument { .. }
+            | Self::LinesRequireSingleFile { .. }
+            | Self::InvalidPipeline { .. }
+            | Self::ArgumentError { .. }
+            | Self::YamlError { .. }
+            | Self::TomlError { .. }
+            | Self::JsonParseError { .. }
+            | Self::Json5Error { .. } => USAGE_EXIT_CODE,
+            Self::BinaryNameError
+            | Self::FilesFailed { .. }
+            | Self::UnsupportedEncoding { .. }
+            | Self::JsonError { .. }
+            | Self::TomlSerializationError { .. }
+            | Self::IncompatibleGrammar { .. }
+            | Self::IoError { .. } => INTERNAL_EXIT_CODE,
+        }
+    }
+}
+
+/// Outcome of a CLI run that did not fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The command succeeded
+    Success,
+    /// The command succeeded, but files need formatting
+    NeedsFormatting,
+}
+
+impl ExitStatus {
+    /// Get the process exit code for this status.
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Success => SUCCESS_EXIT_CODE,
+            Self::NeedsFormatting => UNFORMATTED_EXIT_CODE,
+        }
+    }
+}
+
+impl From<crate::core::EngineError> for CliError {
+    fn from(error: crate::core::EngineError) -> Self {
+        match error {
+            crate::core::EngineError::Pipeline(source) => Self::InvalidPipeline { source },
+            crate::core::EngineError::Grammar(source) => Self::IncompatibleGrammar { source },
+        }
+    }
+}
+
+impl From<ExitStatus> for std::process::ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        match status {
+            ExitStatus::Success => Self::SUCCESS,
+            ExitStatus::NeedsFormatting => Self::from(UNFORMATTED_EXIT_CODE as u8),
+        }
+    }
+}
+
+/// Join the errors of config validators into one line.
+fn join_config_errors(errors: &[crate::cli::commands::ConfigError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 /// Exit the program with a CLI error
 ///
 /// This function prints the error message to stderr and exits the program
-/// with status code 1. It's intended for fatal errors that should terminate
+/// with the error's exit code. It's intended for fatal errors that should terminate
 /// the application immediately.
 pub fn exit_with_error(error: &CliError) -> ! {
+    if let CliError::ArgumentError { source } = error {
+        source.exit();
+    }
     error!("Error: {error}");
-    std::process::exit(1);
+    std::process::exit(error.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(CliError::NeedsFormatting { count: 2 }, UNFORMATTED_EXIT_CODE)]
+    #[case(CliError::FailFast { path: "a.json".into() }, UNFORMATTED_EXIT_CODE)]
+    #[case(CliError::DiagnosticErrors { count: 1 }, UNFORMATTED_EXIT_CODE)]
+    #[case(CliError::UnsupportedConfigExtension, USAGE_EXIT_CODE)]
+    #[case(CliError::UnknownRule { code: "FMT999".into() }, USAGE_EXIT_CODE)]
+    #[case(CliError::InvalidArgument { arg: "mode".into(), value: "x".into() }, USAGE_EXIT_CODE)]
+    #[case(
+        CliError::from(crate::pipeline::PipelineError::DependencyCycle { passes: vec!["A", "B"] }),
+        USAGE_EXIT_CODE
+    )]
+    #[case(CliError::from(std::io::Error::other("disk")), INTERNAL_EXIT_CODE)]
+    #[case(CliError::FilesFailed { count: 1 }, INTERNAL_EXIT_CODE)]
+    #[case(
+        CliError::from(crate::parser::GrammarError::IncompatibleAbi { grammar: "json".into(), abi: 99 }),
+        INTERNAL_EXIT_CODE
+    )]
+    #[case(
+        CliError::from(crate::core::EngineError::Grammar(
+            crate::parser::GrammarError::IncompatibleAbi { grammar: "json".into(), abi: 99 }
+        )),
+        INTERNAL_EXIT_CODE
+    )]
+    fn test_exit_code(#[case] error: CliError, #[case] expected: i32) {
+        assert_eq!(error.exit_code(), expected);
+    }
 }
@@ -1,10 +1,22 @@
-use crate::cli::cli_entry::{build_cli, CliCommand, FormatMode};
-use crate::cli::commands::{format, init};
-use crate::cli::error::{exit_with_error, CliError, CliResult};
+use crate::cli::cli_entry::{
+    build_cli, embedded_name, CliCommand, FormatMode, OutputFormat, CONFIG_SHOW,
+};
+use crate::cli::commands::{
+    config_show, explain, format, init, list_passes, reporter_for, validate, ConfigLayers,
+    ConfigOverride, FormatOptions, InitOptions, WorkspaceRoot,
+};
+use crate::cli::custom_command::find_command;
+use crate::cli::error::{exit_with_error, CliError, CliResult, ExitStatus};
+use crate::cli::settings::CliSettings;
+use crate::core::LineRange;
 use crate::parser::LanguageProvider;
-use crate::pipeline::Pipeline;
+use crate::pipeline::{Pipeline, RuntimeOptions};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches};
+use log::info;
 use serde::{de::DeserializeOwned, Serialize};
 use std::env;
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 
 /// Parse command string to `CliCommand` enum.
@@ -18,6 +30,10 @@ fn parse_command(cmd_str: &str) -> Option<CliCommand> {
     match cmd_str {
         cmd if cmd == CliCommand::Init.as_str() => Some(CliCommand::Init),
         cmd if cmd == CliCommand::Format.as_str() => Some(CliCommand::Format),
+        cmd if cmd == CliCommand::Validate.as_str() => Some(CliCommand::Validate),
+        cmd if cmd == CliCommand::Explain.as_str() => Some(CliCommand::Explain),
+        cmd if cmd == CliCommand::ListPasses.as_str() => Some(CliCommand::ListPasses),
+        cmd if cmd == CliCommand::Config.as_str() => Some(CliCommand::Config),
         _ => None,
     }
 }
@@ -37,6 +53,41 @@ fn parse_mode(mode_str: &str) -> Option<FormatMode> {
     }
 }
 
+/// Parse output string to `OutputFormat` enum.
+///
+/// # Arguments
+/// * `output_str` - The output format string to parse
+///
+/// # Returns
+/// `Some(OutputFormat)` if the string matches a known format, `None` otherwise
+fn parse_output(output_str: &str) -> Option<OutputFormat> {
+    match output_str {
+        output if output == OutputFormat::Human.as_str() => Some(OutputFormat::Human),
+        output if output == OutputFormat::Json.as_str() => Some(OutputFormat::Json),
+        output if output == OutputFormat::Sarif.as_str() => Some(OutputFormat::Sarif),
+        output if output == OutputFormat::Quiet.as_str() => Some(OutputFormat::Quiet),
+        _ => None,
+    }
+}
+
+/// Get the output format from a subcommand's `--output` argument.
+///
+/// # Arguments
+/// * `sub_matches` - Command line argument matches of the subcommand
+///
+/// # Returns
+/// The output format, or an error if the value is unknown
+fn get_output(sub_matches: &ArgMatches) -> CliResult<OutputFormat> {
+    let output_str = sub_matches
+        .get_one::<String>("output")
+        .map_or(OutputFormat::Human.as_str(), std::string::String::as_str);
+
+    parse_output(output_str).ok_or_else(|| CliError::InvalidArgument {
+        arg: "output".to_string(),
+        value: output_str.to_string(),
+    })
+}
+
 /// Handle command line interface for the formatter tool
 ///
 /// This function parses command line arguments and executes the appropriate command
@@ -48,110 +99,378 @@ fn parse_mode(mode_str: &str) -> Option<FormatMode> {
 ///
 /// # Arguments
 /// * `pipeline` - The formatting pipeline to use for format operations
+/// * `settings` - Customizations of the CLI from `CliBuilder`
 ///
 /// # Errors
-/// This function will print error messages to stderr and call `process::exit(1)`
-/// if any critical error occurs during CLI processing.
-pub fn handle_cli<Language, Config>(pipeline: Pipeline<Config>)
+/// This function will print error messages to stderr and exit the process if
+/// any error occurs: with code 1 if files need formatting, 2 for usage and
+/// config errors, and 3 for internal errors.
+pub fn handle_cli<Language, Config>(pipeline: Pipeline<Config>, settings: CliSettings)
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
-    // Initialize logger with default configuration
-    env_logger::Builder::from_default_env()
+    // Initialize logger with default configuration, unless the host already did
+    let _ = env_logger::Builder::from_default_env()
         .filter_level(log::LevelFilter::Warn)
-        .init();
+        .try_init();
 
-    if let Err(e) = try_handle_cli::<Language, Config>(pipeline) {
-        exit_with_error(&e);
+    match try_handle_cli::<Language, Config, _, _>(pipeline, &settings, env::args_os()) {
+        Ok(ExitStatus::Success) => {}
+        Ok(status) => std::process::exit(status.code()),
+        Err(e) => exit_with_error(&e),
     }
 }
 
-/// Internal implementation of CLI handling that returns Results
-fn try_handle_cli<Language, Config>(pipeline: Pipeline<Config>) -> CliResult<()>
+/// Handle command line interface for the formatter tool without exiting the process.
+///
+/// Unlike `handle_cli`, this function neither initializes a logger nor calls
+/// `process::exit`; `--help` and `--version` are printed and reported as success.
+///
+/// # Arguments
+/// * `pipeline` - The formatting pipeline to use for format operations
+/// * `settings` - Customizations of the CLI from `CliBuilder`
+/// * `args` - Command line arguments, starting with the binary name
+///
+/// # Returns
+/// The exit status of the command, or the error that stopped it
+pub fn try_handle_cli<Language, Config, I, T>(
+    pipeline: Pipeline<Config>,
+    settings: &CliSettings,
+    args: I,
+) -> CliResult<ExitStatus>
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
+    Language: LanguageProvider,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let bin_name = get_binary_name(args.first()).unwrap_or_else(|_| "fmt-runner".to_string());
+    let matches = match build_cli(&bin_name, settings).try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(err) if !err.use_stderr() => {
+            err.print()?;
+            return Ok(ExitStatus::Success);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let name = settings.name.unwrap_or(&bin_name);
+    run_command::<Language, Config>(pipeline, settings, &matches, name)
+}
+
+/// Run the subcommand selected in `matches`.
+///
+/// `matches` belongs to the command the subcommands are mounted on: our own
+/// root command, or a host application's command when embedded.
+/// Without a name set on the builder, the formatter is named after the
+/// host's binary, as in `build_embedded_commands`.
+///
+/// # Arguments
+/// * `pipeline` - The formatting pipeline to use for format operations
+/// * `settings` - Customizations of the CLI from `CliBuilder`
+/// * `matches` - Matches of the command holding the subcommands
+///
+/// # Returns
+/// The exit status of the subcommand, or the error that stopped it
+pub fn dispatch<Language, Config>(
+    pipeline: Pipeline<Config>,
+    settings: &CliSettings,
+    matches: &ArgMatches,
+) -> CliResult<ExitStatus>
+where
+    Config: Serialize + DeserializeOwned + Default + Sync,
+    Language: LanguageProvider,
+{
+    let name = embedded_name(settings);
+    run_command::<Language, Config>(pipeline, settings, matches, &name)
+}
+
+/// Run the subcommand selected in `matches` for the formatter called `name`.
+///
+/// `name` selects the directories of the system and user configs.
+fn run_command<Language, Config>(
+    pipeline: Pipeline<Config>,
+    settings: &CliSettings,
+    matches: &ArgMatches,
+    name: &str,
+) -> CliResult<ExitStatus>
+where
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
-    let bin_name = get_binary_name().unwrap_or_else(|_| "fmt-runner".to_string());
-    let matches = build_cli(&bin_name).get_matches();
+    let layers = if settings.ignore_config_layers {
+        ConfigLayers::new()
+    } else {
+        ConfigLayers::discover(name)
+    };
+    let root = env::current_dir()
+        .ok()
+        .and_then(|dir| WorkspaceRoot::discover(&dir, &settings.root_markers));
+    let root = root.as_ref();
 
-    match matches.subcommand() {
+    let result = match matches.subcommand() {
         Some((cmd_str, sub_matches)) => match parse_command(cmd_str) {
-            Some(CliCommand::Init) => {
-                handle_init_command::<Config>(sub_matches)?;
+            Some(CliCommand::Init) => handle_init_command::<Config>(sub_matches, settings, root),
+            Some(CliCommand::Validate) => {
+                handle_validate_command::<Config>(sub_matches, settings, root)
             }
-            Some(CliCommand::Format) => {
-                handle_format_command::<Language, Config>(sub_matches, pipeline)?;
+            Some(CliCommand::Format) => handle_format_command::<Language, Config>(
+                sub_matches,
+                pipeline,
+                settings,
+                name,
+                root,
+                &layers,
+            ),
+            Some(CliCommand::Explain) => handle_explain_command(sub_matches, &pipeline),
+            Some(CliCommand::ListPasses) => {
+                handle_list_passes_command::<Config>(sub_matches, pipeline, settings, root, &layers)
             }
-            None => {
-                exit_with_error(&CliError::UnknownCommand {
-                    command: cmd_str.to_string(),
-                });
+            Some(CliCommand::Config) => {
+                handle_config_command::<Config>(sub_matches, settings, root, &layers)
             }
+            None => match find_command(&settings.custom_commands, cmd_str) {
+                Some(custom_command) => custom_command.run(sub_matches),
+                None => Err(CliError::UnknownCommand {
+                    command: cmd_str.to_string(),
+                }),
+            },
         },
-        None => {
-            exit_with_error(&CliError::NoValidSubcommand);
+        None => Err(CliError::NoValidSubcommand),
+    };
+
+    match result {
+        Ok(()) => Ok(ExitStatus::Success),
+        Err(err @ (CliError::NeedsFormatting { .. } | CliError::FailFast { .. })) => {
+            info!("{err}");
+            Ok(ExitStatus::NeedsFormatting)
         }
+        Err(err) => Err(err),
     }
-
-    Ok(())
 }
 
-/// Get the binary name from command line arguments.
+/// Get the binary name from the first command line argument.
 ///
 /// # Returns
 /// The binary name without path, or an error if it cannot be determined
-fn get_binary_name() -> CliResult<String> {
-    env::args()
-        .next()
-        .and_then(|path| {
-            std::path::Path::new(&path)
-                .file_name()
-                .and_then(|name| name.to_str())
-                .map(std::string::ToString::to_string)
-        })
-        .ok_or(CliError::BinaryNameError)
+fn get_binary_name(arg: Option<&OsString>) -> CliResult<String> {
+    arg.and_then(|path| {
+        Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(std::string::ToString::to_string)
+    })
+    .ok_or(CliError::BinaryNameError)
+}
+
+/// Get the config path of a subcommand.
+///
+/// The default config path is resolved against the project root, so it is
+/// found from any subdirectory; paths given with `--config` are relative
+/// to the working directory.
+///
+/// # Arguments
+/// * `sub_matches` - Command line argument matches of the subcommand
+/// * `root` - Root of the project, if one was found
+///
+/// # Returns
+/// The config path, or an error if the argument is missing
+fn get_config_path(sub_matches: &ArgMatches, root: Option<&WorkspaceRoot>) -> CliResult<PathBuf> {
+    let config_path = sub_matches
+        .get_one::<String>("config_path")
+        .map(PathBuf::from)
+        .ok_or(CliError::ConfigPathMissing)?;
+
+    match root {
+        Some(root)
+            if sub_matches.value_source("config_path") == Some(ValueSource::DefaultValue) =>
+        {
+            Ok(root.resolve(&config_path))
+        }
+        _ => Ok(config_path),
+    }
 }
 
 /// Handle the 'init' subcommand.
 ///
 /// # Arguments
 /// * `sub_matches` - Command line argument matches for the init subcommand
+/// * `settings` - Customizations of the CLI from `CliBuilder`
+/// * `root` - Root of the project, if one was found
 ///
 /// # Returns
 /// `Ok(())` on success, or a CLI error
-fn handle_init_command<Config>(sub_matches: &clap::ArgMatches) -> CliResult<()>
+fn handle_init_command<Config>(
+    sub_matches: &ArgMatches,
+    settings: &CliSettings,
+    root: Option<&WorkspaceRoot>,
+) -> CliResult<()>
 where
     Config: Serialize + DeserializeOwned + Default,
 {
-    let config_path = sub_matches
-        .get_one::<String>("config_path")
-        .ok_or(CliError::ConfigPathMissing)?;
+    let config_path = get_config_path(sub_matches, root)?;
 
-    init::<Config>(config_path.into())?;
+    let options = InitOptions {
+        force: sub_matches.get_flag("force"),
+        update: sub_matches.get_flag("update"),
+        interactive: sub_matches.get_flag("interactive"),
+        preset: sub_matches.get_one::<String>("preset").and_then(|name| {
+            settings
+                .presets
+                .iter()
+                .find(|preset| preset.name == name)
+                .cloned()
+        }),
+        migrations: settings.migrations.clone(),
+        validators: settings.validators.clone(),
+    };
+
+    init::<Config>(config_path, &options, settings.config_prompt.as_deref())?;
     Ok(())
 }
 
+/// Handle the 'validate' subcommand.
+///
+/// # Arguments
+/// * `sub_matches` - Command line argument matches for the validate subcommand
+/// * `settings` - Customizations of the CLI from `CliBuilder`
+/// * `root` - Root of the project, if one was found
+///
+/// # Returns
+/// `Ok(())` if the config is valid, or a CLI error
+fn handle_validate_command<Config>(
+    sub_matches: &ArgMatches,
+    settings: &CliSettings,
+    root: Option<&WorkspaceRoot>,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    let config_path = get_config_path(sub_matches, root)?;
+
+    validate::<Config>(
+        &config_path,
+        get_output(sub_matches)?,
+        &settings.migrations,
+        &settings.validators,
+    )
+}
+
+/// Handle the 'explain' subcommand.
+///
+/// # Arguments
+/// * `sub_matches` - Command line argument matches for the explain subcommand
+/// * `pipeline` - The pipeline whose passes register the rules
+///
+/// # Returns
+/// `Ok(())` if the rule was found, or a CLI error
+fn handle_explain_command<Config>(
+    sub_matches: &ArgMatches,
+    pipeline: &Pipeline<Config>,
+) -> CliResult<()> {
+    let code = sub_matches
+        .get_one::<String>("code")
+        .ok_or_else(|| CliError::InvalidArgument {
+            arg: "code".to_string(),
+            value: String::new(),
+        })?;
+
+    explain(pipeline, code)
+}
+
+/// Handle the 'list-passes' subcommand.
+///
+/// # Arguments
+/// * `sub_matches` - Command line argument matches for the list-passes subcommand
+/// * `pipeline` - The pipeline whose passes are listed
+/// * `settings` - Customizations of the CLI from `CliBuilder`
+/// * `root` - Root of the project, if one was found
+/// * `layers` - System and user configs under the project config
+///
+/// # Returns
+/// `Ok(())` on success, or a CLI error
+fn handle_list_passes_command<Config>(
+    sub_matches: &ArgMatches,
+    mut pipeline: Pipeline<Config>,
+    settings: &CliSettings,
+    root: Option<&WorkspaceRoot>,
+    layers: &ConfigLayers,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    let config_path = get_config_path(sub_matches, root)?;
+
+    pipeline.resolve_order()?;
+    list_passes::<Config>(
+        &pipeline,
+        &config_path,
+        layers,
+        get_output(sub_matches)?,
+        settings.require_config || sub_matches.get_flag("require_config"),
+    )
+}
+
+/// Handle the 'config' subcommand.
+///
+/// # Arguments
+/// * `sub_matches` - Command line argument matches for the config subcommand
+/// * `settings` - Customizations of the CLI from `CliBuilder`
+/// * `root` - Root of the project, if one was found
+/// * `layers` - System and user configs under the project config
+///
+/// # Returns
+/// `Ok(())` on success, or a CLI error
+fn handle_config_command<Config>(
+    sub_matches: &ArgMatches,
+    settings: &CliSettings,
+    root: Option<&WorkspaceRoot>,
+    layers: &ConfigLayers,
+) -> CliResult<()>
+where
+    Config: Serialize + DeserializeOwned + Default,
+{
+    match sub_matches.subcommand() {
+        Some((CONFIG_SHOW, show_matches)) => config_show::<Config>(
+            &get_config_path(show_matches, root)?,
+            layers,
+            &settings.migrations,
+            show_matches.get_flag("origin"),
+        ),
+        Some((command, _)) => Err(CliError::UnknownCommand {
+            command: command.to_string(),
+        }),
+        None => Err(CliError::NoValidSubcommand),
+    }
+}
+
 /// Handle the 'format' subcommand.
 ///
 /// # Arguments
 /// * `sub_matches` - Command line argument matches for the format subcommand
 /// * `pipeline` - The formatting pipeline to use
+/// * `settings` - Customizations of the CLI from `CliBuilder`
+/// * `name` - Name of the formatter, used in SARIF logs
+/// * `root` - Root of the project, if one was found
+/// * `layers` - System and user configs under the project config
 ///
 /// # Returns
 /// `Ok(())` on success, or a CLI error
 fn handle_format_command<Language, Config>(
-    sub_matches: &clap::ArgMatches,
-    pipeline: Pipeline<Config>,
+    sub_matches: &ArgMatches,
+    mut pipeline: Pipeline<Config>,
+    settings: &CliSettings,
+    name: &str,
+    root: Option<&WorkspaceRoot>,
+    layers: &ConfigLayers,
 ) -> CliResult<()>
 where
-    Config: Serialize + DeserializeOwned + Default,
+    Config: Serialize + DeserializeOwned + Default + Sync,
     Language: LanguageProvider,
 {
-    let config_path = sub_matches
-        .get_one::<String>("config_path")
-        .ok_or(CliError::ConfigPathMissing)?;
+    let config_path = get_config_path(sub_matches, root)?;
 
     let files_path: Vec<String> = sub_matches
         .get_many::<String>("files_path")
@@ -168,9 +487,97 @@ where
         value: mode_str.to_string(),
     })?;
 
+    let output = get_output(sub_matches)?;
+
+    let lines = sub_matches
+        .get_one::<String>("lines")
+        .map(|lines_str| {
+            LineRange::parse(lines_str).ok_or_else(|| CliError::InvalidArgument {
+                arg: "lines".to_string(),
+                value: lines_str.to_string(),
+            })
+        })
+        .transpose()?;
+
+    // Config flags come first, so `--set` takes precedence over them
+    let mut overrides = Vec::new();
+    for flag in &settings.config_flags {
+        overrides.extend(flag.override_from(sub_matches)?);
+    }
+    for text in sub_matches.get_many::<String>("set").unwrap_or_default() {
+        overrides.push(
+            ConfigOverride::parse(text).ok_or_else(|| CliError::InvalidArgument {
+                arg: "set".to_string(),
+                value: text.to_string(),
+            })?,
+        );
+    }
+
     let files_path: Vec<PathBuf> = files_path.into_iter().map(PathBuf::from).collect();
+    let options = FormatOptions {
+        mode,
+        output,
+        source_map: sub_matches
+            .get_one::<String>("source_map")
+            .map(PathBuf::from),
+        profile_io: sub_matches.get_flag("profile_io"),
+        timings: sub_matches.get_flag("timings"),
+        trace_edits: sub_matches
+            .get_one::<String>("trace_edits")
+            .map(PathBuf::from),
+        jobs: sub_matches.get_one::<usize>("jobs").copied().unwrap_or(1),
+        max_file_size: sub_matches.get_one::<usize>("max_file_size").copied(),
+        fail_fast: sub_matches.get_flag("fail_fast"),
+        lines,
+        backup: sub_matches.get_one::<String>("backup").cloned(),
+        files_from: sub_matches
+            .get_one::<String>("files_from")
+            .map(PathBuf::from),
+        nul_delimited: sub_matches.get_flag("null"),
+        require_config: settings.require_config || sub_matches.get_flag("require_config"),
+        overrides,
+        // Relative cache directories are shared by the whole project
+        cache_dir: sub_matches.get_one::<String>("cache_dir").map(|dir| {
+            root.map_or_else(|| PathBuf::from(dir), |root| root.resolve(Path::new(dir)))
+        }),
+        root: root.cloned(),
+        layers: layers.clone(),
+        migrations: settings.migrations.clone(),
+        validators: settings.validators.clone(),
+        version: settings.version.unwrap_or(env!("CARGO_PKG_VERSION")),
+    };
 
-    format::<Language, Config>(Path::new(config_path), &files_path, pipeline, mode)?;
+    pipeline.configure(&runtime_options(sub_matches, &settings.extra_args));
+    let mut reporter = settings
+        .reporter
+        .as_ref()
+        .map_or_else(|| reporter_for(output, name), |factory| factory());
+    format::<Language, Config>(
+        &config_path,
+        &files_path,
+        pipeline,
+        &options,
+        &mut *reporter,
+    )?;
 
     Ok(())
 }
+
+/// Collect the raw values of the extra arguments.
+///
+/// # Arguments
+/// * `matches` - Matches of the subcommand, including propagated global arguments
+/// * `extra_args` - Arguments registered by the formatter binary
+fn runtime_options(matches: &ArgMatches, extra_args: &[Arg]) -> RuntimeOptions {
+    let mut options = RuntimeOptions::new();
+    for arg in extra_args {
+        let id = arg.get_id().as_str();
+        if let Some(values) = matches.get_raw(id) {
+            let values = values
+                .map(|value| value.to_string_lossy().into_owned())
+                .collect();
+            options.insert(id, values);
+        }
+    }
+    options
+}
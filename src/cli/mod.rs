@@ -1,8 +1,18 @@
 mod builder;
 mod cli_entry;
 mod commands;
+mod custom_command;
 mod error;
 mod handler;
+mod settings;
 
-pub use builder::{cli_builder, CliBuilder};
-pub use error::{CliError, CliResult};
+pub use builder::{cli_builder, CliBuilder, CliDispatcher};
+pub use cli_entry::FormatMode;
+#[cfg(feature = "mmap")]
+pub use commands::MappedFile;
+pub use commands::{
+    CollectorConfig, ConfigError, ConfigMigrator, ConfigPrompt, FileCollector, FileReader,
+    FileRecord, HumanReporter, JsonReporter, QuietReporter, ReadOutcome, ReaderConfig, Reporter,
+    RunStatus, SarifReporter, SymlinkPolicy,
+};
+pub use error::{CliError, CliResult, ExitStatus};
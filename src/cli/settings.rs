@@ -0,0 +1,42 @@
+use crate::cli::commands::{
+    ConfigFlag, ConfigMigrations, ConfigPrompt, ConfigValidators, Preset, Reporter,
+};
+use crate::cli::custom_command::CustomCommand;
+use clap::Arg;
+
+/// Customizations of the generated CLI, collected by `CliBuilder`.
+///
+/// Unset values fall back to defaults derived from the binary name.
+#[derive(Default)]
+pub struct CliSettings {
+    /// Binary name shown in help output, instead of the name from argv
+    pub name: Option<&'static str>,
+    /// Version shown by `--version`
+    pub version: Option<&'static str>,
+    /// Description shown in help output
+    pub about: Option<&'static str>,
+    /// Default value of `--config`, instead of `<name>.yml`
+    pub default_config_path: Option<&'static str>,
+    /// Fail when the config file is missing, as if `--require-config` were always given
+    pub require_config: bool,
+    /// Top-level config fields exposed as flags of `format`
+    pub config_flags: Vec<ConfigFlag>,
+    /// Only load the project config, not the system and user configs
+    pub ignore_config_layers: bool,
+    /// File names marking the project root, on top of `.git`
+    pub root_markers: Vec<&'static str>,
+    /// Subcommands registered by the formatter binary
+    pub custom_commands: Vec<CustomCommand>,
+    /// Global arguments whose values are handed to the passes
+    pub extra_args: Vec<Arg>,
+    /// Field descriptions for `init --interactive`
+    pub config_prompt: Option<Box<dyn ConfigPrompt>>,
+    /// Named configs selectable with `init --preset`
+    pub presets: Vec<Preset>,
+    /// Upgrades of config files written for older schemas
+    pub migrations: ConfigMigrations,
+    /// Checks of invariants of the deserialized config
+    pub validators: ConfigValidators,
+    /// Creates the reporter of `format`, instead of the one selected by `--output`
+    pub reporter: Option<Box<dyn Fn() -> Box<dyn Reporter>>>,
+}
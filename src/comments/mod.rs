@@ -0,0 +1,269 @@
+//! Comment helpers for structural passes.
+//!
+//! Comments are extras in tree-sitter grammars: they may appear between any
+//! two tokens, so passes that rebuild a region from its items easily lose
+//! them. These helpers find the comments attached to a node, check whether
+//! a range holds comments, and tell which comments a rebuilt text dropped.
+//!
+//! Comment node kinds are passed in, usually from
+//! `LanguageCapabilities::comment_node_kinds`.
+
+use crate::walk::{walk, Visit};
+use std::collections::HashMap;
+use tree_sitter::Node;
+
+/// Comments that belong to a node: the ones right before it and the one ending its line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttachedComments<'tree> {
+    /// Comments right before the node, in source order
+    pub leading: Vec<Node<'tree>>,
+    /// Comment after the node on the same line, if any
+    pub trailing: Option<Node<'tree>>,
+}
+
+impl AttachedComments<'_> {
+    /// Returns true if no comment is attached.
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_none()
+    }
+}
+
+/// Find the comments attached to `node`.
+///
+/// See `leading_comments` and `trailing_comment`.
+///
+/// # Arguments
+/// * `node` - Node the comments belong to
+/// * `source` - The source code
+/// * `kinds` - Node kinds of comments
+pub fn attached_comments<'tree>(
+    node: Node<'tree>,
+    source: &str,
+    kinds: &[&str],
+) -> AttachedComments<'tree> {
+    AttachedComments {
+        leading: leading_comments(node, source, kinds),
+        trailing: trailing_comment(node, kinds),
+    }
+}
+
+/// Find the comments right before `node`, in source order.
+///
+/// A run of comment siblings directly before the node leads it, up to a
+/// blank line or a comment ending the line of an earlier sibling, which
+/// trails that sibling instead.
+///
+/// # Arguments
+/// * `node` - Node the comments belong to
+/// * `source` - The source code
+/// * `kinds` - Node kinds of comments
+pub fn leading_comments<'tree>(
+    node: Node<'tree>,
+    source: &str,
+    kinds: &[&str],
+) -> Vec<Node<'tree>> {
+    let mut comments = Vec::new();
+    let mut current = node;
+    while let Some(previous) = current.prev_sibling() {
+        if !kinds.contains(&previous.kind()) {
+            break;
+        }
+        let gap = source
+            .get(previous.end_byte()..current.start_byte())
+            .unwrap_or_default();
+        if gap.matches('\n').count() > 1 {
+            break;
+        }
+        let trails_sibling = previous.prev_sibling().is_some_and(|before| {
+            !kinds.contains(&before.kind())
+                && before.end_position().row == previous.start_position().row
+        });
+        if trails_sibling {
+            break;
+        }
+        comments.push(previous);
+        current = previous;
+    }
+    comments.reverse();
+    comments
+}
+
+/// Find the comment ending the line of `node`, if any.
+///
+/// Punctuation such as separators may sit between the node and the
+/// comment; another named node on the line ends the search.
+///
+/// # Arguments
+/// * `node` - Node the comment belongs to
+/// * `kinds` - Node kinds of comments
+pub fn trailing_comment<'tree>(node: Node<'tree>, kinds: &[&str]) -> Option<Node<'tree>> {
+    let row = node.end_position().row;
+    let mut current = node;
+    while let Some(next) = current.next_sibling() {
+        if next.start_position().row != row {
+            return None;
+        }
+        if kinds.contains(&next.kind()) {
+            return Some(next);
+        }
+        if next.is_named() {
+            return None;
+        }
+        current = next;
+    }
+    None
+}
+
+/// Find the comments inside the byte range `range`, in source order.
+///
+/// # Arguments
+/// * `root` - Node to search under
+/// * `range` - Byte range the comments must lie in
+/// * `kinds` - Node kinds of comments
+pub fn comments_in<'tree>(
+    root: Node<'tree>,
+    range: (usize, usize),
+    kinds: &[&str],
+) -> Vec<Node<'tree>> {
+    let (start, end) = range;
+    let mut comments = Vec::new();
+    walk(root, |node, _| {
+        if node.end_byte() <= start || node.start_byte() >= end {
+            return Visit::SkipChildren;
+        }
+        if kinds.contains(&node.kind()) {
+            if node.start_byte() >= start && node.end_byte() <= end {
+                comments.push(node);
+            }
+            return Visit::SkipChildren;
+        }
+        Visit::Continue
+    });
+    comments
+}
+
+/// Returns true if a comment lies inside the byte range `range`.
+pub fn contains_comments(root: Node, range: (usize, usize), kinds: &[&str]) -> bool {
+    !comments_in(root, range, kinds).is_empty()
+}
+
+/// Get the text of the `comments` that `rebuilt` no longer contains.
+///
+/// Use it to check that text built from the items of a region kept every
+/// comment of the region. A comment that appears several times must appear
+/// as often in `rebuilt`.
+///
+/// # Arguments
+/// * `comments` - Comments of the original region
+/// * `source` - The source code the comments are in
+/// * `rebuilt` - Text replacing the region
+pub fn dropped_comments<'s>(comments: &[Node], source: &'s str, rebuilt: &str) -> Vec<&'s str> {
+    let mut needed: HashMap<&str, usize> = HashMap::new();
+    let mut dropped = Vec::new();
+    for comment in comments {
+        let Some(text) = source.get(comment.start_byte()..comment.end_byte()) else {
+            continue;
+        };
+        let count = needed.entry(text).or_default();
+        *count += 1;
+        if rebuilt.matches(text).count() < *count {
+            dropped.push(text);
+        }
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: &[&str] = &["comment"];
+
+    fn parse(source: &str) -> tree_sitter::Tree {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    fn texts<'s>(nodes: &[Node], source: &'s str) -> Vec<&'s str> {
+        nodes
+            .iter()
+            .map(|node| &source[node.start_byte()..node.end_byte()])
+            .collect()
+    }
+
+    /// Get the named children of the array at the root of `tree`, skipping comments.
+    fn elements(tree: &tree_sitter::Tree) -> Vec<Node<'_>> {
+        let array = tree.root_node().named_child(0).unwrap();
+        let mut cursor = array.walk();
+        array
+            .named_children(&mut cursor)
+            .filter(|node| !KINDS.contains(&node.kind()))
+            .collect()
+    }
+
+    #[test]
+    fn test_attached_comments() {
+        let source = "[\n  // one\n  1, // after one\n\n  // lost\n\n  /* a */ // b\n  2,\n  3 /* after three */\n]";
+        let tree = parse(source);
+        let elements = elements(&tree);
+
+        let one = attached_comments(elements[0], source, KINDS);
+        assert_eq!(texts(&one.leading, source), ["// one"]);
+        assert_eq!(
+            texts(&one.trailing.into_iter().collect::<Vec<_>>(), source),
+            ["// after one"]
+        );
+
+        let two = attached_comments(elements[1], source, KINDS);
+        assert_eq!(texts(&two.leading, source), ["/* a */", "// b"]);
+        assert_eq!(two.trailing, None);
+
+        let three = attached_comments(elements[2], source, KINDS);
+        assert!(three.leading.is_empty());
+        assert!(three.trailing.is_some());
+    }
+
+    #[test]
+    fn test_trailing_comment_of_previous_sibling_does_not_lead() {
+        let source = "[1, // after one\n  2]";
+        let tree = parse(source);
+        let elements = elements(&tree);
+
+        assert!(leading_comments(elements[1], source, KINDS).is_empty());
+        assert!(attached_comments(elements[1], source, KINDS).is_empty());
+    }
+
+    #[test]
+    fn test_comments_in_range() {
+        let source = "[1, /* a */ 2, /* b */ 3]";
+        let tree = parse(source);
+        let root = tree.root_node();
+
+        assert_eq!(
+            texts(&comments_in(root, (0, source.len()), KINDS), source),
+            ["/* a */", "/* b */"]
+        );
+        assert_eq!(
+            texts(&comments_in(root, (2, 14), KINDS), source),
+            ["/* a */"]
+        );
+        assert!(!contains_comments(root, (5, 14), KINDS));
+        assert!(!contains_comments(root, (0, 3), KINDS));
+    }
+
+    #[test]
+    fn test_dropped_comments() {
+        let source = "[/* a */ 1, /* a */ 2, // b\n 3]";
+        let tree = parse(source);
+        let comments = comments_in(tree.root_node(), (0, source.len()), KINDS);
+
+        assert!(dropped_comments(&comments, source, "[/* a */ 1, /* a */ 2, // b\n3]").is_empty());
+        assert_eq!(
+            dropped_comments(&comments, source, "[/* a */ 1, 2, 3]"),
+            ["/* a */", "// b"]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::core::PassPanic;
+use serde::Serialize;
+use std::fmt;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The source was formatted, but something deserves attention
+    Warning,
+    /// The source could not be formatted as requested, or breaks a rule
+    Error,
+}
+
+impl Severity {
+    const WARNING: &'static str = "warning";
+    const ERROR: &'static str = "error";
+
+    /// Get the string representation of the severity.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => Self::WARNING,
+            Severity::Error => Self::ERROR,
+        }
+    }
+}
+
+/// A message about a source, reported by the engine or one of its passes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// How serious the diagnostic is
+    pub severity: Severity,
+    /// Name of the pass that reported it, if any
+    pub pass: Option<&'static str>,
+    /// Code of the rule it is about, if any
+    pub code: Option<&'static str>,
+    /// Byte range of the source it refers to, if any
+    pub range: Option<(usize, usize)>,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic not tied to a pass or range.
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            pass: None,
+            code: None,
+            range: None,
+            message: message.into(),
+        }
+    }
+
+    /// Create a warning.
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    /// Create an error.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    /// Attribute the diagnostic to `pass`.
+    #[must_use]
+    pub fn with_pass(mut self, pass: &'static str) -> Self {
+        self.pass = Some(pass);
+        self
+    }
+
+    /// Tag the diagnostic with the code of the rule it is about.
+    #[must_use]
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Point the diagnostic at a byte range of the source.
+    #[must_use]
+    pub fn with_range(mut self, range: (usize, usize)) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Returns true if the diagnostic is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.severity.as_str())?;
+        if let Some(pass) = self.pass {
+            write!(f, "[{pass}]")?;
+        }
+        if let Some(code) = self.code {
+            write!(f, " {code}")?;
+        }
+        if let Some((start, end)) = self.range {
+            write!(f, " at {start}..{end}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl From<PassPanic> for Diagnostic {
+    fn from(panic: PassPanic) -> Self {
+        Diagnostic::error(format!("panicked: {}", panic.message)).with_pass(panic.pass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let diagnostic = Diagnostic::warning("line too long")
+            .with_pass("WidthPass")
+            .with_range((4, 9));
+        assert_eq!(
+            diagnostic.to_string(),
+            "warning[WidthPass] at 4..9: line too long"
+        );
+        assert_eq!(
+            Diagnostic::error("line too long")
+                .with_pass("WidthPass")
+                .with_code("FMT001")
+                .to_string(),
+            "error[WidthPass] FMT001: line too long"
+        );
+
+        let panic = PassPanic {
+            pass: "ColonPass",
+            message: "bad node".to_string(),
+        };
+        assert_eq!(
+            Diagnostic::from(panic).to_string(),
+            "error[ColonPass]: panicked: bad node"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt::Write;
+use std::path::Path;
+
+/// Unchanged lines shown around every change.
+const CONTEXT_LINES: usize = 3;
+
+/// Edit distance (in lines) above which the changed region is shown as one block.
+const MAX_EDIT_LINES: usize = 1000;
+
+/// One step of a line diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    /// The line is in both texts
+    Equal,
+    /// The line is only in the original
+    Delete,
+    /// The line is only in the formatted text
+    Insert,
+}
+
+/// Build a unified diff from `original` to `formatted`.
+///
+/// # Arguments
+/// * `path` - Path shown in the `---`/`+++` header lines
+/// * `original` - Text before formatting
+/// * `formatted` - Text after formatting
+///
+/// # Returns
+/// The diff, empty if the texts are equal
+pub(crate) fn unified_diff(path: &Path, original: &str, formatted: &str) -> String {
+    if original == formatted {
+        return String::new();
+    }
+
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = formatted.split_inclusive('\n').collect();
+    let ops = diff_lines(&old, &new);
+
+    // Line positions in both texts before every op
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut o, mut n) = (0, 0);
+    for op in &ops {
+        positions.push((o, n));
+        match op {
+            DiffOp::Equal => (o, n) = (o + 1, n + 1),
+            DiffOp::Delete => o += 1,
+            DiffOp::Insert => n += 1,
+        }
+    }
+    positions.push((o, n));
+
+    let mut out = format!("--- a/{0}\n+++ b/{0}\n", path.display());
+    for (start, end) in hunks(&ops) {
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        let _ = writeln!(
+            out,
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        );
+        for (op, (o, n)) in ops[start..end].iter().zip(&positions[start..end]) {
+            let (prefix, line) = match op {
+                DiffOp::Equal => (' ', old[*o]),
+                DiffOp::Delete => ('-', old[*o]),
+                DiffOp::Insert => ('+', new[*n]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Format the `start,count` part of a hunk header from a 0-based start.
+fn hunk_range(start: usize, count: usize) -> String {
+    // An empty range names the line before it
+    let start = if count == 0 { start } else { start + 1 };
+    format!("{start},{count}")
+}
+
+/// Group changed ops with their context into hunks of op indices `start..end`.
+fn hunks(ops: &[DiffOp]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        if *op == DiffOp::Equal {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + 1 + CONTEXT_LINES).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    hunks
+}
+
+/// Diff two lists of lines, trimming their common prefix and suffix first.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let middle = myers(old_middle, new_middle).unwrap_or_else(|| {
+        let mut ops = vec![DiffOp::Delete; old_middle.len()];
+        ops.resize(old_middle.len() + new_middle.len(), DiffOp::Insert);
+        ops
+    });
+
+    let mut ops = vec![DiffOp::Equal; prefix];
+    ops.extend(middle);
+    ops.resize(ops.len() + suffix, DiffOp::Equal);
+    ops
+}
+
+/// Shortest edit script between `old` and `new` with Myers' algorithm.
+///
+/// # Returns
+/// The ops, or `None` if more than `MAX_EDIT_LINES` lines differ
+fn myers(old: &[&str], new: &[&str]) -> Option<Vec<DiffOp>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let limit = (n + m).min(MAX_EDIT_LINES as isize);
+    let offset = limit + 1;
+    let index = |k: isize| (k + offset) as usize;
+
+    // Furthest x reached on every diagonal k = x - y, before each step d
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+
+    for d in 0..=limit {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m, index));
+            }
+        }
+    }
+    None
+}
+
+/// Walk the Myers trace back from the end to recover the ops.
+fn backtrack(
+    trace: &[Vec<isize>],
+    n: isize,
+    m: isize,
+    index: impl Fn(isize) -> usize,
+) -> Vec<DiffOp> {
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(DiffOp::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            ops.push(if x == prev_x {
+                DiffOp::Insert
+            } else {
+                DiffOp::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_with_context() {
+        let original: String = (1..=12).map(|i| format!("{i}\n")).collect();
+        let formatted: String = (1..=12)
+            .map(|i| match i {
+                2 => "X\n".to_string(),
+                11 => "Y\n".to_string(),
+                _ => format!("{i}\n"),
+            })
+            .collect();
+        let diff = unified_diff(Path::new("x.txt"), &original, &formatted);
+
+        assert_eq!(
+            diff,
+            "--- a/x.txt\n+++ b/x.txt\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+X\n 3\n 4\n 5\n\
+             @@ -8,5 +8,5 @@\n 8\n 9\n 10\n-11\n+Y\n 12\n"
+        );
+        assert_eq!(unified_diff(Path::new("x.txt"), &original, &original), "");
+    }
+
+    #[test]
+    fn test_diff_lines_is_minimal() {
+        let old = ["a\n", "b\n", "c\n", "a\n", "b\n", "b\n", "a\n"];
+        let new = ["c\n", "b\n", "a\n", "b\n", "a\n", "c\n"];
+        let ops = diff_lines(&old, &new);
+
+        let changes = ops.iter().filter(|op| **op != DiffOp::Equal).count();
+        assert_eq!(changes, 5);
+        assert_eq!(ops.iter().filter(|op| **op != DiffOp::Insert).count(), 7);
+        assert_eq!(ops.iter().filter(|op| **op != DiffOp::Delete).count(), 6);
+    }
+
+    #[test]
+    fn test_insertion_into_empty_text() {
+        assert_eq!(
+            unified_diff(Path::new("x"), "", "a"),
+            "--- a/x\n+++ b/x\n@@ -0,0 +1,1 @@\n+a\n\\ No newline at end of file\n"
+        );
+    }
+}
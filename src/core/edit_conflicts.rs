@@ -0,0 +1,144 @@
+use crate::core::Diagnostic;
+use crate::pipeline::{ConflictPolicy, Edit};
+use log::debug;
+use std::collections::BTreeMap;
+
+/// Combine the edits several passes proposed for the same text.
+///
+/// The edits of each pass must already be valid on their own (see
+/// `validate_edits`). An edit conflicts with an edit of another pass if
+/// their ranges overlap; insertions at the same offset, or at either end
+/// of a replacement, do not conflict.
+///
+/// # Arguments
+/// * `policy` - How to resolve conflicts; a batched policy
+/// * `proposed` - The name and edits of every pass, in pipeline order
+///
+/// # Returns
+/// The kept edits in pipeline order, or one error diagnostic per conflict
+/// under `ConflictPolicy::Error`
+pub(crate) fn resolve_conflicts(
+    policy: ConflictPolicy,
+    proposed: Vec<(&'static str, Vec<Edit>)>,
+) -> Result<Vec<Edit>, Vec<Diagnostic>> {
+    let mut order: Vec<usize> = (0..proposed.len()).collect();
+    if policy == ConflictPolicy::LastWins {
+        order.reverse();
+    }
+
+    // Kept ranges of the passes visited so far, with the pass that owns them
+    let mut kept: BTreeMap<(usize, usize), &'static str> = BTreeMap::new();
+    let mut keep: Vec<Vec<bool>> = proposed
+        .iter()
+        .map(|(_, edits)| vec![true; edits.len()])
+        .collect();
+    let mut conflicts = Vec::new();
+
+    for i in order {
+        let (pass, edits) = &proposed[i];
+        for (edit, keep) in edits.iter().zip(keep[i].iter_mut()) {
+            let Some((range, other)) = overlapping(&kept, edit.range) else {
+                continue;
+            };
+            *keep = false;
+            if policy == ConflictPolicy::Error {
+                conflicts.push(
+                    Diagnostic::error(format!(
+                        "edit conflicts with the edit of {other} at {}..{}; no edits were applied",
+                        range.0, range.1
+                    ))
+                    .with_pass(pass)
+                    .with_range(edit.range),
+                );
+            } else {
+                debug!(
+                    "Dropped the edit of {pass} at {:?}, which conflicts with {other}",
+                    edit.range
+                );
+            }
+        }
+        for (edit, _) in edits.iter().zip(&keep[i]).filter(|(_, keep)| **keep) {
+            kept.insert(edit.range, pass);
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+    Ok(proposed
+        .into_iter()
+        .zip(keep)
+        .flat_map(|((_, edits), keep)| {
+            edits
+                .into_iter()
+                .zip(keep)
+                .filter_map(|(edit, keep)| keep.then_some(edit))
+        })
+        .collect())
+}
+
+/// Find a kept range overlapping `range`.
+///
+/// Kept ranges never overlap each other, so only the one starting last
+/// before the end of `range` can overlap it.
+fn overlapping(
+    kept: &BTreeMap<(usize, usize), &'static str>,
+    range: (usize, usize),
+) -> Option<((usize, usize), &'static str)> {
+    let (&other, &pass) = kept.range(..(range.1, 0)).next_back()?;
+    (other.0 < range.1 && range.0 < other.1).then_some((other, pass))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn edit(start: usize, end: usize, content: &str) -> Edit {
+        Edit {
+            range: (start, end),
+            content: content.to_string(),
+        }
+    }
+
+    fn proposed() -> Vec<(&'static str, Vec<Edit>)> {
+        vec![
+            ("First", vec![edit(0, 4, "a"), edit(6, 6, "b")]),
+            (
+                "Second",
+                vec![edit(2, 3, "c"), edit(4, 6, "d"), edit(6, 6, "e")],
+            ),
+        ]
+    }
+
+    #[rstest]
+    #[case(ConflictPolicy::FirstWins, vec![edit(0, 4, "a"), edit(6, 6, "b"), edit(4, 6, "d"), edit(6, 6, "e")])]
+    #[case(ConflictPolicy::LastWins, vec![edit(6, 6, "b"), edit(2, 3, "c"), edit(4, 6, "d"), edit(6, 6, "e")])]
+    fn test_conflicting_edits_are_dropped(
+        #[case] policy: ConflictPolicy,
+        #[case] expected: Vec<Edit>,
+    ) {
+        assert_eq!(resolve_conflicts(policy, proposed()), Ok(expected));
+    }
+
+    #[test]
+    fn test_error_policy_reports_every_conflict() {
+        let diagnostics = resolve_conflicts(ConflictPolicy::Error, proposed()).unwrap_err();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[Second] at 2..3: edit conflicts with the edit of First at 0..4; no edits were applied"
+        );
+    }
+
+    #[test]
+    fn test_overlapping_finds_ranges_covering_an_insertion() {
+        let kept = BTreeMap::from([((2, 5), "First"), ((5, 5), "First")]);
+
+        assert_eq!(overlapping(&kept, (3, 3)), Some(((2, 5), "First")));
+        assert_eq!(overlapping(&kept, (5, 5)), None);
+        assert_eq!(overlapping(&kept, (5, 8)), None);
+        assert_eq!(overlapping(&kept, (4, 8)), Some(((5, 5), "First")));
+    }
+}
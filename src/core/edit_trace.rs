@@ -0,0 +1,82 @@
+use crate::pipeline::Edit;
+use std::sync::Mutex;
+
+/// An edit produced by a pass, with the text it replaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedEdit {
+    /// Index of the source in the batch passed to the engine
+    pub file: usize,
+    /// Name of the pass that produced the edit
+    pub pass: &'static str,
+    /// Byte range of the edit in the source the pass saw
+    pub range: (usize, usize),
+    /// Text in `range` before the edit
+    pub before: String,
+    /// Replacement text
+    pub after: String,
+}
+
+/// Edits collected by an engine, shared between worker threads.
+#[derive(Debug, Default)]
+pub(crate) struct EditTrace {
+    edits: Mutex<Vec<TracedEdit>>,
+}
+
+impl EditTrace {
+    /// Record the edits `pass` produced for the source at index `file`.
+    pub(crate) fn record(&self, file: usize, pass: &'static str, source: &str, edits: &[Edit]) {
+        let traced = edits.iter().map(|edit| TracedEdit {
+            file,
+            pass,
+            range: edit.range,
+            before: source
+                .get(edit.range.0..edit.range.1)
+                .unwrap_or_default()
+                .to_string(),
+            after: edit.content.clone(),
+        });
+        self.edits
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .extend(traced);
+    }
+
+    /// Take the recorded edits, sorted by file and then in the order they were produced.
+    pub(crate) fn take(&self) -> Vec<TracedEdit> {
+        let mut edits = std::mem::take(
+            &mut *self
+                .edits
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        edits.sort_by_key(|edit| edit.file);
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_keeps_replaced_text() {
+        let trace = EditTrace::default();
+        let edit = |start, end, content: &str| Edit {
+            range: (start, end),
+            content: content.to_string(),
+        };
+        trace.record(1, "B", "{\"a\" :1}", &[edit(4, 6, ": ")]);
+        trace.record(0, "A", "[1,2]", &[edit(2, 3, ", "), edit(9, 12, "x")]);
+
+        let taken = trace.take();
+        let rows: Vec<_> = taken
+            .iter()
+            .map(|t| (t.file, t.pass, t.before.as_str(), t.after.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(0, "A", ",", ", "), (0, "A", "", "x"), (1, "B", " :", ": ")]
+        );
+        assert!(trace.take().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::core::Diagnostic;
+use crate::pipeline::Edit;
+
+/// Check that the edits of one pass can be applied to `source` unambiguously.
+///
+/// Every edit must have `start <= end`, stay within the source, and start
+/// and end on UTF-8 character boundaries. No two edits may overlap, and
+/// no two insertions may share an offset, since their order would be
+/// ambiguous.
+///
+/// # Arguments
+/// * `pass` - Name of the pass that produced the edits
+/// * `source` - The source the edits apply to
+/// * `edits` - The edits produced by the pass
+///
+/// # Returns
+/// One error diagnostic per problem, empty if the edits are valid
+pub(crate) fn validate_edits(pass: &'static str, source: &str, edits: &[Edit]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut valid: Vec<(usize, usize)> = Vec::with_capacity(edits.len());
+
+    for edit in edits {
+        let (start, end) = edit.range;
+        let problem = if start > end {
+            Some("has its start after its end".to_string())
+        } else if end > source.len() {
+            Some(format!("ends past the source of {} bytes", source.len()))
+        } else if !source.is_char_boundary(start) || !source.is_char_boundary(end) {
+            Some("is not on a UTF-8 character boundary".to_string())
+        } else {
+            None
+        };
+
+        match problem {
+            Some(problem) => diagnostics.push(invalid(pass, edit.range, &problem)),
+            None => valid.push(edit.range),
+        }
+    }
+
+    valid.sort_unstable();
+    for pair in valid.windows(2) {
+        let (previous, range) = (pair[0], pair[1]);
+        if range.0 < previous.1 {
+            diagnostics.push(invalid(
+                pass,
+                range,
+                &format!("overlaps the edit at {}..{}", previous.0, previous.1),
+            ));
+        } else if range == previous && range.0 == range.1 {
+            diagnostics.push(invalid(
+                pass,
+                range,
+                "inserts at the same offset as another edit",
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Build the diagnostic of an edit at `range` with the given problem.
+fn invalid(pass: &'static str, range: (usize, usize), problem: &str) -> Diagnostic {
+    Diagnostic::error(format!("edit {problem}; edits of the pass were rejected"))
+        .with_pass(pass)
+        .with_range(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn edit(start: usize, end: usize) -> Edit {
+        Edit {
+            range: (start, end),
+            content: "x".to_string(),
+        }
+    }
+
+    #[rstest]
+    #[case(vec![edit(0, 1), edit(1, 2), edit(4, 4), edit(4, 5), edit(7, 7)], 0)]
+    #[case(vec![edit(0, 3), edit(2, 4)], 1)]
+    #[case(vec![edit(2, 2), edit(2, 2)], 1)]
+    #[case(vec![edit(3, 1)], 1)]
+    #[case(vec![edit(5, 9)], 1)]
+    #[case(vec![edit(0, 1), edit(6, 6)], 1)]
+    fn test_validate_edits(#[case] edits: Vec<Edit>, #[case] problems: usize) {
+        // "é" occupies bytes 5..7
+        let source = "abcdeé";
+        let diagnostics = validate_edits("TestPass", source, &edits);
+
+        assert_eq!(diagnostics.len(), problems);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.is_error() && d.pass == Some("TestPass")));
+    }
+
+    #[test]
+    fn test_overlap_message_names_both_ranges() {
+        let diagnostics = validate_edits("TestPass", "abcdef", &[edit(0, 3), edit(2, 4)]);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[TestPass] at 2..4: edit overlaps the edit at 0..3; edits of the pass were rejected"
+        );
+    }
+}
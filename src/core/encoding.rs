@@ -0,0 +1,213 @@
+use std::io;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+/// Number of leading bytes inspected by `Encoding::is_binary`, as in git.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Encoding of a source file on disk.
+///
+/// Sources are formatted as UTF-8 text without a byte order mark; the
+/// encoding is detected when reading and applied again when writing, so
+/// formatting never changes it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8 without a byte order mark
+    #[default]
+    Utf8,
+    /// UTF-8 with a byte order mark
+    Utf8Bom,
+    /// Little-endian UTF-16 with a byte order mark
+    Utf16Le,
+    /// Big-endian UTF-16 with a byte order mark
+    Utf16Be,
+    /// ISO-8859-1, used for files that are not valid UTF-8 when enabled
+    Latin1,
+}
+
+impl Encoding {
+    const UTF8: &'static str = "utf-8";
+    const UTF8_BOM: &'static str = "utf-8-bom";
+    const UTF16_LE: &'static str = "utf-16le";
+    const UTF16_BE: &'static str = "utf-16be";
+    const LATIN1: &'static str = "latin-1";
+
+    /// Get the string representation of the encoding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => Self::UTF8,
+            Encoding::Utf8Bom => Self::UTF8_BOM,
+            Encoding::Utf16Le => Self::UTF16_LE,
+            Encoding::Utf16Be => Self::UTF16_BE,
+            Encoding::Latin1 => Self::LATIN1,
+        }
+    }
+
+    /// Detect the encoding of `bytes` and decode them.
+    ///
+    /// UTF-16 is only recognized by its byte order mark. Bytes that are not
+    /// valid in the detected encoding are decoded as Latin-1 if
+    /// `latin1_fallback` is set.
+    ///
+    /// # Returns
+    /// The decoded text without byte order mark and its encoding, or `None`
+    /// if the bytes cannot be decoded
+    pub fn decode(bytes: Vec<u8>, latin1_fallback: bool) -> Option<(String, Encoding)> {
+        let decoded = if bytes.starts_with(UTF8_BOM) {
+            std::str::from_utf8(&bytes[UTF8_BOM.len()..])
+                .ok()
+                .map(|text| (text.to_string(), Encoding::Utf8Bom))
+        } else if bytes.starts_with(UTF16_LE_BOM) {
+            decode_utf16(&bytes[UTF16_LE_BOM.len()..], u16::from_le_bytes)
+                .map(|text| (text, Encoding::Utf16Le))
+        } else if bytes.starts_with(UTF16_BE_BOM) {
+            decode_utf16(&bytes[UTF16_BE_BOM.len()..], u16::from_be_bytes)
+                .map(|text| (text, Encoding::Utf16Be))
+        } else {
+            return match String::from_utf8(bytes) {
+                Ok(text) => Some((text, Encoding::Utf8)),
+                Err(err) if latin1_fallback => Some((
+                    err.as_bytes().iter().map(|&b| char::from(b)).collect(),
+                    Encoding::Latin1,
+                )),
+                Err(_) => None,
+            };
+        };
+
+        match decoded {
+            Some(decoded) => Some(decoded),
+            None if latin1_fallback => Some((
+                bytes.iter().map(|&b| char::from(b)).collect(),
+                Encoding::Latin1,
+            )),
+            None => None,
+        }
+    }
+
+    /// Guess whether `bytes` are binary data rather than text.
+    ///
+    /// Only the first 8000 bytes are inspected. Text with a UTF-16 byte
+    /// order mark is never binary. Otherwise bytes are binary if they contain
+    /// a NUL byte, or if they are not valid UTF-8 and more than a tenth of
+    /// them are control characters other than whitespace.
+    pub fn is_binary(bytes: &[u8]) -> bool {
+        if bytes.starts_with(UTF16_LE_BOM) || bytes.starts_with(UTF16_BE_BOM) {
+            return false;
+        }
+        let sniffed = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+        if sniffed.contains(&0) {
+            return true;
+        }
+        // A character cut at the end of the window is not an error.
+        let invalid_utf8 = std::str::from_utf8(sniffed).is_err_and(|err| err.error_len().is_some());
+        let control = sniffed
+            .iter()
+            .filter(|&&b| b < 0x20 && !b"\t\n\r\x0C".contains(&b) || b == 0x7F)
+            .count();
+        invalid_utf8 && control * 10 > sniffed.len()
+    }
+
+    /// Encode `text` in this encoding, including its byte order mark.
+    ///
+    /// # Errors
+    /// Returns an `InvalidData` error if `text` contains characters that
+    /// Latin-1 cannot represent.
+    pub fn encode(&self, text: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf8Bom => Ok([UTF8_BOM, text.as_bytes()].concat()),
+            Encoding::Utf16Le => Ok(UTF16_LE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect()),
+            Encoding::Utf16Be => Ok(UTF16_BE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("'{c}' cannot be encoded as Latin-1"),
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Decode UTF-16 code units read with `from_bytes`; `None` if they are invalid.
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Encoding::Utf8)]
+    #[case(Encoding::Utf8Bom)]
+    #[case(Encoding::Utf16Le)]
+    #[case(Encoding::Utf16Be)]
+    fn test_decode_round_trips(#[case] encoding: Encoding) {
+        let text = "{\"a\": \"é🌍\"}\n";
+        let bytes = encoding.encode(text).unwrap();
+
+        assert_eq!(
+            Encoding::decode(bytes, false),
+            Some((text.to_string(), encoding))
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_utf8_without_fallback() {
+        assert_eq!(Encoding::decode(b"caf\xE9".to_vec(), false), None);
+    }
+
+    #[test]
+    fn test_decode_falls_back_to_latin1() {
+        let (text, encoding) = Encoding::decode(b"caf\xE9".to_vec(), true).unwrap();
+
+        assert_eq!(text, "café");
+        assert_eq!(encoding, Encoding::Latin1);
+        assert_eq!(encoding.encode(&text).unwrap(), b"caf\xE9");
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_utf16() {
+        assert_eq!(Encoding::decode(b"\xFF\xFEa".to_vec(), false), None);
+    }
+
+    #[rstest]
+    #[case(b"{\"a\": 1}\n", false)]
+    #[case(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", true)]
+    #[case(b"caf\xE9", false)]
+    #[case(b"\xE9\x01\x02\x03\x04", true)]
+    #[case(b"\xFF\xFE{\0}\0", false)]
+    fn test_is_binary(#[case] bytes: &[u8], #[case] expected: bool) {
+        assert_eq!(Encoding::is_binary(bytes), expected);
+    }
+
+    #[test]
+    fn test_encode_latin1_rejects_wide_characters() {
+        let err = Encoding::Latin1.encode("🌍").unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,8 +1,30 @@
+use crate::core::cancellation::CancellationToken;
+use crate::core::diff::unified_diff;
+use crate::core::edit_conflicts::resolve_conflicts;
+use crate::core::edit_trace::{EditTrace, TracedEdit};
+use crate::core::edit_validation::validate_edits;
+use crate::core::engine_cache::{CacheKey, EngineCache};
+use crate::core::engine_observer::EngineObserver;
+use crate::core::line_endings::{convert_line_endings, LineEnding};
+use crate::core::pass_panic::{attribute_panic, isolate, PassPanic};
+use crate::core::pass_timings::{PassTiming, PassTimings};
+use crate::core::suppression::{disables_file, Suppressions};
+use crate::core::{
+    fnv1a_64, Diagnostic, EngineError, FileError, FileFormatOutcome, FileWriter, FirstStop,
+    FormatResult, LineEndings, LineRange, ParseErrorPolicy, ParseMetrics, RuleLevel, RunMetrics,
+    RunReport, Severity,
+};
 use crate::parser::{LanguageProvider, ParseState, Parser};
-use crate::pipeline::Pipeline;
+use crate::pipeline::{Edit, Phase, Pipeline, ProjectEdit, ProjectFile, SourceMap};
 use log::debug;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
 /// The main formatting engine that coordinates parsing and pipeline execution.
 ///
@@ -16,128 +38,3769 @@ use std::path::PathBuf;
 /// # Examples
 /// ```ignore
 /// let pipeline = Pipeline::new();
-/// let mut engine = Engine::<MyLanguage, MyConfig>::new(pipeline);
+/// let mut engine = Engine::<MyLanguage, MyConfig>::new(pipeline).with_jobs(4);
 /// ```
 pub struct Engine<Language: LanguageProvider, Config> {
     pipeline: Pipeline<Config>,
     parser: Parser<Language>,
+    jobs: usize,
+    recorders: Recorders,
+    cache: Option<EngineCache>,
+    diffs: bool,
+    reparse_check: Option<Severity>,
+    on_parse_error: ParseErrorPolicy,
+    line_endings: Option<LineEndings>,
+    generated_markers: Vec<String>,
+    source_maps: bool,
     _marker: PhantomData<(Language, Config)>,
 }
 
 impl<Language: LanguageProvider, C> Engine<Language, C> {
     /// Create a new engine with the given pipeline.
     ///
+    /// A convenience wrapper around `try_new` for pipelines known to be
+    /// valid.
+    ///
     /// # Arguments
     /// * `pipeline` - The formatting pipeline to use
+    ///
+    /// # Panics
+    /// Panics if the passes of the pipeline cannot be put in order, or if
+    /// the grammar of the language is incompatible with the linked
+    /// tree-sitter runtime.
     pub fn new(pipeline: Pipeline<C>) -> Self {
-        Self {
+        Self::try_new(pipeline).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Create a new engine with the given pipeline, reporting invalid pipelines and grammars.
+    ///
+    /// The passes of the pipeline are put in order first; see
+    /// `Pipeline::resolve_order`.
+    ///
+    /// # Arguments
+    /// * `pipeline` - The formatting pipeline to use
+    ///
+    /// # Errors
+    /// Returns `EngineError::Pipeline` with the error of
+    /// `Pipeline::resolve_order` if the passes depend on an unknown pass, on
+    /// a pass of a later phase, or on each other in a cycle, and
+    /// `EngineError::Grammar` if the grammar of the language is incompatible
+    /// with the linked tree-sitter runtime
+    pub fn try_new(mut pipeline: Pipeline<C>) -> Result<Self, EngineError> {
+        pipeline.resolve_order()?;
+        Ok(Self {
             pipeline,
-            parser: Parser::new(),
+            parser: Parser::try_new()?,
+            jobs: 1,
+            recorders: Recorders::default(),
+            cache: None,
+            diffs: false,
+            reparse_check: None,
+            on_parse_error: ParseErrorPolicy::default(),
+            line_endings: None,
+            generated_markers: Vec::new(),
+            source_maps: false,
             _marker: PhantomData,
-        }
+        })
     }
 
-    /// Run the pipeline on the given parse state.
+    /// Set the number of worker threads used for batch operations.
+    ///
+    /// `1` (the default) formats files sequentially on the calling thread,
+    /// `0` uses one worker per available CPU. Wasm targets have no threads
+    /// and always format sequentially.
+    ///
+    /// # Arguments
+    /// * `jobs` - Number of worker threads
+    #[must_use]
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Record the wall time of every pass on every source.
+    ///
+    /// Retrieve the timings with `take_timings` after formatting.
+    #[must_use]
+    pub fn with_timings(mut self) -> Self {
+        self.recorders.timings = Some(PassTimings::default());
+        self
+    }
+
+    /// Record every edit produced by every pass, with the text it replaces.
+    ///
+    /// Retrieve the edits with `take_edit_trace` after formatting.
+    #[must_use]
+    pub fn with_edit_trace(mut self) -> Self {
+        self.recorders.edit_trace = Some(EditTrace::default());
+        self
+    }
+
+    /// Report diagnostics tagged with a rule code at the level set for the code.
+    ///
+    /// Diagnostics of rules set to `RuleLevel::Off` are dropped; the others
+    /// get the severity of their level. Codes not in `levels` keep the
+    /// severity their pass gave them.
+    ///
+    /// # Arguments
+    /// * `levels` - Level of each rule code
+    #[must_use]
+    pub fn with_rule_levels(mut self, levels: BTreeMap<String, RuleLevel>) -> Self {
+        self.recorders.rule_levels = levels;
+        self
+    }
+
+    /// Leave sources unformatted if their first lines contain one of `markers`.
+    ///
+    /// Sources containing the disable-file directive of the language
+    /// (`LanguageCapabilities::disable_file_marker`) in their first lines
+    /// are always left unformatted; `markers` adds texts such as
+    /// `@generated` that mark generated files. Batch methods report these
+    /// files as ignored.
+    ///
+    /// # Arguments
+    /// * `markers` - Texts marking files that must not be formatted
+    #[must_use]
+    pub fn with_generated_markers(mut self, markers: Vec<String>) -> Self {
+        self.generated_markers = markers;
+        self
+    }
+
+    /// Skip sources whose formatting result is already in `cache`.
+    ///
+    /// Batch methods then only run the passes on sources not known to be
+    /// formatted, and record the results of the others. Pipelines with
+    /// consistency or project passes bypass the cache, since their output
+    /// depends on every file of the run. Persist new results with `save_cache`.
+    ///
+    /// # Arguments
+    /// * `cache` - Results of earlier runs
+    #[must_use]
+    pub fn with_cache(mut self, cache: EngineCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Include a unified diff in the outcome of every changed file.
+    ///
+    /// Applies to `format_files`.
+    #[must_use]
+    pub fn with_diffs(mut self) -> Self {
+        self.diffs = true;
+        self
+    }
+
+    /// Include a source map of the applied edits in the outcome of every formatted file.
+    ///
+    /// Applies to `format_files`; files taken from the cache or ignored get
+    /// no map.
+    #[must_use]
+    pub fn with_source_maps(mut self) -> Self {
+        self.source_maps = true;
+        self
+    }
+
+    /// Reparse every changed source from scratch after formatting, and report
+    /// syntax errors that the original did not have.
+    ///
+    /// This guards against passes that generate invalid code. With
+    /// `Severity::Warning` the formatted text is kept; with `Severity::Error`
+    /// the source is left unformatted. Either way a diagnostic of that
+    /// severity is reported, as `format_str` and `format_files` return them.
+    ///
+    /// # Arguments
+    /// * `severity` - Severity of the diagnostic, deciding whether to keep the output
+    #[must_use]
+    pub fn with_reparse_check(mut self, severity: Severity) -> Self {
+        self.reparse_check = Some(severity);
+        self
+    }
+
+    /// Reparse the source with each edit applied on its own, and reject
+    /// edits that introduce syntax errors.
+    ///
+    /// Unlike `with_reparse_check`, which judges the formatted text as a
+    /// whole, this pins a broken edit on the pass that produced it: the
+    /// edit is dropped, the other edits are applied, and an error
+    /// diagnostic names the pass. Every edit costs a full parse, so this
+    /// suits developing passes more than formatting large trees.
+    ///
+    /// # Arguments
+    /// * `check` - Whether to check every edit
+    #[must_use]
+    pub fn with_edit_check(mut self, check: bool) -> Self {
+        self.recorders.check_edits = check;
+        self
+    }
+
+    /// Compare the tree after every pass with a full parse of the new source.
+    ///
+    /// A difference means the edits of the pass were described wrongly to
+    /// tree-sitter, so later passes would see stale nodes. It is reported
+    /// as an error diagnostic naming the pass and the edit after which the
+    /// trees diverged, and the full parse replaces the stale tree. Every
+    /// pass then costs a full parse, so this suits testing passes and
+    /// changes to incremental parsing.
+    ///
+    /// # Arguments
+    /// * `check` - Whether to check the tree after every pass
+    #[must_use]
+    pub fn with_incremental_check(mut self, check: bool) -> Self {
+        self.parser.set_incremental_check(check);
+        self
+    }
+
+    /// Set what to do with sources that have syntax errors before formatting.
+    ///
+    /// Defaults to `ParseErrorPolicy::Format`. Skipped sources are also left
+    /// out of the summaries of consistency passes and the files of project
+    /// passes.
+    ///
+    /// # Arguments
+    /// * `policy` - The policy for sources with syntax errors
+    #[must_use]
+    pub fn with_parse_error_policy(mut self, policy: ParseErrorPolicy) -> Self {
+        self.on_parse_error = policy;
+        self
+    }
+
+    /// Run passes on sources with `\n` line endings and give the results `endings`.
     ///
-    /// This method applies all passes in the pipeline sequentially,
-    /// collecting edits and applying them in reverse order to maintain
-    /// correct byte offsets.
+    /// Without this, passes see line endings as they are, and text they
+    /// insert may mix `\n` into a `\r\n` source.
+    ///
+    /// # Arguments
+    /// * `endings` - The line endings of formatted sources
+    #[must_use]
+    pub fn with_line_endings(mut self, endings: LineEndings) -> Self {
+        self.line_endings = Some(endings);
+        self
+    }
+
+    /// Stop formatting when `token` is cancelled.
+    ///
+    /// The token is checked before every source and between passes. Sources
+    /// not finished by then are left unchanged and get an error diagnostic,
+    /// so `format_files` reports them; `find_first_unformatted` stops
+    /// looking and returns `None`.
+    ///
+    /// # Arguments
+    /// * `token` - Token the host cancels to abort the run
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.parser.set_cancellation(Some(token.clone()));
+        self.recorders.cancellation = Some(token);
+        self
+    }
+
+    /// Give up on sources that take longer than `timeout` to parse.
+    ///
+    /// Such sources are left unchanged and get an error diagnostic, so a
+    /// pathological source or a buggy grammar cannot hang the run. The
+    /// limit applies to every parse, including the reparses after edits.
+    ///
+    /// # Arguments
+    /// * `timeout` - Longest time a single parse may take
+    #[must_use]
+    pub fn with_parse_timeout(mut self, timeout: Duration) -> Self {
+        self.parser.set_timeout(Some(timeout));
+        self
+    }
+
+    /// Report progress to `observer` while formatting.
+    ///
+    /// # Arguments
+    /// * `observer` - Receives an event when a file starts, a pass is done, and a file is done
+    #[must_use]
+    pub fn with_observer(mut self, observer: impl EngineObserver + 'static) -> Self {
+        self.recorders.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Write the results recorded in the cache back to its directory.
+    ///
+    /// Does nothing unless `with_cache` was used.
+    ///
+    /// # Errors
+    /// Returns an error if the cache cannot be written
+    pub fn save_cache(&self) -> std::io::Result<()> {
+        self.cache.as_ref().map_or(Ok(()), EngineCache::save)
+    }
+
+    /// Take the pass timings recorded since the last call.
+    ///
+    /// Sources are identified by their index in the batch; single-source
+    /// methods record under index `0`. Empty unless `with_timings` was used.
+    pub fn take_timings(&mut self) -> Vec<PassTiming> {
+        self.recorders
+            .timings
+            .as_ref()
+            .map(PassTimings::take)
+            .unwrap_or_default()
+    }
+
+    /// Take the edits recorded since the last call.
+    ///
+    /// Sources are identified by their index in the batch; single-source
+    /// methods record under index `0`. Empty unless `with_edit_trace` was used.
+    pub fn take_edit_trace(&mut self) -> Vec<TracedEdit> {
+        self.recorders
+            .edit_trace
+            .as_ref()
+            .map(EditTrace::take)
+            .unwrap_or_default()
+    }
+
+    /// Run the pipeline on the given parse state.
     ///
     /// # Arguments
     /// * `config` - Configuration to pass to each pass
     /// * `state` - The parse state containing source and tree
-    fn run(&mut self, config: &C, state: &mut ParseState) {
-        // Ensure we have a parsed tree
-        if !state.has_tree() {
-            self.parser.parse(state);
+    /// * `source_map` - Optional source map recording every applied edit
+    /// * `range` - Optional byte range outside of which edits are dropped
+    ///
+    /// The source is restored if the engine skips it or the reparse check
+    /// discards the result. Ignored sources are left as they are.
+    ///
+    /// # Returns
+    /// The diagnostics reported while running the pipeline
+    fn run(
+        &mut self,
+        config: &C,
+        state: &mut ParseState,
+        mut source_map: Option<&mut SourceMap>,
+        mut range: Option<&mut (usize, usize)>,
+    ) -> Vec<Diagnostic> {
+        if ignores_source::<Language>(state.source(), &self.generated_markers) {
+            return self.take_diagnostics();
+        }
+        let (source, original_range) = (state.source().to_string(), range.as_deref().copied());
+        let ending = self.line_endings.map(|endings| endings.target(&source));
+        if ending.is_some() {
+            convert_line_endings(
+                state,
+                LineEnding::Lf,
+                source_map.as_deref_mut(),
+                range.as_deref_mut(),
+            );
+        }
+
+        let recorder = self.recorders.for_file(0);
+        recorder.start();
+        let formatted = !recorder.is_cancelled()
+            && accepts_parse(&mut self.parser, state, self.on_parse_error, recorder);
+        if formatted {
+            self.run_passes(
+                config,
+                state,
+                source_map.as_deref_mut(),
+                range.as_deref_mut(),
+            );
+        }
+        if let Some(ending) = ending {
+            convert_line_endings(
+                state,
+                ending,
+                source_map.as_deref_mut(),
+                range.as_deref_mut(),
+            );
+        }
+
+        let recorder = self.recorders.for_file(0);
+        let cancelled = recorder.is_cancelled();
+        if cancelled {
+            recorder.report([cancelled_diagnostic()]);
+        }
+        let discard = !formatted
+            || cancelled
+            || self.reparse_check.is_some_and(|severity| {
+                reparse_check(
+                    &mut self.parser,
+                    &source,
+                    state.source(),
+                    severity,
+                    recorder,
+                )
+            });
+        if discard {
+            *state = ParseState::new(source);
+            if let Some(map) = source_map {
+                *map = SourceMap::new(state.source_len());
+            }
+            if let (Some(range), Some(original_range)) = (range, original_range) {
+                *range = original_range;
+            }
         }
 
-        // Apply each pass in the pipeline
-        for pass in self.pipeline.passes() {
-            let root = state
-                .tree()
-                .expect("Tree should exist after parsing")
-                .root_node();
-            let source = state.source();
+        self.take_diagnostics()
+    }
 
-            let mut edits = pass.run(config, &root, source);
-            debug!("Pass generated {} edit(s)", edits.len());
+    /// Run the pipeline, then the consistency and project passes, on a single parsed source.
+    fn run_passes(
+        &mut self,
+        config: &C,
+        state: &mut ParseState,
+        mut source_map: Option<&mut SourceMap>,
+        mut range: Option<&mut (usize, usize)>,
+    ) {
+        let recorder = self.recorders.for_file(0);
+        run_pipeline(
+            &self.pipeline,
+            &mut self.parser,
+            config,
+            state,
+            source_map.as_deref_mut(),
+            range.as_deref_mut(),
+            recorder,
+        );
 
-            // Sort edits in reverse order to maintain byte offsets
-            edits.sort_by(|a, b| b.range.0.cmp(&a.range.0));
+        // A single source forms a project of its own
+        for pass in self.pipeline.consistency_passes() {
+            if recorder.is_cancelled() {
+                return;
+            }
+            attribute_panic(pass.name(), || {
+                recorder.time_pass(pass.name(), || {
+                    let summary = pass.summarize(config, &root_node(state), state.source());
+                    let project = pass.combine(config, vec![summary]);
+                    let edits =
+                        pass.run(config, project.as_ref(), &root_node(state), state.source());
+                    let count = edits.len();
+                    apply_edits(
+                        &mut self.parser,
+                        state,
+                        pass.name(),
+                        edits,
+                        source_map.as_deref_mut(),
+                        range.as_deref_mut(),
+                        recorder,
+                    );
+                    count
+                })
+            });
+        }
 
-            // Apply each edit
-            for edit in edits {
-                debug!("Applying edit at range {:?}", edit.range);
-                self.parser
-                    .apply_edit(state, edit.range.0, edit.range.1, &edit.content);
+        for pass in self.pipeline.project_passes() {
+            if recorder.is_cancelled() {
+                return;
             }
+            attribute_panic(pass.name(), || {
+                recorder.time_pass(pass.name(), || {
+                    let file = ProjectFile {
+                        path: None,
+                        config,
+                        root: root_node(state),
+                        source: state.source(),
+                    };
+                    let edits: Vec<Edit> = pass
+                        .run(config, &[file])
+                        .into_iter()
+                        .filter(|edit| edit.file == 0)
+                        .map(|edit| edit.edit)
+                        .collect();
+                    let count = edits.len();
+                    apply_edits(
+                        &mut self.parser,
+                        state,
+                        pass.name(),
+                        edits,
+                        source_map.as_deref_mut(),
+                        range.as_deref_mut(),
+                        recorder,
+                    );
+                    count
+                })
+            });
         }
     }
 
-    /// Check if files need formatting (returns list of files that would be changed).
+    /// Notify the observer that the single source `source` is done.
     ///
-    /// This method runs the pipeline on each file and compares the result
-    /// with the original content without writing changes to disk.
+    /// # Arguments
+    /// * `source` - The source before formatting
+    /// * `formatted` - The formatted text, or the panic that stopped formatting
+    /// * `diagnostics` - Diagnostics reported while formatting, without the panic
+    fn notify_done(
+        &self,
+        source: &str,
+        formatted: Result<&str, &PassPanic>,
+        diagnostics: &[Diagnostic],
+    ) {
+        if let Some(observer) = &self.recorders.observer {
+            let formatted = FormattedSource {
+                result: formatted.map(str::to_string).map_err(PassPanic::clone),
+                duration: Duration::ZERO,
+                parse: None,
+                diagnostics: diagnostics.to_vec(),
+                source_map: None,
+            };
+            let result = BatchResult::Formatted(formatted);
+            observer.on_file_done(&file_outcome(PathBuf::new(), source, result, self.diffs));
+        }
+    }
+
+    /// Take the diagnostics reported since the last call, dropping their source index.
+    fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        self.recorders
+            .take_diagnostics()
+            .into_iter()
+            .map(|(_, diagnostic)| diagnostic)
+            .collect()
+    }
+
+    /// Format a single source in memory.
+    ///
+    /// Rejected edits are dropped silently; use `format_str` to get them
+    /// as diagnostics.
     ///
     /// # Arguments
     /// * `config` - Configuration to pass to formatting passes
-    /// * `codes` - Source code contents of the files
-    /// * `files` - File paths corresponding to the source codes
+    /// * `code` - Source code to format
     ///
     /// # Returns
-    /// A vector of file paths that would be changed by formatting
-    pub fn check(&mut self, config: &C, codes: &[String], files: &[PathBuf]) -> Vec<PathBuf> {
-        let mut changed_files = Vec::new();
-
-        for (i, code) in codes.iter().enumerate() {
-            let mut state = ParseState::new(code.clone());
-            self.run(config, &mut state);
+    /// The formatted code
+    pub fn format(&mut self, config: &C, code: &str) -> String {
+        let mut state = ParseState::new(code.to_string());
+        let diagnostics = self.run(config, &mut state, None, None);
+        self.notify_done(code, Ok(state.source()), &diagnostics);
+        state.into_source()
+    }
 
-            if state.source() != code && i < files.len() {
-                changed_files.push(files[i].clone());
+    /// Format a single source in memory, reporting problems instead of panicking.
+    ///
+    /// A pass panicking leaves the source unformatted and is reported as
+    /// an error diagnostic naming the pass. So are edits rejected for
+    /// overlapping or reaching outside the source, which are not applied.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `source` - Source code to format
+    ///
+    /// # Returns
+    /// The formatted text, whether it changed, and the diagnostics
+    pub fn format_str(&mut self, config: &C, source: &str) -> FormatResult {
+        let mut state = ParseState::new(source.to_string());
+        match isolate("parser", || self.run(config, &mut state, None, None)) {
+            Ok(diagnostics) => {
+                self.notify_done(source, Ok(state.source()), &diagnostics);
+                FormatResult {
+                    changed: state.source() != source,
+                    formatted: state.into_source(),
+                    diagnostics,
+                }
+            }
+            Err(err) => {
+                let mut diagnostics = self.take_diagnostics();
+                self.notify_done(source, Err(&err), &diagnostics);
+                diagnostics.push(Diagnostic::from(err));
+                FormatResult {
+                    formatted: source.to_string(),
+                    changed: false,
+                    diagnostics,
+                }
             }
         }
+    }
 
-        changed_files
+    /// Format only a byte range of a single source, reporting problems instead of panicking.
+    ///
+    /// Code outside the range is left untouched. An edit reaching across a
+    /// boundary of the range is first narrowed to the text it actually
+    /// changes, leaving out the parts it replaces with the same text; it is
+    /// dropped if it still reaches outside. The range grows or shrinks as
+    /// edits inside it are applied. Problems are reported like `format_str`.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `source` - Source code to format
+    /// * `range` - Byte range of `source` to format; clamped to the source
+    ///   and widened to character boundaries
+    ///
+    /// # Returns
+    /// The formatted text, whether it changed, and the diagnostics
+    pub fn format_range(
+        &mut self,
+        config: &C,
+        source: &str,
+        range: (usize, usize),
+    ) -> FormatResult {
+        let outcome = self.format_file_range(config, Path::new(""), source, range);
+        FormatResult {
+            changed: outcome.changed,
+            formatted: outcome.formatted.unwrap_or_else(|_| source.to_string()),
+            diagnostics: outcome.diagnostics,
+        }
     }
 
-    /// Format files and write changes (returns list of files that were changed).
+    /// Format only a byte range of a file, like `format_range`.
     ///
-    /// This method runs the pipeline on each file, writes the formatted
-    /// content to disk if it differs from the original, and returns the
-    /// list of modified files.
+    /// A pass panicking on the file fails it, as in `format_files`, instead
+    /// of only being reported as a diagnostic.
     ///
     /// # Arguments
     /// * `config` - Configuration to pass to formatting passes
-    /// * `codes` - Source code contents of the files
-    /// * `files` - File paths corresponding to the source codes
+    /// * `file` - Path of the file
+    /// * `source` - Content of the file
+    /// * `range` - Byte range of `source` to format, as in `format_range`
     ///
     /// # Returns
-    /// A `Result` containing a vector of changed file paths, or an IO error
+    /// The outcome of the file, without diff, duration or parse metrics
+    pub fn format_file_range(
+        &mut self,
+        config: &C,
+        file: &Path,
+        source: &str,
+        range: (usize, usize),
+    ) -> FileFormatOutcome {
+        let mut range = clamp_range(source, range);
+        let mut state = ParseState::new(source.to_string());
+        let (formatted, diagnostics) = match isolate("parser", || {
+            self.run(config, &mut state, None, Some(&mut range))
+        }) {
+            Ok(diagnostics) => {
+                self.notify_done(source, Ok(state.source()), &diagnostics);
+                (Ok(state.into_source()), diagnostics)
+            }
+            Err(err) => {
+                let diagnostics = self.take_diagnostics();
+                self.notify_done(source, Err(&err), &diagnostics);
+                (Err(err), diagnostics)
+            }
+        };
+        let mut outcome = FileFormatOutcome::new(file.to_path_buf(), source, formatted);
+        // A panic is reported after the diagnostics of the passes before it
+        outcome.diagnostics.splice(0..0, diagnostics);
+        outcome
+    }
+
+    /// Format only the given lines of a single source.
     ///
-    /// # Errors
-    /// Returns an error if writing to any file fails
-    pub fn format_and_write(
+    /// Edits are restricted to the lines like `format_range` does, but
+    /// rejected edits are dropped silently and panics are not caught.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `code` - Source code to format
+    /// * `lines` - Lines of `code` to format
+    ///
+    /// # Returns
+    /// The formatted code
+    pub fn format_lines(&mut self, config: &C, code: &str, lines: LineRange) -> String {
+        let mut state = ParseState::new(code.to_string());
+        let mut range = lines.byte_range(code);
+        let diagnostics = self.run(config, &mut state, None, Some(&mut range));
+        self.notify_done(code, Ok(state.source()), &diagnostics);
+        state.into_source()
+    }
+
+    /// Format a single source and build a source map of the applied edits.
+    ///
+    /// The source map relates byte ranges of `code` to byte ranges of the
+    /// formatted text, so positions held into the old text can be remapped.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `code` - Source code to format
+    ///
+    /// # Returns
+    /// The formatted code and its source map
+    pub fn format_with_source_map(&mut self, config: &C, code: &str) -> (String, SourceMap) {
+        let mut state = ParseState::new(code.to_string());
+        let mut source_map = SourceMap::new(code.len());
+        let diagnostics = self.run(config, &mut state, Some(&mut source_map), None);
+        self.notify_done(code, Ok(state.source()), &diagnostics);
+
+        (state.into_source(), source_map)
+    }
+}
+
+impl<Language: LanguageProvider, C: Serialize + Sync> Engine<Language, C> {
+    /// Format many sources, in parallel when more than one job is configured.
+    ///
+    /// Each worker thread owns its own `Parser`. Results are returned in the
+    /// same order as `codes` regardless of the number of jobs.
+    ///
+    /// After the per-file passes, each consistency pass first summarizes
+    /// every source, then combines the summaries and edits every source
+    /// using the combined view. Project passes run last, on all sources at
+    /// once.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents to format
+    ///
+    /// # Returns
+    /// The formatted code of each source, in input order
+    ///
+    /// # Panics
+    /// Panics if a pass panics on any source; use `try_format_all` to keep
+    /// the other sources.
+    pub fn format_all(&mut self, config: &C, codes: &[String]) -> Vec<String> {
+        self.try_format_all(config, codes)
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|err| panic!("{err}")))
+            .collect()
+    }
+
+    /// Format many sources like `format_all`, isolating panicking passes per source.
+    ///
+    /// A source on which a pass panics is dropped from the remaining passes,
+    /// including the project views of consistency passes and the files of
+    /// project passes; the other sources are formatted as usual. A panicking
+    /// project pass fails every source it was given.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents to format
+    ///
+    /// # Returns
+    /// The formatted code of each source, or the panic that stopped it, in input order
+    pub fn try_format_all(
         &mut self,
         config: &C,
         codes: &[String],
+    ) -> Vec<Result<String, PassPanic>> {
+        // Only clean results are reused, which leave the source unchanged
+        self.format_uncached(Configs::shared(config), codes, &[], |changed| !changed)
+            .into_iter()
+            .zip(codes)
+            .map(|(result, code)| match result {
+                BatchResult::Cached(_) | BatchResult::Ignored => Ok(code.clone()),
+                BatchResult::Formatted(formatted) => formatted.result,
+            })
+            .collect()
+    }
+
+    /// Format the sources whose cached result is not accepted by `reuse`.
+    ///
+    /// Sources disabling formatting in their first lines are ignored.
+    /// New results are recorded in the cache, if any, unless they came with
+    /// diagnostics that a cache hit would hide.
+    ///
+    /// # Arguments
+    /// * `configs` - Configurations to pass to formatting passes
+    /// * `codes` - Source code contents to format
+    /// * `files` - File paths of the sources, or empty if they have none
+    /// * `reuse` - Whether a cached result, given whether the source needed formatting, is used
+    fn format_uncached(
+        &mut self,
+        configs: Configs<C>,
+        codes: &[String],
         files: &[PathBuf],
-    ) -> Result<Vec<PathBuf>, std::io::Error> {
-        let mut changed_files = Vec::new();
+        reuse: impl Fn(bool) -> bool,
+    ) -> Vec<BatchResult> {
+        let ignored: Vec<bool> = codes
+            .iter()
+            .map(|code| ignores_source::<Language>(code, &self.generated_markers))
+            .collect();
+        let keys = self.cache_keys(configs, codes);
+        let cached: Vec<Option<bool>> = match (&self.cache, &keys) {
+            (Some(cache), Some(keys)) => keys
+                .iter()
+                .map(|key| cache.get(key).filter(|changed| reuse(*changed)))
+                .collect(),
+            _ => vec![None; codes.len()],
+        };
 
-        for (i, code) in codes.iter().enumerate() {
-            let mut state = ParseState::new(code.clone());
-            self.run(config, &mut state);
+        // These are done before any source is formatted
+        if let Some(observer) = &self.recorders.observer {
+            for (i, code) in codes.iter().enumerate() {
+                let result = match cached[i] {
+                    _ if ignored[i] => BatchResult::Ignored,
+                    Some(changed) => BatchResult::Cached(changed),
+                    None => continue,
+                };
+                let path = files.get(i).cloned().unwrap_or_default();
+                observer.on_file_done(&file_outcome(path, code, result, self.diffs));
+            }
+        }
+
+        let selected: Vec<usize> = (0..codes.len())
+            .filter(|i| !ignored[*i] && cached[*i].is_none())
+            .collect();
+        let mut formatted = self
+            .format_selected(configs, codes, files, &selected)
+            .into_iter();
+
+        let results: Vec<BatchResult> = cached
+            .into_iter()
+            .zip(ignored)
+            .map(|(cached, ignored)| match cached {
+                _ if ignored => BatchResult::Ignored,
+                Some(changed) => BatchResult::Cached(changed),
+                None => BatchResult::Formatted(
+                    formatted
+                        .next()
+                        .expect("Every selected source should be formatted"),
+                ),
+            })
+            .collect();
+
+        if let (Some(cache), Some(keys)) = (&self.cache, &keys) {
+            for ((result, key), code) in results.iter().zip(keys).zip(codes) {
+                if let BatchResult::Formatted(FormattedSource {
+                    result: Ok(formatted),
+                    diagnostics,
+                    ..
+                }) = result
+                {
+                    if diagnostics.is_empty() {
+                        cache.insert(*key, formatted != code);
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    /// Get the cache key of every source, or `None` if the cache does not apply.
+    fn cache_keys(&self, configs: Configs<C>, codes: &[String]) -> Option<Vec<CacheKey>> {
+        self.cache.as_ref()?;
+        if self.pipeline.has_run_level_passes() {
+            return None;
+        }
+        // Sources usually share a few configs, which are hashed once each
+        let mut hashes: Vec<(&C, u64)> = Vec::new();
+        let mut config_hashes = Vec::with_capacity(codes.len());
+        for file in 0..codes.len() {
+            let config = configs.of(file);
+            let hash = match hashes.iter().find(|(seen, _)| std::ptr::eq(*seen, config)) {
+                Some(&(_, hash)) => hash,
+                None => match serde_yaml::to_string(config) {
+                    Ok(yaml) => {
+                        let hash = fnv1a_64(yaml.as_bytes());
+                        hashes.push((config, hash));
+                        hash
+                    }
+                    Err(err) => {
+                        debug!("Failed to hash the config ({err}), not using the cache");
+                        return None;
+                    }
+                },
+            };
+            config_hashes.push(hash);
+        }
+        // The engine options change results like the pipeline does
+        let mut pipeline = self.pipeline.fingerprint();
+        if let Some(endings) = self.line_endings {
+            pipeline = fnv1a_64(format!("{pipeline} {}", endings.as_str()).as_bytes());
+        }
+        let policy = self.on_parse_error.as_str();
+        pipeline = fnv1a_64(format!("{pipeline} on-parse-error={policy}").as_bytes());
+        if let Some(severity) = self.reparse_check {
+            let severity = severity.as_str();
+            pipeline = fnv1a_64(format!("{pipeline} reparse-check={severity}").as_bytes());
+        }
+        for marker in &self.generated_markers {
+            pipeline = fnv1a_64(format!("{pipeline} generated={marker}").as_bytes());
+        }
+        for (code, level) in &self.recorders.rule_levels {
+            pipeline = fnv1a_64(format!("{pipeline} {code}={}", level.as_str()).as_bytes());
+        }
+        if self.recorders.check_edits {
+            pipeline = fnv1a_64(format!("{pipeline} check-edits").as_bytes());
+        }
+        if self.parser.incremental_check() {
+            pipeline = fnv1a_64(format!("{pipeline} check-incremental").as_bytes());
+        }
+        Some(
+            codes
+                .iter()
+                .zip(config_hashes)
+                .map(|(code, config)| CacheKey::new(code, config, pipeline))
+                .collect(),
+        )
+    }
+
+    /// Format the sources at the `selected` indices, as `try_format_all` does.
+    ///
+    /// Diagnostics are recorded under the index of each source in `codes`.
+    /// `files` holds the path of each source, or is empty if they have none.
+    ///
+    /// # Returns
+    /// Every selected source formatted, in the order of `selected`
+    fn format_selected(
+        &mut self,
+        configs: Configs<C>,
+        codes: &[String],
+        files: &[PathBuf],
+        selected: &[usize],
+    ) -> Vec<FormattedSource> {
+        let jobs = self.jobs;
+        let check = self.reparse_check;
+        let on_parse_error = self.on_parse_error;
+        let endings = self.line_endings;
+        let pipeline = &self.pipeline;
+        let parser = &mut self.parser;
+        let recorders = &self.recorders;
+
+        let diffs = self.diffs;
+        let source_maps = self.source_maps;
+        let run_level = pipeline.has_run_level_passes();
 
-            let formatted_code = state.source();
-            if formatted_code != code && i < files.len() {
-                let file_path = &files[i];
-                std::fs::write(file_path, formatted_code)?;
-                changed_files.push(file_path.clone());
+        let mut items: Vec<BatchItem> = selected
+            .iter()
+            .map(|&file| BatchItem {
+                file,
+                state: ParseState::new(codes[file].clone()),
+                failure: None,
+                skipped: false,
+                duration: Duration::ZERO,
+                parse: None,
+                diagnostics: Vec::new(),
+                source_map: source_maps.then(|| SourceMap::new(codes[file].len())),
+            })
+            .collect();
+
+        // Restore or convert the source, check it and report it as done
+        let finish = |parser: &mut Parser<Language>, item: &mut BatchItem| {
+            let started = Instant::now();
+            let code = &codes[item.file];
+            if item.skipped {
+                item.restore(code);
+            } else if item.failure.is_none() {
+                if let Some(endings) = endings {
+                    let target = endings.target(code);
+                    convert_line_endings(&mut item.state, target, item.source_map.as_mut(), None);
+                }
+                let recorder = recorders.for_file(item.file);
+                if check.is_some_and(|severity| {
+                    reparse_check(parser, code, item.state.source(), severity, recorder)
+                }) {
+                    item.restore(code);
+                }
+            }
+            item.duration += started.elapsed();
+            item.diagnostics = recorders.take_file_diagnostics(item.file);
+            if let Some(observer) = &recorders.observer {
+                let path = files.get(item.file).cloned().unwrap_or_default();
+                let result = BatchResult::Formatted(item.formatted());
+                observer.on_file_done(&file_outcome(path, code, result, diffs));
+            }
+        };
+
+        for_each_state(jobs, parser, &mut items, |parser, _, item| {
+            let started = Instant::now();
+            let recorder = recorders
+                .for_file(item.file)
+                .with_path(files.get(item.file).map(PathBuf::as_path));
+            if recorder.is_cancelled() {
+                item.cancel(recorder);
+            } else {
+                recorder.start();
+                let (state, source_map) = (&mut item.state, &mut item.source_map);
+                if endings.is_some() {
+                    convert_line_endings(state, LineEnding::Lf, source_map.as_mut(), None);
+                }
+                match isolate("parser", || {
+                    let parse = measured_parse(parser, state, recorder);
+                    let accepted = accepts_parse(parser, state, on_parse_error, recorder);
+                    if accepted {
+                        let source_map = source_map.as_mut();
+                        let config = configs.of(item.file);
+                        run_pipeline(pipeline, parser, config, state, source_map, None, recorder);
+                    }
+                    (accepted, parse)
+                }) {
+                    Ok(_) if recorder.is_cancelled() => item.cancel(recorder),
+                    Ok((accepted, parse)) => {
+                        item.skipped = !accepted;
+                        item.parse = parse;
+                    }
+                    Err(err) => item.failure = Some(err),
+                }
+            }
+            item.duration += started.elapsed();
+            // Without run-level passes, nothing else changes the source
+            if !run_level {
+                finish(parser, item);
+            }
+        });
+
+        for pass in pipeline.consistency_passes() {
+            if recorders.is_cancelled() {
+                for item in items.iter_mut().filter(|item| item.is_formatting()) {
+                    item.cancel(recorders.for_file(item.file));
+                }
+                break;
+            }
+            let summaries = map_states(jobs, &items, |_, item| {
+                item.is_formatting().then(|| {
+                    let started = Instant::now();
+                    let summary = isolate(pass.name(), || {
+                        recorders.for_file(item.file).time(pass.name(), || {
+                            let config = configs.of(item.file);
+                            pass.summarize(config, &root_node(&item.state), item.state.source())
+                        })
+                    });
+                    (summary, started.elapsed())
+                })
+            });
+
+            let mut collected = Vec::with_capacity(summaries.len());
+            for (item, summary) in items.iter_mut().zip(summaries) {
+                let Some((summary, elapsed)) = summary else {
+                    continue;
+                };
+                item.duration += elapsed;
+                match summary {
+                    Ok(summary) => collected.push(summary),
+                    Err(err) => item.failure = Some(err),
+                }
+            }
+
+            let project = match isolate(pass.name(), || pass.combine(configs.run, collected)) {
+                Ok(project) => project,
+                Err(err) => {
+                    for item in items.iter_mut().filter(|item| item.is_formatting()) {
+                        item.failure = Some(err.clone());
+                    }
+                    break;
+                }
+            };
+
+            for_each_state(jobs, parser, &mut items, |parser, _, item| {
+                if !item.is_formatting() {
+                    return;
+                }
+                let started = Instant::now();
+                let recorder = recorders.for_file(item.file);
+                let (state, source_map) = (&mut item.state, item.source_map.as_mut());
+                if let Err(err) = isolate(pass.name(), || {
+                    recorder.time_pass(pass.name(), || {
+                        let config = configs.of(item.file);
+                        let edits =
+                            pass.run(config, project.as_ref(), &root_node(state), state.source());
+                        let count = edits.len();
+                        apply_edits(
+                            parser,
+                            state,
+                            pass.name(),
+                            edits,
+                            source_map,
+                            None,
+                            recorder,
+                        );
+                        count
+                    });
+                }) {
+                    item.failure = Some(err);
+                } else if recorder.is_cancelled() {
+                    item.cancel(recorder);
+                }
+                item.duration += started.elapsed();
+            });
+        }
+
+        for pass in pipeline.project_passes() {
+            if recorders.is_cancelled() {
+                for item in items.iter_mut().filter(|item| item.is_formatting()) {
+                    item.cancel(recorders.for_file(item.file));
+                }
+                break;
+            }
+            let formatting: Vec<usize> = (0..items.len())
+                .filter(|&i| items[i].is_formatting())
+                .collect();
+            let project_files: Vec<ProjectFile<C>> = formatting
+                .iter()
+                .map(|&i| ProjectFile {
+                    path: files.get(items[i].file).map(PathBuf::as_path),
+                    config: configs.of(items[i].file),
+                    root: root_node(&items[i].state),
+                    source: items[i].state.source(),
+                })
+                .collect();
+            let edits = match isolate(pass.name(), || pass.run(configs.run, &project_files)) {
+                Ok(edits) => edits,
+                Err(err) => {
+                    for &i in &formatting {
+                        items[i].failure = Some(err.clone());
+                    }
+                    break;
+                }
+            };
+            drop(project_files);
+
+            let mut item_edits = vec![Vec::new(); items.len()];
+            for ProjectEdit { file, edit } in edits {
+                match formatting.get(file) {
+                    Some(&i) => item_edits[i].push(edit),
+                    None => debug!("Dropped an edit of {} to unknown file {file}", pass.name()),
+                }
             }
+            for_each_state(jobs, parser, &mut items, |parser, i, item| {
+                if !item.is_formatting() || item_edits[i].is_empty() {
+                    return;
+                }
+                let started = Instant::now();
+                let recorder = recorders.for_file(item.file);
+                recorder.time_pass(pass.name(), || {
+                    let edits = item_edits[i].clone();
+                    let count = edits.len();
+                    apply_edits(
+                        parser,
+                        &mut item.state,
+                        pass.name(),
+                        edits,
+                        item.source_map.as_mut(),
+                        None,
+                        recorder,
+                    );
+                    count
+                });
+                item.duration += started.elapsed();
+            });
         }
 
-        Ok(changed_files)
+        if run_level {
+            for_each_state(jobs, parser, &mut items, |parser, _, item| {
+                finish(parser, item)
+            });
+        }
+
+        items
+            .into_iter()
+            .map(|item| FormattedSource {
+                result: match item.failure {
+                    Some(err) => Err(err),
+                    None => Ok(item.state.into_source()),
+                },
+                duration: item.duration,
+                parse: item.parse,
+                diagnostics: item.diagnostics,
+                source_map: item.source_map,
+            })
+            .collect()
+    }
+
+    /// Format many files without writing them, reporting the outcome of each.
+    ///
+    /// Like `try_format_all`, a pass panicking on one file only fails that
+    /// file; the panic is reported in its outcome as an error diagnostic.
+    /// Files whose result is cached as clean are not formatted again.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// The outcome of every file, in input order
+    pub fn format_files(
+        &mut self,
+        config: &C,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> Vec<FileFormatOutcome> {
+        self.file_outcomes(Configs::shared(config), codes, files).0
+    }
+
+    /// Format many files like `format_files`, collecting everything about the run in a report.
+    ///
+    /// The report takes the pass timings recorded so far, like `take_timings`.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// The report of the run, listing files on which a pass panicked in its `errors`
+    pub fn format_report(&mut self, config: &C, codes: &[String], files: &[PathBuf]) -> RunReport {
+        self.report(Configs::shared(config), codes, files)
+    }
+
+    /// Format many files like `format_report`, each with its own configuration.
+    ///
+    /// Files under a nested config get a configuration of their own, while
+    /// run-level passes still see every file at once: per-file passes and
+    /// the per-file steps of consistency passes get the configuration of
+    /// the file, `ConsistencyPass::combine` and `ProjectPass::run` get the
+    /// configuration of the run, and project passes find the configuration
+    /// of each file in its `ProjectFile`.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration of the run
+    /// * `file_configs` - Configuration of each file, indexed like `codes`
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// The report of the run, listing files on which a pass panicked in its `errors`
+    ///
+    /// # Panics
+    /// Panics if `file_configs` and `codes` differ in length
+    pub fn format_report_with_configs(
+        &mut self,
+        config: &C,
+        file_configs: &[&C],
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> RunReport {
+        assert_eq!(
+            file_configs.len(),
+            codes.len(),
+            "every file needs a configuration"
+        );
+        let configs = Configs {
+            run: config,
+            files: file_configs,
+        };
+        self.report(configs, codes, files)
+    }
+
+    /// Format many files, collecting everything about the run in a report.
+    fn report(&mut self, configs: Configs<C>, codes: &[String], files: &[PathBuf]) -> RunReport {
+        let started = Instant::now();
+        let (outcomes, cached) = self.file_outcomes(configs, codes, files);
+        let metrics = RunMetrics {
+            cached,
+            timings: self.take_timings(),
+            parse_duration: outcomes
+                .iter()
+                .filter_map(|outcome| outcome.parse)
+                .map(|parse| parse.duration)
+                .sum(),
+        };
+        RunReport::new(outcomes, metrics, started.elapsed())
+    }
+
+    /// Format many files, returning their outcomes and how many were taken from the cache.
+    fn file_outcomes(
+        &mut self,
+        configs: Configs<C>,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> (Vec<FileFormatOutcome>, usize) {
+        let diffs = self.diffs;
+        let mut cached = 0;
+        let outcomes = self
+            .format_uncached(configs, codes, files, |changed| !changed)
+            .into_iter()
+            .zip(codes)
+            .zip(files)
+            .map(|((result, code), file)| {
+                if let BatchResult::Cached(_) = result {
+                    cached += 1;
+                }
+                file_outcome(file.clone(), code, result, diffs)
+            })
+            .collect();
+        (outcomes, cached)
+    }
+
+    /// Find the first file that needs formatting, stopping as early as possible.
+    ///
+    /// Like `try_find_first_unformatted`, for pipelines whose passes do not panic.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// The first file (in input order) that would be changed, if any
+    ///
+    /// # Panics
+    /// Panics if a pass panics on a file checked before the first one that
+    /// would be changed
+    pub fn find_first_unformatted(
+        &mut self,
+        config: &C,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> Option<PathBuf> {
+        match self.try_find_first_unformatted(config, codes, files)? {
+            FirstStop::Unformatted(path) => Some(path),
+            FirstStop::Failed(error) => panic!("{}", error.message),
+        }
+    }
+
+    /// Find the first file that needs formatting or fails, stopping as early as possible.
+    ///
+    /// Files are checked in order; in parallel mode, work on files after
+    /// the first stop is cancelled. A pass panicking on a file stops the
+    /// check at that file, like a file that needs formatting. Pipelines
+    /// with consistency or project passes need every file, so they fall
+    /// back to formatting all files.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// The first file (in input order) that would be changed or failed, if any
+    pub fn try_find_first_unformatted(
+        &mut self,
+        config: &C,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> Option<FirstStop> {
+        if self.pipeline.has_run_level_passes() {
+            return self
+                .format_files(config, codes, files)
+                .into_iter()
+                .find_map(|outcome| match outcome.formatted {
+                    Err(err) => Some(FirstStop::Failed(FileError::new(&outcome.path, err))),
+                    Ok(_) if outcome.changed => Some(FirstStop::Unformatted(outcome.path)),
+                    Ok(_) => None,
+                });
+        }
+
+        let jobs = self.jobs;
+        let pipeline = &self.pipeline;
+        let parser = &mut self.parser;
+        let recorders = &self.recorders;
+        let check = self.reparse_check;
+        let on_parse_error = self.on_parse_error;
+        let endings = self.line_endings;
+        let generated_markers = &self.generated_markers;
+        // Stop at a file that needs formatting, or at one a pass panicked on
+        let stop = |parser: &mut Parser<Language>, (i, code): (usize, &String)| {
+            let recorder = recorders
+                .for_file(i)
+                .with_path(files.get(i).map(PathBuf::as_path));
+            if recorder.is_cancelled() || ignores_source::<Language>(code, generated_markers) {
+                return None;
+            }
+            recorder.start();
+            let mut state = ParseState::new(code.clone());
+            if endings.is_some() {
+                convert_line_endings(&mut state, LineEnding::Lf, None, None);
+            }
+            let changed = isolate("parser", || {
+                if !accepts_parse(parser, &mut state, on_parse_error, recorder) {
+                    return false;
+                }
+                run_pipeline(pipeline, parser, config, &mut state, None, None, recorder);
+                if recorder.is_cancelled() {
+                    return false;
+                }
+                if let Some(endings) = endings {
+                    convert_line_endings(&mut state, endings.target(code), None, None);
+                }
+                let discarded = check.is_some_and(|severity| {
+                    reparse_check(parser, code, state.source(), severity, recorder)
+                });
+                !discarded && state.source() != code
+            });
+            match changed {
+                Ok(false) => None,
+                Ok(true) => Some((i, None)),
+                Err(err) => Some((i, Some(err))),
+            }
+        };
+
+        let first = if is_sequential(jobs, codes.len()) {
+            codes
+                .iter()
+                .enumerate()
+                .find_map(|entry| stop(parser, entry))
+        } else {
+            let new_parser = worker_parser(parser);
+            in_pool(jobs, || {
+                codes
+                    .par_iter()
+                    .enumerate()
+                    .map_init(new_parser, stop)
+                    .find_map_first(|stop| stop)
+            })
+        };
+
+        // Only the result matters here, not why edits were rejected
+        recorders.take_diagnostics();
+        let (i, failure) = first?;
+        let path = files.get(i)?.clone();
+        Some(match failure {
+            None => FirstStop::Unformatted(path),
+            Some(err) => FirstStop::Failed(FileError::new(&path, err)),
+        })
+    }
+
+    /// Check if files need formatting (returns list of files that would be changed).
+    ///
+    /// This method runs the pipeline on each file and compares the result
+    /// with the original content without writing changes to disk. With a
+    /// cache, neither formatted nor unformatted files seen before are
+    /// formatted again.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// A vector of file paths that would be changed by formatting, in input order
+    ///
+    /// # Panics
+    /// Panics if a pass panics on any source
+    pub fn check(&mut self, config: &C, codes: &[String], files: &[PathBuf]) -> Vec<PathBuf> {
+        self.format_uncached(Configs::shared(config), codes, files, |_| true)
+            .into_iter()
+            .zip(codes)
+            .zip(files)
+            .filter(|((result, code), _)| match result {
+                BatchResult::Cached(changed) => *changed,
+                BatchResult::Ignored => false,
+                BatchResult::Formatted(formatted) => match &formatted.result {
+                    Ok(formatted) => formatted != *code,
+                    Err(err) => panic!("{err}"),
+                },
+            })
+            .map(|(_, file)| file.clone())
+            .collect()
+    }
+
+    /// Format files and write changes (returns list of files that were changed).
+    ///
+    /// This method runs the pipeline on each file, atomically writes the
+    /// formatted content to disk if it differs from the original, and
+    /// returns the list of modified files. Files are written on the same
+    /// worker threads as they are formatted.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// A `Result` containing a vector of changed file paths in input order, or an IO error
+    ///
+    /// # Errors
+    /// Returns the error of the first file (in input order) that could not be
+    /// written; the other changed files are still written
+    ///
+    /// # Panics
+    /// Panics if a pass panics on any source
+    pub fn format_and_write(
+        &mut self,
+        config: &C,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> Result<Vec<PathBuf>, std::io::Error> {
+        let outcomes = self.format_files(config, codes, files);
+        if let Some(err) = outcomes
+            .iter()
+            .find_map(|outcome| outcome.formatted.as_ref().err())
+        {
+            panic!("{err}");
+        }
+        let writer = FileWriter::new();
+
+        map_states(self.jobs, &outcomes, |_, outcome| {
+            match &outcome.formatted {
+                Ok(formatted_code) if outcome.changed => writer
+                    .write(&outcome.path, formatted_code)
+                    .map(|()| Some(outcome.path.clone())),
+                _ => Ok(None),
+            }
+        })
+        .into_iter()
+        .filter_map(Result::transpose)
+        .collect()
+    }
+}
+
+/// Run all passes of a pipeline on the given parse state.
+///
+/// Phases run one after the other, each on the output of the previous
+/// one. Within a phase, passes either run sequentially, applying their
+/// edits in reverse order to maintain correct byte offsets, or together
+/// on the same text, depending on the conflict policy of the phase.
+///
+/// # Arguments
+/// * `pipeline` - The passes to run
+/// * `parser` - Parser used for the initial parse and incremental reparses
+/// * `config` - Configuration to pass to each pass
+/// * `state` - The parse state containing source and tree
+/// * `source_map` - Optional source map recording every applied edit
+/// * `range` - Optional byte range outside of which edits are dropped
+/// * `recorder` - Records diagnostics, and timings and edits of each pass if enabled
+fn run_pipeline<Language: LanguageProvider, C>(
+    pipeline: &Pipeline<C>,
+    parser: &mut Parser<Language>,
+    config: &C,
+    state: &mut ParseState,
+    mut source_map: Option<&mut SourceMap>,
+    mut range: Option<&mut (usize, usize)>,
+    recorder: PassRecorder,
+) {
+    // Ensure we have a parsed tree
+    if !state.has_tree() {
+        parser.parse(state);
+    }
+
+    for phase in pipeline.phases() {
+        if recorder.is_cancelled() {
+            return;
+        }
+        if phase.conflict_policy().is_batched() {
+            run_passes_together(
+                &phase,
+                parser,
+                config,
+                state,
+                source_map.as_deref_mut(),
+                range.as_deref_mut(),
+                recorder,
+            );
+        } else {
+            run_passes_in_turn(
+                &phase,
+                parser,
+                config,
+                state,
+                source_map.as_deref_mut(),
+                range.as_deref_mut(),
+                recorder,
+            );
+        }
+    }
+}
+
+/// Run the passes of a phase one after the other, each on the output of the previous one.
+fn run_passes_in_turn<Language: LanguageProvider, C>(
+    phase: &Phase<C>,
+    parser: &mut Parser<Language>,
+    config: &C,
+    state: &mut ParseState,
+    mut source_map: Option<&mut SourceMap>,
+    mut range: Option<&mut (usize, usize)>,
+    recorder: PassRecorder,
+) {
+    for pass in phase.passes() {
+        if recorder.is_cancelled() {
+            return;
+        }
+        attribute_panic(pass.name(), || {
+            if !pass.enabled(config) || !pass.should_run(config, recorder.path, state.source()) {
+                return;
+            }
+            recorder.time_pass(pass.name(), || {
+                let edits = pass.run(config, &root_node(state), state.source());
+                let diagnostics = pass.diagnose(config, &root_node(state), state.source());
+                let diagnostics = unsuppressed::<Language>(state, pass.name(), diagnostics);
+                recorder.report_pass(pass.name(), diagnostics);
+                let count = edits.len();
+                apply_edits(
+                    parser,
+                    state,
+                    pass.name(),
+                    edits,
+                    source_map.as_deref_mut(),
+                    range.as_deref_mut(),
+                    recorder,
+                );
+                count
+            });
+        });
+    }
+}
+
+/// Run every pass of a phase on the same text and apply their edits together.
+///
+/// The edits of each pass are validated on their own, then combined
+/// according to the conflict policy of the phase.
+fn run_passes_together<Language: LanguageProvider, C>(
+    phase: &Phase<C>,
+    parser: &mut Parser<Language>,
+    config: &C,
+    state: &mut ParseState,
+    source_map: Option<&mut SourceMap>,
+    range: Option<&mut (usize, usize)>,
+    recorder: PassRecorder,
+) {
+    let mut proposed = Vec::with_capacity(phase.passes().len());
+    for pass in phase.passes() {
+        if recorder.is_cancelled() {
+            return;
+        }
+        let mut edits = Vec::new();
+        let runs = attribute_panic(pass.name(), || {
+            pass.enabled(config) && pass.should_run(config, recorder.path, state.source())
+        });
+        if !runs {
+            continue;
+        }
+        attribute_panic(pass.name(), || {
+            recorder.time_pass(pass.name(), || {
+                edits = pass.run(config, &root_node(state), state.source());
+                let diagnostics = pass.diagnose(config, &root_node(state), state.source());
+                let diagnostics = unsuppressed::<Language>(state, pass.name(), diagnostics);
+                recorder.report_pass(pass.name(), diagnostics);
+                edits.len()
+            });
+        });
+        if let Some(edits) = checked_edits(parser, state, pass.name(), edits, recorder) {
+            proposed.push((pass.name(), edits));
+        }
+    }
+
+    let passes: Vec<&'static str> = proposed
+        .iter()
+        .filter(|(_, edits)| !edits.is_empty())
+        .map(|(pass, _)| *pass)
+        .collect();
+    match resolve_conflicts(phase.conflict_policy(), proposed) {
+        Ok(edits) => {
+            apply_valid_edits(parser, state, edits, source_map, range);
+            report_divergence(parser, &passes, recorder);
+        }
+        Err(conflicts) => recorder.report(conflicts),
+    }
+}
+
+/// Returns true if `source` disables formatting of the whole file in its first lines.
+fn ignores_source<Language: LanguageProvider>(source: &str, generated_markers: &[String]) -> bool {
+    let directive = Language::capabilities().disable_file_marker();
+    disables_file(
+        source,
+        std::iter::once(directive).chain(generated_markers.iter().map(String::as_str)),
+    )
+}
+
+/// Drop the diagnostics of `pass` about the source of `state` that comments suppress.
+fn unsuppressed<Language: LanguageProvider>(
+    state: &ParseState,
+    pass: &'static str,
+    diagnostics: Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    if diagnostics.is_empty() {
+        return diagnostics;
+    }
+    suppressions::<Language>(state).retain_diagnostics(pass, diagnostics)
+}
+
+/// Find the regions of the source of `state` where comments suppress edits and diagnostics.
+fn suppressions<Language: LanguageProvider>(state: &ParseState) -> Suppressions {
+    Suppressions::find(root_node(state), state.source(), Language::capabilities())
+}
+
+/// Name under which the time spent parsing a source is recorded with the pass timings.
+const PARSE_TIMING: &str = "(parse)";
+
+/// Parse the source of `state` from scratch and measure the tree.
+///
+/// The parse time is also recorded with the pass timings, so it shows up
+/// in `--timings`.
+///
+/// # Returns
+/// The measurements, or `None` if parsing was aborted
+fn measured_parse<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    state: &mut ParseState,
+    recorder: PassRecorder,
+) -> Option<ParseMetrics> {
+    let started = Instant::now();
+    recorder.time(PARSE_TIMING, || parser.parse(state));
+    let duration = started.elapsed();
+    state.tree().map(|tree| ParseMetrics::new(tree, duration))
+}
+
+/// Parse the state if needed and decide whether passes may run on it.
+///
+/// # Returns
+/// False if parsing was aborted, or if the source has syntax errors and
+/// `policy` skips it
+fn accepts_parse<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    state: &mut ParseState,
+    policy: ParseErrorPolicy,
+    recorder: PassRecorder,
+) -> bool {
+    if !state.has_tree() {
+        parser.parse(state);
+    }
+    if !state.has_tree() {
+        if let (Some(timeout), false) = (parser.timeout(), recorder.is_cancelled()) {
+            recorder.report([Diagnostic::error(format!(
+                "parsing took longer than {} ms; the source was left unchanged",
+                timeout.as_millis()
+            ))]);
+        }
+        return false;
+    }
+    if policy == ParseErrorPolicy::Format {
+        return true;
+    }
+    let errors = state.syntax_errors();
+    let Some(first) = errors.first() else {
+        return true;
+    };
+
+    let skip = policy == ParseErrorPolicy::Skip;
+    let outcome = if skip {
+        "it was left unformatted"
+    } else {
+        "formatting it anyway"
+    };
+    recorder.report([Diagnostic::warning(format!(
+        "source has {} syntax error(s) before formatting; {outcome}",
+        errors.len()
+    ))
+    .with_range(*first)]);
+    !skip
+}
+
+/// Reparse `formatted` from scratch and report syntax errors that `original` did not have.
+///
+/// Only the number of errors is compared, since their positions move with
+/// the edits. The diagnostic points at the first error of `formatted`.
+///
+/// # Returns
+/// True if the formatted text must be discarded
+fn reparse_check<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    original: &str,
+    formatted: &str,
+    severity: Severity,
+    recorder: PassRecorder,
+) -> bool {
+    if original == formatted {
+        return false;
+    }
+
+    let mut output = ParseState::new(formatted.to_string());
+    parser.parse(&mut output);
+    let errors = output.syntax_errors();
+    if errors.is_empty() {
+        return false;
+    }
+
+    let mut input = ParseState::new(original.to_string());
+    parser.parse(&mut input);
+    let before = input.syntax_errors().len();
+    if errors.len() <= before {
+        return false;
+    }
+
+    let outcome = match severity {
+        Severity::Error => "the source was left unformatted",
+        Severity::Warning => "the formatted text was kept",
+    };
+    debug!(
+        "Reparse found {} syntax error(s), {before} before formatting",
+        errors.len()
+    );
+    recorder.report([Diagnostic::new(
+        severity,
+        format!(
+            "formatting introduced syntax errors ({} in the output, {before} in the input); {outcome}",
+            errors.len()
+        ),
+    )
+    .with_range(errors[0])]);
+    severity == Severity::Error
+}
+
+/// The configurations of the sources of a batch.
+struct Configs<'a, C> {
+    /// Configuration of the run, for the steps that see every source at once
+    run: &'a C,
+    /// Configuration of each source, or empty if every source uses `run`
+    files: &'a [&'a C],
+}
+
+impl<'a, C> Configs<'a, C> {
+    /// Use `run` for every source.
+    fn shared(run: &'a C) -> Self {
+        Self { run, files: &[] }
+    }
+
+    /// Get the configuration of the source at index `file`.
+    fn of(&self, file: usize) -> &'a C {
+        self.files.get(file).copied().unwrap_or(self.run)
+    }
+}
+
+impl<C> Clone for Configs<'_, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for Configs<'_, C> {}
+
+/// A source being formatted in a batch, with the panic that stopped it, if any.
+struct BatchItem {
+    /// Index of the source in the batch
+    file: usize,
+    state: ParseState,
+    failure: Option<PassPanic>,
+    /// Whether the source was left unformatted for its syntax errors or a cancellation
+    skipped: bool,
+    /// Time spent on the source so far
+    duration: Duration,
+    /// Measurements of the tree parsed before running passes
+    parse: Option<ParseMetrics>,
+    /// Diagnostics reported about the source, once it is finished
+    diagnostics: Vec<Diagnostic>,
+    /// Edits applied to the source so far, if source maps are recorded
+    source_map: Option<SourceMap>,
+}
+
+impl BatchItem {
+    /// Get a copy of the source's result, to report it before the batch is done.
+    fn formatted(&self) -> FormattedSource {
+        FormattedSource {
+            result: match &self.failure {
+                Some(err) => Err(err.clone()),
+                None => Ok(self.state.source().to_string()),
+            },
+            duration: self.duration,
+            parse: self.parse,
+            diagnostics: self.diagnostics.clone(),
+            source_map: self.source_map.clone(),
+        }
+    }
+
+    /// Put the original source `code` back, discarding the applied edits.
+    fn restore(&mut self, code: &str) {
+        self.state = ParseState::new(code.to_string());
+        if let Some(map) = &mut self.source_map {
+            *map = SourceMap::new(code.len());
+        }
+    }
+
+    /// Returns true if passes still run on the source.
+    fn is_formatting(&self) -> bool {
+        self.failure.is_none() && !self.skipped
+    }
+
+    /// Leave the source unformatted because the run was cancelled.
+    fn cancel(&mut self, recorder: PassRecorder) {
+        self.skipped = true;
+        recorder.report([cancelled_diagnostic()]);
+    }
+}
+
+/// The diagnostic of a source left unchanged because the run was cancelled.
+fn cancelled_diagnostic() -> Diagnostic {
+    Diagnostic::error("formatting was cancelled; the source was left unchanged")
+}
+
+/// The result of one source of a batch, either taken from the cache or formatted.
+enum BatchResult {
+    /// Whether the source needed formatting, as cached
+    Cached(bool),
+    /// The source disables formatting
+    Ignored,
+    /// The source was formatted
+    Formatted(FormattedSource),
+}
+
+/// Build the outcome of the source `code` at `path` from its result in a batch.
+///
+/// A cached result carries no formatted text, so `formatted` holds `code`.
+fn file_outcome(path: PathBuf, code: &str, result: BatchResult, diffs: bool) -> FileFormatOutcome {
+    match result {
+        BatchResult::Cached(changed) => {
+            let mut outcome = FileFormatOutcome::new(path, code, Ok(code.to_string()));
+            outcome.changed = changed;
+            outcome
+        }
+        BatchResult::Ignored => {
+            let mut outcome = FileFormatOutcome::new(path, code, Ok(code.to_string()));
+            outcome.ignored = true;
+            outcome
+        }
+        BatchResult::Formatted(formatted) => {
+            let mut outcome = FileFormatOutcome::new(path, code, formatted.result);
+            outcome.duration = formatted.duration;
+            outcome.parse = formatted.parse;
+            outcome.source_map = formatted.source_map;
+            outcome.diagnostics.splice(0..0, formatted.diagnostics);
+            if let (true, Ok(formatted)) = (diffs && outcome.changed, &outcome.formatted) {
+                outcome.diff = Some(unified_diff(&outcome.path, code, formatted));
+            }
+            outcome
+        }
+    }
+}
+
+/// A source formatted in a batch.
+struct FormattedSource {
+    /// The formatted content, or the panic that stopped it
+    result: Result<String, PassPanic>,
+    /// Time spent formatting the source
+    duration: Duration,
+    /// Measurements of the tree parsed before running passes
+    parse: Option<ParseMetrics>,
+    /// Diagnostics reported while formatting the source
+    diagnostics: Vec<Diagnostic>,
+    /// Map between the source and its formatted content, if recorded
+    source_map: Option<SourceMap>,
+}
+
+/// Diagnostics an engine records while running passes; timings and edits are optional.
+///
+/// Passes are run through the recorders, so they also carry the token that
+/// cancels the run and the observer notified of progress.
+#[derive(Default)]
+struct Recorders {
+    timings: Option<PassTimings>,
+    edit_trace: Option<EditTrace>,
+    /// Diagnostics by the index of the source they are about
+    diagnostics: Mutex<BTreeMap<usize, Vec<Diagnostic>>>,
+    cancellation: Option<CancellationToken>,
+    observer: Option<Arc<dyn EngineObserver>>,
+    /// Levels overriding the severity of diagnostics by rule code
+    rule_levels: BTreeMap<String, RuleLevel>,
+    /// Whether every edit is reparsed on its own before it is applied
+    check_edits: bool,
+}
+
+impl Recorders {
+    /// Get a recorder for the source at index `file`.
+    fn for_file(&self, file: usize) -> PassRecorder<'_> {
+        PassRecorder {
+            recorders: self,
+            file,
+            path: None,
+        }
+    }
+
+    /// Returns true if the run was cancelled.
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Take the diagnostics reported since the last call, with their source index.
+    fn take_diagnostics(&self) -> Vec<(usize, Diagnostic)> {
+        let diagnostics = std::mem::take(
+            &mut *self
+                .diagnostics
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        diagnostics
+            .into_iter()
+            .flat_map(|(file, diagnostics)| diagnostics.into_iter().map(move |d| (file, d)))
+            .collect()
+    }
+
+    /// Take the diagnostics reported about the source at index `file`.
+    fn take_file_diagnostics(&self, file: usize) -> Vec<Diagnostic> {
+        self.diagnostics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&file)
+            .unwrap_or_default()
+    }
+}
+
+/// Records diagnostics of one source into the engine's recorders.
+#[derive(Clone, Copy)]
+struct PassRecorder<'a> {
+    recorders: &'a Recorders,
+    file: usize,
+    /// Path of the source, passed to `Pass::should_run`
+    path: Option<&'a Path>,
+}
+
+impl<'a> PassRecorder<'a> {
+    /// Set the path of the source, if it has one.
+    fn with_path(self, path: Option<&'a Path>) -> Self {
+        Self { path, ..self }
+    }
+
+    /// Run `op`, recording its wall time under `pass` when timings are enabled.
+    fn time<R>(self, pass: &'static str, op: impl FnOnce() -> R) -> R {
+        let Some(timings) = &self.recorders.timings else {
+            return op();
+        };
+
+        let started = Instant::now();
+        let result = op();
+        timings.record(self.file, pass, started.elapsed());
+        result
+    }
+
+    /// Run `pass` with `op`, which returns the number of edits it produced.
+    ///
+    /// Records the wall time like `time` and notifies the observer.
+    fn time_pass(self, pass: &'static str, op: impl FnOnce() -> usize) {
+        let started = Instant::now();
+        let edit_count = self.time(pass, op);
+        if let Some(observer) = &self.recorders.observer {
+            observer.on_pass_done(self.file, pass, started.elapsed(), edit_count);
+        }
+    }
+
+    /// Notify the observer that passes start running on the source.
+    fn start(self) {
+        if let Some(observer) = &self.recorders.observer {
+            observer.on_file_start(self.file);
+        }
+    }
+
+    /// Record the edits `pass` produced for `source` when tracing is enabled.
+    fn trace(self, pass: &'static str, source: &str, edits: &[Edit]) {
+        if let Some(edit_trace) = &self.recorders.edit_trace {
+            edit_trace.record(self.file, pass, source, edits);
+        }
+    }
+
+    /// Returns true if the run was cancelled and no further passes should run.
+    fn is_cancelled(self) -> bool {
+        self.recorders.is_cancelled()
+    }
+
+    /// Report diagnostics of `pass` about the source, attributing those without a pass to it.
+    fn report_pass(self, pass: &'static str, diagnostics: Vec<Diagnostic>) {
+        self.report(
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| match diagnostic.pass {
+                    Some(_) => diagnostic,
+                    None => diagnostic.with_pass(pass),
+                }),
+        );
+    }
+
+    /// Report diagnostics about the source, at the levels set for their rule codes.
+    fn report(self, diagnostics: impl IntoIterator<Item = Diagnostic>) {
+        let levels = &self.recorders.rule_levels;
+        let diagnostics = diagnostics.into_iter().filter_map(|diagnostic| {
+            match diagnostic.code.and_then(|code| levels.get(code)) {
+                Some(level) => level.apply(diagnostic),
+                None => Some(diagnostic),
+            }
+        });
+        self.recorders
+            .diagnostics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(self.file)
+            .or_default()
+            .extend(diagnostics);
+    }
+}
+
+/// Apply the edits produced by one pass to the parse state.
+///
+/// If any edit is out of bounds, splits a character, or overlaps another,
+/// none of the edits are applied and the problems are reported as
+/// diagnostics.
+fn apply_edits<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    state: &mut ParseState,
+    pass: &'static str,
+    edits: Vec<Edit>,
+    source_map: Option<&mut SourceMap>,
+    range: Option<&mut (usize, usize)>,
+    recorder: PassRecorder,
+) {
+    if let Some(edits) = checked_edits(parser, state, pass, edits, recorder) {
+        apply_valid_edits(parser, state, edits, source_map, range);
+    }
+    report_divergence(parser, &[pass], recorder);
+}
+
+/// Report the divergence found by the incremental check of the last edits, if any.
+///
+/// The diagnostic is attributed to the pass in `passes` if there is only
+/// one; edits of several passes reparsed together are named in its message.
+fn report_divergence<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    passes: &[&'static str],
+    recorder: PassRecorder,
+) {
+    let Some(divergence) = parser.take_divergence() else {
+        return;
+    };
+    let diagnostic = match passes {
+        [pass] => Diagnostic::error(divergence.to_string()).with_pass(pass),
+        _ => Diagnostic::error(format!(
+            "{divergence} (edits of {} applied together)",
+            passes.join(", ")
+        )),
+    };
+    recorder.report([match divergence.edit {
+        Some((start, old_end, _)) => diagnostic.with_range((start, old_end)),
+        None => diagnostic,
+    }]);
+}
+
+/// Trace and validate the edits `pass` produced for the source of `state`.
+///
+/// Valid edits changing text that comments suppress for `pass` are dropped,
+/// and so are edits introducing syntax errors if edits are checked.
+///
+/// # Returns
+/// The edits, or `None` if they were rejected and reported as diagnostics
+fn checked_edits<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    state: &ParseState,
+    pass: &'static str,
+    edits: Vec<Edit>,
+    recorder: PassRecorder,
+) -> Option<Vec<Edit>> {
+    let source = state.source();
+    debug!("Pass {pass} generated {} edit(s)", edits.len());
+    recorder.trace(pass, source, &edits);
+
+    let problems = validate_edits(pass, source, &edits);
+    if problems.is_empty() {
+        let suppressions = suppressions::<Language>(state);
+        let edits = if suppressions.is_empty() {
+            edits
+        } else {
+            let edits = suppressions.retain_edits(source, pass, edits);
+            debug!(
+                "{} edit(s) of {pass} outside suppressed regions",
+                edits.len()
+            );
+            edits
+        };
+        if !recorder.recorders.check_edits || edits.is_empty() {
+            return Some(edits);
+        }
+        return Some(parsing_edits(parser, state, pass, edits, recorder));
+    }
+    debug!(
+        "Rejected the edits of {pass}: {} problem(s)",
+        problems.len()
+    );
+    recorder.report(problems);
+    None
+}
+
+/// Keep the edits that parse when applied on their own to the source of `state`.
+///
+/// Each edit is applied to a copy of the source, which is parsed from
+/// scratch; an edit is rejected, with an error diagnostic, if the copy has
+/// more syntax errors than the source.
+fn parsing_edits<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    state: &ParseState,
+    pass: &'static str,
+    edits: Vec<Edit>,
+    recorder: PassRecorder,
+) -> Vec<Edit> {
+    let source = state.source();
+    let before = state.syntax_errors().len();
+    let mut rejected = Vec::new();
+    let edits = edits
+        .into_iter()
+        .filter(|edit| {
+            let (start, end) = edit.range;
+            let mut copy = ParseState::new(format!(
+                "{}{}{}",
+                &source[..start],
+                edit.content,
+                &source[end..]
+            ));
+            parser.parse(&mut copy);
+            let errors = copy.syntax_errors().len();
+            if errors <= before {
+                return true;
+            }
+            rejected.push(
+                Diagnostic::error(format!(
+                    "edit introduces syntax errors ({errors} with it, {before} without); it was not applied"
+                ))
+                .with_pass(pass)
+                .with_range(edit.range),
+            );
+            false
+        })
+        .collect();
+    if !rejected.is_empty() {
+        debug!(
+            "Rejected {} edit(s) of {pass} that do not parse",
+            rejected.len()
+        );
+        recorder.report(rejected);
+    }
+    edits
+}
+
+/// Apply non-overlapping edits, given in pipeline order, to the parse state.
+///
+/// Edits are applied in reverse order of their ranges so that earlier
+/// offsets stay valid while later text is replaced. Insertions at the same
+/// offset keep their order.
+///
+/// With a `range`, edits not entirely within it are dropped and its end
+/// is moved by the length change of every applied edit.
+fn apply_valid_edits<Language: LanguageProvider>(
+    parser: &mut Parser<Language>,
+    state: &mut ParseState,
+    mut edits: Vec<Edit>,
+    mut source_map: Option<&mut SourceMap>,
+    mut range: Option<&mut (usize, usize)>,
+) {
+    if let Some((start, end)) = range.as_deref() {
+        let source = state.source();
+        let within = |edit: &Edit| edit.range.0 >= *start && edit.range.1 <= *end;
+        edits = edits
+            .into_iter()
+            .map(|edit| {
+                if within(&edit) {
+                    edit
+                } else {
+                    narrow_edit(source, edit)
+                }
+            })
+            .filter(within)
+            .collect();
+        debug!("{} edit(s) within range {start}..{end}", edits.len());
+    }
+
+    // Sort edits in reverse order to maintain byte offsets; an insertion
+    // goes before a replacement starting at the same offset. Reversing
+    // first makes the stable sort apply later insertions first, leaving
+    // them after earlier ones at the same offset.
+    edits.reverse();
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range));
+
+    for edit in &edits {
+        debug!("Applying edit at range {:?}", edit.range);
+        if let Some(map) = source_map.as_deref_mut() {
+            map.record_edit(edit.range.0, edit.range.1, edit.content.len());
+        }
+        if let Some((_, end)) = range.as_deref_mut() {
+            *end = *end + edit.content.len() - (edit.range.1 - edit.range.0);
+        }
+    }
+    let edits: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .map(|edit| (edit.range.0, edit.range.1, edit.content.as_str()))
+        .collect();
+    parser.apply_edits(state, &edits);
+}
+
+/// Narrow `edit` to the text it changes, leaving out what it replaces with the same text.
+///
+/// The common prefix and suffix of the replaced text and the new content
+/// are cut off at character boundaries.
+pub(crate) fn narrow_edit(source: &str, edit: Edit) -> Edit {
+    let (start, end) = edit.range;
+    let old = &source[start..end];
+    let new = edit.content.as_str();
+
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new.len()), |((i, _), _)| i);
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix: usize = old_rest
+        .chars()
+        .rev()
+        .zip(new_rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+
+    Edit {
+        range: (start + prefix, end - suffix),
+        content: new_rest[..new_rest.len() - suffix].to_string(),
+    }
+}
+
+/// Clamp `range` to `source`, widening it to character boundaries.
+fn clamp_range(source: &str, (start, end): (usize, usize)) -> (usize, usize) {
+    let mut start = start.min(source.len());
+    let mut end = end.clamp(start, source.len());
+    while !source.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !source.is_char_boundary(end) {
+        end += 1;
+    }
+    (start, end)
+}
+
+/// Get the root node of a parsed state.
+fn root_node(state: &ParseState) -> tree_sitter::Node<'_> {
+    state
+        .tree()
+        .expect("Tree should exist after parsing unless the parse was aborted")
+        .root_node()
+}
+
+/// Get a function creating parsers for worker threads, with the limits of `parser`.
+fn worker_parser<Language: LanguageProvider>(
+    parser: &Parser<Language>,
+) -> impl Fn() -> Parser<Language> + Send + Sync {
+    let (timeout, cancellation) = (parser.timeout(), parser.cancellation().cloned());
+    let incremental_check = parser.incremental_check();
+    move || {
+        let mut parser =
+            Parser::try_new().expect("the grammar was checked when the engine was created");
+        parser.set_timeout(timeout);
+        parser.set_cancellation(cancellation.clone());
+        parser.set_incremental_check(incremental_check);
+        parser
+    }
+}
+
+/// Run `op` on every state, on `jobs` worker threads each owning a parser.
+///
+/// With a single job (or a single state) the states are processed in order
+/// on the calling thread using `parser`. `op` also receives the index of the state.
+fn for_each_state<Language, T, F>(
+    jobs: usize,
+    parser: &mut Parser<Language>,
+    states: &mut [T],
+    op: F,
+) where
+    Language: LanguageProvider,
+    T: Send,
+    F: Fn(&mut Parser<Language>, usize, &mut T) + Send + Sync,
+{
+    if is_sequential(jobs, states.len()) {
+        for (i, state) in states.iter_mut().enumerate() {
+            op(parser, i, state);
+        }
+        return;
+    }
+
+    let new_parser = worker_parser(parser);
+    in_pool(jobs, || {
+        states
+            .par_iter_mut()
+            .enumerate()
+            .for_each_init(new_parser, |parser, (i, state)| {
+                op(parser, i, state);
+            });
+    });
+}
+
+/// Map every state and its index with `op` on `jobs` worker threads, preserving order.
+fn map_states<T, R, F>(jobs: usize, states: &[T], op: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Send + Sync,
+{
+    if is_sequential(jobs, states.len()) {
+        return states
+            .iter()
+            .enumerate()
+            .map(|(i, state)| op(i, state))
+            .collect();
+    }
+
+    in_pool(jobs, || {
+        states
+            .par_iter()
+            .enumerate()
+            .map(|(i, state)| op(i, state))
+            .collect()
+    })
+}
+
+/// Whether `count` items should be processed on the calling thread.
+///
+/// Wasm targets cannot spawn threads, so they always run sequentially.
+fn is_sequential(jobs: usize, count: usize) -> bool {
+    cfg!(target_family = "wasm") || jobs == 1 || count < 2
+}
+
+/// Run `op` inside a thread pool with `jobs` threads (`0` = one per CPU).
+fn in_pool<R: Send>(jobs: usize, op: impl FnOnce() -> R + Send) -> R {
+    match ThreadPoolBuilder::new().num_threads(jobs).build() {
+        Ok(pool) => pool.install(op),
+        Err(err) => {
+            debug!("Failed to build thread pool ({err}), using the global pool");
+            op()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LanguageCapabilities;
+    use crate::pipeline::{ConflictPolicy, ConsistencyPass, Pass, PipelineError, ProjectPass};
+    use crate::supported_extension::SupportedExtension;
+    use rstest::rstest;
+    use serde::{Deserialize, Serialize};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tree_sitter::Node;
+
+    struct JsonLanguage;
+
+    impl LanguageProvider for JsonLanguage {
+        fn language() -> tree_sitter::Language {
+            tree_sitter_json::LANGUAGE.into()
+        }
+
+        fn supported_extension() -> &'static SupportedExtension {
+            static JSON_EXTENSIONS: SupportedExtension = SupportedExtension::new(&["json"]);
+            &JSON_EXTENSIONS
+        }
+
+        fn capabilities() -> &'static LanguageCapabilities {
+            static JSON_CAPABILITIES: LanguageCapabilities = LanguageCapabilities::new()
+                .with_line_comment("//")
+                .with_block_comment("/*", "*/");
+            &JSON_CAPABILITIES
+        }
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct TestConfig;
+
+    /// Normalizes the separator between pair keys and values to `": "`.
+    struct ColonSpacingPass;
+
+    impl Pass for ColonSpacingPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, root: &Node, source: &str) -> Vec<Edit> {
+            let mut edits = Vec::new();
+            let mut stack = vec![*root];
+            while let Some(node) = stack.pop() {
+                if node.kind() == "pair" {
+                    let key = node.child_by_field_name("key").unwrap();
+                    let value = node.child_by_field_name("value").unwrap();
+                    if &source[key.end_byte()..value.start_byte()] != ": " {
+                        edits.push(Edit {
+                            range: (key.end_byte(), value.start_byte()),
+                            content: ": ".to_string(),
+                        });
+                    }
+                }
+                let mut cursor = node.walk();
+                stack.extend(node.children(&mut cursor));
+            }
+            edits
+        }
+    }
+
+    /// Collects all key/value separators of a file.
+    fn separators(root: &Node, source: &str) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut stack = vec![*root];
+        while let Some(node) = stack.pop() {
+            if node.kind() == "pair" {
+                let key = node.child_by_field_name("key").unwrap();
+                let value = node.child_by_field_name("value").unwrap();
+                ranges.push((key.end_byte(), value.start_byte()));
+            }
+            let mut cursor = node.walk();
+            stack.extend(node.children(&mut cursor));
+        }
+        ranges.retain(|(start, end)| source[*start..*end].trim() == ":");
+        ranges
+    }
+
+    /// Rewrites every separator to the style used most across all files.
+    struct MajoritySeparatorPass;
+
+    impl ConsistencyPass for MajoritySeparatorPass {
+        type Config = TestConfig;
+        type Summary = Vec<String>;
+        type Project = String;
+
+        fn summarize(&self, _config: &TestConfig, root: &Node, source: &str) -> Vec<String> {
+            separators(root, source)
+                .into_iter()
+                .map(|(start, end)| source[start..end].to_string())
+                .collect()
+        }
+
+        fn combine(&self, _config: &TestConfig, summaries: Vec<Vec<String>>) -> String {
+            let all: Vec<String> = summaries.into_iter().flatten().collect();
+            let compact = all.iter().filter(|sep| sep.as_str() == ":").count();
+            if compact * 2 > all.len() {
+                ":".to_string()
+            } else {
+                ": ".to_string()
+            }
+        }
+
+        fn run(
+            &self,
+            _config: &TestConfig,
+            project: &String,
+            root: &Node,
+            source: &str,
+        ) -> Vec<Edit> {
+            separators(root, source)
+                .into_iter()
+                .filter(|(start, end)| &source[*start..*end] != project)
+                .map(|range| Edit {
+                    range,
+                    content: project.clone(),
+                })
+                .collect()
+        }
+    }
+
+    fn engine(jobs: usize) -> Engine<JsonLanguage, TestConfig> {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(ColonSpacingPass);
+        Engine::new(pipeline).with_jobs(jobs)
+    }
+
+    fn sources() -> Vec<String> {
+        (0..32)
+            .map(|i| {
+                if i % 3 == 0 {
+                    format!("{{\"a{i}\": {i}}}")
+                } else {
+                    format!("{{\"a{i}\" :{i}, \"b\"  :  [1, 2]}}")
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_format_applies_edits() {
+        let formatted = engine(1).format(&TestConfig, "{\"a\" :1, \"b\":2}");
+        assert_eq!(formatted, "{\"a\": 1, \"b\": 2}");
+    }
+
+    #[test]
+    fn test_format_lines_only_touches_range() {
+        let code = "{\n\"a\" :1,\n\"b\" :2,\n\"c\" :3\n}";
+        let lines = LineRange::new(2, 3).unwrap();
+
+        let formatted = engine(1).format_lines(&TestConfig, code, lines);
+        assert_eq!(formatted, "{\n\"a\": 1,\n\"b\": 2,\n\"c\" :3\n}");
+    }
+
+    #[test]
+    fn test_parallel_results_match_sequential_order() {
+        let codes = sources();
+        let sequential = engine(1).format_all(&TestConfig, &codes);
+        let parallel = engine(4).format_all(&TestConfig, &codes);
+
+        assert_eq!(sequential, parallel);
+        assert_eq!(parallel[1], "{\"a1\": 1, \"b\": [1, 2]}");
+    }
+
+    #[test]
+    fn test_consistency_pass_sees_all_files() {
+        let codes = vec![
+            "{\"a\":1}".to_string(),
+            "{\"b\":2, \"c\":3}".to_string(),
+            "{\"d\": 4}".to_string(),
+        ];
+
+        for jobs in [1, 3] {
+            let mut pipeline = Pipeline::new();
+            pipeline.add_consistency_pass(MajoritySeparatorPass);
+            let mut engine = Engine::<JsonLanguage, TestConfig>::new(pipeline).with_jobs(jobs);
+
+            let formatted = engine.format_all(&TestConfig, &codes);
+            assert_eq!(formatted[0], codes[0]);
+            assert_eq!(formatted[1], codes[1]);
+            assert_eq!(formatted[2], "{\"d\":4}");
+        }
+    }
+
+    #[test]
+    fn test_consistency_pass_runs_after_file_passes() {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ColonSpacingPass)
+            .add_consistency_pass(MajoritySeparatorPass);
+        let mut engine = Engine::<JsonLanguage, TestConfig>::new(pipeline);
+
+        // The per-file pass normalizes to ": " first, so the majority is ": "
+        let formatted = engine.format(&TestConfig, "{\"a\":1, \"b\" : 2}");
+        assert_eq!(formatted, "{\"a\": 1, \"b\": 2}");
+    }
+
+    /// Replaces the values of keys defined in an earlier file with `null`.
+    struct DuplicateKeysPass;
+
+    impl ProjectPass for DuplicateKeysPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &TestConfig, files: &[ProjectFile<TestConfig>]) -> Vec<ProjectEdit> {
+            let mut seen = std::collections::HashSet::new();
+            let mut edits = Vec::new();
+            for (i, file) in files.iter().enumerate() {
+                let mut keys = Vec::new();
+                crate::walk::walk_kinds(file.root, &["pair"], |pair, _| {
+                    let key = pair.child_by_field_name("key").unwrap();
+                    let value = pair.child_by_field_name("value").unwrap();
+                    let key = &file.source[key.start_byte()..key.end_byte()];
+                    if seen.contains(key) {
+                        edits.push(ProjectEdit {
+                            file: i,
+                            edit: Edit {
+                                range: (value.start_byte(), value.end_byte()),
+                                content: "null".to_string(),
+                            },
+                        });
+                    }
+                    keys.push(key);
+                    crate::walk::Visit::SkipChildren
+                });
+                seen.extend(keys);
+            }
+            edits
+        }
+    }
+
+    #[test]
+    fn test_project_pass_edits_across_files() {
+        let codes = vec![
+            "{\"a\" :1, \"b\": 2}".to_string(),
+            "{\"b\": 3}".to_string(),
+            "{\"c\": 4, \"a\": 5}".to_string(),
+        ];
+
+        for jobs in [1, 3] {
+            let mut pipeline = Pipeline::new();
+            pipeline
+                .add_pass(ColonSpacingPass)
+                .add_project_pass(DuplicateKeysPass);
+            let mut engine = Engine::<JsonLanguage, TestConfig>::new(pipeline).with_jobs(jobs);
+
+            let formatted = engine.format_all(&TestConfig, &codes);
+            assert_eq!(
+                formatted,
+                [
+                    "{\"a\": 1, \"b\": 2}",
+                    "{\"b\": null}",
+                    "{\"c\": 4, \"a\": null}"
+                ]
+            );
+            // A single source is a project of its own
+            assert_eq!(engine.format(&TestConfig, &codes[1]), codes[1]);
+        }
+    }
+
+    #[test]
+    fn test_find_first_unformatted_returns_first_in_order() {
+        let codes = sources();
+        let files: Vec<PathBuf> = (0..codes.len())
+            .map(|i| PathBuf::from(format!("file{i}.json")))
+            .collect();
+
+        for jobs in [1, 4] {
+            let first = engine(jobs).find_first_unformatted(&TestConfig, &codes, &files);
+            assert_eq!(first, Some(PathBuf::from("file1.json")));
+        }
+
+        let clean = vec!["{\"a\": 1}".to_string()];
+        let first = engine(1).find_first_unformatted(&TestConfig, &clean, &files[..1]);
+        assert_eq!(first, None);
+    }
+
+    #[test]
+    fn test_timings_cover_every_pass_and_file() {
+        let codes = sources();
+        for jobs in [1, 4] {
+            let mut engine = engine(jobs).with_timings();
+            engine.format_all(&TestConfig, &codes);
+
+            let timings = engine.take_timings();
+            let recorded: Vec<_> = timings
+                .iter()
+                .map(|timing| (timing.file, timing.pass))
+                .collect();
+            let expected: Vec<_> = (0..codes.len())
+                .flat_map(|file| [(file, PARSE_TIMING), (file, "ColonSpacingPass")])
+                .collect();
+            assert_eq!(recorded, expected);
+            assert!(engine.take_timings().is_empty());
+        }
+
+        assert!(engine(1).take_timings().is_empty());
+    }
+
+    #[test]
+    fn test_edit_trace_records_replaced_text() {
+        let mut tracing = engine(1).with_edit_trace();
+        tracing.format(&TestConfig, "{\"a\" :1}");
+
+        let trace = tracing.take_edit_trace();
+        assert_eq!(
+            trace,
+            vec![TracedEdit {
+                file: 0,
+                pass: "ColonSpacingPass",
+                range: (4, 6),
+                before: " :".to_string(),
+                after: ": ".to_string(),
+            }]
+        );
+        assert!(engine(1).take_edit_trace().is_empty());
+    }
+
+    /// Panics on sources containing `"boom"`.
+    struct BoomPass;
+
+    impl Pass for BoomPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, _root: &Node, source: &str) -> Vec<Edit> {
+            assert!(!source.contains("boom"), "boom in source");
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn test_try_find_first_unformatted_stops_at_panicking_pass() {
+        let codes = vec!["{\"a\": 1}".to_string(), "{\"boom\": 1}".to_string()];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+        for jobs in [1, 4] {
+            let mut pipeline = Pipeline::new();
+            pipeline.add_pass(BoomPass);
+            let stop = Engine::<JsonLanguage, TestConfig>::new(pipeline)
+                .with_jobs(jobs)
+                .try_find_first_unformatted(&TestConfig, &codes, &files);
+
+            let Some(FirstStop::Failed(error)) = stop else {
+                panic!("expected a failure, got {stop:?}");
+            };
+            assert_eq!(error.path, files[1]);
+            assert!(error.message.contains("boom in source"));
+        }
+    }
+
+    #[test]
+    fn test_try_format_all_isolates_panicking_pass() {
+        let codes = vec![
+            "{\"a\" :1}".to_string(),
+            "{\"boom\" :1}".to_string(),
+            "{\"c\" :1}".to_string(),
+        ];
+
+        for jobs in [1, 4] {
+            let mut pipeline = Pipeline::new();
+            pipeline.add_pass(BoomPass).add_pass(ColonSpacingPass);
+            let results = Engine::<JsonLanguage, TestConfig>::new(pipeline)
+                .with_jobs(jobs)
+                .try_format_all(&TestConfig, &codes);
+
+            assert_eq!(results[0], Ok("{\"a\": 1}".to_string()));
+            assert_eq!(
+                results[1],
+                Err(PassPanic {
+                    pass: "BoomPass",
+                    message: "boom in source".to_string(),
+                })
+            );
+            assert_eq!(results[2], Ok("{\"c\": 1}".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_check_reports_changed_files_in_input_order() {
+        let codes = sources();
+        let files: Vec<PathBuf> = (0..codes.len())
+            .map(|i| PathBuf::from(format!("file{i}.json")))
+            .collect();
+
+        let changed = engine(0).check(&TestConfig, &codes, &files);
+        let expected: Vec<PathBuf> = files
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 3 != 0)
+            .map(|(_, file)| file.clone())
+            .collect();
+
+        assert_eq!(changed, expected);
+    }
+
+    #[test]
+    fn test_should_run_skips_files() {
+        /// Spaces colons, except in vendored files.
+        struct FirstPartyPass;
+
+        impl Pass for FirstPartyPass {
+            type Config = TestConfig;
+
+            fn run(&self, config: &TestConfig, root: &Node, source: &str) -> Vec<Edit> {
+                ColonSpacingPass.run(config, root, source)
+            }
+
+            fn should_run(&self, _config: &TestConfig, path: Option<&Path>, _source: &str) -> bool {
+                !path.is_some_and(|path| path.starts_with("vendor"))
+            }
+        }
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(FirstPartyPass);
+        let mut engine: Engine<JsonLanguage, TestConfig> = Engine::new(pipeline);
+        let codes = vec!["{\"a\" :1}".to_string(), "{\"a\" :1}".to_string()];
+        let files = vec![PathBuf::from("src/a.json"), PathBuf::from("vendor/a.json")];
+
+        assert_eq!(
+            engine.check(&TestConfig, &codes, &files),
+            [files[0].clone()]
+        );
+        assert_eq!(
+            engine.find_first_unformatted(&TestConfig, &codes[1..], &files[1..]),
+            None
+        );
+        assert_eq!(engine.format(&TestConfig, &codes[1]), "{\"a\": 1}");
+    }
+
+    #[test]
+    fn test_lint_pass_reports_diagnostics() {
+        /// Flags numbers above ten without changing anything.
+        struct LargeNumberPass;
+
+        impl Pass for LargeNumberPass {
+            type Config = TestConfig;
+
+            fn run(&self, _config: &TestConfig, _root: &Node, _source: &str) -> Vec<Edit> {
+                Vec::new()
+            }
+
+            fn diagnose(&self, _config: &TestConfig, root: &Node, source: &str) -> Vec<Diagnostic> {
+                let mut diagnostics = Vec::new();
+                crate::walk::walk_kinds(*root, &["number"], |node, _| {
+                    let range = (node.start_byte(), node.end_byte());
+                    if source[range.0..range.1].parse::<u32>().unwrap() > 10 {
+                        diagnostics.push(Diagnostic::error("number above ten").with_range(range));
+                    }
+                    crate::walk::Visit::Continue
+                });
+                diagnostics
+            }
+        }
+
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ColonSpacingPass)
+            .add_pass(LargeNumberPass);
+        let mut engine: Engine<JsonLanguage, TestConfig> = Engine::new(pipeline);
+        let codes = vec!["{\"a\" :1, \"b\" :12}".to_string()];
+        let outcomes = engine.format_files(&TestConfig, &codes, &[PathBuf::from("a.json")]);
+
+        assert_eq!(
+            outcomes[0].diagnostics,
+            [Diagnostic::error("number above ten")
+                .with_pass("LargeNumberPass")
+                .with_range((14, 16))]
+        );
+        assert_eq!(
+            outcomes[0].formatted.as_deref(),
+            Ok("{\"a\": 1, \"b\": 12}")
+        );
+    }
+
+    #[rstest]
+    #[case(ConflictPolicy::Sequential)]
+    #[case(ConflictPolicy::FirstWins)]
+    fn test_fmt_off_regions_are_left_alone(#[case] policy: ConflictPolicy) {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ColonSpacingPass)
+            .set_conflict_policy(policy);
+        let mut engine: Engine<JsonLanguage, TestConfig> = Engine::new(pipeline);
+        let source =
+            "{\"a\" :1,\n// fmt: off\n\"b\" :2,\n// fmt: on\n\"c\" :3, /* fmt: off */ \"d\" :4}";
+
+        assert_eq!(
+            engine.format(&TestConfig, source),
+            "{\"a\": 1,\n// fmt: off\n\"b\" :2,\n// fmt: on\n\"c\": 3, /* fmt: off */ \"d\" :4}"
+        );
+    }
+
+    #[test]
+    fn test_ignore_comments_suppress_edits() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(ColonSpacingPass);
+        let mut engine: Engine<JsonLanguage, TestConfig> = Engine::new(pipeline);
+        let source = "{\"a\" :1,\n// fmt-ignore\n\"b\" :2,\n// fmt-ignore-next-line\n\"c\" :3,\n// fmt-ignore: OtherPass\n\"d\" :4}";
+
+        assert_eq!(
+            engine.format(&TestConfig, source),
+            "{\"a\": 1,\n// fmt-ignore\n\"b\" :2,\n// fmt-ignore-next-line\n\"c\" :3,\n// fmt-ignore: OtherPass\n\"d\": 4}"
+        );
+    }
+
+    #[test]
+    fn test_files_disabling_formatting_are_ignored() {
+        let mut engine = engine(1).with_generated_markers(vec!["@generated".to_string()]);
+        let codes = vec![
+            "// fmt-disable-file\n{\"a\" :1}".to_string(),
+            "// @generated by a tool\n{\"a\" :1}".to_string(),
+            "{\"a\" :1}".to_string(),
+        ];
+        let files: Vec<PathBuf> = (0..codes.len())
+            .map(|i| PathBuf::from(format!("file{i}.json")))
+            .collect();
+
+        let outcomes = engine.format_files(&TestConfig, &codes, &files);
+        let ignored: Vec<bool> = outcomes.iter().map(|o| o.ignored).collect();
+        assert_eq!(ignored, [true, true, false]);
+        assert_eq!(outcomes[0].formatted.as_ref(), Ok(&codes[0]));
+
+        assert_eq!(
+            engine.check(&TestConfig, &codes, &files),
+            [files[2].clone()]
+        );
+        assert_eq!(
+            engine.find_first_unformatted(&TestConfig, &codes, &files),
+            Some(files[2].clone())
+        );
+        assert_eq!(engine.format(&TestConfig, &codes[0]), codes[0]);
+    }
+
+    #[test]
+    fn test_rule_levels_remap_diagnostics() {
+        /// Reports one diagnostic per rule on every source.
+        struct ThreeRulesPass;
+
+        impl Pass for ThreeRulesPass {
+            type Config = TestConfig;
+
+            fn run(&self, _config: &TestConfig, _root: &Node, _source: &str) -> Vec<Edit> {
+                Vec::new()
+            }
+
+            fn diagnose(
+                &self,
+                _config: &TestConfig,
+                _root: &Node,
+                _source: &str,
+            ) -> Vec<Diagnostic> {
+                vec![
+                    Diagnostic::error("first").with_code("TST001"),
+                    Diagnostic::error("second").with_code("TST002"),
+                    Diagnostic::warning("third").with_code("TST003"),
+                ]
+            }
+        }
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(ThreeRulesPass);
+        let levels = BTreeMap::from([
+            ("TST001".to_string(), RuleLevel::Off),
+            ("TST002".to_string(), RuleLevel::Warn),
+        ]);
+        let mut engine: Engine<JsonLanguage, TestConfig> =
+            Engine::new(pipeline).with_rule_levels(levels);
+        let outcomes =
+            engine.format_files(&TestConfig, &["{}".to_string()], &[PathBuf::from("a.json")]);
+
+        let severities: Vec<_> = outcomes[0]
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.severity))
+            .collect();
+        assert_eq!(
+            severities,
+            [
+                (Some("TST002"), Severity::Warning),
+                (Some("TST003"), Severity::Warning)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_and_write_writes_changed_files_in_parallel() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let codes = sources();
+        let files: Vec<PathBuf> = (0..codes.len())
+            .map(|i| dir.path().join(format!("file{i}.json")))
+            .collect();
+        for (file, code) in files.iter().zip(&codes) {
+            std::fs::write(file, code).unwrap();
+        }
+
+        let changed = engine(0)
+            .format_and_write(&TestConfig, &codes, &files)
+            .unwrap();
+
+        assert_eq!(changed, engine(1).check(&TestConfig, &codes, &files));
+        let formatted = engine(1).format_all(&TestConfig, &codes);
+        for (file, expected) in files.iter().zip(formatted) {
+            assert_eq!(std::fs::read_to_string(file).unwrap(), expected);
+        }
+    }
+
+    /// Counts the sources it runs on.
+    struct CountingPass(Arc<AtomicUsize>);
+
+    impl Pass for CountingPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, _root: &Node, _source: &str) -> Vec<Edit> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn test_cache_skips_sources_seen_before() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let codes = sources();
+        let files: Vec<PathBuf> = (0..codes.len())
+            .map(|i| PathBuf::from(format!("file{i}.json")))
+            .collect();
+        let cached_engine = || {
+            let mut pipeline = Pipeline::new();
+            pipeline.add_pass(ColonSpacingPass);
+            pipeline.add_pass(CountingPass(Arc::clone(&runs)));
+            Engine::<JsonLanguage, TestConfig>::new(pipeline)
+                .with_jobs(0)
+                .with_cache(EngineCache::open(dir.path()).unwrap())
+        };
+
+        let mut first = cached_engine();
+        let expected = first.check(&TestConfig, &codes, &files);
+        first.save_cache().unwrap();
+        assert_eq!(runs.swap(0, Ordering::SeqCst), codes.len());
+
+        let mut second = cached_engine();
+        assert_eq!(second.check(&TestConfig, &codes, &files), expected);
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+        // Formatting reuses clean results only
+        let formatted = second.try_format_all(&TestConfig, &codes);
+        assert_eq!(runs.load(Ordering::SeqCst), expected.len());
+        let unformatted: Vec<Result<String, PassPanic>> = engine(1)
+            .format_all(&TestConfig, &codes)
+            .into_iter()
+            .map(Ok)
+            .collect();
+        assert_eq!(formatted, unformatted);
+    }
+
+    #[rstest]
+    #[case::parse_error_policy(|engine: Engine<JsonLanguage, TestConfig>| {
+        engine.with_parse_error_policy(ParseErrorPolicy::Skip)
+    })]
+    #[case::reparse_check(|engine: Engine<JsonLanguage, TestConfig>| {
+        engine.with_reparse_check(Severity::Warning)
+    })]
+    #[case::generated_markers(|engine: Engine<JsonLanguage, TestConfig>| {
+        engine.with_generated_markers(vec!["@generated".to_string()])
+    })]
+    fn test_cache_keys_depend_on_engine_options(
+        #[case] configure: fn(Engine<JsonLanguage, TestConfig>) -> Engine<JsonLanguage, TestConfig>,
+    ) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let codes = sources();
+        let cached_engine = || engine(1).with_cache(EngineCache::open(dir.path()).unwrap());
+
+        let keys = cached_engine().cache_keys(Configs::shared(&TestConfig), &codes);
+        let configured =
+            configure(cached_engine()).cache_keys(Configs::shared(&TestConfig), &codes);
+
+        assert!(keys.is_some());
+        assert_ne!(keys, configured);
+    }
+
+    #[test]
+    fn test_format_str_reports_changes_and_panics() {
+        let result = engine(1).format_str(&TestConfig, "{\"a\" :1}");
+        assert_eq!(result.formatted, "{\"a\": 1}");
+        assert!(result.changed);
+        assert!(result.diagnostics.is_empty());
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(ColonSpacingPass).add_pass(BoomPass);
+        let result = Engine::<JsonLanguage, TestConfig>::new(pipeline)
+            .format_str(&TestConfig, "{\"boom\" :1}");
+        assert_eq!(result.formatted, "{\"boom\" :1}");
+        assert!(!result.changed);
+        assert!(result.has_errors());
+        assert_eq!(result.diagnostics[0].pass, Some("BoomPass"));
+    }
+
+    #[test]
+    fn test_format_files_reports_outcomes() {
+        let codes = vec![
+            "{\"a\": 1}".to_string(),
+            "{\"boom\" :1}".to_string(),
+            "{\"c\" :1}".to_string(),
+        ];
+        let files: Vec<PathBuf> = ["a.json", "b.json", "c.json"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(BoomPass).add_pass(ColonSpacingPass);
+        let outcomes = Engine::<JsonLanguage, TestConfig>::new(pipeline)
+            .with_diffs()
+            .format_files(&TestConfig, &codes, &files);
+
+        assert!(!outcomes[0].changed);
+        assert_eq!(outcomes[0].diff, None);
+
+        assert!(outcomes[1].has_errors());
+        assert_eq!(outcomes[1].diagnostics[0].pass, Some("BoomPass"));
+
+        assert!(outcomes[2].changed);
+        assert_eq!(outcomes[2].path, files[2]);
+        assert_eq!(
+            outcomes[2].diff.as_deref(),
+            Some(
+                "--- a/c.json\n+++ b/c.json\n@@ -1,1 +1,1 @@\n\
+                 -{\"c\" :1}\n\\ No newline at end of file\n\
+                 +{\"c\": 1}\n\\ No newline at end of file\n"
+            )
+        );
+        assert!(outcomes[2].duration > Duration::ZERO);
+    }
+
+    #[rstest]
+    #[case::sequential(1)]
+    #[case::parallel(4)]
+    fn test_format_files_records_source_maps(#[case] jobs: usize) {
+        let codes = vec![
+            "{\"a\": 1}".to_string(),
+            "{\"b\" :1,\r\n\"c\" :2}".to_string(),
+        ];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+
+        let outcomes = engine(jobs)
+            .with_line_endings(LineEndings::Crlf)
+            .with_source_maps()
+            .format_files(&TestConfig, &codes, &files);
+
+        let expected = engine(1)
+            .with_line_endings(LineEndings::Crlf)
+            .format_with_source_map(&TestConfig, &codes[1]);
+        assert_eq!(outcomes[1].formatted.as_deref(), Ok(expected.0.as_str()));
+        assert_eq!(outcomes[1].source_map.as_ref(), Some(&expected.1));
+        assert_eq!(outcomes[0].source_map, Some(SourceMap::new(codes[0].len())));
+    }
+
+    /// Replaces the whole source twice, with overlapping edits.
+    struct OverlappingPass;
+
+    impl Pass for OverlappingPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, _root: &Node, source: &str) -> Vec<Edit> {
+            vec![
+                Edit {
+                    range: (0, source.len()),
+                    content: "[]".to_string(),
+                },
+                Edit {
+                    range: (1, 2),
+                    content: "x".to_string(),
+                },
+            ]
+        }
+    }
+
+    fn overlapping_engine(jobs: usize) -> Engine<JsonLanguage, TestConfig> {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(OverlappingPass)
+            .add_pass(ColonSpacingPass);
+        Engine::new(pipeline).with_jobs(jobs)
+    }
+
+    #[test]
+    fn test_overlapping_edits_are_rejected() {
+        let result = overlapping_engine(1).format_str(&TestConfig, "{\"a\" :1}");
+        assert_eq!(result.formatted, "{\"a\": 1}");
+        assert!(result.has_errors());
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].pass, Some("OverlappingPass"));
+        assert_eq!(result.diagnostics[0].range, Some((1, 2)));
+
+        let codes = vec!["{}".to_string(), "{\"b\" :1}".to_string()];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+        for jobs in [1, 4] {
+            let outcomes = overlapping_engine(jobs).format_files(&TestConfig, &codes, &files);
+            assert!(outcomes.iter().all(FileFormatOutcome::has_errors));
+            assert_eq!(outcomes[1].formatted, Ok("{\"b\": 1}".to_string()));
+        }
+    }
+
+    /// Removes the spaces around separators, undoing `ColonSpacingPass`.
+    struct TightColonPass;
+
+    impl Pass for TightColonPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, root: &Node, source: &str) -> Vec<Edit> {
+            separators(root, source)
+                .into_iter()
+                .filter(|(start, end)| &source[*start..*end] != ":")
+                .map(|range| Edit {
+                    range,
+                    content: ":".to_string(),
+                })
+                .collect()
+        }
+    }
+
+    #[rstest]
+    #[case(ConflictPolicy::Sequential, "{\"a\":1}", false)]
+    #[case(ConflictPolicy::FirstWins, "{\"a\": 1}", false)]
+    #[case(ConflictPolicy::LastWins, "{\"a\":1}", false)]
+    #[case(ConflictPolicy::Error, "{\"a\" :1}", true)]
+    fn test_conflict_policy_resolves_edits_of_different_passes(
+        #[case] policy: ConflictPolicy,
+        #[case] expected: &str,
+        #[case] has_errors: bool,
+    ) {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ColonSpacingPass)
+            .add_pass(TightColonPass)
+            .set_conflict_policy(policy);
+        let result =
+            Engine::<JsonLanguage, TestConfig>::new(pipeline).format_str(&TestConfig, "{\"a\" :1}");
+
+        assert_eq!(result.formatted, expected);
+        assert_eq!(result.has_errors(), has_errors);
+        if has_errors {
+            assert_eq!(result.diagnostics[0].pass, Some("TightColonPass"));
+        }
+    }
+
+    #[rstest]
+    #[case(ConflictPolicy::Sequential)]
+    #[case(ConflictPolicy::Error)]
+    fn test_phases_see_output_of_previous_phases(#[case] policy: ConflictPolicy) {
+        let mut spacing = Pipeline::new();
+        spacing
+            .add_pass(ColonSpacingPass)
+            .set_conflict_policy(policy);
+        let mut tight = Pipeline::new();
+        tight.add_pass(TightColonPass).set_conflict_policy(policy);
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_phase("spacing", spacing)
+            .add_phase("tight", tight);
+
+        let result =
+            Engine::<JsonLanguage, TestConfig>::new(pipeline).format_str(&TestConfig, "{\"a\" :1}");
+
+        assert_eq!(result.formatted, "{\"a\":1}");
+        assert!(!result.has_errors());
+    }
+
+    /// Drops the closing brace of the document.
+    struct UnbalancingPass;
+
+    impl Pass for UnbalancingPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, _root: &Node, source: &str) -> Vec<Edit> {
+            let end = source.trim_end().len();
+            if !source[..end].ends_with('}') {
+                return Vec::new();
+            }
+            vec![Edit {
+                range: (end - 1, end),
+                content: String::new(),
+            }]
+        }
+    }
+
+    fn unbalancing_engine(severity: Severity) -> Engine<JsonLanguage, TestConfig> {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ColonSpacingPass)
+            .add_pass(UnbalancingPass);
+        Engine::new(pipeline).with_reparse_check(severity)
+    }
+
+    #[test]
+    fn test_reparse_check_reports_new_syntax_errors() {
+        let source = "{\"a\" :1}";
+
+        let result = unbalancing_engine(Severity::Warning).format_str(&TestConfig, source);
+        assert_eq!(result.formatted, "{\"a\": 1");
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].severity, Severity::Warning);
+
+        let result = unbalancing_engine(Severity::Error).format_str(&TestConfig, source);
+        assert_eq!(result.formatted, source);
+        assert!(!result.changed);
+        assert!(result.has_errors());
+
+        let codes = vec![source.to_string(), "[1, 2]".to_string()];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+        let mut engine = unbalancing_engine(Severity::Error);
+        let outcomes = engine.format_files(&TestConfig, &codes, &files);
+        assert_eq!(outcomes[0].formatted, Ok(source.to_string()));
+        assert!(outcomes[0].has_errors());
+        assert!(outcomes[1].diagnostics.is_empty());
+        assert_eq!(
+            engine.find_first_unformatted(&TestConfig, &codes, &files),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reparse_check_ignores_existing_syntax_errors() {
+        // The trailing text is an error before and after formatting
+        let result = unbalancing_engine(Severity::Error).format_str(&TestConfig, "{\"a\" :1} x");
+        assert_eq!(result.formatted, "{\"a\": 1} x");
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[rstest]
+    #[case(ConflictPolicy::Sequential)]
+    #[case(ConflictPolicy::FirstWins)]
+    fn test_edit_check_rejects_edits_that_do_not_parse(#[case] policy: ConflictPolicy) {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ColonSpacingPass)
+            .add_pass(UnbalancingPass)
+            .set_conflict_policy(policy);
+        let mut engine: Engine<JsonLanguage, TestConfig> =
+            Engine::new(pipeline).with_edit_check(true);
+
+        let result = engine.format_str(&TestConfig, "{\"a\" :1}");
+        assert_eq!(result.formatted, "{\"a\": 1}");
+        assert_eq!(result.diagnostics.len(), 1);
+        assert!(result.diagnostics[0].is_error());
+        assert_eq!(result.diagnostics[0].pass, Some("UnbalancingPass"));
+    }
+
+    #[test]
+    fn test_incremental_check_finds_no_divergence_in_correct_passes() {
+        let codes = vec![
+            "{\"a\" :1,\n\"b\" :[2,\n3]}\n".to_string(),
+            "{\"c\" :\n{\"d\" :4}}".to_string(),
+        ];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+
+        for jobs in [1, 4] {
+            let outcomes =
+                engine(jobs)
+                    .with_incremental_check(true)
+                    .format_files(&TestConfig, &codes, &files);
+
+            let formatted: Vec<_> = outcomes
+                .iter()
+                .map(|outcome| outcome.formatted.clone().unwrap())
+                .collect();
+            assert_eq!(
+                formatted,
+                ["{\"a\": 1,\n\"b\": [2,\n3]}\n", "{\"c\": {\"d\": 4}}"]
+            );
+            assert!(outcomes
+                .iter()
+                .all(|outcome| outcome.diagnostics.is_empty()));
+        }
+    }
+
+    #[test]
+    fn test_incremental_check_attributes_divergence_to_the_batch() {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .set_conflict_policy(ConflictPolicy::FirstWins)
+            .add_pass(ColonSpacingPass)
+            .add_pass(BraceOnOwnLinePass);
+        let mut engine =
+            Engine::<JsonLanguage, TestConfig>::new(pipeline).with_incremental_check(true);
+        let mut state = ParseState::new("{\"a\" :[1,\n 2]}".to_string());
+        engine.parser.parse(&mut state);
+        // A line break inserted before `2` without telling tree-sitter
+        state.set_source("{\"a\" :[1,\n \n2]}".to_string());
+
+        for phase in engine.pipeline.phases() {
+            let recorder = engine.recorders.for_file(0);
+            run_passes_together(
+                &phase,
+                &mut engine.parser,
+                &TestConfig,
+                &mut state,
+                None,
+                None,
+                recorder,
+            );
+        }
+
+        assert_eq!(state.source(), "{\"a\": [1,\n \n2]\n}");
+        let diagnostics = engine.take_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].pass, None);
+        assert!(diagnostics[0]
+            .message
+            .contains("(edits of ColonSpacingPass, BraceOnOwnLinePass applied together)"));
+    }
+
+    #[rstest]
+    #[case(ParseErrorPolicy::Skip, "{\"a\" :1,}", 1)]
+    #[case(ParseErrorPolicy::Warn, "{\"a\": 1,}", 1)]
+    #[case(ParseErrorPolicy::Format, "{\"a\": 1,}", 0)]
+    fn test_parse_error_policy(
+        #[case] policy: ParseErrorPolicy,
+        #[case] expected: &str,
+        #[case] warnings: usize,
+    ) {
+        let codes = vec!["{\"a\" :1,}".to_string(), "{\"b\" :1}".to_string()];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+
+        for jobs in [1, 4] {
+            let mut engine = engine(jobs).with_parse_error_policy(policy);
+            let result = engine.format_str(&TestConfig, &codes[0]);
+            assert_eq!(result.formatted, expected);
+            assert_eq!(result.diagnostics.len(), warnings);
+            assert!(!result.has_errors());
+
+            let outcomes = engine.format_files(&TestConfig, &codes, &files);
+            assert_eq!(outcomes[0].formatted, Ok(expected.to_string()));
+            assert_eq!(outcomes[0].diagnostics.len(), warnings);
+            assert_eq!(outcomes[1].formatted, Ok("{\"b\": 1}".to_string()));
+        }
+    }
+
+    /// Puts the closing brace of the document on its own line.
+    struct BraceOnOwnLinePass;
+
+    impl Pass for BraceOnOwnLinePass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, _root: &Node, source: &str) -> Vec<Edit> {
+            match source.strip_suffix('}') {
+                Some(rest) if !rest.ends_with('\n') => vec![Edit {
+                    range: (rest.len(), rest.len()),
+                    content: "\n".to_string(),
+                }],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(LineEndings::Preserve, "{\r\n\"a\": 1\r\n}")]
+    #[case(LineEndings::Lf, "{\n\"a\": 1\n}")]
+    #[case(LineEndings::Crlf, "{\r\n\"a\": 1\r\n}")]
+    fn test_line_endings(#[case] endings: LineEndings, #[case] expected: &str) {
+        let source = "{\r\n\"a\" :1}";
+        let files = vec![PathBuf::from("a.json")];
+
+        for jobs in [1, 4] {
+            let mut pipeline = Pipeline::new();
+            pipeline
+                .add_pass(ColonSpacingPass)
+                .add_pass(BraceOnOwnLinePass);
+            let mut engine = Engine::<JsonLanguage, TestConfig>::new(pipeline)
+                .with_jobs(jobs)
+                .with_line_endings(endings);
+
+            assert_eq!(engine.format(&TestConfig, source), expected);
+            let outcomes = engine.format_files(&TestConfig, &[source.to_string()], &files);
+            assert_eq!(outcomes[0].formatted, Ok(expected.to_string()));
+            let clean = engine.find_first_unformatted(&TestConfig, &[expected.to_string()], &files);
+            assert_eq!(clean, None);
+        }
+    }
+
+    /// Cancels the run when it sees a source containing `stop`.
+    struct CancellingPass(CancellationToken);
+
+    impl Pass for CancellingPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, _root: &Node, source: &str) -> Vec<Edit> {
+            if source.contains("stop") {
+                self.0.cancel();
+            }
+            Vec::new()
+        }
+    }
+
+    fn cancelling_engine(token: &CancellationToken) -> Engine<JsonLanguage, TestConfig> {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ColonSpacingPass)
+            .add_pass(CancellingPass(token.clone()))
+            .add_pass(BraceOnOwnLinePass);
+        Engine::new(pipeline).with_cancellation(token.clone())
+    }
+
+    #[test]
+    fn test_cancellation_leaves_unfinished_sources_unchanged() {
+        let codes = vec![
+            "{\"a\" :1}".to_string(),
+            "{\"stop\" :1}".to_string(),
+            "{\"c\" :1}".to_string(),
+        ];
+        let files: Vec<PathBuf> = ["a.json", "b.json", "c.json"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let token = CancellationToken::new();
+
+        let outcomes = cancelling_engine(&token).format_files(&TestConfig, &codes, &files);
+
+        assert!(token.is_cancelled());
+        assert_eq!(outcomes[0].formatted, Ok("{\"a\": 1\n}".to_string()));
+        assert!(!outcomes[0].has_errors());
+        for outcome in &outcomes[1..] {
+            assert!(!outcome.changed);
+            assert_eq!(
+                outcome.diagnostics[0].message,
+                "formatting was cancelled; the source was left unchanged"
+            );
+        }
+    }
+
+    #[test]
+    fn test_cancellation_stops_single_sources_and_checks() {
+        let token = CancellationToken::new();
+        let mut engine = cancelling_engine(&token);
+
+        let result = engine.format_str(&TestConfig, "{\"stop\" :1}");
+        assert!(!result.changed);
+        assert!(result.diagnostics[0].is_error());
+
+        let codes = vec!["{\"a\" :1}".to_string()];
+        let first = engine.find_first_unformatted(&TestConfig, &codes, &[PathBuf::from("a.json")]);
+        assert_eq!(first, None);
+    }
+
+    #[test]
+    fn test_parse_timeout_leaves_sources_unchanged() {
+        let items: Vec<String> = (0..50_000).map(|i| format!("{{\"k\" :{i}}}")).collect();
+        let codes = vec![format!("[{}]", items.join(", ")), "{\"a\" :1}".to_string()];
+        let files: Vec<PathBuf> = ["big.json", "small.json"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(ColonSpacingPass);
+        let mut engine =
+            Engine::<JsonLanguage, TestConfig>::new(pipeline).with_parse_timeout(Duration::ZERO);
+
+        let outcomes = engine.format_files(&TestConfig, &codes, &files);
+
+        assert!(!outcomes[0].changed);
+        assert!(outcomes[0].has_errors());
+        assert_eq!(
+            outcomes[0].diagnostics[0].message,
+            "parsing took longer than 0 ms; the source was left unchanged"
+        );
+        // tree-sitter checks the time every few steps, so tiny sources finish
+        assert_eq!(outcomes[1].formatted, Ok("{\"a\": 1}".to_string()));
+    }
+
+    /// Runs after a pass that is never added.
+    struct OrphanPass;
+
+    impl Pass for OrphanPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &TestConfig, _root: &Node, _source: &str) -> Vec<Edit> {
+            Vec::new()
+        }
+
+        fn depends_on(&self) -> &'static [&'static str] {
+            &["MissingPass"]
+        }
+    }
+
+    #[test]
+    fn test_try_new_reports_invalid_pipelines() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(OrphanPass);
+
+        let err = Engine::<JsonLanguage, TestConfig>::try_new(pipeline)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            EngineError::Pipeline(PipelineError::UnknownDependency {
+                pass: "OrphanPass",
+                dependency: "MissingPass",
+            })
+        );
+    }
+
+    /// Records observer events as strings.
+    struct RecordingObserver(Arc<Mutex<Vec<String>>>);
+
+    impl EngineObserver for RecordingObserver {
+        fn on_file_start(&self, file: usize) {
+            self.0.lock().unwrap().push(format!("start {file}"));
+        }
+
+        fn on_file_done(&self, outcome: &FileFormatOutcome) {
+            let path = outcome.path.display();
+            self.0.lock().unwrap().push(format!("done {path}"));
+        }
+
+        fn on_pass_done(&self, file: usize, pass: &'static str, _: Duration, edit_count: usize) {
+            let event = format!("{pass} on {file}: {edit_count} edit(s)");
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn test_observer_receives_progress_events() {
+        let codes = vec!["{\"a\": 1}".to_string(), "{\"b\" :1, \"c\":2}".to_string()];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        engine(1)
+            .with_observer(RecordingObserver(Arc::clone(&events)))
+            .format_files(&TestConfig, &codes, &files);
+
+        // Files are reported as they finish, before the next one starts
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "start 0",
+                "ColonSpacingPass on 0: 0 edit(s)",
+                "done a.json",
+                "start 1",
+                "ColonSpacingPass on 1: 2 edit(s)",
+                "done b.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_observer_reports_every_file_in_parallel() {
+        let codes: Vec<String> = (0..8).map(|i| format!("{{\"k{i}\" :1}}")).collect();
+        let files: Vec<PathBuf> = (0..8).map(|i| PathBuf::from(format!("{i}.json"))).collect();
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let outcomes = engine(4)
+            .with_observer(RecordingObserver(Arc::clone(&events)))
+            .format_files(&TestConfig, &codes, &files);
+
+        let events = events.lock().unwrap();
+        let done: Vec<&String> = events.iter().filter(|e| e.starts_with("done")).collect();
+        assert_eq!(done.len(), outcomes.len());
+        // Every file is reported from its worker, after its own passes
+        for (i, file) in files.iter().enumerate() {
+            let pass_done = format!("ColonSpacingPass on {i}: 1 edit(s)");
+            let position = |event: &str| events.iter().position(|e| e == event).unwrap();
+            assert!(position(&pass_done) < position(&format!("done {}", file.display())));
+        }
+    }
+
+    #[test]
+    fn test_observer_is_notified_on_every_path() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = engine(1).with_observer(RecordingObserver(Arc::clone(&events)));
+        let codes = vec!["{\"a\" :1}".to_string()];
+        let files = vec![PathBuf::from("a.json")];
+
+        engine.format_str(&TestConfig, "{\"a\" :1}");
+        engine.format_all(&TestConfig, &codes);
+        engine.check(&TestConfig, &codes, &files);
+
+        let done: Vec<String> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.starts_with("done"))
+            .cloned()
+            .collect();
+        assert_eq!(done, ["done ", "done ", "done a.json"]);
+    }
+
+    #[test]
+    fn test_format_report_collects_the_run() {
+        let codes = vec!["{\"a\": 1}".to_string(), "{\"boom\" :1}".to_string()];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(BoomPass).add_pass(ColonSpacingPass);
+
+        let report = Engine::<JsonLanguage, TestConfig>::new(pipeline)
+            .with_timings()
+            .format_report(&TestConfig, &codes, &files);
+
+        assert_eq!(report.outcomes.len(), 2);
+        assert!(report.changed_paths().is_empty());
+        assert!(report.has_errors());
+        assert_eq!(report.errors[0].path, files[1]);
+        assert_eq!(report.metrics.cached, 0);
+        assert!(!report.metrics.timings.is_empty());
+    }
+
+    #[test]
+    fn test_format_report_measures_parses() {
+        let codes = vec!["[1, [2]]".to_string(), "[1 2]".to_string()];
+        let files: Vec<PathBuf> = ["a.json", "b.json"].iter().map(PathBuf::from).collect();
+
+        let report = engine(1).format_report(&TestConfig, &codes, &files);
+
+        let parses: Vec<ParseMetrics> = report
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.parse.unwrap())
+            .collect();
+        assert_eq!(
+            (parses[0].depth, parses[0].nodes, parses[0].errors),
+            (4, 10, 0)
+        );
+        assert_eq!(parses[1].errors, 1);
+        assert_eq!(
+            report.metrics.parse_duration,
+            parses[0].duration + parses[1].duration
+        );
+    }
+
+    /// Replaces the whole document with its separators normalized, like an external formatter.
+    struct WholeDocumentPass;
+
+    impl Pass for WholeDocumentPass {
+        type Config = TestConfig;
+
+        fn run(&self, _config: &Self::Config, _root: &Node, source: &str) -> Vec<Edit> {
+            vec![Edit {
+                range: (0, source.len()),
+                content: source.replace(" :", ": "),
+            }]
+        }
+    }
+
+    #[rstest]
+    #[case("{\"a\": 1,\n\"b\" :2}\n", "{\"a\": 1,\n\"b\": 2}\n")]
+    #[case("{\"a\" :1,\n\"b\" :2}\n", "{\"a\" :1,\n\"b\" :2}\n")]
+    fn test_format_range_narrows_edits_across_the_boundary(
+        #[case] source: &str,
+        #[case] expected: &str,
+    ) {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(WholeDocumentPass);
+        let mut engine = Engine::<JsonLanguage, TestConfig>::new(pipeline);
+        let range = LineRange::new(2, 2).unwrap().byte_range(source);
+
+        let result = engine.format_range(&TestConfig, source, range);
+
+        assert_eq!(result.formatted, expected);
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[rstest]
+    #[case((0, 6), "x", (0, 6), "x")]
+    #[case((0, 6), "abXdef", (2, 3), "X")]
+    #[case((0, 3), "aé", (1, 3), "é")]
+    #[case((2, 4), "cdcd", (4, 4), "cd")]
+    fn test_narrow_edit(
+        #[case] range: (usize, usize),
+        #[case] content: &str,
+        #[case] expected_range: (usize, usize),
+        #[case] expected_content: &str,
+    ) {
+        let narrowed = narrow_edit(
+            "abcdef",
+            Edit {
+                range,
+                content: content.to_string(),
+            },
+        );
+        assert_eq!(narrowed.range, expected_range);
+        assert_eq!(narrowed.content, expected_content);
+    }
+
+    #[test]
+    fn test_format_range_clamps_the_range() {
+        let source = "{\"é\" :1}";
+        let result = engine(1).format_range(&TestConfig, source, (3, 100));
+
+        assert_eq!(result.formatted, "{\"é\": 1}");
     }
 }
@@ -0,0 +1,187 @@
+use crate::core::{fnv1a_64, FileWriter};
+use log::debug;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Name of the cache file inside the cache directory.
+const CACHE_FILE: &str = "engine-cache-v1";
+
+/// Identifies the result of formatting one source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    content: u64,
+    config: u64,
+    pipeline: u64,
+}
+
+impl CacheKey {
+    /// Create the key of formatting `content` with the hashed config and pipeline.
+    pub(crate) fn new(content: &str, config: u64, pipeline: u64) -> Self {
+        Self {
+            content: fnv1a_64(content.as_bytes()),
+            config,
+            pipeline,
+        }
+    }
+}
+
+/// Remembers which sources were already formatted, across runs.
+///
+/// Results are keyed by the hashes of the source, the config, and the
+/// pipeline fingerprint, so any change to one of them misses the cache.
+/// Only the entries looked up or recorded since the cache was opened are
+/// saved, which drops results of deleted or edited files.
+///
+/// # Examples
+/// ```no_run
+/// use fmt_runner::EngineCache;
+///
+/// let cache = EngineCache::open(".fmt-cache").unwrap();
+/// // engine = engine.with_cache(cache); ... engine.save_cache()
+/// ```
+#[derive(Debug)]
+pub struct EngineCache {
+    path: PathBuf,
+    previous: HashMap<CacheKey, bool>,
+    current: Mutex<HashMap<CacheKey, bool>>,
+}
+
+impl EngineCache {
+    /// Open the cache stored in `dir`, starting empty if there is none.
+    ///
+    /// Unreadable entries are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if the cache file exists but cannot be read
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let path = dir.as_ref().join(CACHE_FILE);
+        let previous = match fs::read_to_string(&path) {
+            Ok(text) => text.lines().filter_map(parse_entry).collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        debug!(
+            "Loaded {} cache entries from {}",
+            previous.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            path,
+            previous,
+            current: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Get whether the source with `key` needed formatting, if known.
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<bool> {
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(changed) = current.get(key) {
+            return Some(*changed);
+        }
+        let changed = *self.previous.get(key)?;
+        current.insert(*key, changed);
+        Some(changed)
+    }
+
+    /// Record whether the source with `key` needed formatting.
+    pub(crate) fn insert(&self, key: CacheKey, changed: bool) {
+        self.current
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, changed);
+    }
+
+    /// Write the entries used since opening back to the cache directory.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created or the cache
+    /// file cannot be written
+    pub fn save(&self) -> io::Result<()> {
+        let current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut lines: Vec<String> = current
+            .iter()
+            .map(|(key, changed)| {
+                let status = if *changed { "dirty" } else { "clean" };
+                format!(
+                    "{:016x} {:016x} {:016x} {status}",
+                    key.content, key.config, key.pipeline
+                )
+            })
+            .collect();
+        lines.sort_unstable();
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        FileWriter::new().write(&self.path, &text)
+    }
+}
+
+/// Parse a `content config pipeline clean|dirty` line of the cache file.
+fn parse_entry(line: &str) -> Option<(CacheKey, bool)> {
+    let mut fields = line.split_whitespace();
+    let mut hash = || u64::from_str_radix(fields.next()?, 16).ok();
+    let key = CacheKey {
+        content: hash()?,
+        config: hash()?,
+        pipeline: hash()?,
+    };
+    let changed = match fields.next()? {
+        "clean" => false,
+        "dirty" => true,
+        _ => return None,
+    };
+    Some((key, changed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_saved_entries_survive_reopen() {
+        let dir = TempDir::new().unwrap();
+        let clean = CacheKey::new("{}", 1, 2);
+        let dirty = CacheKey::new("{ }", 1, 2);
+
+        let cache = EngineCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get(&clean), None);
+        cache.insert(clean, false);
+        cache.insert(dirty, true);
+        cache.save().unwrap();
+
+        let cache = EngineCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get(&clean), Some(false));
+        assert_eq!(cache.get(&CacheKey::new("{}", 1, 3)), None);
+        cache.save().unwrap();
+
+        // Only the entry looked up since reopening is kept
+        let cache = EngineCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get(&clean), Some(false));
+        assert_eq!(cache.get(&dirty), None);
+    }
+
+    #[test]
+    fn test_malformed_lines_are_ignored() {
+        let dir = TempDir::new().unwrap();
+        let key = CacheKey::new("x", 0, 0);
+        fs::write(
+            dir.path().join(CACHE_FILE),
+            format!(
+                "garbage\n{:016x} {:016x} {:016x} clean\n",
+                key.content, key.config, key.pipeline
+            ),
+        )
+        .unwrap();
+
+        let cache = EngineCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get(&key), Some(false));
+    }
+}
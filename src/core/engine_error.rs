@@ -0,0 +1,15 @@
+use crate::parser::GrammarError;
+use crate::pipeline::PipelineError;
+use thiserror::Error;
+
+/// Error creating an engine.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EngineError {
+    /// The passes of the pipeline cannot be put in order
+    #[error(transparent)]
+    Pipeline(#[from] PipelineError),
+
+    /// The linked tree-sitter runtime cannot load the grammar of the language
+    #[error(transparent)]
+    Grammar(#[from] GrammarError),
+}
@@ -0,0 +1,59 @@
+use crate::core::FileFormatOutcome;
+use std::time::Duration;
+
+/// Receives progress events from an engine.
+///
+/// Sources are identified by their index in the batch, like in
+/// `PassTiming`; single-source methods use index `0`. In parallel mode the
+/// methods are called from worker threads, so events of different sources
+/// interleave. Every method does nothing by default.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::{EngineObserver, FileFormatOutcome};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// #[derive(Default)]
+/// struct Progress {
+///     done: AtomicUsize,
+/// }
+///
+/// impl EngineObserver for Progress {
+///     fn on_file_done(&self, outcome: &FileFormatOutcome) {
+///         let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+///         eprintln!("[{done}] {}", outcome.path.display());
+///     }
+/// }
+/// ```
+pub trait EngineObserver: Send + Sync {
+    /// Called before the passes run on the source at index `file`.
+    ///
+    /// Not called for sources whose result is taken from the cache.
+    fn on_file_start(&self, _file: usize) {}
+
+    /// Called with the final outcome of every source as soon as it is finished.
+    ///
+    /// In parallel mode the call comes from the worker thread that finished
+    /// the source, so the order of completion is unspecified. Sources whose
+    /// result is taken from the cache, or that disable formatting, are
+    /// reported first. Sources without a path, like those of `format_str`,
+    /// have an empty `path`. `find_first_unformatted` reports no outcomes,
+    /// as it stops at the first source that needs formatting.
+    fn on_file_done(&self, _outcome: &FileFormatOutcome) {}
+
+    /// Called after `pass` ran on the source at index `file`.
+    ///
+    /// # Arguments
+    /// * `file` - Index of the source in the batch
+    /// * `pass` - Name of the pass
+    /// * `duration` - Time spent running the pass and applying its edits
+    /// * `edit_count` - Number of edits the pass produced, before validation
+    fn on_pass_done(
+        &self,
+        _file: usize,
+        _pass: &'static str,
+        _duration: Duration,
+        _edit_count: usize,
+    ) {
+    }
+}
@@ -0,0 +1,282 @@
+use crate::core::Encoding;
+use log::debug;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Tells apart the temporary files of concurrent writes in one process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Writes formatted files atomically, optionally keeping a backup.
+///
+/// Contents are written to a temporary file in the target's directory and
+/// then renamed over the target, so an interrupted write never leaves a
+/// truncated file behind. The temporary file takes over the permissions
+/// of the file it replaces and, on Unix, its owner and group where the
+/// process is allowed to set them. Writing to a symlink replaces its
+/// target and keeps the link.
+///
+/// # Examples
+/// ```no_run
+/// use fmt_runner::FileWriter;
+/// use std::path::Path;
+///
+/// let writer = FileWriter::new().with_backup(".bak");
+/// writer.write(Path::new("src/main.x"), "formatted").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FileWriter {
+    backup_suffix: Option<String>,
+}
+
+impl FileWriter {
+    /// Create a writer that does not keep backups.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the original of every written file next to it.
+    ///
+    /// # Arguments
+    /// * `suffix` - Appended to the file name of the original (e.g. `.bak`)
+    #[must_use]
+    pub fn with_backup(mut self, suffix: impl Into<String>) -> Self {
+        self.backup_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Atomically replace the contents of `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the temporary file cannot be written, the backup
+    /// cannot be created, or the rename fails. The target is left untouched
+    /// in all of these cases.
+    pub fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.write_bytes(path, contents.as_bytes())
+    }
+
+    /// Atomically replace the contents of `path` with `contents` in `encoding`.
+    ///
+    /// # Errors
+    /// Like `write`, and if `contents` cannot be represented in `encoding`.
+    pub fn write_encoded(&self, path: &Path, contents: &str, encoding: Encoding) -> io::Result<()> {
+        self.write_bytes(path, &encoding.encode(contents)?)
+    }
+
+    /// Atomically replace the contents of `path` with raw bytes.
+    fn write_bytes(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let is_symlink = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        let target = if is_symlink {
+            fs::canonicalize(path)?
+        } else {
+            path.to_path_buf()
+        };
+        let path = target.as_path();
+        let (temp_file, temp_path) = create_temp(path)?;
+
+        let result = fill_temp(temp_file, path, contents).and_then(|()| {
+            if let Some(suffix) = &self.backup_suffix {
+                let backup_path = sibling_path(path, "", suffix);
+                debug!("Backing up {} to {}", path.display(), backup_path.display());
+                fs::copy(path, backup_path)?;
+            }
+            fs::rename(&temp_path, path)
+        });
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+}
+
+/// Create a temporary file next to `path` under a name no other write uses.
+///
+/// The name carries the process id and a counter, so a leftover from an
+/// interrupted run is skipped instead of failing the write.
+fn create_temp(path: &Path) -> io::Result<(File, PathBuf)> {
+    loop {
+        let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let suffix = format!(".{}.{count}.tmp", process::id());
+        let temp_path = sibling_path(path, ".", &suffix);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((file, temp_path)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Write `contents` to the temporary file, copying the permissions and ownership of `path`.
+fn fill_temp(mut file: File, path: &Path, contents: &[u8]) -> io::Result<()> {
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    if let Ok(metadata) = fs::metadata(path) {
+        copy_ownership(&file, path, &metadata);
+        // Set after the owner, since changing it can clear the setuid and setgid bits
+        file.set_permissions(metadata.permissions())?;
+    }
+    Ok(())
+}
+
+/// Give the temporary file the owner and group of the original.
+///
+/// Unprivileged processes can usually only keep the group, or nothing if
+/// the file belongs to another user; the file is still written then.
+#[cfg(unix)]
+fn copy_ownership(file: &File, path: &Path, metadata: &fs::Metadata) {
+    use std::os::unix::fs::{fchown, MetadataExt};
+
+    let Ok(temp) = file.metadata() else {
+        return;
+    };
+    if (temp.uid(), temp.gid()) == (metadata.uid(), metadata.gid()) {
+        return;
+    }
+    if fchown(file, Some(metadata.uid()), Some(metadata.gid())).is_err()
+        && fchown(file, None, Some(metadata.gid())).is_err()
+    {
+        debug!("Could not keep the owner of {}", path.display());
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_file: &File, _path: &Path, _metadata: &fs::Metadata) {}
+
+/// Build a path in the same directory as `path` with a decorated file name.
+fn sibling_path(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_replaces_contents_without_leftovers() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "old").unwrap();
+
+        FileWriter::new().write(&path, "new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_keeps_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "old").unwrap();
+
+        FileWriter::new()
+            .with_backup(".bak")
+            .write(&path, "new")
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt.bak")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn test_failed_write_leaves_target_untouched() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "old").unwrap();
+        // A directory in place of the backup makes the copy fail
+        fs::create_dir(dir.path().join("a.txt.bak")).unwrap();
+
+        assert!(FileWriter::new()
+            .with_backup(".bak")
+            .write(&path, "new")
+            .is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_write_succeeds_over_stale_temp_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        let stale = dir.path().join(".a.txt.tmp");
+        fs::write(&path, "old").unwrap();
+        fs::write(&stale, "stale").unwrap();
+
+        FileWriter::new().write(&path, "new").unwrap();
+        FileWriter::new().write(&path, "newer").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "newer");
+        assert_eq!(fs::read_to_string(&stale).unwrap(), "stale");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("run.sh");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        FileWriter::new().write(&path, "new").unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_preserves_ownership() {
+        use std::os::unix::fs::{chown, MetadataExt};
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("owned.txt");
+        fs::write(&path, "old").unwrap();
+        if chown(&path, Some(4321), Some(4321)).is_err() {
+            // Only privileged processes can hand files to other users
+            return;
+        }
+
+        FileWriter::new().write(&path, "new").unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        assert_eq!((metadata.uid(), metadata.gid()), (4321, 4321));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_through_symlink_keeps_the_link() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("a.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        FileWriter::new().write(&link, "new").unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+    }
+}
@@ -0,0 +1,31 @@
+/// Initial state of a 64-bit FNV-1a hash.
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64-bit FNV-1a hash.
+///
+/// Unlike `std::hash`, the result does not depend on the Rust version or
+/// process, so it can be persisted.
+pub(crate) fn fnv1a_64(bytes: &[u8]) -> u64 {
+    fnv1a_64_extend(FNV_OFFSET_BASIS, bytes)
+}
+
+/// Continue the 64-bit FNV-1a hash `hash` with more bytes.
+pub(crate) fn fnv1a_64_extend(hash: u64, bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_known_values() {
+        assert_eq!(fnv1a_64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a_64(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a_64_extend(fnv1a_64(b"a"), b"b"), fnv1a_64(b"ab"));
+    }
+}
@@ -0,0 +1,322 @@
+use crate::core::{Diagnostic, Engine, FileFormatOutcome, FormatResult};
+use crate::parser::LanguageProvider;
+use crate::supported_extension::FileMatcher;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// An engine with its language erased, so engines of several languages
+/// sharing a config type can be kept together.
+trait LanguageEngine<Config> {
+    fn format_str(&mut self, config: &Config, source: &str) -> FormatResult;
+
+    fn format_files(
+        &mut self,
+        config: &Config,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> Vec<FileFormatOutcome>;
+}
+
+impl<Language, Config> LanguageEngine<Config> for Engine<Language, Config>
+where
+    Language: LanguageProvider,
+    Config: Serialize + Sync,
+{
+    fn format_str(&mut self, config: &Config, source: &str) -> FormatResult {
+        Engine::format_str(self, config, source)
+    }
+
+    fn format_files(
+        &mut self,
+        config: &Config,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> Vec<FileFormatOutcome> {
+        Engine::format_files(self, config, codes, files)
+    }
+}
+
+/// A language of a registry, with the engine formatting its files.
+struct RegisteredLanguage<Config> {
+    name: &'static str,
+    files: &'static FileMatcher,
+    engine: Box<dyn LanguageEngine<Config>>,
+}
+
+/// Engines of several languages, dispatching each file to the language matching it.
+///
+/// A single binary can format a template language and its host language,
+/// or any other set of languages, each with its own pipeline. All engines
+/// share the formatter's config type. When the extensions of languages
+/// overlap, the language registered first wins.
+///
+/// Registries are used from library code; `CliBuilder` runs a single
+/// language and is out of scope for them.
+///
+/// # Examples
+/// ```ignore
+/// let mut registry = LanguageRegistry::new();
+/// registry
+///     .register("json", Engine::<Json, Config>::new(json_pipeline))
+///     .register("jsonc", Engine::<Jsonc, Config>::new(jsonc_pipeline));
+///
+/// let (files, _) = FileCollector::default().collect_registered(&registry, &paths);
+/// let outcomes = registry.format_files(&config, &codes, &files);
+/// ```
+pub struct LanguageRegistry<Config> {
+    languages: Vec<RegisteredLanguage<Config>>,
+}
+
+impl<Config> LanguageRegistry<Config> {
+    /// Create a registry without languages.
+    pub fn new() -> Self {
+        Self {
+            languages: Vec::new(),
+        }
+    }
+
+    /// Register a language with the engine formatting its files.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the language, as reported by `language_of`
+    /// * `engine` - Engine for the language, configured as needed
+    pub fn register<Language>(
+        &mut self,
+        name: &'static str,
+        engine: Engine<Language, Config>,
+    ) -> &mut Self
+    where
+        Language: LanguageProvider + 'static,
+        Config: Serialize + Sync + 'static,
+    {
+        self.languages.push(RegisteredLanguage {
+            name,
+            files: Language::supported_extension(),
+            engine: Box::new(engine),
+        });
+        self
+    }
+
+    /// Get the names of the registered languages, in registration order.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.languages
+            .iter()
+            .map(|language| language.name)
+            .collect()
+    }
+
+    /// Get the name of the language formatting `path`, if any.
+    ///
+    /// Files without extension may be read to find their shebang line.
+    pub fn language_of(&self, path: &Path) -> Option<&'static str> {
+        self.languages
+            .iter()
+            .find(|language| language.files.matches_file(path))
+            .map(|language| language.name)
+    }
+
+    /// Returns true if a registered language formats `path`.
+    ///
+    /// Files without extension may be read to find their shebang line.
+    pub fn matches(&self, path: &Path) -> bool {
+        self.language_of(path).is_some()
+    }
+
+    /// Format a single source with the language of `path`.
+    ///
+    /// # Returns
+    /// The result of `Engine::format_str`, or `None` if no language formats `path`
+    pub fn format_str(
+        &mut self,
+        config: &Config,
+        path: &Path,
+        source: &str,
+    ) -> Option<FormatResult> {
+        let i = self.position(path, source)?;
+        Some(self.languages[i].engine.format_str(config, source))
+    }
+
+    /// Format many files, each with the engine of its language.
+    ///
+    /// The files of each language are formatted together, so consistency and
+    /// project passes see every file of their language. Files no language
+    /// formats are left unchanged and get an error diagnostic.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to pass to formatting passes
+    /// * `codes` - Source code contents of the files
+    /// * `files` - File paths corresponding to the source codes
+    ///
+    /// # Returns
+    /// The outcome of every file, in input order
+    pub fn format_files(
+        &mut self,
+        config: &Config,
+        codes: &[String],
+        files: &[PathBuf],
+    ) -> Vec<FileFormatOutcome> {
+        let mut outcomes: Vec<Option<FileFormatOutcome>> = vec![None; files.len()];
+        let mut selected: Vec<Vec<usize>> = vec![Vec::new(); self.languages.len()];
+        for (i, file) in files.iter().enumerate() {
+            match self.position(file, &codes[i]) {
+                Some(language) => selected[language].push(i),
+                None => {
+                    let mut outcome =
+                        FileFormatOutcome::new(file.clone(), &codes[i], Ok(codes[i].clone()));
+                    outcome.diagnostics.push(Diagnostic::error(format!(
+                        "no language is registered for {}",
+                        file.display()
+                    )));
+                    outcomes[i] = Some(outcome);
+                }
+            }
+        }
+
+        for (language, indices) in self.languages.iter_mut().zip(selected) {
+            if indices.is_empty() {
+                continue;
+            }
+            let language_codes: Vec<String> = indices.iter().map(|&i| codes[i].clone()).collect();
+            let language_files: Vec<PathBuf> = indices.iter().map(|&i| files[i].clone()).collect();
+            let formatted = language
+                .engine
+                .format_files(config, &language_codes, &language_files);
+            for (i, outcome) in indices.into_iter().zip(formatted) {
+                outcomes[i] = Some(outcome);
+            }
+        }
+
+        outcomes.into_iter().flatten().collect()
+    }
+
+    /// Find the index of the first language formatting `path`, whose content is `source`.
+    fn position(&self, path: &Path, source: &str) -> Option<usize> {
+        self.languages
+            .iter()
+            .position(|language| language.files.matches_source(path, source))
+    }
+}
+
+impl<Config> Default for LanguageRegistry<Config> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{Edit, Pass, Pipeline};
+    use crate::supported_extension::SupportedExtension;
+    use serde::Deserialize;
+    use tree_sitter::Node;
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct TestConfig;
+
+    struct Json;
+
+    impl LanguageProvider for Json {
+        fn language() -> tree_sitter::Language {
+            tree_sitter_json::LANGUAGE.into()
+        }
+
+        fn supported_extension() -> &'static SupportedExtension {
+            static EXTENSIONS: SupportedExtension = SupportedExtension::new(&["json"]);
+            &EXTENSIONS
+        }
+    }
+
+    /// JSON with comments, formatted by a different pipeline.
+    struct Jsonc;
+
+    impl LanguageProvider for Jsonc {
+        fn language() -> tree_sitter::Language {
+            tree_sitter_json::LANGUAGE.into()
+        }
+
+        fn supported_extension() -> &'static SupportedExtension {
+            static EXTENSIONS: SupportedExtension = SupportedExtension::new(&["jsonc", "json"]);
+            &EXTENSIONS
+        }
+    }
+
+    /// Appends a marker to the source.
+    struct MarkPass(&'static str);
+
+    impl Pass for MarkPass {
+        type Config = TestConfig;
+
+        fn name(&self) -> &'static str {
+            "MarkPass"
+        }
+
+        fn run(&self, _: &TestConfig, _: &Node, source: &str) -> Vec<Edit> {
+            if source.ends_with(self.0) {
+                return Vec::new();
+            }
+            vec![Edit {
+                range: (source.len(), source.len()),
+                content: self.0.to_string(),
+            }]
+        }
+    }
+
+    fn registry() -> LanguageRegistry<TestConfig> {
+        let engine = |mark| {
+            let mut pipeline = Pipeline::new();
+            pipeline.add_pass(MarkPass(mark));
+            pipeline
+        };
+        let mut registry = LanguageRegistry::new();
+        registry
+            .register("json", Engine::<Json, TestConfig>::new(engine(" // json")))
+            .register(
+                "jsonc",
+                Engine::<Jsonc, TestConfig>::new(engine(" // jsonc")),
+            );
+        registry
+    }
+
+    #[test]
+    fn test_language_of_prefers_first_registered() {
+        let registry = registry();
+        assert_eq!(registry.names(), ["json", "jsonc"]);
+        assert_eq!(registry.language_of(Path::new("a.json")), Some("json"));
+        assert_eq!(registry.language_of(Path::new("a.JSONC")), Some("jsonc"));
+        assert!(!registry.matches(Path::new("a.yaml")));
+    }
+
+    #[test]
+    fn test_format_files_dispatches_by_language() {
+        let mut registry = registry();
+        let files: Vec<PathBuf> = ["a.jsonc", "b.json", "c.txt", "d.jsonc"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let codes = vec!["1".to_string(); files.len()];
+
+        let outcomes = registry.format_files(&TestConfig, &codes, &files);
+
+        let formatted: Vec<_> = outcomes
+            .iter()
+            .map(|outcome| (outcome.path.clone(), outcome.formatted.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            formatted,
+            [
+                (files[0].clone(), "1 // jsonc".to_string()),
+                (files[1].clone(), "1 // json".to_string()),
+                (files[2].clone(), "1".to_string()),
+                (files[3].clone(), "1 // jsonc".to_string()),
+            ]
+        );
+        assert!(outcomes[2].has_errors());
+
+        let result = registry.format_str(&TestConfig, Path::new("x.json"), "2");
+        assert_eq!(result.unwrap().formatted, "2 // json");
+        assert!(registry
+            .format_str(&TestConfig, Path::new("x.txt"), "2")
+            .is_none());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::parser::ParseState;
+use crate::pipeline::SourceMap;
+use serde::{Deserialize, Serialize};
+
+/// Which line endings formatted sources get.
+///
+/// Passes always see sources with `\n` line endings, so text they insert
+/// matches the rest of the source; the chosen endings are applied to the
+/// result.
+///
+/// # Examples
+/// ```yaml
+/// runner:
+///   line_endings: lf
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndings {
+    /// Keep the line endings of each source, as detected from its first line
+    #[default]
+    Preserve,
+    /// Use `\n`
+    Lf,
+    /// Use `\r\n`
+    Crlf,
+}
+
+impl LineEndings {
+    const PRESERVE: &'static str = "preserve";
+    const LF: &'static str = "lf";
+    const CRLF: &'static str = "crlf";
+
+    /// Get the string representation of the line endings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEndings::Preserve => Self::PRESERVE,
+            LineEndings::Lf => Self::LF,
+            LineEndings::Crlf => Self::CRLF,
+        }
+    }
+
+    /// Get the line ending the formatted version of `source` should use.
+    pub(crate) fn target(&self, source: &str) -> LineEnding {
+        match self {
+            LineEndings::Preserve => LineEnding::detect(source),
+            LineEndings::Lf => LineEnding::Lf,
+            LineEndings::Crlf => LineEnding::Crlf,
+        }
+    }
+}
+
+/// A concrete line ending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Detect the line ending of `text` from its first line; `Lf` if it has a single line.
+    pub(crate) fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(i) if text[..i].ends_with('\r') => LineEnding::Crlf,
+            _ => LineEnding::Lf,
+        }
+    }
+}
+
+/// Convert the line endings of the state's source to `ending`.
+///
+/// Converted line endings are recorded in `source_map` like edits, and
+/// with a `range` only line endings entirely within it are converted,
+/// moving its end accordingly. The tree no longer matches the source
+/// afterwards and is dropped if anything changed.
+pub(crate) fn convert_line_endings(
+    state: &mut ParseState,
+    ending: LineEnding,
+    source_map: Option<&mut SourceMap>,
+    range: Option<&mut (usize, usize)>,
+) {
+    let (from, to) = match ending {
+        LineEnding::Lf => ("\r\n", "\n"),
+        LineEnding::Crlf => ("\n", "\r\n"),
+    };
+    let source = state.source();
+    let (start, end) = range.as_deref().copied().unwrap_or((0, source.len()));
+
+    // Offsets of the line endings to convert
+    let positions: Vec<usize> = source[start..end]
+        .match_indices(from)
+        .map(|(i, _)| start + i)
+        .filter(|&i| ending == LineEnding::Lf || i == 0 || source.as_bytes()[i - 1] != b'\r')
+        .collect();
+    if positions.is_empty() {
+        return;
+    }
+
+    let mut converted = String::with_capacity(source.len() + positions.len());
+    let mut copied = 0;
+    for &i in &positions {
+        converted.push_str(&source[copied..i]);
+        converted.push_str(to);
+        copied = i + from.len();
+    }
+    converted.push_str(&source[copied..]);
+
+    if let Some(map) = source_map {
+        for &i in positions.iter().rev() {
+            map.record_edit(i, i + from.len(), to.len());
+        }
+    }
+    if let Some((_, end)) = range {
+        *end = *end + positions.len() * to.len() - positions.len() * from.len();
+    }
+    *state = ParseState::new(converted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("a\r\nb\nc", LineEnding::Crlf)]
+    #[case("a\nb\r\nc", LineEnding::Lf)]
+    #[case("a", LineEnding::Lf)]
+    fn test_detect(#[case] text: &str, #[case] expected: LineEnding) {
+        assert_eq!(LineEnding::detect(text), expected);
+    }
+
+    #[rstest]
+    #[case("a\r\nb\nc\r\n", LineEnding::Lf, "a\nb\nc\n")]
+    #[case("a\r\nb\nc\r\n", LineEnding::Crlf, "a\r\nb\r\nc\r\n")]
+    #[case("\na\r\n", LineEnding::Crlf, "\r\na\r\n")]
+    fn test_convert_line_endings(
+        #[case] text: &str,
+        #[case] ending: LineEnding,
+        #[case] expected: &str,
+    ) {
+        let mut state = ParseState::new(text.to_string());
+        convert_line_endings(&mut state, ending, None, None);
+        assert_eq!(state.source(), expected);
+    }
+
+    #[test]
+    fn test_convert_line_endings_within_range() {
+        let text = "a\r\nb\r\nc\r\nd";
+        let mut state = ParseState::new(text.to_string());
+        let mut source_map = SourceMap::new(text.len());
+        let mut range = (3, 9);
+
+        convert_line_endings(
+            &mut state,
+            LineEnding::Lf,
+            Some(&mut source_map),
+            Some(&mut range),
+        );
+
+        assert_eq!(state.source(), "a\r\nb\nc\nd");
+        assert_eq!(range, (3, 7));
+        assert_eq!(source_map.map_offset(9), 7);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::core::PositionEncoding;
+use tree_sitter::Point;
+
+/// A 0-based line and column in a text; the column counts bytes.
+///
+/// This is how tree-sitter counts positions, and converts to and from
+/// `tree_sitter::Point`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Position {
+    /// 0-based line
+    pub line: usize,
+    /// 0-based column, in bytes from the start of the line
+    pub column: usize,
+}
+
+impl Position {
+    /// Create a position.
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+impl From<Point> for Position {
+    fn from(point: Point) -> Self {
+        Self::new(point.row, point.column)
+    }
+}
+
+impl From<Position> for Point {
+    fn from(position: Position) -> Self {
+        Point {
+            row: position.line,
+            column: position.column,
+        }
+    }
+}
+
+/// Start offsets of the lines of a source, for converting between byte
+/// offsets and line/column positions.
+///
+/// Lines break after every `\n`, so a `\r` before it ends its line. Build
+/// the index once per source and keep it in step with edits through
+/// `apply_edit` instead of indexing the whole source again.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::{LineIndex, Position};
+///
+/// let source = "{\n  \"a\": 1\n}\n";
+/// let lines = LineIndex::new(source);
+/// assert_eq!(lines.byte_to_position(4), Position::new(1, 2));
+/// assert_eq!(lines.position_to_byte(Position::new(2, 0)), Some(11));
+/// assert_eq!(lines.line(source, 1), Some("  \"a\": 1"));
+/// ```
+///
+/// Columns can also be counted in UTF-16 code units or characters:
+/// ```
+/// use fmt_runner::{LineIndex, Position, PositionEncoding};
+///
+/// let source = "\"é\": 1";
+/// let lines = LineIndex::new(source);
+/// let utf16 = lines.encode_position(source, 5, PositionEncoding::Utf16);
+/// assert_eq!(utf16, Position::new(0, 4));
+/// assert_eq!(lines.decode_position(source, utf16, PositionEncoding::Utf16), Some(5));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    /// Index the lines of `source`.
+    pub fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0).chain(line_breaks(source, 0)).collect();
+        Self {
+            line_starts,
+            len: source.len(),
+        }
+    }
+
+    /// Get the number of lines; text after the last line break counts as a
+    /// line even if it is empty.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Get the length in bytes of the indexed source.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the indexed source is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Convert a byte offset to a position.
+    ///
+    /// Offsets past the end of the source are clamped to it.
+    pub fn byte_to_position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        Position::new(line, offset - self.line_starts[line])
+    }
+
+    /// Convert a position to a byte offset.
+    ///
+    /// # Returns
+    /// The offset, or `None` if the line does not exist or the column is
+    /// past its line break
+    pub fn position_to_byte(&self, position: Position) -> Option<usize> {
+        let (start, end) = self.line_range(position.line)?;
+        let offset = start + position.column;
+        let last_line = position.line + 1 == self.line_count();
+        (offset < end || (last_line && offset == end)).then_some(offset)
+    }
+
+    /// Get the byte range of a 0-based line, including its line break.
+    ///
+    /// # Returns
+    /// The range, or `None` if the line does not exist
+    pub fn line_range(&self, line: usize) -> Option<(usize, usize)> {
+        let start = *self.line_starts.get(line)?;
+        let end = self.line_starts.get(line + 1).copied().unwrap_or(self.len);
+        Some((start, end))
+    }
+
+    /// Get the text of a 0-based line of `source`, without its line break.
+    ///
+    /// `source` must be the text the index was built for.
+    pub fn line<'a>(&self, source: &'a str, line: usize) -> Option<&'a str> {
+        let (start, end) = self.line_range(line)?;
+        let text = source.get(start..end)?;
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// Convert a byte offset of `source` to a position whose column is
+    /// counted in units of `encoding`.
+    ///
+    /// `source` must be the text the index was built for. Offsets inside a
+    /// character are moved back to its start.
+    pub fn encode_position(
+        &self,
+        source: &str,
+        offset: usize,
+        encoding: PositionEncoding,
+    ) -> Position {
+        let mut offset = offset.min(self.len);
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let position = self.byte_to_position(offset);
+        let start = offset - position.column;
+        Position::new(position.line, encoding.len(&source[start..offset]))
+    }
+
+    /// Convert a position whose column is counted in units of `encoding`
+    /// to a byte offset of `source`.
+    ///
+    /// `source` must be the text the index was built for.
+    ///
+    /// # Returns
+    /// The offset, or `None` if the line does not exist, or the column is
+    /// past its line break or inside a character
+    pub fn decode_position(
+        &self,
+        source: &str,
+        position: Position,
+        encoding: PositionEncoding,
+    ) -> Option<usize> {
+        let (start, end) = self.line_range(position.line)?;
+        let column = encoding.byte_offset(source.get(start..end)?, position.column)?;
+        self.position_to_byte(Position::new(position.line, column))
+    }
+
+    /// Update the index for replacing `start..old_end` of the source with `new_text`.
+    ///
+    /// Only the lines from the edit on are indexed again.
+    pub fn apply_edit(&mut self, start: usize, old_end: usize, new_text: &str) {
+        let first = self.line_starts.partition_point(|&line| line <= start);
+        let last = self.line_starts.partition_point(|&line| line <= old_end);
+        let new_len = self.len - (old_end - start) + new_text.len();
+        let inserted: Vec<usize> = line_breaks(new_text, start).collect();
+        self.line_starts.splice(first..last, inserted);
+        let shifted = first + new_text.matches('\n').count();
+        for line_start in &mut self.line_starts[shifted..] {
+            *line_start = *line_start - old_end + start + new_text.len();
+        }
+        self.len = new_len;
+    }
+}
+
+/// Iterate over the offsets just after each `\n` of `text`, shifted by `base`.
+fn line_breaks(text: &str, base: usize) -> impl Iterator<Item = usize> + '_ {
+    text.match_indices('\n')
+        .map(move |(offset, _)| base + offset + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, (0, 0))]
+    #[case(2, (0, 2))]
+    #[case(3, (1, 0))]
+    #[case(4, (2, 0))]
+    #[case(6, (2, 2))]
+    #[case(7, (3, 0))]
+    #[case(100, (3, 0))]
+    fn test_byte_to_position(#[case] offset: usize, #[case] expected: (usize, usize)) {
+        let lines = LineIndex::new("ab\n\ncd\n");
+        let position = lines.byte_to_position(offset);
+        assert_eq!((position.line, position.column), expected);
+        if offset <= 7 {
+            assert_eq!(lines.position_to_byte(position), Some(offset));
+        }
+    }
+
+    #[test]
+    fn test_position_to_byte_rejects_missing_positions() {
+        let lines = LineIndex::new("ab\ncd");
+        assert_eq!(lines.position_to_byte(Position::new(0, 3)), None);
+        assert_eq!(lines.position_to_byte(Position::new(1, 2)), Some(5));
+        assert_eq!(lines.position_to_byte(Position::new(1, 3)), None);
+        assert_eq!(lines.position_to_byte(Position::new(2, 0)), None);
+    }
+
+    #[test]
+    fn test_line() {
+        let source = "a\r\nb\n\nc";
+        let lines = LineIndex::new(source);
+        assert_eq!(lines.line_count(), 4);
+        let text: Vec<_> = (0..5).map(|line| lines.line(source, line)).collect();
+        assert_eq!(text, [Some("a"), Some("b"), Some(""), Some("c"), None]);
+        assert_eq!(lines.line_range(1), Some((3, 5)));
+    }
+
+    #[rstest]
+    #[case(PositionEncoding::Utf8, 9, (1, 7))]
+    #[case(PositionEncoding::Utf16, 9, (1, 5))]
+    #[case(PositionEncoding::Utf32, 9, (1, 4))]
+    #[case(PositionEncoding::Utf16, 12, (1, 8))]
+    fn test_encoded_positions(
+        #[case] encoding: PositionEncoding,
+        #[case] offset: usize,
+        #[case] expected: (usize, usize),
+    ) {
+        let source = "{\n  \"😀\": 1\n}";
+        let lines = LineIndex::new(source);
+        let position = lines.encode_position(source, offset, encoding);
+        assert_eq!((position.line, position.column), expected);
+        assert_eq!(
+            lines.decode_position(source, position, encoding),
+            Some(offset)
+        );
+    }
+
+    #[test]
+    fn test_encoded_positions_inside_characters() {
+        let source = "😀\nb";
+        let lines = LineIndex::new(source);
+        let encoding = PositionEncoding::Utf16;
+        assert_eq!(
+            lines.encode_position(source, 2, encoding),
+            Position::new(0, 0)
+        );
+        assert_eq!(
+            lines.decode_position(source, Position::new(0, 1), encoding),
+            None
+        );
+        assert_eq!(
+            lines.decode_position(source, Position::new(0, 3), encoding),
+            None
+        );
+        assert_eq!(
+            lines.decode_position(source, Position::new(1, 1), encoding),
+            Some(6)
+        );
+    }
+
+    #[rstest]
+    #[case(1, 2, "")]
+    #[case(1, 1, "x\ny\n")]
+    #[case(0, 9, "\n")]
+    #[case(2, 6, "z")]
+    #[case(9, 9, "\n\n")]
+    #[case(3, 3, "")]
+    fn test_apply_edit_matches_new_index(
+        #[case] start: usize,
+        #[case] end: usize,
+        #[case] text: &str,
+    ) {
+        let mut source = "a\nb\ncd\n\ne".to_string();
+        let mut lines = LineIndex::new(&source);
+
+        lines.apply_edit(start, end, text);
+        source.replace_range(start..end, text);
+
+        assert_eq!(lines, LineIndex::new(&source));
+    }
+}
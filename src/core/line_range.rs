@@ -0,0 +1,109 @@
+/// An inclusive, 1-based range of lines used to restrict formatting.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::LineRange;
+///
+/// let lines = LineRange::parse("2:3").unwrap();
+/// assert_eq!(lines.byte_range("a\nb\nc\nd\n"), (2, 6));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    start: usize,
+    end: usize,
+}
+
+impl LineRange {
+    /// Create a line range.
+    ///
+    /// # Arguments
+    /// * `start` - First line of the range (1-based)
+    /// * `end` - Last line of the range (inclusive)
+    ///
+    /// # Returns
+    /// `None` if `start` is zero or greater than `end`
+    pub fn new(start: usize, end: usize) -> Option<Self> {
+        (start >= 1 && start <= end).then_some(Self { start, end })
+    }
+
+    /// Parse a range written as `START:END`.
+    ///
+    /// # Returns
+    /// `Some(LineRange)` if the string is a valid range, `None` otherwise
+    pub fn parse(range_str: &str) -> Option<Self> {
+        let (start, end) = range_str.split_once(':')?;
+        Self::new(start.trim().parse().ok()?, end.trim().parse().ok()?)
+    }
+
+    /// Get the first line of the range.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Get the last line of the range.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Convert the range to a byte range of `source`.
+    ///
+    /// The byte range starts at the beginning of the first line and ends
+    /// after the line break of the last line. Lines past the end of the
+    /// source are clamped to the source length.
+    pub fn byte_range(&self, source: &str) -> (usize, usize) {
+        (
+            line_start_offset(source, self.start),
+            line_start_offset(source, self.end + 1),
+        )
+    }
+}
+
+/// Get the byte offset at which the 1-based `line` starts.
+///
+/// Returns the source length if the source has fewer lines.
+fn line_start_offset(source: &str, line: usize) -> usize {
+    if line <= 1 {
+        return 0;
+    }
+
+    source
+        .match_indices('\n')
+        .nth(line - 2)
+        .map_or(source.len(), |(offset, _)| offset + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("10:40", Some((10, 40)))]
+    #[case("3:3", Some((3, 3)))]
+    #[case(" 1 : 2 ", Some((1, 2)))]
+    #[case("0:4", None)]
+    #[case("5:4", None)]
+    #[case("5", None)]
+    #[case("a:b", None)]
+    fn test_parse(#[case] input: &str, #[case] expected: Option<(usize, usize)>) {
+        let parsed = LineRange::parse(input).map(|range| (range.start(), range.end()));
+        assert_eq!(parsed, expected);
+    }
+
+    #[rstest]
+    #[case(1, 1, (0, 4))]
+    #[case(2, 3, (4, 14))]
+    #[case(3, 10, (8, 14))]
+    #[case(5, 6, (14, 14))]
+    fn test_byte_range(#[case] start: usize, #[case] end: usize, #[case] expected: (usize, usize)) {
+        let source = "one\ntwo\nthree\n";
+        let range = LineRange::new(start, end).unwrap();
+        assert_eq!(range.byte_range(source), expected);
+    }
+
+    #[test]
+    fn test_byte_range_without_trailing_newline() {
+        let range = LineRange::new(2, 2).unwrap();
+        assert_eq!(range.byte_range("a\nbc"), (2, 4));
+    }
+}
@@ -1,3 +1,49 @@
+mod cancellation;
+mod diagnostic;
+mod diff;
+mod edit_conflicts;
+mod edit_trace;
+mod edit_validation;
+mod encoding;
 mod engine;
+mod engine_cache;
+mod engine_error;
+mod engine_observer;
+mod file_writer;
+mod hash;
+mod language_registry;
+mod line_endings;
+mod line_index;
+mod line_range;
+mod parse_error_policy;
+mod parse_metrics;
+mod pass_panic;
+mod pass_timings;
+mod position_encoding;
+mod result;
+mod rule_level;
+mod run_report;
+mod suppression;
 
+pub use cancellation::CancellationToken;
+pub use diagnostic::{Diagnostic, Severity};
+pub use edit_trace::TracedEdit;
+pub use encoding::Encoding;
 pub use engine::Engine;
+pub use engine_cache::EngineCache;
+pub use engine_error::EngineError;
+pub use engine_observer::EngineObserver;
+pub use file_writer::FileWriter;
+pub(crate) use hash::{fnv1a_64, fnv1a_64_extend, FNV_OFFSET_BASIS};
+pub use language_registry::LanguageRegistry;
+pub use line_endings::LineEndings;
+pub use line_index::{LineIndex, Position};
+pub use line_range::LineRange;
+pub use parse_error_policy::ParseErrorPolicy;
+pub use parse_metrics::ParseMetrics;
+pub use pass_panic::PassPanic;
+pub use pass_timings::PassTiming;
+pub use position_encoding::PositionEncoding;
+pub use result::{FileFormatOutcome, FormatResult};
+pub use rule_level::RuleLevel;
+pub use run_report::{FileError, FirstStop, RunMetrics, RunReport};
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// What the engine does with sources that already have syntax errors.
+///
+/// Passes see the tree produced by tree-sitter's error recovery, which may
+/// not match what the author meant, so formatting a broken source can
+/// mangle it further.
+///
+/// # Examples
+/// ```yaml
+/// runner:
+///   on_parse_error: skip
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParseErrorPolicy {
+    /// Leave the source unformatted and report a warning
+    Skip,
+    /// Format the source and report a warning
+    Warn,
+    /// Format the source without reporting anything
+    #[default]
+    Format,
+}
+
+impl ParseErrorPolicy {
+    const SKIP: &'static str = "skip";
+    const WARN: &'static str = "warn";
+    const FORMAT: &'static str = "format";
+
+    /// Get the string representation of the policy
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParseErrorPolicy::Skip => Self::SKIP,
+            ParseErrorPolicy::Warn => Self::WARN,
+            ParseErrorPolicy::Format => Self::FORMAT,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::walk::{walk, Visit};
+use std::time::Duration;
+use tree_sitter::Tree;
+
+/// Measurements of the tree parsed from a source before formatting it.
+///
+/// Large or deeply nested trees and trees full of errors are the usual
+/// reason a file dominates the runtime of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// Time spent parsing the source
+    pub duration: Duration,
+    /// Number of levels of the tree, counting the root
+    pub depth: usize,
+    /// Number of nodes of the tree, named and anonymous
+    pub nodes: usize,
+    /// Number of error and missing nodes of the tree
+    pub errors: usize,
+}
+
+impl ParseMetrics {
+    /// Measure `tree`, which took `duration` to parse.
+    pub fn new(tree: &Tree, duration: Duration) -> Self {
+        let mut metrics = Self {
+            duration,
+            ..Self::default()
+        };
+        walk(tree.root_node(), |node, depth| {
+            metrics.depth = metrics.depth.max(depth + 1);
+            metrics.nodes += 1;
+            if node.is_error() || node.is_missing() {
+                metrics.errors += 1;
+            }
+            Visit::Continue
+        });
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    // document > array > [ 1 , 2 ]
+    #[case("[1, 2]", 3, 7, 0)]
+    // document > array > [ array > [ ] ]
+    #[case("[[]]", 4, 7, 0)]
+    // document > array > [ 1 ERROR > 2 ]
+    #[case("[1 2]", 4, 7, 1)]
+    fn test_new(
+        #[case] source: &str,
+        #[case] depth: usize,
+        #[case] nodes: usize,
+        #[case] errors: usize,
+    ) {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let metrics = ParseMetrics::new(&tree, Duration::from_micros(3));
+
+        assert_eq!(
+            (metrics.depth, metrics.nodes, metrics.errors),
+            (depth, nodes, errors)
+        );
+        assert_eq!(metrics.duration, Duration::from_micros(3));
+    }
+}
@@ -0,0 +1,82 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+/// A pass panicked while formatting a source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassPanic {
+    /// Name of the pass that panicked
+    pub pass: &'static str,
+    /// The panic message
+    pub message: String,
+}
+
+impl fmt::Display for PassPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pass '{}' panicked: {}", self.pass, self.message)
+    }
+}
+
+impl std::error::Error for PassPanic {}
+
+/// Run `op` on behalf of `pass`, re-raising any panic as a `PassPanic` payload.
+///
+/// The payload lets `isolate` further up the stack tell which pass panicked.
+pub(crate) fn attribute_panic<R>(pass: &'static str, op: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(op)) {
+        Ok(result) => result,
+        Err(payload) => panic::resume_unwind(Box::new(into_pass_panic(pass, payload))),
+    }
+}
+
+/// Run `op` on behalf of `pass`, turning a panic into a `PassPanic`.
+///
+/// Panics raised through `attribute_panic` keep the pass they name.
+pub(crate) fn isolate<R>(pass: &'static str, op: impl FnOnce() -> R) -> Result<R, PassPanic> {
+    panic::catch_unwind(AssertUnwindSafe(op)).map_err(|payload| into_pass_panic(pass, payload))
+}
+
+/// Convert a panic payload into a `PassPanic`, keeping an existing one as is.
+fn into_pass_panic(pass: &'static str, payload: Box<dyn Any + Send>) -> PassPanic {
+    let payload = match payload.downcast::<PassPanic>() {
+        Ok(pass_panic) => return *pass_panic,
+        Err(payload) => payload,
+    };
+
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+    PassPanic { pass, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolate_keeps_attributed_pass() {
+        let result = isolate("Outer", || attribute_panic("Inner", || panic!("bad node")));
+        assert_eq!(
+            result,
+            Err(PassPanic {
+                pass: "Inner",
+                message: "bad node".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_isolate_formats_message() {
+        let id = 7;
+        let result: Result<(), _> = isolate("Pass", || panic!("node {id}"));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "pass 'Pass' panicked: node 7"
+        );
+        assert_eq!(isolate("Pass", || 1), Ok(1));
+    }
+}
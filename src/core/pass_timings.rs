@@ -0,0 +1,76 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Wall time spent in one pass on one source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassTiming {
+    /// Index of the source in the batch passed to the engine
+    pub file: usize,
+    /// Name of the pass
+    pub pass: &'static str,
+    /// Time spent running the pass and applying its edits
+    pub duration: Duration,
+}
+
+/// Pass timings collected by an engine, shared between worker threads.
+#[derive(Debug, Default)]
+pub(crate) struct PassTimings {
+    timings: Mutex<Vec<PassTiming>>,
+}
+
+impl PassTimings {
+    /// Record the time spent in `pass` on the source at index `file`.
+    ///
+    /// Time recorded repeatedly for the same file and pass is summed.
+    pub(crate) fn record(&self, file: usize, pass: &'static str, duration: Duration) {
+        let mut timings = self
+            .timings
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        match timings
+            .iter_mut()
+            .find(|timing| timing.file == file && timing.pass == pass)
+        {
+            Some(timing) => timing.duration += duration,
+            None => timings.push(PassTiming {
+                file,
+                pass,
+                duration,
+            }),
+        }
+    }
+
+    /// Take the recorded timings, sorted by file and then in recording order.
+    pub(crate) fn take(&self) -> Vec<PassTiming> {
+        let mut timings = std::mem::take(
+            &mut *self
+                .timings
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        timings.sort_by_key(|timing| timing.file);
+        timings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_sums_repeated_timings() {
+        let timings = PassTimings::default();
+        timings.record(1, "A", Duration::from_micros(5));
+        timings.record(0, "A", Duration::from_micros(3));
+        timings.record(1, "A", Duration::from_micros(2));
+        timings.record(1, "B", Duration::from_micros(1));
+
+        let taken = timings.take();
+        let rows: Vec<_> = taken
+            .iter()
+            .map(|t| (t.file, t.pass, t.duration.as_micros()))
+            .collect();
+        assert_eq!(rows, vec![(0, "A", 3), (1, "A", 7), (1, "B", 1)]);
+        assert!(timings.take().is_empty());
+    }
+}
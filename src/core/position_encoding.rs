@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+/// Unit in which the columns of a position are counted.
+///
+/// tree-sitter and the engine count bytes of UTF-8; the language server
+/// protocol counts UTF-16 code units by default, and editors often count
+/// characters. On ASCII lines the three agree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PositionEncoding {
+    /// Bytes of UTF-8
+    #[default]
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// UTF-16 code units, so characters outside the basic multilingual
+    /// plane count twice
+    #[serde(rename = "utf-16")]
+    Utf16,
+    /// Unicode scalar values, that is characters
+    #[serde(rename = "utf-32")]
+    Utf32,
+}
+
+impl PositionEncoding {
+    const UTF8: &'static str = "utf-8";
+    const UTF16: &'static str = "utf-16";
+    const UTF32: &'static str = "utf-32";
+
+    /// Get the string representation of the encoding, as named by the
+    /// language server protocol
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PositionEncoding::Utf8 => Self::UTF8,
+            PositionEncoding::Utf16 => Self::UTF16,
+            PositionEncoding::Utf32 => Self::UTF32,
+        }
+    }
+
+    /// Get the length of `text` in units of the encoding.
+    pub fn len(&self, text: &str) -> usize {
+        match self {
+            PositionEncoding::Utf8 => text.len(),
+            PositionEncoding::Utf16 => text.chars().map(char::len_utf16).sum(),
+            PositionEncoding::Utf32 => text.chars().count(),
+        }
+    }
+
+    /// Convert a column in units of the encoding to a byte offset in `line`.
+    ///
+    /// # Returns
+    /// The offset, or `None` if the column is past the end of the line or
+    /// falls inside a character
+    pub fn byte_offset(&self, line: &str, column: usize) -> Option<usize> {
+        if *self == PositionEncoding::Utf8 {
+            return line.is_char_boundary(column).then_some(column);
+        }
+        let mut units = 0;
+        for (offset, ch) in line.char_indices() {
+            if units >= column {
+                return (units == column).then_some(offset);
+            }
+            units += match self {
+                PositionEncoding::Utf16 => ch.len_utf16(),
+                _ => 1,
+            };
+        }
+        (units == column).then_some(line.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    // `é` takes 2 bytes and `😀` 4 bytes or 2 UTF-16 units
+    const LINE: &str = "aé😀b";
+
+    #[rstest]
+    #[case(PositionEncoding::Utf8, 8, &[(1, Some(1)), (2, None), (3, Some(3)), (8, Some(8)), (9, None)])]
+    #[case(PositionEncoding::Utf16, 5, &[(2, Some(3)), (3, None), (4, Some(7)), (5, Some(8)), (6, None)])]
+    #[case(PositionEncoding::Utf32, 4, &[(2, Some(3)), (3, Some(7)), (4, Some(8)), (5, None)])]
+    fn test_columns(
+        #[case] encoding: PositionEncoding,
+        #[case] len: usize,
+        #[case] offsets: &[(usize, Option<usize>)],
+    ) {
+        assert_eq!(encoding.len(LINE), len);
+        for &(column, offset) in offsets {
+            assert_eq!(
+                encoding.byte_offset(LINE, column),
+                offset,
+                "column {column}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_serde_names() {
+        let encoding: PositionEncoding = serde_json::from_str("\"utf-16\"").unwrap();
+        assert_eq!(encoding, PositionEncoding::Utf16);
+        assert_eq!(
+            serde_json::to_string(&PositionEncoding::Utf32).unwrap(),
+            format!("\"{}\"", PositionEncoding::Utf32.as_str())
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use crate::core::{Diagnostic, ParseMetrics, PassPanic};
+use crate::pipeline::SourceMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The outcome of formatting one file of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFormatOutcome {
+    /// Path of the file
+    pub path: PathBuf,
+    /// The formatted content, or the panic that stopped formatting the file
+    pub formatted: Result<String, PassPanic>,
+    /// Whether the formatted content differs from the original
+    pub changed: bool,
+    /// Whether the file was left unformatted because it disables formatting
+    pub ignored: bool,
+    /// Diagnostics reported while formatting the file, including a pass panic
+    pub diagnostics: Vec<Diagnostic>,
+    /// Unified diff of the changes, if requested with `Engine::with_diffs`
+    pub diff: Option<String>,
+    /// Time spent formatting the file; zero if its result was cached
+    pub duration: Duration,
+    /// Measurements of the tree parsed before formatting; `None` if the file was not parsed
+    pub parse: Option<ParseMetrics>,
+    /// Map between the original and formatted content, if requested with `Engine::with_source_maps`
+    pub source_map: Option<SourceMap>,
+}
+
+impl FileFormatOutcome {
+    /// Create the outcome of formatting `original`, without diff or duration.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file
+    /// * `original` - Content of the file before formatting
+    /// * `formatted` - Result of formatting `original`
+    pub fn new(path: PathBuf, original: &str, formatted: Result<String, PassPanic>) -> Self {
+        let changed = formatted
+            .as_ref()
+            .is_ok_and(|formatted| formatted != original);
+        let diagnostics = match &formatted {
+            Ok(_) => Vec::new(),
+            Err(err) => vec![Diagnostic::from(err.clone())],
+        };
+        Self {
+            path,
+            formatted,
+            changed,
+            ignored: false,
+            diagnostics,
+            diff: None,
+            duration: Duration::ZERO,
+            parse: None,
+            source_map: None,
+        }
+    }
+
+    /// Returns true if any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
+
+/// The result of formatting one source in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatResult {
+    /// The formatted text; the original text if formatting failed
+    pub formatted: String,
+    /// Whether `formatted` differs from the original text
+    pub changed: bool,
+    /// Diagnostics reported while formatting
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl FormatResult {
+    /// Returns true if any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
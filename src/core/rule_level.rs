@@ -0,0 +1,70 @@
+use crate::core::{Diagnostic, Severity};
+use serde::{Deserialize, Serialize};
+
+/// How diagnostics of a rule are reported, overriding the severity passes give them.
+///
+/// # Examples
+/// ```yaml
+/// runner:
+///   rules:
+///     FMT001: warn
+///     FMT002: off
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    /// Drop diagnostics of the rule
+    Off,
+    /// Report diagnostics of the rule as warnings
+    Warn,
+    /// Report diagnostics of the rule as errors
+    Error,
+}
+
+impl RuleLevel {
+    const OFF: &'static str = "off";
+    const WARN: &'static str = "warn";
+    const ERROR: &'static str = "error";
+
+    /// Get the string representation of the level
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleLevel::Off => Self::OFF,
+            RuleLevel::Warn => Self::WARN,
+            RuleLevel::Error => Self::ERROR,
+        }
+    }
+
+    /// Apply the level to a diagnostic of the rule.
+    ///
+    /// # Returns
+    /// The diagnostic with the severity of the level, or `None` if the rule is off
+    pub fn apply(self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        diagnostic.severity = match self {
+            RuleLevel::Off => return None,
+            RuleLevel::Warn => Severity::Warning,
+            RuleLevel::Error => Severity::Error,
+        };
+        Some(diagnostic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let diagnostic = Diagnostic::error("line too long").with_code("FMT001");
+
+        assert_eq!(RuleLevel::Off.apply(diagnostic.clone()), None);
+        assert_eq!(
+            RuleLevel::Warn.apply(diagnostic.clone()).unwrap().severity,
+            Severity::Warning
+        );
+        assert_eq!(
+            RuleLevel::Error.apply(diagnostic).unwrap().severity,
+            Severity::Error
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::core::{FileFormatOutcome, PassTiming};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A file that could not be processed, with the reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileError {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Human-readable description of the failure
+    pub message: String,
+}
+
+impl FileError {
+    /// Create a failure of `path` with the error's message.
+    pub fn new(path: &Path, error: impl fmt::Display) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Why `Engine::try_find_first_unformatted` stopped checking files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirstStop {
+    /// The file needs formatting
+    Unformatted(PathBuf),
+    /// A pass panicked on the file
+    Failed(FileError),
+}
+
+/// Counters and timings collected during a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunMetrics {
+    /// Number of files whose result was taken from the cache
+    pub cached: usize,
+    /// Time spent in each pass on each file; empty unless `Engine::with_timings` was used
+    pub timings: Vec<PassTiming>,
+    /// Time spent parsing the files before formatting them, summed over all files
+    pub parse_duration: Duration,
+}
+
+/// Everything a batch run produced, in one place.
+///
+/// # Examples
+/// ```ignore
+/// let report = engine.format_report(&config, &codes, &files);
+/// for path in report.changed_paths() {
+///     println!("{}", path.display());
+/// }
+/// assert!(!report.has_errors());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunReport {
+    /// The outcome of every file, in input order
+    pub outcomes: Vec<FileFormatOutcome>,
+    /// Counters and timings of the run
+    pub metrics: RunMetrics,
+    /// Wall time of the whole run
+    pub duration: Duration,
+    /// Files that could not be processed; hosts add the files they fail to read or write
+    pub errors: Vec<FileError>,
+}
+
+impl RunReport {
+    /// Create the report of a run, listing the files that failed to format in `errors`.
+    ///
+    /// # Arguments
+    /// * `outcomes` - The outcome of every file, in input order
+    /// * `metrics` - Counters and timings of the run
+    /// * `duration` - Wall time of the run
+    pub fn new(outcomes: Vec<FileFormatOutcome>, metrics: RunMetrics, duration: Duration) -> Self {
+        let errors = outcomes
+            .iter()
+            .filter_map(|outcome| {
+                let err = outcome.formatted.as_ref().err()?;
+                Some(FileError::new(&outcome.path, err))
+            })
+            .collect();
+        Self {
+            outcomes,
+            metrics,
+            duration,
+            errors,
+        }
+    }
+
+    /// Get the files that formatting changed, in input order, leaving out failed files.
+    pub fn changed_paths(&self) -> Vec<PathBuf> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.changed && !self.has_failed(&outcome.path))
+            .map(|outcome| outcome.path.clone())
+            .collect()
+    }
+
+    /// Returns true if any file failed or has an error diagnostic.
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty() || self.outcomes.iter().any(FileFormatOutcome::has_errors)
+    }
+
+    /// Returns true if `path` is listed in `errors`.
+    pub fn has_failed(&self, path: &Path) -> bool {
+        self.errors.iter().any(|error| error.path == path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::PassPanic;
+
+    fn report() -> RunReport {
+        let outcomes = vec![
+            FileFormatOutcome::new("a.json".into(), "{}", Ok("{}".to_string())),
+            FileFormatOutcome::new("b.json".into(), "{ }", Ok("{}".to_string())),
+            FileFormatOutcome::new(
+                "c.json".into(),
+                "{ }",
+                Err(PassPanic {
+                    pass: "BoomPass",
+                    message: "boom".to_string(),
+                }),
+            ),
+        ];
+        RunReport::new(outcomes, RunMetrics::default(), Duration::ZERO)
+    }
+
+    #[test]
+    fn test_new_lists_files_that_failed_to_format() {
+        let report = report();
+
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].path, PathBuf::from("c.json"));
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn test_changed_paths_leave_out_failed_files() {
+        let mut report = report();
+        assert_eq!(report.changed_paths(), [PathBuf::from("b.json")]);
+
+        report
+            .errors
+            .push(FileError::new(Path::new("b.json"), "read-only"));
+        assert!(report.changed_paths().is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+use crate::core::engine::narrow_edit;
+use crate::core::Diagnostic;
+use crate::parser::LanguageCapabilities;
+use crate::pipeline::Edit;
+use crate::walk::{walk_kinds, Visit};
+use tree_sitter::Node;
+
+/// Number of lines at the top of a source searched for file directives.
+pub(crate) const DIRECTIVE_LINES: usize = 5;
+
+/// Returns true if one of the first `DIRECTIVE_LINES` lines of `source` contains one of `markers`.
+///
+/// Used for the directive disabling a whole file and for markers of
+/// generated files such as `@generated`.
+pub(crate) fn disables_file<'m>(
+    source: &str,
+    markers: impl Iterator<Item = &'m str> + Clone,
+) -> bool {
+    source.lines().take(DIRECTIVE_LINES).any(|line| {
+        markers
+            .clone()
+            .any(|marker| !marker.is_empty() && line.contains(marker))
+    })
+}
+
+/// Parts of a source where comments suppress edits and diagnostics.
+///
+/// Marker comments of the language turn formatting off for a region
+/// (`fmt: off` up to `fmt: on`), for the next node (`fmt-ignore`), or for
+/// the next line (`fmt-ignore-next-line`). Ignore comments may name the
+/// passes or rule codes they apply to, e.g. `fmt-ignore: SortKeys, FMT001`.
+#[derive(Debug, Default)]
+pub(crate) struct Suppressions {
+    regions: Vec<Region>,
+}
+
+/// A suppressed byte range and what it applies to.
+#[derive(Debug, PartialEq, Eq)]
+struct Region {
+    range: (usize, usize),
+    /// Names of passes and rule codes the region applies to; empty for all of them
+    rules: Vec<String>,
+}
+
+impl Region {
+    /// Returns true if the region suppresses the output of `pass` or of the rule `code`.
+    fn applies_to(&self, pass: &str, code: Option<&str>) -> bool {
+        self.rules.is_empty()
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule == pass || Some(rule.as_str()) == code)
+    }
+}
+
+/// What an ignore comment applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IgnoreTarget {
+    /// The node following the comment
+    Node,
+    /// The line following the comment
+    NextLine,
+}
+
+impl Suppressions {
+    /// Find the regions suppressed by marker comments.
+    ///
+    /// A `fmt: off` region starts at its comment and ends after the next
+    /// `fmt: on` comment, or at the end of the source if formatting is
+    /// never turned back on. On markers outside such a region are ignored.
+    ///
+    /// # Arguments
+    /// * `root` - Root node of the parsed source
+    /// * `source` - The source code
+    /// * `capabilities` - Comment syntax, comment node kinds and markers of the language
+    pub(crate) fn find(root: Node, source: &str, capabilities: &LanguageCapabilities) -> Self {
+        let (off, on) = capabilities.suppression_markers();
+        let ignore = capabilities.ignore_marker();
+        // Most sources have no markers; skip the walk for them
+        if !source.contains(off) && !source.contains(ignore) {
+            return Self::default();
+        }
+
+        let mut regions = Vec::new();
+        let mut start = None;
+        walk_kinds(root, capabilities.comment_node_kinds(), |node, _| {
+            let text = source
+                .get(node.start_byte()..node.end_byte())
+                .map(|comment| capabilities.comment_text(comment))
+                .unwrap_or_default();
+            match start {
+                None if text == off => start = Some(node.start_byte()),
+                Some(range_start) if text == on => {
+                    regions.push(Region {
+                        range: (range_start, node.end_byte()),
+                        rules: Vec::new(),
+                    });
+                    start = None;
+                }
+                _ => {
+                    let Some((target, rules)) = parse_ignore(text, ignore) else {
+                        return Visit::SkipChildren;
+                    };
+                    let range = match target {
+                        IgnoreTarget::Node => next_node(node, capabilities),
+                        IgnoreTarget::NextLine => next_line(source, node.end_byte()),
+                    };
+                    regions.extend(range.map(|range| Region { range, rules }));
+                }
+            }
+            Visit::SkipChildren
+        });
+        if let Some(range_start) = start {
+            regions.push(Region {
+                range: (range_start, source.len()),
+                rules: Vec::new(),
+            });
+        }
+        Self { regions }
+    }
+
+    /// Returns true if nothing is suppressed.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Drop the edits of `pass` that change suppressed text.
+    ///
+    /// Edits reaching into a suppressed region are first narrowed to the
+    /// text they actually change, like edits crossing a formatted range.
+    pub(crate) fn retain_edits(&self, source: &str, pass: &str, edits: Vec<Edit>) -> Vec<Edit> {
+        let regions: Vec<_> = self
+            .regions
+            .iter()
+            .filter(|region| region.applies_to(pass, None))
+            .collect();
+        if regions.is_empty() {
+            return edits;
+        }
+        let inside = |edit: &Edit| {
+            regions
+                .iter()
+                .any(|region| changes_inside(edit, region.range))
+        };
+        edits
+            .into_iter()
+            .map(|edit| {
+                if inside(&edit) {
+                    narrow_edit(source, edit)
+                } else {
+                    edit
+                }
+            })
+            .filter(|edit| !inside(edit))
+            .collect()
+    }
+
+    /// Drop the diagnostics of `pass` that start in suppressed text.
+    ///
+    /// Diagnostics without a range are kept.
+    pub(crate) fn retain_diagnostics(
+        &self,
+        pass: &str,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| {
+                let Some((start, _)) = diagnostic.range else {
+                    return true;
+                };
+                !self.regions.iter().any(|region| {
+                    region.range.0 <= start
+                        && start < region.range.1
+                        && region.applies_to(pass, diagnostic.code)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parse the text of an ignore comment.
+///
+/// # Returns
+/// What the comment ignores and the passes or rules it is limited to, or
+/// `None` if the text is not an ignore comment
+fn parse_ignore(text: &str, marker: &str) -> Option<(IgnoreTarget, Vec<String>)> {
+    let rest = text.strip_prefix(marker)?;
+    let (target, rest) = match rest.strip_prefix("-next-line") {
+        Some(rest) => (IgnoreTarget::NextLine, rest),
+        None => (IgnoreTarget::Node, rest),
+    };
+    let rest = rest.trim_start();
+    if rest.is_empty() {
+        return Some((target, Vec::new()));
+    }
+    let rules = rest
+        .strip_prefix(':')?
+        .split(',')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(str::to_string)
+        .collect();
+    Some((target, rules))
+}
+
+/// Get the byte range of the first node after `comment` that is not a comment.
+fn next_node(comment: Node, capabilities: &LanguageCapabilities) -> Option<(usize, usize)> {
+    let mut next = comment.next_named_sibling();
+    while let Some(node) = next {
+        if !capabilities.is_comment_kind(node.kind()) {
+            return Some((node.start_byte(), node.end_byte()));
+        }
+        next = node.next_named_sibling();
+    }
+    None
+}
+
+/// Get the byte range of the line after the one holding `offset`, without its terminator.
+fn next_line(source: &str, offset: usize) -> Option<(usize, usize)> {
+    let start = offset + source.get(offset..)?.find('\n')? + 1;
+    let line = source[start..].split('\n').next().unwrap_or_default();
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    Some((start, start + line.len()))
+}
+
+/// Returns true if `edit` changes text inside `range`.
+///
+/// Edits ending where the range starts or starting where it ends only
+/// touch it; insertions at its boundaries don't change its text either.
+pub(crate) fn changes_inside(edit: &Edit, (start, end): (usize, usize)) -> bool {
+    let (edit_start, edit_end) = edit.range;
+    if edit_start == edit_end {
+        start < edit_start && edit_start < end
+    } else {
+        edit_start < end && start < edit_end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    static JSON: LanguageCapabilities = LanguageCapabilities::new()
+        .with_line_comment("//")
+        .with_block_comment("/*", "*/");
+
+    fn find(source: &str) -> (tree_sitter::Tree, Suppressions) {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let suppressions = Suppressions::find(tree.root_node(), source, &JSON);
+        (tree, suppressions)
+    }
+
+    fn ranges(source: &str) -> Vec<(usize, usize)> {
+        find(source)
+            .1
+            .regions
+            .iter()
+            .map(|region| region.range)
+            .collect()
+    }
+
+    #[test]
+    fn test_fmt_off_regions() {
+        let source = "[1,\n// fmt: off\n2 ,\n/* fmt: on */\n3, // fmt: on\n// fmt: off\n4]";
+        let off = source.find("// fmt: off").unwrap();
+        let on = source.find("*/").unwrap() + 2;
+        let last = source.rfind("// fmt: off").unwrap();
+
+        assert_eq!(ranges(source), [(off, on), (last, source.len())]);
+    }
+
+    #[test]
+    fn test_markers_must_be_the_whole_comment() {
+        assert!(ranges("[1, // fmt: offset\n2]").is_empty());
+        assert!(ranges("[1, // fmt-ignored\n2]").is_empty());
+        assert!(ranges("[\"fmt: off\"]").is_empty());
+    }
+
+    #[test]
+    fn test_ignore_comments() {
+        let source =
+            "[// fmt-ignore\n// other\n{\"a\": 1},\n// fmt-ignore-next-line: SortKeys\n2, 3,\n4]";
+        let object = source.find('{').unwrap();
+        let line = source.find("2, 3,").unwrap();
+        let (_, suppressions) = find(source);
+
+        assert_eq!(
+            suppressions.regions,
+            [
+                Region {
+                    range: (object, object + 8),
+                    rules: Vec::new(),
+                },
+                Region {
+                    range: (line, line + 5),
+                    rules: vec!["SortKeys".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("fmt-ignore", Some((IgnoreTarget::Node, vec![])))]
+    #[case("fmt-ignore-next-line", Some((IgnoreTarget::NextLine, vec![])))]
+    #[case("fmt-ignore: A, FMT001,", Some((IgnoreTarget::Node, vec!["A", "FMT001"])))]
+    #[case("fmt-ignore-next-line:A", Some((IgnoreTarget::NextLine, vec!["A"])))]
+    #[case("fmt-ignore because", None)]
+    #[case("fmt-ignores", None)]
+    fn test_parse_ignore(#[case] text: &str, #[case] expected: Option<(IgnoreTarget, Vec<&str>)>) {
+        let parsed = parse_ignore(text, "fmt-ignore");
+        let expected = expected
+            .map(|(target, rules)| (target, rules.into_iter().map(str::to_string).collect()));
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn test_scoped_regions_keep_output_of_other_passes() {
+        let source = "[// fmt-ignore: SortKeys, FMT001\n2 ]";
+        let (_, suppressions) = find(source);
+        let number = source.find('2').unwrap();
+        let edit = Edit {
+            range: (number, number + 1),
+            content: "3".to_string(),
+        };
+
+        assert!(suppressions
+            .retain_edits(source, "SortKeys", vec![edit.clone()])
+            .is_empty());
+        assert_eq!(
+            suppressions.retain_edits(source, "Other", vec![edit.clone()]),
+            [edit]
+        );
+
+        let diagnostics = vec![
+            Diagnostic::warning("a").with_range((number, number + 1)),
+            Diagnostic::warning("b")
+                .with_code("FMT001")
+                .with_range((number, number + 1)),
+            Diagnostic::warning("c").with_code("FMT001"),
+        ];
+        let kept = suppressions.retain_diagnostics("Other", diagnostics);
+        let messages: Vec<_> = kept.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["a", "c"]);
+    }
+
+    #[rstest]
+    #[case("// fmt-disable-file\n[1]", true)]
+    #[case("[\n1,\n2,\n3,\n4 // @generated\n]", true)]
+    #[case("[\n1,\n2,\n3,\n4,\n// fmt-disable-file\n]", false)]
+    #[case("[1] // fmt-disable", false)]
+    fn test_disables_file(#[case] source: &str, #[case] expected: bool) {
+        let markers = ["fmt-disable-file", "@generated", ""];
+        assert_eq!(disables_file(source, markers.into_iter()), expected);
+    }
+
+    #[rstest]
+    #[case((0, 2), false)]
+    #[case((2, 2), false)]
+    #[case((3, 3), true)]
+    #[case((1, 3), true)]
+    #[case((5, 7), true)]
+    #[case((6, 8), false)]
+    fn test_changes_inside(#[case] range: (usize, usize), #[case] expected: bool) {
+        let edit = Edit {
+            range,
+            content: String::new(),
+        };
+        assert_eq!(changes_inside(&edit, (2, 6)), expected);
+    }
+}
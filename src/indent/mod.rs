@@ -0,0 +1,287 @@
+//! Indentation helpers for structural passes.
+//!
+//! Passes that move or rebuild code need to know how deep a node is nested,
+//! how the file indents, and how to indent a block of text to a given level.
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+/// Widths tried by `IndentStyle::detect`.
+const DETECTABLE_WIDTHS: &[usize] = &[2, 3, 4, 8];
+
+/// Character used for indentation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentKind {
+    /// Indent with spaces
+    #[default]
+    Spaces,
+    /// Indent with tabs
+    Tabs,
+}
+
+impl IndentKind {
+    const SPACES: &'static str = "spaces";
+    const TABS: &'static str = "tabs";
+
+    /// Get the string representation of the indent kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndentKind::Spaces => Self::SPACES,
+            IndentKind::Tabs => Self::TABS,
+        }
+    }
+}
+
+/// How one indentation level is written.
+///
+/// Formatter configs can embed it to make indentation configurable.
+///
+/// # Examples
+/// ```yaml
+/// indent:
+///   kind: tabs
+///   width: 4
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndentStyle {
+    /// Character used for indentation
+    pub kind: IndentKind,
+    /// Columns per level; also the width of a tab
+    pub width: usize,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self::spaces(4)
+    }
+}
+
+impl IndentStyle {
+    /// Create a style indenting with `width` spaces per level.
+    pub const fn spaces(width: usize) -> Self {
+        Self {
+            kind: IndentKind::Spaces,
+            width,
+        }
+    }
+
+    /// Create a style indenting with one tab per level, tabs being `width` columns wide.
+    pub const fn tabs(width: usize) -> Self {
+        Self {
+            kind: IndentKind::Tabs,
+            width,
+        }
+    }
+
+    /// Get the indentation of `level` levels.
+    pub fn indentation(&self, level: usize) -> String {
+        match self.kind {
+            IndentKind::Spaces => " ".repeat(level * self.width),
+            IndentKind::Tabs => "\t".repeat(level),
+        }
+    }
+
+    /// Get the indentation of `columns` columns: whole levels, then spaces for the rest.
+    fn indentation_of_columns(&self, columns: usize) -> String {
+        let width = self.width.max(1);
+        let mut indentation = self.indentation(columns / width);
+        indentation.push_str(&" ".repeat(columns % width));
+        indentation
+    }
+
+    /// Get the width in columns of leading whitespace, counting tabs as `width` columns.
+    pub fn columns(&self, whitespace: &str) -> usize {
+        whitespace
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .map(|c| if c == '\t' { self.width } else { 1 })
+            .sum()
+    }
+
+    /// Detect the indentation style of `source` from the indentation of its lines.
+    ///
+    /// Tabs win if more lines are indented with tabs than with spaces. The
+    /// width of space indentation is the largest width that fits the most
+    /// indentation changes between lines.
+    ///
+    /// # Returns
+    /// The detected style, or `None` if no line is indented
+    pub fn detect(source: &str) -> Option<Self> {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        let mut steps = Vec::new();
+        let mut previous = 0;
+        for line in source.lines().filter(|line| !line.trim().is_empty()) {
+            match line.as_bytes()[0] {
+                b'\t' => tab_lines += 1,
+                b' ' => space_lines += 1,
+                _ => {}
+            }
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            if spaces != previous {
+                steps.push(spaces.abs_diff(previous));
+            }
+            previous = spaces;
+        }
+
+        if tab_lines == 0 && space_lines == 0 {
+            return None;
+        }
+        if tab_lines > space_lines {
+            return Some(Self::tabs(Self::default().width));
+        }
+        let width = DETECTABLE_WIDTHS
+            .iter()
+            .copied()
+            .max_by_key(|&width| {
+                let fitting = steps.iter().filter(|&&step| step % width == 0).count();
+                (fitting, width)
+            })
+            .unwrap_or(Self::default().width);
+        Some(Self::spaces(width))
+    }
+}
+
+/// Get the indentation level of `node` from the tree.
+///
+/// The level is the number of distinct lines, before the line of `node`, on
+/// which its ancestors other than the root start. In most grammars this is
+/// the number of blocks, argument lists, and similar constructs the line of
+/// `node` is nested in, whatever the current indentation of the source.
+pub fn indent_level(node: &Node) -> usize {
+    let row = node.start_position().row;
+    let mut rows = Vec::new();
+    let mut current = node.parent();
+    while let Some(ancestor) = current {
+        current = ancestor.parent();
+        if current.is_none() {
+            break;
+        }
+        let ancestor_row = ancestor.start_position().row;
+        if ancestor_row < row && !rows.contains(&ancestor_row) {
+            rows.push(ancestor_row);
+        }
+    }
+    rows.len()
+}
+
+/// Get the leading whitespace of the line containing the byte at `offset`.
+pub fn line_indentation(source: &str, offset: usize) -> &str {
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &source[line_start..];
+    let end = line
+        .find(|c: char| c != ' ' && c != '\t')
+        .unwrap_or(line.len());
+    &line[..end]
+}
+
+/// Indent a block of text to `level` levels, keeping its relative indentation.
+///
+/// The indentation common to all non-blank lines is replaced by `level`
+/// levels of `style`; deeper lines keep their extra columns, rewritten in
+/// `style`. Blank lines become empty.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::indent::{reindent, IndentStyle};
+///
+/// let block = "    if x {\n        y();\n    }";
+/// assert_eq!(
+///     reindent(block, 1, &IndentStyle::tabs(4)),
+///     "\tif x {\n\t\ty();\n\t}"
+/// );
+/// ```
+pub fn reindent(text: &str, level: usize, style: &IndentStyle) -> String {
+    let common = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| style.columns(line))
+        .min()
+        .unwrap_or(0);
+    let base = style.indentation(level);
+
+    let mut result = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            result.push('\n');
+        }
+        let content = line.trim_start_matches([' ', '\t']);
+        if content.trim().is_empty() {
+            continue;
+        }
+        result.push_str(&base);
+        result.push_str(&style.indentation_of_columns(style.columns(line) - common));
+        result.push_str(content);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn parse(source: &str) -> tree_sitter::Tree {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    #[rstest]
+    #[case("{\n  \"a\": {\n    \"b\": 1\n  }\n}\n", Some(IndentStyle::spaces(2)))]
+    #[case("{\n    \"a\": [\n        1\n    ]\n}\n", Some(IndentStyle::spaces(4)))]
+    #[case("{\n\t\"a\": 1,\n\t\"b\": 2\n}\n", Some(IndentStyle::tabs(4)))]
+    #[case("{\"a\": 1}\n", None)]
+    fn test_detect(#[case] source: &str, #[case] expected: Option<IndentStyle>) {
+        assert_eq!(IndentStyle::detect(source), expected);
+    }
+
+    #[test]
+    fn test_indent_level() {
+        // The indentation of the source does not matter
+        let source = "{\n\"a\": {\n\"b\": [\n1, 2]},\n\"c\": 3}";
+        let tree = parse(source);
+        let level_at = |offset: usize| {
+            let node = tree
+                .root_node()
+                .descendant_for_byte_range(offset, offset)
+                .unwrap();
+            indent_level(&node)
+        };
+
+        assert_eq!(level_at(source.find("\"a\"").unwrap()), 1);
+        assert_eq!(level_at(source.find("\"b\"").unwrap()), 2);
+        assert_eq!(level_at(source.find('1').unwrap()), 3);
+        assert_eq!(level_at(source.find("\"c\"").unwrap()), 1);
+    }
+
+    #[test]
+    fn test_line_indentation() {
+        let source = "a\n  \tb c\n";
+
+        assert_eq!(line_indentation(source, source.find('c').unwrap()), "  \t");
+        assert_eq!(line_indentation(source, 0), "");
+    }
+
+    #[rstest]
+    #[case(
+        "  a\n    b\n\n  c",
+        1,
+        IndentStyle::spaces(4),
+        "    a\n      b\n\n    c"
+    )]
+    #[case("\ta\n\t\tb", 0, IndentStyle::spaces(2), "a\n  b")]
+    #[case("a\n  b", 2, IndentStyle::tabs(2), "\t\ta\n\t\t\tb")]
+    fn test_reindent(
+        #[case] text: &str,
+        #[case] level: usize,
+        #[case] style: IndentStyle,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(reindent(text, level, &style), expected);
+    }
+}
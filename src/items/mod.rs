@@ -0,0 +1,309 @@
+//! Sorting, deduplication, and grouping of items for structural passes.
+//!
+//! These are the common steps of `StructuredPass::transform` in passes
+//! such as import sorting: order the items by a key, drop duplicates, and
+//! split them into groups rendered with blank lines in between.
+//!
+//! # Examples
+//! ```
+//! use fmt_runner::items::{dedup_by_key, group_by_key, join_groups, sort_by_key, SortOrder};
+//!
+//! let mut imports = vec!["crate::b", "std::io", "crate::a", "std::io"];
+//! sort_by_key(&mut imports, SortOrder::Natural, |import| *import);
+//! dedup_by_key(&mut imports, |import| *import);
+//! let groups = group_by_key(imports, |import| !import.starts_with("std::"));
+//!
+//! assert_eq!(
+//!     join_groups(&groups, 1, |import| format!("use {import};")),
+//!     "use std::io;\n\nuse crate::a;\nuse crate::b;"
+//! );
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// How the keys of items are compared when sorting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Compare keys byte by byte
+    #[default]
+    Lexical,
+    /// Compare runs of digits by their value and the rest byte by byte,
+    /// so `item2` sorts before `item10`
+    Natural,
+    /// Compare the numbers keys start with; keys without one sort last
+    Numeric,
+    /// Compare keys byte by byte, ignoring case
+    #[serde(rename = "case-insensitive")]
+    CaseInsensitive,
+}
+
+impl SortOrder {
+    const LEXICAL: &'static str = "lexical";
+    const NATURAL: &'static str = "natural";
+    const NUMERIC: &'static str = "numeric";
+    const CASE_INSENSITIVE: &'static str = "case-insensitive";
+
+    /// Get the string representation of the sort order
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Lexical => Self::LEXICAL,
+            SortOrder::Natural => Self::NATURAL,
+            SortOrder::Numeric => Self::NUMERIC,
+            SortOrder::CaseInsensitive => Self::CASE_INSENSITIVE,
+        }
+    }
+
+    /// Compare two keys in this order.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            SortOrder::Lexical => a.cmp(b),
+            SortOrder::Natural => natural_cmp(a, b),
+            SortOrder::Numeric => numeric_cmp(a, b),
+            SortOrder::CaseInsensitive => a
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.chars().flat_map(char::to_lowercase)),
+        }
+    }
+}
+
+/// Sort items by a string key, keeping the order of items with equal keys.
+///
+/// # Arguments
+/// * `items` - The items to sort
+/// * `order` - How keys are compared
+/// * `key` - Gets the key of an item
+pub fn sort_by_key<T, K, F>(items: &mut [T], order: SortOrder, key: F)
+where
+    K: AsRef<str>,
+    F: Fn(&T) -> K,
+{
+    items.sort_by(|a, b| order.compare(key(a).as_ref(), key(b).as_ref()));
+}
+
+/// Remove every item whose key an earlier item already has.
+///
+/// Unlike `Vec::dedup_by_key`, duplicates need not be adjacent.
+///
+/// # Returns
+/// The removed items, in their original order, e.g. to report them
+pub fn dedup_by_key<T, K, F>(items: &mut Vec<T>, key: F) -> Vec<T>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    let mut seen = HashSet::new();
+    let mut removed = Vec::new();
+    let mut kept = Vec::with_capacity(items.len());
+    for item in items.drain(..) {
+        if seen.insert(key(&item)) {
+            kept.push(item);
+        } else {
+            removed.push(item);
+        }
+    }
+    *items = kept;
+    removed
+}
+
+/// Items sharing a group key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group<K, T> {
+    /// The key shared by the items
+    pub key: K,
+    /// The items, in their original order
+    pub items: Vec<T>,
+}
+
+/// Split items into groups by key, ordered by key.
+///
+/// Items keep their relative order within a group, so sort them before
+/// grouping to get sorted groups.
+pub fn group_by_key<T, K, F>(items: Vec<T>, key: F) -> Vec<Group<K, T>>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    let mut groups: Vec<Group<K, T>> = Vec::new();
+    for item in items {
+        let item_key = key(&item);
+        match groups.binary_search_by(|group| group.key.cmp(&item_key)) {
+            Ok(index) => groups[index].items.push(item),
+            Err(index) => groups.insert(
+                index,
+                Group {
+                    key: item_key,
+                    items: vec![item],
+                },
+            ),
+        }
+    }
+    groups
+}
+
+/// Render groups one item per line, with `blank_lines` empty lines between groups.
+///
+/// # Arguments
+/// * `groups` - The groups to render
+/// * `blank_lines` - Number of empty lines separating groups
+/// * `render` - Renders one item, without a line ending
+pub fn join_groups<K, T, F>(groups: &[Group<K, T>], blank_lines: usize, render: F) -> String
+where
+    F: Fn(&T) -> String,
+{
+    let separator = "\n".repeat(blank_lines + 1);
+    groups
+        .iter()
+        .filter(|group| !group.items.is_empty())
+        .map(|group| {
+            group
+                .items
+                .iter()
+                .map(&render)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join(&separator)
+}
+
+/// Compare runs of ASCII digits by value and everything else byte by byte.
+fn natural_cmp(mut a: &str, mut b: &str) -> Ordering {
+    loop {
+        if a.is_empty() || b.is_empty() {
+            return a.len().min(1).cmp(&b.len().min(1));
+        }
+        let (chunk_a, rest_a) = split_chunk(a);
+        let (chunk_b, rest_b) = split_chunk(b);
+        let ordering = if is_digits(chunk_a) && is_digits(chunk_b) {
+            let value_a = chunk_a.trim_start_matches('0');
+            let value_b = chunk_b.trim_start_matches('0');
+            value_a
+                .len()
+                .cmp(&value_b.len())
+                .then_with(|| value_a.cmp(value_b))
+        } else {
+            chunk_a.cmp(chunk_b)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        a = rest_a;
+        b = rest_b;
+    }
+}
+
+/// Split off the leading run of digits or non-digits of a non-empty string.
+fn split_chunk(text: &str) -> (&str, &str) {
+    let digits = text.starts_with(|c: char| c.is_ascii_digit());
+    let end = text
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
+
+fn is_digits(chunk: &str) -> bool {
+    chunk.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Compare the numbers keys start with, then the keys themselves.
+fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    match (leading_number(a), leading_number(b)) {
+        (Some(x), Some(y)) => x.total_cmp(&y).then_with(|| a.cmp(b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// Parse the decimal number a key starts with, if any.
+fn leading_number(key: &str) -> Option<f64> {
+    let key = key.trim_start();
+    let sign = usize::from(key.starts_with(['-', '+']));
+    let integer = key[sign..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(key.len(), |end| sign + end);
+    let mut end = integer;
+    if key[integer..].starts_with('.') {
+        let fraction = key[integer + 1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(key.len(), |end| integer + 1 + end);
+        if fraction > integer + 1 {
+            end = fraction;
+        }
+    }
+    key[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn sorted(order: SortOrder, keys: &[&'static str]) -> Vec<&'static str> {
+        let mut keys = keys.to_vec();
+        sort_by_key(&mut keys, order, |key| *key);
+        keys
+    }
+
+    #[rstest]
+    #[case(SortOrder::Lexical, &["b10", "B", "a2", "b9"], &["B", "a2", "b10", "b9"])]
+    #[case(SortOrder::Natural, &["b10", "a2", "b9", "b09x", "b"], &["a2", "b", "b9", "b09x", "b10"])]
+    #[case(SortOrder::Numeric, &["10 kb", "x", "-1.5", "2", "a"], &["-1.5", "2", "10 kb", "a", "x"])]
+    #[case(SortOrder::CaseInsensitive, &["b", "B", "a", "A"], &["a", "A", "b", "B"])]
+    fn test_sort_orders(
+        #[case] order: SortOrder,
+        #[case] keys: &[&'static str],
+        #[case] expected: &[&'static str],
+    ) {
+        assert_eq!(sorted(order, keys), expected);
+    }
+
+    #[test]
+    fn test_sort_order_names() {
+        for order in [
+            SortOrder::Lexical,
+            SortOrder::Natural,
+            SortOrder::Numeric,
+            SortOrder::CaseInsensitive,
+        ] {
+            let name = serde_yaml::to_string(&order).unwrap();
+            assert_eq!(name.trim(), order.as_str());
+        }
+    }
+
+    #[test]
+    fn test_dedup_keeps_first_occurrence() {
+        let mut items = vec![("a", 1), ("b", 2), ("a", 3), ("c", 4), ("b", 5)];
+        let removed = dedup_by_key(&mut items, |item| item.0);
+        assert_eq!(items, [("a", 1), ("b", 2), ("c", 4)]);
+        assert_eq!(removed, [("a", 3), ("b", 5)]);
+    }
+
+    #[test]
+    fn test_group_and_join() {
+        let groups = group_by_key(vec![3, 10, 1, 12, 2], |n| *n >= 10);
+        assert_eq!(
+            groups,
+            [
+                Group {
+                    key: false,
+                    items: vec![3, 1, 2]
+                },
+                Group {
+                    key: true,
+                    items: vec![10, 12]
+                },
+            ]
+        );
+        assert_eq!(
+            join_groups(&groups, 2, i32::to_string),
+            "3\n1\n2\n\n\n10\n12"
+        );
+        assert_eq!(join_groups(&groups, 0, i32::to_string), "3\n1\n2\n10\n12");
+    }
+}
@@ -1,11 +1,39 @@
+pub mod align;
 mod cli;
+pub mod comments;
 mod core;
+pub mod indent;
+pub mod items;
 pub mod parser;
 mod pipeline;
 pub mod supported_extension;
+pub mod walk;
+pub mod wrap;
 
-pub use cli::{cli_builder, CliBuilder, CliError, CliResult};
-pub use core::Engine;
-pub use parser::{LanguageProvider, ParseState, Parser};
-pub use pipeline::{Edit, EditTarget, Pass, Pipeline, StructuredPass};
-pub use supported_extension::SupportedExtension;
+/// Re-export of `clap`, for defining custom subcommands with `CliBuilder::add_command`.
+pub use clap;
+#[cfg(feature = "mmap")]
+pub use cli::MappedFile;
+pub use cli::{
+    cli_builder, CliBuilder, CliDispatcher, CliError, CliResult, CollectorConfig, ConfigError,
+    ConfigMigrator, ConfigPrompt, ExitStatus, FileCollector, FileReader, FileRecord, FormatMode,
+    HumanReporter, JsonReporter, QuietReporter, ReadOutcome, ReaderConfig, Reporter, RunStatus,
+    SarifReporter, SymlinkPolicy,
+};
+pub use core::{
+    CancellationToken, Diagnostic, Encoding, Engine, EngineCache, EngineError, EngineObserver,
+    FileError, FileFormatOutcome, FileWriter, FirstStop, FormatResult, LanguageRegistry,
+    LineEndings, LineIndex, LineRange, ParseErrorPolicy, ParseMetrics, PassPanic, PassTiming,
+    Position, PositionEncoding, RuleLevel, RunMetrics, RunReport, Severity, TracedEdit,
+};
+pub use parser::{
+    GrammarError, LanguageCapabilities, LanguageProvider, ParseState, Parser, TreeDivergence,
+};
+pub use pipeline::{
+    serialized_hash, stable_hash, BlankLinesPass, BuildCache, ConflictPolicy, ConsistencyPass,
+    Edit, EditTarget, ExternalPass, FinalNewlinePass, HasWhitespaceConfig, LineEdit, LinePass,
+    Pass, Phase, Pipeline, PipelineError, ProjectEdit, ProjectFile, ProjectPass, QueryCache,
+    QueryCaptures, QueryPass, Rule, RuntimeOptions, SourceMap, SourceMapSegment, Spanned,
+    StableHasher, StructuredContext, StructuredPass, TrailingWhitespacePass, WhitespaceConfig,
+};
+pub use supported_extension::{FileMatcher, SupportedExtension};
@@ -0,0 +1,262 @@
+/// Grammar-level facts that generic, language-agnostic passes rely on.
+///
+/// Built-in passes (suppression comments, header insertion, comment
+/// preservation, verbatim string handling) query these instead of
+/// hard-coding node kinds or comment syntax for a particular language.
+/// All values are `'static` so capabilities can be declared in a `static`.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::LanguageCapabilities;
+///
+/// static RUST_CAPABILITIES: LanguageCapabilities = LanguageCapabilities::new()
+///     .with_line_comment("//")
+///     .with_block_comment("/*", "*/")
+///     .with_statement_terminator(";")
+///     .with_comment_node_kinds(&["line_comment", "block_comment"])
+///     .with_string_node_kinds(&["string_literal", "raw_string_literal"]);
+///
+/// assert!(RUST_CAPABILITIES.has_block_comments());
+/// assert!(RUST_CAPABILITIES.is_string_kind("raw_string_literal"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageCapabilities {
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    statement_terminator: Option<&'static str>,
+    comment_node_kinds: &'static [&'static str],
+    string_node_kinds: &'static [&'static str],
+    suppression_markers: (&'static str, &'static str),
+    ignore_marker: &'static str,
+    disable_file_marker: &'static str,
+}
+
+/// Comment node kinds used by most tree-sitter grammars.
+const DEFAULT_COMMENT_KINDS: &[&str] = &["comment", "line_comment", "block_comment"];
+
+/// String node kinds used by most tree-sitter grammars.
+const DEFAULT_STRING_KINDS: &[&str] = &["string", "string_literal", "raw_string_literal"];
+
+/// Comments turning formatting off and back on, as used by many formatters.
+const DEFAULT_SUPPRESSION_MARKERS: (&str, &str) = ("fmt: off", "fmt: on");
+
+/// Comment ignoring the next node, the base of the other ignore comments.
+const DEFAULT_IGNORE_MARKER: &str = "fmt-ignore";
+
+/// Comment near the top of a file that leaves the whole file unformatted.
+const DEFAULT_DISABLE_FILE_MARKER: &str = "fmt-disable-file";
+
+impl LanguageCapabilities {
+    /// Create capabilities with no comment syntax and the common node kind names.
+    pub const fn new() -> Self {
+        Self {
+            line_comment: None,
+            block_comment: None,
+            statement_terminator: None,
+            comment_node_kinds: DEFAULT_COMMENT_KINDS,
+            string_node_kinds: DEFAULT_STRING_KINDS,
+            suppression_markers: DEFAULT_SUPPRESSION_MARKERS,
+            ignore_marker: DEFAULT_IGNORE_MARKER,
+            disable_file_marker: DEFAULT_DISABLE_FILE_MARKER,
+        }
+    }
+
+    /// Set the line comment prefix (e.g. `//` or `#`).
+    pub const fn with_line_comment(mut self, prefix: &'static str) -> Self {
+        self.line_comment = Some(prefix);
+        self
+    }
+
+    /// Set the block comment delimiters (e.g. `/*` and `*/`).
+    pub const fn with_block_comment(mut self, open: &'static str, close: &'static str) -> Self {
+        self.block_comment = Some((open, close));
+        self
+    }
+
+    /// Set the token terminating statements (e.g. `;`).
+    pub const fn with_statement_terminator(mut self, terminator: &'static str) -> Self {
+        self.statement_terminator = Some(terminator);
+        self
+    }
+
+    /// Set the node kinds the grammar uses for comments.
+    pub const fn with_comment_node_kinds(mut self, kinds: &'static [&'static str]) -> Self {
+        self.comment_node_kinds = kinds;
+        self
+    }
+
+    /// Set the node kinds whose text must be kept verbatim (string literals).
+    pub const fn with_string_node_kinds(mut self, kinds: &'static [&'static str]) -> Self {
+        self.string_node_kinds = kinds;
+        self
+    }
+
+    /// Set the comment texts turning formatting off and back on (e.g. `fmt: off` and `fmt: on`).
+    pub const fn with_suppression_markers(mut self, off: &'static str, on: &'static str) -> Self {
+        self.suppression_markers = (off, on);
+        self
+    }
+
+    /// Set the comment text ignoring the next node (e.g. `myfmt-ignore`).
+    ///
+    /// `{marker}-next-line` ignores the next line instead, and both can be
+    /// limited to some passes or rules, e.g. `myfmt-ignore: SortKeys, FMT001`.
+    pub const fn with_ignore_marker(mut self, marker: &'static str) -> Self {
+        self.ignore_marker = marker;
+        self
+    }
+
+    /// Set the directive leaving a whole file unformatted (e.g. `myfmt-disable-file`).
+    ///
+    /// The directive only counts in the first lines of a file.
+    pub const fn with_disable_file_marker(mut self, marker: &'static str) -> Self {
+        self.disable_file_marker = marker;
+        self
+    }
+
+    /// Get the line comment prefix, if the language has line comments.
+    pub fn line_comment(&self) -> Option<&'static str> {
+        self.line_comment
+    }
+
+    /// Get the block comment delimiters, if the language has block comments.
+    pub fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        self.block_comment
+    }
+
+    /// Returns true if the language has block comments.
+    pub fn has_block_comments(&self) -> bool {
+        self.block_comment.is_some()
+    }
+
+    /// Returns true if the language has any comment syntax.
+    pub fn has_comments(&self) -> bool {
+        self.line_comment.is_some() || self.block_comment.is_some()
+    }
+
+    /// Get the statement terminator token, if any.
+    pub fn statement_terminator(&self) -> Option<&'static str> {
+        self.statement_terminator
+    }
+
+    /// Get the node kinds used for comments.
+    pub fn comment_node_kinds(&self) -> &'static [&'static str] {
+        self.comment_node_kinds
+    }
+
+    /// Get the node kinds used for string literals.
+    pub fn string_node_kinds(&self) -> &'static [&'static str] {
+        self.string_node_kinds
+    }
+
+    /// Get the comment texts turning formatting off and back on.
+    pub fn suppression_markers(&self) -> (&'static str, &'static str) {
+        self.suppression_markers
+    }
+
+    /// Get the comment text ignoring the next node.
+    pub fn ignore_marker(&self) -> &'static str {
+        self.ignore_marker
+    }
+
+    /// Get the directive leaving a whole file unformatted.
+    pub fn disable_file_marker(&self) -> &'static str {
+        self.disable_file_marker
+    }
+
+    /// Returns true if nodes of `kind` are comments.
+    pub fn is_comment_kind(&self, kind: &str) -> bool {
+        self.comment_node_kinds.contains(&kind)
+    }
+
+    /// Returns true if nodes of `kind` are string literals.
+    pub fn is_string_kind(&self, kind: &str) -> bool {
+        self.string_node_kinds.contains(&kind)
+    }
+
+    /// Get the text of a comment without its delimiters and surrounding whitespace.
+    ///
+    /// Delimiters the language does not declare are kept.
+    pub fn comment_text<'t>(&self, comment: &'t str) -> &'t str {
+        let mut text = comment.trim();
+        if let Some(inner) = self
+            .line_comment
+            .and_then(|prefix| text.strip_prefix(prefix))
+        {
+            text = inner;
+        } else if let Some(inner) = self.block_comment.and_then(|(open, close)| {
+            text.strip_prefix(open)
+                .and_then(|text| text.strip_suffix(close))
+        }) {
+            text = inner;
+        }
+        text.trim()
+    }
+
+    /// Wrap `text` in a comment using the language's comment syntax.
+    ///
+    /// Prefers line comments and falls back to block comments.
+    /// Returns `None` if the language has no comment syntax.
+    pub fn comment(&self, text: &str) -> Option<String> {
+        if let Some(prefix) = self.line_comment {
+            return Some(format!("{prefix} {text}"));
+        }
+        self.block_comment
+            .map(|(open, close)| format!("{open} {text} {close}"))
+    }
+}
+
+impl Default for LanguageCapabilities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SHELL: LanguageCapabilities = LanguageCapabilities::new().with_line_comment("#");
+    static CSS: LanguageCapabilities = LanguageCapabilities::new()
+        .with_block_comment("/*", "*/")
+        .with_statement_terminator(";");
+
+    #[test]
+    fn test_defaults() {
+        let caps = LanguageCapabilities::default();
+        assert!(!caps.has_comments());
+        assert!(caps.is_comment_kind("comment"));
+        assert!(caps.is_string_kind("string"));
+        assert!(!caps.is_string_kind("identifier"));
+        assert_eq!(caps.comment("x"), None);
+    }
+
+    #[test]
+    fn test_comment_rendering() {
+        assert_eq!(SHELL.comment("hello").as_deref(), Some("# hello"));
+        assert_eq!(CSS.comment("hello").as_deref(), Some("/* hello */"));
+        assert!(CSS.has_block_comments());
+        assert_eq!(CSS.statement_terminator(), Some(";"));
+    }
+
+    #[test]
+    fn test_comment_text() {
+        assert_eq!(SHELL.comment_text("#  fmt: off "), "fmt: off");
+        assert_eq!(CSS.comment_text("/* fmt: on */"), "fmt: on");
+        assert_eq!(CSS.comment_text("// fmt: on"), "// fmt: on");
+        assert_eq!(SHELL.suppression_markers(), ("fmt: off", "fmt: on"));
+        assert_eq!(SHELL.ignore_marker(), "fmt-ignore");
+        assert_eq!(SHELL.disable_file_marker(), "fmt-disable-file");
+    }
+
+    #[test]
+    fn test_custom_node_kinds() {
+        static CUSTOM: LanguageCapabilities = LanguageCapabilities::new()
+            .with_comment_node_kinds(&["doc"])
+            .with_string_node_kinds(&["heredoc"]);
+
+        assert!(CUSTOM.is_comment_kind("doc"));
+        assert!(!CUSTOM.is_comment_kind("comment"));
+        assert!(CUSTOM.is_string_kind("heredoc"));
+    }
+}
@@ -0,0 +1,74 @@
+use thiserror::Error;
+use tree_sitter::{Language, LANGUAGE_VERSION, MIN_COMPATIBLE_LANGUAGE_VERSION};
+
+/// Error loading the grammar of a language into the tree-sitter runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GrammarError {
+    /// The grammar was generated for an ABI the linked runtime does not support
+    #[error(
+        "Grammar '{grammar}' uses tree-sitter ABI version {abi}, but the linked tree-sitter \
+         runtime supports ABI versions {min} to {max}; {hint}",
+        min = MIN_COMPATIBLE_LANGUAGE_VERSION,
+        max = LANGUAGE_VERSION,
+        hint = if *abi > LANGUAGE_VERSION {
+            "upgrade the tree-sitter dependency or regenerate the grammar with an older tree-sitter CLI"
+        } else {
+            "regenerate the grammar with a newer tree-sitter CLI"
+        }
+    )]
+    IncompatibleAbi {
+        /// Name of the grammar, or `unknown` if the grammar has no name
+        grammar: String,
+        /// ABI version the grammar was generated for
+        abi: usize,
+    },
+}
+
+impl GrammarError {
+    /// Check that the linked tree-sitter runtime can load `language`.
+    pub fn check(language: &Language) -> Result<(), GrammarError> {
+        check_abi(language.name().unwrap_or("unknown"), language.abi_version())
+    }
+}
+
+/// Check that the linked tree-sitter runtime supports the ABI version `abi`.
+fn check_abi(grammar: &str, abi: usize) -> Result<(), GrammarError> {
+    if (MIN_COMPATIBLE_LANGUAGE_VERSION..=LANGUAGE_VERSION).contains(&abi) {
+        return Ok(());
+    }
+    Err(GrammarError::IncompatibleAbi {
+        grammar: grammar.to_string(),
+        abi,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_accepts_linked_grammar() {
+        assert_eq!(
+            GrammarError::check(&tree_sitter_json::LANGUAGE.into()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_check_abi_reports_versions() {
+        assert_eq!(check_abi("json", LANGUAGE_VERSION), Ok(()));
+        assert_eq!(check_abi("json", MIN_COMPATIBLE_LANGUAGE_VERSION), Ok(()));
+
+        let newer = check_abi("json", LANGUAGE_VERSION + 1).unwrap_err();
+        let message = newer.to_string();
+        assert!(message.starts_with(&format!(
+            "Grammar 'json' uses tree-sitter ABI version {}, but the linked tree-sitter runtime \
+             supports ABI versions {MIN_COMPATIBLE_LANGUAGE_VERSION} to {LANGUAGE_VERSION}",
+            LANGUAGE_VERSION + 1
+        )));
+        assert!(message.contains("upgrade the tree-sitter dependency"));
+
+        let older = check_abi("json", MIN_COMPATIBLE_LANGUAGE_VERSION - 1).unwrap_err();
+        assert!(older.to_string().contains("with a newer tree-sitter CLI"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::parser::capabilities::LanguageCapabilities;
 use crate::supported_extension::SupportedExtension;
 use tree_sitter::Language;
 
@@ -34,7 +35,18 @@ pub trait LanguageProvider {
 
     /// Get the supported file extensions for this language.
     ///
-    /// Returns a reference to a static `SupportedExtension` that defines
-    /// which file extensions should be processed by this language's formatter.
+    /// Returns a reference to a static `SupportedExtension` (a `FileMatcher`)
+    /// that defines which files should be processed by this language's
+    /// formatter: by extension, file name, pattern or shebang line.
     fn supported_extension() -> &'static SupportedExtension;
+
+    /// Get the formatting capabilities of this language's grammar.
+    ///
+    /// Generic passes use these to work across languages without
+    /// per-language code. The default describes a language without
+    /// comment syntax and with the common comment/string node kinds.
+    fn capabilities() -> &'static LanguageCapabilities {
+        static DEFAULT_CAPABILITIES: LanguageCapabilities = LanguageCapabilities::new();
+        &DEFAULT_CAPABILITIES
+    }
 }
@@ -1,7 +1,13 @@
+mod capabilities;
+mod grammar_error;
 mod language_provider;
 mod parse_state;
 mod parser_core;
+mod tree_divergence;
 
+pub use capabilities::LanguageCapabilities;
+pub use grammar_error::GrammarError;
 pub use language_provider::LanguageProvider;
 pub use parse_state::ParseState;
 pub use parser_core::Parser;
+pub use tree_divergence::TreeDivergence;
@@ -1,12 +1,19 @@
-use tree_sitter::Tree;
+use ropey::Rope;
+use std::sync::OnceLock;
+use tree_sitter::{Point, Tree};
 
 /// State for parsing, containing source text and optional parse tree.
 ///
 /// This structure maintains the source code and its corresponding parse tree,
 /// providing a clean interface for accessing and managing the parsing state.
+/// The source is kept in a rope, so an edit takes time logarithmic in its
+/// length however large the source is; `source` flattens it into a string
+/// on the first call after an edit.
 #[derive(Debug)]
 pub struct ParseState {
-    pub(crate) source: String,
+    rope: Rope,
+    /// The rope as a string, once `source` was called since the last edit
+    text: OnceLock<String>,
     pub(crate) tree: Option<Tree>,
 }
 
@@ -24,7 +31,11 @@ impl ParseState {
     /// assert_eq!(state.source(), "fn main() {}");
     /// ```
     pub fn new(source: String) -> Self {
-        Self { source, tree: None }
+        Self {
+            rope: Rope::from_str(&source),
+            text: OnceLock::from(source),
+            tree: None,
+        }
     }
 
     /// Get a reference to the latest parse tree, if any.
@@ -36,13 +47,98 @@ impl ParseState {
 
     /// Access the current source text.
     pub fn source(&self) -> &str {
-        &self.source
+        self.text.get_or_init(|| self.rope.to_string())
+    }
+
+    /// Take the current source text, dropping the tree.
+    pub fn into_source(self) -> String {
+        let Self { rope, text, .. } = self;
+        text.into_inner().unwrap_or_else(|| rope.to_string())
+    }
+
+    /// Get the length of the source in bytes.
+    pub(crate) fn source_len(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    /// Get the rope holding the source.
+    pub(crate) fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
+    /// Returns true if `byte` is within the source and on a character boundary.
+    pub(crate) fn is_char_boundary(&self, byte: usize) -> bool {
+        byte <= self.rope.len_bytes()
+            && self.rope.char_to_byte(self.rope.byte_to_char(byte)) == byte
+    }
+
+    /// Get the row and byte column of the offset `byte` in the source.
+    pub(crate) fn position(&self, byte: usize) -> Point {
+        let row = self.rope.byte_to_line(byte);
+        Point {
+            row,
+            column: byte - self.rope.line_to_byte(row),
+        }
+    }
+
+    /// Replace `start..end` of the source with `text`, leaving the tree as it is.
+    ///
+    /// Both offsets must be character boundaries within the source.
+    pub(crate) fn replace(&mut self, start: usize, end: usize, text: &str) {
+        let start_char = self.rope.byte_to_char(start);
+        if end > start {
+            self.rope.remove(start_char..self.rope.byte_to_char(end));
+        }
+        if !text.is_empty() {
+            self.rope.insert(start_char, text);
+        }
+        self.text = OnceLock::new();
+    }
+
+    /// Copy the source and tree; copying the rope takes constant time.
+    pub(crate) fn snapshot(&self) -> Self {
+        Self {
+            rope: self.rope.clone(),
+            text: OnceLock::new(),
+            tree: self.tree.clone(),
+        }
+    }
+
+    /// Replace the source without updating the tree, as a buggy pass might.
+    #[cfg(test)]
+    pub(crate) fn set_source(&mut self, source: String) {
+        self.rope = Rope::from_str(&source);
+        self.text = OnceLock::from(source);
     }
 
     /// Check if the parse state has a valid tree.
     pub fn has_tree(&self) -> bool {
         self.tree.is_some()
     }
+
+    /// Get the byte ranges of the syntax errors in the tree, in source order.
+    ///
+    /// Every `ERROR` node and every `MISSING` node inserted by error
+    /// recovery counts once; nodes inside an `ERROR` node are not reported
+    /// separately. Empty if the source has not been parsed yet.
+    pub fn syntax_errors(&self) -> Vec<(usize, usize)> {
+        let Some(tree) = &self.tree else {
+            return Vec::new();
+        };
+
+        let mut errors = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if node.is_error() || node.is_missing() {
+                errors.push((node.start_byte(), node.end_byte()));
+            } else if node.has_error() {
+                let mut cursor = node.walk();
+                stack.extend(node.children(&mut cursor));
+            }
+        }
+        errors.sort_unstable();
+        errors
+    }
 }
 
 #[cfg(test)]
@@ -63,4 +159,22 @@ mod tests {
         let state = ParseState::new("test".to_string());
         assert!(!state.has_tree());
     }
+
+    #[test]
+    fn test_syntax_errors() {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let mut parse = |source: &str| {
+            let mut state = ParseState::new(source.to_string());
+            state.tree = parser.parse(source, None);
+            state
+        };
+
+        assert!(ParseState::new("{".to_string()).syntax_errors().is_empty());
+        assert!(parse("{\"a\": [1, 2]}").syntax_errors().is_empty());
+        assert_eq!(parse("{\"a\": [1, 2}").syntax_errors(), vec![(11, 11)]);
+        assert_eq!(parse("{\"a\" 1}").syntax_errors().len(), 1);
+    }
 }
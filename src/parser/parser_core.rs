@@ -1,41 +1,189 @@
+use crate::core::CancellationToken;
+use crate::parser::grammar_error::GrammarError;
 use crate::parser::language_provider::LanguageProvider;
 use crate::parser::parse_state::ParseState;
-use tree_sitter::{InputEdit, Parser as TsParser};
+use crate::parser::tree_divergence::{first_difference, TreeDivergence};
+use ropey::Rope;
+use std::time::{Duration, Instant};
+use tree_sitter::{InputEdit, ParseOptions, Parser as TsParser, Point, Tree};
+
+/// Size of the chunks `Parser::parse_bytes` hands to tree-sitter.
+const PARSE_CHUNK_SIZE: usize = 64 * 1024;
 
 /// Generic parser that owns a tree-sitter parser.
 /// The source and tree are managed separately in ParseState.
+///
+/// A timeout and a cancellation token bound every parse, so a pathological
+/// source or a buggy grammar cannot hang a run. An aborted parse leaves the
+/// state without a tree.
 pub struct Parser<Language: LanguageProvider> {
     ts_parser: TsParser,
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    incremental_check: bool,
+    divergence: Option<TreeDivergence>,
     _marker: std::marker::PhantomData<Language>,
 }
 
 impl<Language: LanguageProvider> Parser<Language> {
     /// Create a new parser for the language.
+    ///
+    /// # Panics
+    /// Panics if the grammar of the language is incompatible with the linked
+    /// tree-sitter runtime; see `try_new`.
     pub fn new() -> Self {
+        Self::try_new().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Create a new parser for the language, if the linked tree-sitter runtime can load its grammar.
+    ///
+    /// # Returns
+    /// The parser, or an error naming the ABI version of the grammar and the
+    /// versions the runtime supports
+    pub fn try_new() -> Result<Self, GrammarError> {
+        let language = Language::language();
+        GrammarError::check(&language)?;
         let mut ts_parser = TsParser::new();
         ts_parser
-            .set_language(&Language::language())
-            .expect("Error loading grammar");
+            .set_language(&language)
+            .expect("grammar ABI version was checked");
 
-        Self {
+        Ok(Self {
             ts_parser,
+            timeout: None,
+            cancellation: None,
+            incremental_check: false,
+            divergence: None,
             _marker: std::marker::PhantomData,
-        }
+        })
+    }
+
+    /// Abort parses that take longer than `timeout`; `None` never aborts them.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Get the time after which parses are aborted, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Abort parses when `token` is cancelled.
+    pub fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// Get the token that aborts parses when cancelled, if any.
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Compare the tree after every `apply_edits` with a full parse of the new source.
+    ///
+    /// A tree that differs from the full parse is replaced by it, and the
+    /// difference is kept for `take_divergence`, naming the edit after which
+    /// the trees diverged. Every edit then costs a full parse, so this is
+    /// meant for testing passes and changes to incremental parsing.
+    pub fn set_incremental_check(&mut self, check: bool) {
+        self.incremental_check = check;
+    }
+
+    /// Returns true if trees are compared with a full parse after edits.
+    pub fn incremental_check(&self) -> bool {
+        self.incremental_check
+    }
+
+    /// Take the difference found by the last incremental check, if any.
+    pub fn take_divergence(&mut self) -> Option<TreeDivergence> {
+        self.divergence.take()
     }
 
     /// Parse the source in the state from scratch.
+    ///
+    /// The state is left without a tree if the parse is aborted.
     pub fn parse(&mut self, state: &mut ParseState) {
-        state.tree = self.ts_parser.parse(&state.source, None);
+        state.tree = self.parse_rope(state.rope(), None);
+    }
+
+    /// Parse UTF-8 text that is not owned by a `ParseState`, such as a mapped file.
+    ///
+    /// The text is handed to tree-sitter in chunks through its callback
+    /// API, so it is never copied as a whole.
+    ///
+    /// # Returns
+    /// The parse tree, or `None` if parsing was aborted
+    pub fn parse_bytes(&mut self, text: &[u8]) -> Option<Tree> {
+        self.parse_text(text, None)
     }
 
     /// Incrementally reparse using the existing tree (if any).
+    ///
+    /// The state is left without a tree if the parse is aborted.
     pub fn reparse(&mut self, state: &mut ParseState) {
-        state.tree = self.ts_parser.parse(&state.source, state.tree.as_ref());
+        state.tree = self.parse_rope(state.rope(), state.tree.as_ref());
+    }
+
+    /// Parse `text`, handing it to tree-sitter in chunks of `PARSE_CHUNK_SIZE`.
+    fn parse_text(&mut self, text: &[u8], old_tree: Option<&Tree>) -> Option<Tree> {
+        self.parse_chunks(
+            |offset| {
+                let start = offset.min(text.len());
+                &text[start..(start + PARSE_CHUNK_SIZE).min(text.len())]
+            },
+            old_tree,
+        )
+    }
+
+    /// Parse the text of `rope`, handing it to tree-sitter chunk by chunk.
+    fn parse_rope(&mut self, rope: &Rope, old_tree: Option<&Tree>) -> Option<Tree> {
+        self.parse_chunks(
+            |offset| {
+                if offset >= rope.len_bytes() {
+                    return &[][..];
+                }
+                let (chunk, start, _, _) = rope.chunk_at_byte(offset);
+                &chunk.as_bytes()[offset - start..]
+            },
+            old_tree,
+        )
+    }
+
+    /// Parse the text returned by `read` from a byte offset on, aborting when
+    /// the timeout expires or the token is cancelled.
+    fn parse_chunks<'a>(
+        &mut self,
+        mut read: impl FnMut(usize) -> &'a [u8],
+        old_tree: Option<&Tree>,
+    ) -> Option<Tree> {
+        let mut read = |offset: usize, _| read(offset);
+        if self.timeout.is_none() && self.cancellation.is_none() {
+            return self.ts_parser.parse_with_options(&mut read, old_tree, None);
+        }
+
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let cancellation = self.cancellation.clone();
+        let mut abort = |_: &tree_sitter::ParseState| {
+            deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                || cancellation
+                    .as_ref()
+                    .is_some_and(CancellationToken::is_cancelled)
+        };
+        let options = ParseOptions::new().progress_callback(&mut abort);
+        let tree = self
+            .ts_parser
+            .parse_with_options(&mut read, old_tree, Some(options));
+        if tree.is_none() {
+            // Start the next parse afresh instead of resuming this one
+            self.ts_parser.reset();
+        }
+        tree
     }
 
     /// Apply an edit to the source in the state and update tree-sitter's tree edit before reparsing.
     ///
-    /// `start_byte..old_end_byte` will be replaced with `new_text`.
+    /// `start_byte..old_end_byte` will be replaced with `new_text`. The rows
+    /// and columns of the edit are computed from the source, so the
+    /// reparsed tree keeps correct positions in multi-line sources.
     pub fn apply_edit(
         &mut self,
         state: &mut ParseState,
@@ -43,30 +191,142 @@ impl<Language: LanguageProvider> Parser<Language> {
         old_end_byte: usize,
         new_text: &str,
     ) {
-        state
-            .source
-            .replace_range(start_byte..old_end_byte, new_text);
-        if let Some(tree) = &mut state.tree {
-            let edit = InputEdit {
-                start_byte,
-                old_end_byte,
-                new_end_byte: start_byte + new_text.len(),
-                start_position: tree_sitter::Point {
-                    row: 0,
-                    column: start_byte,
-                },
-                old_end_position: tree_sitter::Point {
-                    row: 0,
-                    column: old_end_byte,
-                },
-                new_end_position: tree_sitter::Point {
-                    row: 0,
-                    column: start_byte + new_text.len(),
-                },
-            };
-            tree.edit(&edit);
+        self.apply_edits(state, &[(start_byte, old_end_byte, new_text)]);
+    }
+
+    /// Apply many edits to the source in the state, then reparse once.
+    ///
+    /// Each edit replaces `start..old_end` with its text. Edits are given in
+    /// the order they are applied, from the end of the source to its start:
+    /// every edit ends at or before the start of the previous one. Every
+    /// edit changes the rope of the state in time logarithmic in the length
+    /// of the source, so applying many edits to a large source is not
+    /// quadratic.
+    ///
+    /// # Panics
+    /// Panics if an edit ends before it starts, reaches past the source or
+    /// splits a character, or if the edits are out of order.
+    pub fn apply_edits(&mut self, state: &mut ParseState, edits: &[(usize, usize, &str)]) {
+        if edits.is_empty() {
+            return;
+        }
+        assert!(
+            edits.iter().all(|&(start, old_end, _)| start <= old_end
+                && state.is_char_boundary(start)
+                && state.is_char_boundary(old_end)),
+            "edits must end after they start, on character boundaries within the source"
+        );
+        assert!(
+            edits.windows(2).all(|pair| pair[1].1 <= pair[0].0),
+            "edits must be ordered from the end of the source to its start"
+        );
+
+        let before = (self.incremental_check && state.tree.is_some()).then(|| state.snapshot());
+        // Text before an edit is unchanged by the edits after it, so
+        // positions are computed from the source as it is edited.
+        for &(start, old_end, text) in edits {
+            if state.tree.is_some() {
+                let edit = Self::input_edit(state, start, old_end, text);
+                if let Some(tree) = &mut state.tree {
+                    tree.edit(&edit);
+                }
+            }
+            state.replace(start, old_end, text);
         }
         self.reparse(state);
+
+        if let Some(before) = before {
+            self.check_reparse(state, before, edits);
+        }
+    }
+
+    /// Compare the reparsed tree of `state` with a full parse, keeping the full parse if they differ.
+    ///
+    /// `before` is the state the `edits` were applied to.
+    fn check_reparse(
+        &mut self,
+        state: &mut ParseState,
+        before: ParseState,
+        edits: &[(usize, usize, &str)],
+    ) {
+        let Some(reparsed) = &state.tree else {
+            return;
+        };
+        let Some(full) = self.parse_rope(state.rope(), None) else {
+            return;
+        };
+        let Some((incremental, expected)) =
+            first_difference(reparsed.root_node(), full.root_node())
+        else {
+            return;
+        };
+
+        let edit = match edits {
+            [edit] => Some(*edit),
+            _ => self.diverging_edit(before, edits),
+        };
+        self.divergence = Some(TreeDivergence {
+            edit: edit.map(|(start, old_end, text)| (start, old_end, text.to_string())),
+            incremental,
+            full: expected,
+        });
+        state.tree = Some(full);
+    }
+
+    /// Apply `edits` one at a time to `state`, finding the first after which the reparsed tree diverges.
+    fn diverging_edit<'e>(
+        &mut self,
+        mut state: ParseState,
+        edits: &[(usize, usize, &'e str)],
+    ) -> Option<(usize, usize, &'e str)> {
+        let check = std::mem::replace(&mut self.incremental_check, false);
+        let mut found = None;
+        for &edit in edits {
+            self.apply_edits(&mut state, &[edit]);
+            let (Some(reparsed), Some(full)) =
+                (state.tree.clone(), self.parse_rope(state.rope(), None))
+            else {
+                break;
+            };
+            if first_difference(reparsed.root_node(), full.root_node()).is_some() {
+                found = Some(edit);
+                break;
+            }
+        }
+        self.incremental_check = check;
+        found
+    }
+
+    /// Describe replacing `start_byte..old_end_byte` of the source of `state` with `new_text` to tree-sitter.
+    fn input_edit(
+        state: &ParseState,
+        start_byte: usize,
+        old_end_byte: usize,
+        new_text: &str,
+    ) -> InputEdit {
+        let start_position = state.position(start_byte);
+        InputEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte: start_byte + new_text.len(),
+            start_position,
+            old_end_position: state.position(old_end_byte),
+            new_end_position: Self::end_position(start_position, new_text),
+        }
+    }
+
+    /// Get the position reached after inserting `text` at `start`.
+    fn end_position(start: Point, text: &str) -> Point {
+        match text.rfind('\n') {
+            Some(last_break) => Point {
+                row: start.row + text.matches('\n').count(),
+                column: text.len() - last_break - 1,
+            },
+            None => Point {
+                row: start.row,
+                column: start.column + text.len(),
+            },
+        }
     }
 }
 
@@ -75,3 +335,281 @@ impl<Language: LanguageProvider> Default for Parser<Language> {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::supported_extension::SupportedExtension;
+    use rstest::rstest;
+
+    struct JsonLanguage;
+
+    impl LanguageProvider for JsonLanguage {
+        fn language() -> tree_sitter::Language {
+            tree_sitter_json::LANGUAGE.into()
+        }
+
+        fn supported_extension() -> &'static SupportedExtension {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_parse_bytes_matches_parse_across_chunks() {
+        let items: Vec<String> = (0..20_000).map(|i| format!("\"k{i}\": {i}")).collect();
+        let source = format!("{{{}}}", items.join(", "));
+        assert!(source.len() > 2 * PARSE_CHUNK_SIZE);
+        let mut parser = Parser::<JsonLanguage>::new();
+        let mut state = ParseState::new(source.clone());
+        parser.parse(&mut state);
+
+        let tree = parser.parse_bytes(source.as_bytes()).unwrap();
+
+        assert_eq!(
+            tree.root_node().to_sexp(),
+            state.tree().unwrap().root_node().to_sexp()
+        );
+        assert!(!tree.root_node().has_error());
+    }
+
+    /// Apply `edits` one by one, checking the tree against a full parse after each.
+    fn assert_incremental_matches_full(source: &str, edits: &[(usize, usize, &str)]) {
+        let mut parser = Parser::<JsonLanguage>::new();
+        let mut state = ParseState::new(source.to_string());
+        parser.parse(&mut state);
+
+        for &(start, end, text) in edits {
+            parser.apply_edit(&mut state, start, end, text);
+            let mut full = ParseState::new(state.source().to_string());
+            parser.parse(&mut full);
+
+            let (incremental, full) = (state.tree().unwrap(), full.tree().unwrap());
+            assert_eq!(
+                incremental.root_node().to_sexp(),
+                full.root_node().to_sexp()
+            );
+            assert_eq!(positions(incremental), positions(full), "after {text:?}");
+        }
+    }
+
+    /// List the kind and positions of every node of the tree.
+    fn positions(tree: &Tree) -> Vec<(&'static str, Point, Point)> {
+        let mut positions = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            positions.push((node.kind(), node.start_position(), node.end_position()));
+            let mut cursor = node.walk();
+            stack.extend(node.children(&mut cursor));
+        }
+        positions
+    }
+
+    #[test]
+    fn test_apply_edits_matches_edits_one_by_one() {
+        let source = "{\n  \"a\": [1, 2],\n  \"b\": {\n    \"c\": null\n  }\n}\n";
+        let edits = [
+            (source.len() - 1, source.len() - 1, "\n"),
+            (35, 39, "true,\n    \"d\": false"),
+            (17, 17, "\n  "),
+            (13, 14, "20"),
+            (10, 11, "\n    1"),
+            (1, 1, "\n"),
+        ];
+        let mut parser = Parser::<JsonLanguage>::new();
+        let mut one_by_one = ParseState::new(source.to_string());
+        parser.parse(&mut one_by_one);
+        let mut batched = ParseState::new(source.to_string());
+        parser.parse(&mut batched);
+
+        for &(start, end, text) in &edits {
+            parser.apply_edit(&mut one_by_one, start, end, text);
+        }
+        parser.apply_edits(&mut batched, &edits);
+
+        assert_eq!(batched.source(), one_by_one.source());
+        let mut full = ParseState::new(batched.source().to_string());
+        parser.parse(&mut full);
+        assert!(!full.tree().unwrap().root_node().has_error());
+        assert_eq!(
+            positions(batched.tree().unwrap()),
+            positions(full.tree().unwrap())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "edits must be ordered")]
+    fn test_apply_edits_rejects_unordered_edits() {
+        let mut state = ParseState::new("[1, 2]".to_string());
+        Parser::<JsonLanguage>::new().apply_edits(&mut state, &[(1, 2, "3"), (4, 5, "4")]);
+    }
+
+    #[rstest]
+    #[case::single_reversed(&[(2, 1, "3")])]
+    #[case::last_reversed(&[(4, 5, "4"), (2, 1, "3")])]
+    #[case::past_the_end(&[(5, 7, "]")])]
+    #[case::inside_a_character(&[(2, 3, "x")])]
+    #[should_panic(expected = "edits must end after they start")]
+    fn test_apply_edits_rejects_invalid_edits(#[case] edits: &[(usize, usize, &str)]) {
+        let mut state = ParseState::new("[\"é\"]".to_string());
+        Parser::<JsonLanguage>::new().apply_edits(&mut state, edits);
+    }
+
+    #[test]
+    fn test_apply_edits_many_edits_to_a_large_source() {
+        let items: Vec<String> = (0..20_000).map(|i| format!("\"k{i}\":{i}")).collect();
+        let source = format!("{{{}}}", items.join(","));
+        let mut edits = Vec::new();
+        let mut offset = 1;
+        for item in &items {
+            let colon = offset + item.find(':').unwrap();
+            edits.push((colon + 1, colon + 1, " "));
+            offset += item.len() + 1;
+        }
+        edits.reverse();
+        let mut parser = Parser::<JsonLanguage>::new();
+        let mut state = ParseState::new(source.clone());
+        parser.parse(&mut state);
+
+        parser.apply_edits(&mut state, &edits);
+
+        assert_eq!(state.source(), source.replace(':', ": "));
+        let mut full = ParseState::new(state.source().to_string());
+        parser.parse(&mut full);
+        assert_eq!(
+            positions(state.tree().unwrap()),
+            positions(full.tree().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_apply_edit_keeps_positions_on_multiple_lines() {
+        let source = "{\n  \"a\": 1,\n  \"b\": [\n    2\n  ]\n}\n";
+        assert_incremental_matches_full(
+            source,
+            &[
+                // Replace within a line
+                (8, 9, "100"),
+                // Insert lines before later nodes
+                (2, 2, "\"x\": true,\n  \"y\": null,\n  "),
+                // Join lines
+                (1, 4, ""),
+                // Delete a line break on a later line
+                (source.len() - 2, source.len() - 1, ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_apply_edit_across_line_breaks() {
+        assert_incremental_matches_full(
+            "[\n1,\n2,\n3\n]",
+            &[(3, 8, "\n\n4,\n"), (0, 1, "[\n\n"), (4, 12, "")],
+        );
+    }
+
+    #[test]
+    fn test_parse_stops_when_limits_are_reached() {
+        let items: Vec<String> = (0..50_000)
+            .map(|i| format!("[{i}, {{\"k\": {i}}}]"))
+            .collect();
+        let source = format!("[{}]", items.join(", "));
+        let mut parser = Parser::<JsonLanguage>::new();
+        parser.set_timeout(Some(Duration::ZERO));
+        let mut state = ParseState::new(source.clone());
+
+        parser.parse(&mut state);
+        assert!(!state.has_tree());
+
+        let token = CancellationToken::new();
+        parser.set_timeout(None);
+        parser.set_cancellation(Some(token.clone()));
+        parser.parse(&mut state);
+        assert!(state.has_tree());
+        token.cancel();
+        assert!(parser.parse_bytes(source.as_bytes()).is_none());
+
+        // A parser whose parse was aborted starts afresh
+        parser.set_cancellation(None);
+        parser.parse(&mut state);
+        assert!(!state.tree().unwrap().root_node().has_error());
+    }
+
+    #[test]
+    fn test_edited_tree_keeps_positions_after_the_edit() {
+        let source = "[\n  1,\n  2,\n  3\n]";
+        let mut parser = Parser::<JsonLanguage>::new();
+        let mut state = ParseState::new(source.to_string());
+        parser.parse(&mut state);
+        // Turn `1` into two lines, before parsing again
+        let edit = Parser::<JsonLanguage>::input_edit(&state, 4, 5, "10,\n  11");
+        let mut tree = state.tree.unwrap();
+        assert_eq!(edit.start_position, Point { row: 1, column: 2 });
+        assert_eq!(edit.old_end_position, Point { row: 1, column: 3 });
+        assert_eq!(edit.new_end_position, Point { row: 2, column: 4 });
+        tree.edit(&edit);
+
+        let array = tree.root_node().child(0).unwrap();
+        let last = array.named_child(2).unwrap();
+        assert_eq!(last.start_position(), Point { row: 4, column: 2 });
+        assert_eq!(array.end_position(), Point { row: 5, column: 1 });
+    }
+
+    #[test]
+    fn test_end_position() {
+        let start = Point { row: 2, column: 4 };
+        type JsonParser = Parser<JsonLanguage>;
+        assert_eq!(
+            JsonParser::end_position(start, "ab"),
+            Point { row: 2, column: 6 }
+        );
+        assert_eq!(
+            JsonParser::end_position(start, "a\nbc"),
+            Point { row: 3, column: 2 }
+        );
+        assert_eq!(
+            JsonParser::end_position(start, "\n\n"),
+            Point { row: 4, column: 0 }
+        );
+    }
+
+    #[test]
+    fn test_incremental_check_accepts_correct_edits() {
+        let mut parser = Parser::<JsonLanguage>::new();
+        parser.set_incremental_check(true);
+        let mut state = ParseState::new("{\n  \"a\": [1,\n 2]\n}\n".to_string());
+        parser.parse(&mut state);
+
+        parser.apply_edits(&mut state, &[(14, 15, "\n  3"), (9, 9, "\n")]);
+
+        assert_eq!(parser.take_divergence(), None);
+        assert_eq!(state.source(), "{\n  \"a\": \n[1,\n \n  3]\n}\n");
+    }
+
+    #[test]
+    fn test_incremental_check_replaces_stale_tree() {
+        let mut parser = Parser::<JsonLanguage>::new();
+        parser.set_incremental_check(true);
+        let mut state = ParseState::new("[1,\n 2]".to_string());
+        parser.parse(&mut state);
+        // A line break inserted before `2` without telling tree-sitter
+        state.set_source("[1,\n \n2]".to_string());
+
+        parser.apply_edits(&mut state, &[(1, 2, "3")]);
+
+        let divergence = parser.take_divergence().unwrap();
+        assert_eq!(divergence.edit, Some((1, 2, "3".to_string())));
+        let mut full = ParseState::new(state.source().to_string());
+        parser.parse(&mut full);
+        assert_eq!(
+            state.tree().unwrap().root_node().to_sexp(),
+            full.tree().unwrap().root_node().to_sexp()
+        );
+        assert_eq!(
+            first_difference(
+                state.tree().unwrap().root_node(),
+                full.tree().unwrap().root_node()
+            ),
+            None
+        );
+    }
+}
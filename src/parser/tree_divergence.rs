@@ -0,0 +1,134 @@
+use crate::walk::iter_nodes;
+use std::fmt;
+use tree_sitter::Node;
+
+/// A difference between an incrementally reparsed tree and a full parse of the same source.
+///
+/// Reported by `Parser::take_divergence` when `Parser::set_incremental_check`
+/// is enabled. A divergence means the edits handed to tree-sitter did not
+/// describe the change of the source, so the reused parts of the old tree
+/// are stale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeDivergence {
+    /// The edit after which the trees diverged, as its start, old end and
+    /// new text, or `None` if only the edits together make the trees diverge
+    pub edit: Option<(usize, usize, String)>,
+    /// The first node of the incrementally reparsed tree that differs
+    pub incremental: String,
+    /// The node of the full parse in its place
+    pub full: String,
+}
+
+impl fmt::Display for TreeDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "incremental reparse diverged from a full parse")?;
+        if let Some((start, old_end, text)) = &self.edit {
+            write!(f, " after replacing {start}..{old_end} with {text:?}")?;
+        }
+        write!(f, ": found {}, expected {}", self.incremental, self.full)
+    }
+}
+
+/// Compare two trees node by node in preorder.
+///
+/// # Returns
+/// The descriptions of the first pair of nodes that differ in kind, byte
+/// range or position, with `incremental` first
+pub(crate) fn first_difference(incremental: Node, full: Node) -> Option<(String, String)> {
+    let mut incremental = iter_nodes(incremental);
+    let mut full = iter_nodes(full);
+    loop {
+        match (incremental.next(), full.next()) {
+            (None, None) => return None,
+            (a, b) if a.map(shape) == b.map(shape) => continue,
+            (a, b) => return Some((describe(a), describe(b))),
+        }
+    }
+}
+
+/// What has to match between a node and the node parsed in its place.
+fn shape(node: Node) -> (u16, bool, bool, std::ops::Range<usize>, tree_sitter::Range) {
+    (
+        node.kind_id(),
+        node.is_missing(),
+        node.is_error(),
+        node.byte_range(),
+        node.range(),
+    )
+}
+
+fn describe(node: Option<Node>) -> String {
+    let Some(node) = node else {
+        return "no node".to_string();
+    };
+    let (start, end) = (node.start_position(), node.end_position());
+    format!(
+        "{} at {}..{} ({}:{}-{}:{})",
+        node.kind(),
+        node.start_byte(),
+        node.end_byte(),
+        start.row,
+        start.column,
+        end.row,
+        end.column
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::{InputEdit, Point};
+
+    fn parser() -> tree_sitter::Parser {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        parser
+    }
+
+    #[test]
+    fn test_same_source_has_no_difference() {
+        let mut parser = parser();
+        let a = parser.parse("[1,\n 2]", None).unwrap();
+        let b = parser.parse("[1,\n 2]", None).unwrap();
+
+        assert_eq!(first_difference(a.root_node(), b.root_node()), None);
+    }
+
+    #[test]
+    fn test_stale_positions_are_found() {
+        let mut parser = parser();
+        let mut edited = parser.parse("[1,\n 2]", None).unwrap();
+        // Inserting a line break before `2`, described without the new row
+        edited.edit(&InputEdit {
+            start_byte: 5,
+            old_end_byte: 5,
+            new_end_byte: 6,
+            start_position: Point::new(1, 1),
+            old_end_position: Point::new(1, 1),
+            new_end_position: Point::new(1, 2),
+        });
+        let full = parser.parse("[1,\n \n2]", None).unwrap();
+
+        let (found, expected) = first_difference(edited.root_node(), full.root_node()).unwrap();
+
+        assert_eq!(found, "document at 0..8 (0:0-1:4)");
+        assert_eq!(expected, "document at 0..8 (0:0-2:2)");
+    }
+
+    #[test]
+    fn test_display_names_the_edit() {
+        let divergence = TreeDivergence {
+            edit: Some((5, 5, "\n".to_string())),
+            incremental: "number at 6..7 (1:2-1:3)".to_string(),
+            full: "number at 6..7 (2:0-2:1)".to_string(),
+        };
+
+        assert_eq!(
+            divergence.to_string(),
+            "incremental reparse diverged from a full parse after replacing 5..5 with \"\\n\": \
+             found number at 6..7 (1:2-1:3), expected number at 6..7 (2:0-2:1)"
+        );
+    }
+}
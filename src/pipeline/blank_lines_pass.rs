@@ -0,0 +1,170 @@
+use crate::parser::{LanguageCapabilities, LanguageProvider};
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::Pass;
+use crate::pipeline::verbatim::{overlaps_any, verbatim_ranges};
+use crate::pipeline::whitespace_config::HasWhitespaceConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+use tree_sitter::Node;
+
+/// Built-in pass collapsing runs of blank lines.
+///
+/// Runs longer than `whitespace.max_blank_lines` are shortened to that
+/// many lines. Lines holding only spaces and tabs count as blank. Runs
+/// overlapping verbatim nodes, such as multiline strings, are kept; like
+/// in `TrailingWhitespacePass`, those are the string node kinds of the
+/// language or the kinds given with `with_verbatim_kinds`.
+///
+/// # Examples
+/// ```ignore
+/// let mut pipeline = Pipeline::<MyConfig>::new();
+/// pipeline.add_pass(BlankLinesPass::for_language::<MyLanguage>());
+/// ```
+pub struct BlankLinesPass<Config> {
+    verbatim_kinds: &'static [&'static str],
+    _marker: PhantomData<fn() -> Config>,
+}
+
+impl<Config> BlankLinesPass<Config> {
+    /// Create the pass, keeping nodes of the common string node kinds verbatim.
+    pub fn new() -> Self {
+        Self {
+            verbatim_kinds: LanguageCapabilities::new().string_node_kinds(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create the pass, keeping nodes of the string node kinds of `Language` verbatim.
+    pub fn for_language<Language: LanguageProvider>() -> Self {
+        Self::new().with_verbatim_kinds(Language::capabilities().string_node_kinds())
+    }
+
+    /// Set the node kinds whose blank lines are kept.
+    #[must_use]
+    pub fn with_verbatim_kinds(mut self, kinds: &'static [&'static str]) -> Self {
+        self.verbatim_kinds = kinds;
+        self
+    }
+}
+
+impl<Config> Default for BlankLinesPass<Config> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Config> Pass for BlankLinesPass<Config>
+where
+    Config: Serialize + DeserializeOwned + HasWhitespaceConfig,
+{
+    type Config = Config;
+
+    fn description(&self) -> Option<&'static str> {
+        Some("Collapse runs of blank lines")
+    }
+
+    fn enabled(&self, config: &Config) -> bool {
+        config.whitespace().max_blank_lines.is_some()
+    }
+
+    fn config_keys(&self) -> &'static [&'static str] {
+        &["whitespace.max_blank_lines"]
+    }
+
+    fn run(&self, config: &Config, root: &Node, source: &str) -> Vec<Edit> {
+        let Some(max_blank_lines) = config.whitespace().max_blank_lines else {
+            return Vec::new();
+        };
+
+        let verbatim = verbatim_ranges(root, self.verbatim_kinds);
+        let mut edits = Vec::new();
+        let mut remove = |start: Option<usize>, end: usize| {
+            if let Some(start) = start.filter(|&start| !overlaps_any(&verbatim, start, end)) {
+                edits.push(Edit {
+                    range: (start, end),
+                    content: String::new(),
+                });
+            }
+        };
+
+        let mut blank_lines = 0;
+        let mut excess_start = None;
+        let mut line_start = 0;
+        for line in source.split_inclusive('\n') {
+            if line.ends_with('\n') && line.trim_start_matches([' ', '\t']) == "\n" {
+                blank_lines += 1;
+                if blank_lines == max_blank_lines + 1 {
+                    excess_start = Some(line_start);
+                }
+            } else {
+                remove(excess_start.take(), line_start);
+                blank_lines = 0;
+            }
+            line_start += line.len();
+        }
+        remove(excess_start, line_start);
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::WhitespaceConfig;
+    use rstest::rstest;
+
+    fn format(config: &WhitespaceConfig, source: &str) -> String {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let edits = BlankLinesPass::new().run(config, &tree.root_node(), source);
+
+        let mut result = source.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(edit.range.0..edit.range.1, &edit.content);
+        }
+        result
+    }
+
+    #[rstest]
+    #[case(
+        Some(1),
+        "{\n\n\n\n\"a\": 1,\n\n\"b\": 2\n}",
+        "{\n\n\"a\": 1,\n\n\"b\": 2\n}"
+    )]
+    #[case(Some(0), "{\n \n\t\n\"a\": 1\n}\n\n", "{\n\"a\": 1\n}\n")]
+    #[case(Some(2), "{}\n\n\n\n", "{}\n\n\n")]
+    #[case(None, "{\n\n\n\n}", "{\n\n\n\n}")]
+    fn test_blank_lines(
+        #[case] max_blank_lines: Option<usize>,
+        #[case] source: &str,
+        #[case] expected: &str,
+    ) {
+        let config = WhitespaceConfig {
+            max_blank_lines,
+            ..WhitespaceConfig::default()
+        };
+
+        assert_eq!(format(&config, source), expected);
+    }
+
+    #[test]
+    fn test_keeps_blank_lines_in_verbatim_nodes() {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let source = "[1,\n\n\n2]\n\n\n";
+        let tree = parser.parse(source, None).unwrap();
+        let pass = BlankLinesPass::new().with_verbatim_kinds(&["array"]);
+
+        let edits = pass.run(&WhitespaceConfig::default(), &tree.root_node(), source);
+
+        assert_eq!(
+            edits.iter().map(|edit| edit.range).collect::<Vec<_>>(),
+            vec![(10, 11)]
+        );
+    }
+}
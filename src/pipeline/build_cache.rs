@@ -0,0 +1,170 @@
+use crate::core::Diagnostic;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Results of `StructuredPass::transform` and `build`, kept across runs.
+///
+/// A structured pass returning a cache from `StructuredPass::build_cache`
+/// reuses the text it built for a target whose source text, the config,
+/// and the pass version are unchanged, skipping `transform` and `build`.
+/// This pays off for expensive passes formatting the same sources again
+/// and again, as in a watch mode, daemon, or language server.
+///
+/// Results are looked up by a hash of the target text, the config and the
+/// pass, and only reused if the text they were built from equals the
+/// target's, so a hash collision cannot hand one target the result of
+/// another. Only passes whose output for a target depends on nothing but
+/// its text and the config should use a cache.
+///
+/// # Examples
+/// ```ignore
+/// struct SortImports {
+///     cache: BuildCache,
+/// }
+///
+/// impl StructuredPass for SortImports {
+///     // ...
+///
+///     fn build_cache(&self) -> Option<&BuildCache> {
+///         Some(&self.cache)
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BuildCache {
+    entries: Mutex<HashMap<u64, CachedBuild>>,
+    capacity: usize,
+    hits: AtomicUsize,
+}
+
+/// The outcome of formatting one target.
+#[derive(Debug, Clone)]
+pub(crate) struct CachedBuild {
+    /// The text of the target the result was built from
+    pub(crate) target: String,
+    /// The built text, or `None` if `transform` failed
+    pub(crate) content: Option<String>,
+    /// Diagnostics reported while building, with ranges relative to the
+    /// start of the target
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+impl BuildCache {
+    /// Create a cache holding at most `capacity` results.
+    ///
+    /// When the cache is full it is emptied before a new result is stored.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the number of results held.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns true if the cache holds no result.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of times a result was reused.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Drop every result.
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    /// Get the result stored under `key` for the target text `target`,
+    /// counting a hit if there is one.
+    ///
+    /// A result built from another text under the same key is not reused.
+    pub(crate) fn get(&self, key: u64, target: &str) -> Option<CachedBuild> {
+        let build = self
+            .entries()
+            .get(&key)
+            .filter(|build| build.target == target)
+            .cloned();
+        if build.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        build
+    }
+
+    /// Store the result of the target under `key`.
+    pub(crate) fn insert(&self, key: u64, build: CachedBuild) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries();
+        if entries.len() >= self.capacity {
+            entries.clear();
+        }
+        entries.insert(key, build);
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<u64, CachedBuild>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for BuildCache {
+    /// Create a cache holding up to 1024 results.
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(content: &str) -> CachedBuild {
+        CachedBuild {
+            target: format!("<{content}>"),
+            content: Some(content.to_string()),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_get_counts_hits() {
+        let cache = BuildCache::new(4);
+        cache.insert(1, build("a"));
+
+        assert_eq!(cache.get(1, "<a>").unwrap().content.as_deref(), Some("a"));
+        assert!(cache.get(2, "<a>").is_none());
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn test_result_of_another_target_is_not_reused() {
+        let cache = BuildCache::new(4);
+        cache.insert(1, build("a"));
+
+        assert!(cache.get(1, "<b>").is_none());
+        assert_eq!(cache.hits(), 0);
+    }
+
+    #[test]
+    fn test_full_cache_is_emptied() {
+        let cache = BuildCache::new(2);
+        cache.insert(1, build("a"));
+        cache.insert(2, build("b"));
+        cache.insert(3, build("c"));
+
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(3, "<c>").is_some());
+
+        let disabled = BuildCache::new(0);
+        disabled.insert(1, build("a"));
+        assert!(disabled.is_empty());
+    }
+}
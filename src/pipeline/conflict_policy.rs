@@ -0,0 +1,50 @@
+/// How a pipeline combines the edits of its passes.
+///
+/// By default every pass sees the text produced by the passes before it.
+/// The other policies run all passes on the same text and apply their edits
+/// together, resolving edits of different passes that touch overlapping
+/// ranges as named. Insertions at the same offset do not conflict; they are
+/// applied in pass order.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::{ConflictPolicy, Pipeline};
+///
+/// let mut pipeline: Pipeline<()> = Pipeline::new();
+/// pipeline.set_conflict_policy(ConflictPolicy::FirstWins);
+/// assert_eq!(pipeline.conflict_policy(), ConflictPolicy::FirstWins);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ConflictPolicy {
+    /// Apply the edits of each pass before running the next one
+    #[default]
+    Sequential,
+    /// Keep the edits of the earlier pass and drop those of the later one
+    FirstWins,
+    /// Keep the edits of the later pass and drop those of the earlier one
+    LastWins,
+    /// Report every conflict as an error diagnostic and apply no edits
+    Error,
+}
+
+impl ConflictPolicy {
+    const SEQUENTIAL: &'static str = "sequential";
+    const FIRST_WINS: &'static str = "first-wins";
+    const LAST_WINS: &'static str = "last-wins";
+    const ERROR: &'static str = "error";
+
+    /// Get the string representation of the policy
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Sequential => Self::SEQUENTIAL,
+            ConflictPolicy::FirstWins => Self::FIRST_WINS,
+            ConflictPolicy::LastWins => Self::LAST_WINS,
+            ConflictPolicy::Error => Self::ERROR,
+        }
+    }
+
+    /// Returns true if the passes run on the same text and their edits are applied together.
+    pub fn is_batched(&self) -> bool {
+        *self != ConflictPolicy::Sequential
+    }
+}
@@ -0,0 +1,214 @@
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::short_type_name;
+use crate::pipeline::runtime_options::RuntimeOptions;
+use serde::{de::DeserializeOwned, Serialize};
+use std::any::Any;
+use tree_sitter::Node;
+
+/// Trait for passes that enforce consistency across all files of a run.
+///
+/// A consistency pass runs after the per-file passes, in two parallel
+/// phases joined by a combine step:
+/// 1. `summarize` - Collect a summary from every file
+/// 2. `combine` - Merge all summaries into a project-wide view
+/// 3. `run` - Produce per-file edits using the project-wide view
+///
+/// This enables rules such as "use the same import alias everywhere" that
+/// a single-file `Pass` cannot implement. When a single source is formatted,
+/// the project consists of that source only.
+///
+/// Through the CLI, the project holds every collected file at once,
+/// whatever the `runner.batch_size`. Files under a nested config are
+/// summarized and edited with the config resolved for them, while
+/// `combine` gets the config of the run.
+///
+/// # Type Parameters
+/// * `Config` - The configuration type for this pass
+/// * `Summary` - What the pass learns from a single file
+/// * `Project` - The combined view over all files
+///
+/// # Examples
+/// ```ignore
+/// struct ConsistentAliases;
+///
+/// impl ConsistencyPass for ConsistentAliases {
+///     type Config = MyConfig;
+///     type Summary = Vec<(String, String)>;
+///     type Project = HashMap<String, String>;
+///
+///     fn summarize(&self, _config: &MyConfig, root: &Node, source: &str) -> Self::Summary {
+///         collect_aliases(root, source)
+///     }
+///
+///     fn combine(&self, _config: &MyConfig, summaries: Vec<Self::Summary>) -> Self::Project {
+///         most_common_alias_per_module(summaries)
+///     }
+///
+///     fn run(&self, _config: &MyConfig, project: &Self::Project, root: &Node, source: &str) -> Vec<Edit> {
+///         rename_aliases(project, root, source)
+///     }
+/// }
+/// ```
+pub trait ConsistencyPass {
+    /// The type of configuration for this pass
+    type Config: Serialize + DeserializeOwned;
+    /// Summary collected from a single file
+    type Summary: Send + 'static;
+    /// Project-wide view built from all summaries
+    type Project: Send + Sync + 'static;
+
+    /// Summarize a single file.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration of the file
+    /// * `root` - The root node of the file's AST
+    /// * `source` - The file's source code
+    fn summarize(&self, config: &Self::Config, root: &Node, source: &str) -> Self::Summary;
+
+    /// Combine the summaries of all files into a project-wide view.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration of the run
+    /// * `summaries` - One summary per file of the run, in input order
+    fn combine(&self, config: &Self::Config, summaries: Vec<Self::Summary>) -> Self::Project;
+
+    /// Produce edits for a single file given the project-wide view.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration of the file
+    /// * `project` - The combined view over all files
+    /// * `root` - The root node of the file's AST
+    /// * `source` - The file's source code
+    ///
+    /// # Returns
+    /// A vector of edits to apply to the file
+    fn run(
+        &self,
+        config: &Self::Config,
+        project: &Self::Project,
+        root: &Node,
+        source: &str,
+    ) -> Vec<Edit>;
+
+    /// Receive the values of extra command line arguments.
+    ///
+    /// See `Pass::configure`.
+    fn configure(&mut self, _options: &RuntimeOptions) {}
+
+    /// Name of the pass.
+    ///
+    /// See `Pass::name`.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Version of the formatting rules of this pass.
+    ///
+    /// See `Pass::version`.
+    fn version(&self) -> u32 {
+        0
+    }
+}
+
+/// Type-erased wrapper for consistency passes to enable dynamic dispatch.
+///
+/// Summaries and project views are passed around as `Any` so passes with
+/// different associated types can share one collection.
+pub trait ErasedConsistencyPass<Config>: Send + Sync {
+    /// Summarize a single file.
+    fn summarize(&self, config: &Config, root: &Node, source: &str) -> Box<dyn Any + Send>;
+
+    /// Combine the summaries of all files into a project-wide view.
+    fn combine(
+        &self,
+        config: &Config,
+        summaries: Vec<Box<dyn Any + Send>>,
+    ) -> Box<dyn Any + Send + Sync>;
+
+    /// Produce edits for a single file given the project-wide view.
+    fn run(
+        &self,
+        config: &Config,
+        project: &(dyn Any + Send + Sync),
+        root: &Node,
+        source: &str,
+    ) -> Vec<Edit>;
+
+    /// Receive the values of extra command line arguments.
+    fn configure(&mut self, options: &RuntimeOptions);
+
+    /// Name of the pass, used in diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str>;
+
+    /// Version of the formatting rules of the pass.
+    fn version(&self) -> u32;
+}
+
+impl<T> ErasedConsistencyPass<<T as ConsistencyPass>::Config> for T
+where
+    T: ConsistencyPass + Send + Sync,
+{
+    fn summarize(
+        &self,
+        config: &<T as ConsistencyPass>::Config,
+        root: &Node,
+        source: &str,
+    ) -> Box<dyn Any + Send> {
+        Box::new(<T as ConsistencyPass>::summarize(
+            self, config, root, source,
+        ))
+    }
+
+    fn combine(
+        &self,
+        config: &<T as ConsistencyPass>::Config,
+        summaries: Vec<Box<dyn Any + Send>>,
+    ) -> Box<dyn Any + Send + Sync> {
+        let summaries = summaries
+            .into_iter()
+            .map(|summary| {
+                *summary
+                    .downcast::<T::Summary>()
+                    .expect("Summary type should match the pass that produced it")
+            })
+            .collect();
+        Box::new(<T as ConsistencyPass>::combine(self, config, summaries))
+    }
+
+    fn run(
+        &self,
+        config: &<T as ConsistencyPass>::Config,
+        project: &(dyn Any + Send + Sync),
+        root: &Node,
+        source: &str,
+    ) -> Vec<Edit> {
+        let project = project
+            .downcast_ref::<T::Project>()
+            .expect("Project type should match the pass that produced it");
+        <T as ConsistencyPass>::run(self, config, project, root, source)
+    }
+
+    fn configure(&mut self, options: &RuntimeOptions) {
+        <T as ConsistencyPass>::configure(self, options);
+    }
+
+    fn name(&self) -> &'static str {
+        <T as ConsistencyPass>::name(self)
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        <T as ConsistencyPass>::description(self)
+    }
+
+    fn version(&self) -> u32 {
+        <T as ConsistencyPass>::version(self)
+    }
+}
@@ -1,3 +1,5 @@
+use tree_sitter::Node;
+
 /// Represents a single text edit operation in the source code.
 ///
 /// An edit specifies a range of bytes to replace and the new content
@@ -10,6 +12,37 @@ pub struct Edit {
     pub content: String,
 }
 
+impl Edit {
+    /// Create an edit replacing the text of `node` with `text`.
+    pub fn replace_node(node: &Node, text: impl Into<String>) -> Self {
+        Self {
+            range: (node.start_byte(), node.end_byte()),
+            content: text.into(),
+        }
+    }
+
+    /// Create an edit inserting `text` right before `node`.
+    pub fn insert_before(node: &Node, text: impl Into<String>) -> Self {
+        Self {
+            range: (node.start_byte(), node.start_byte()),
+            content: text.into(),
+        }
+    }
+
+    /// Create an edit inserting `text` right after `node`.
+    pub fn insert_after(node: &Node, text: impl Into<String>) -> Self {
+        Self {
+            range: (node.end_byte(), node.end_byte()),
+            content: text.into(),
+        }
+    }
+
+    /// Create an edit deleting the text of `node`.
+    pub fn delete_node(node: &Node) -> Self {
+        Self::replace_node(node, String::new())
+    }
+}
+
 /// A target for editing containing a byte range and associated items.
 ///
 /// This structure groups together a range in the source code with
@@ -52,6 +85,26 @@ mod tests {
         assert_eq!(edit1, edit2);
     }
 
+    #[test]
+    fn test_node_constructors() {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse("[1, true]", None).unwrap();
+        let node = tree.root_node().child(0).unwrap().named_child(1).unwrap();
+        assert_eq!(node.kind(), "true");
+
+        let edit = |range: (usize, usize), content: &str| Edit {
+            range,
+            content: content.to_string(),
+        };
+        assert_eq!(Edit::replace_node(&node, "false"), edit((4, 8), "false"));
+        assert_eq!(Edit::insert_before(&node, " "), edit((4, 4), " "));
+        assert_eq!(Edit::insert_after(&node, ","), edit((8, 8), ","));
+        assert_eq!(Edit::delete_node(&node), edit((4, 8), ""));
+    }
+
     #[test]
     fn test_edit_target_creation() {
         let target: EditTarget<String> = EditTarget {
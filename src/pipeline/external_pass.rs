@@ -0,0 +1,331 @@
+use crate::core::{fnv1a_64, Diagnostic};
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::Pass;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tree_sitter::Node;
+
+/// How often a running command is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A pass delegating formatting to an external executable.
+///
+/// The pass pipes text through the command on stdin and replaces it with
+/// what the command prints on stdout. By default the text is the whole
+/// source; with `with_node_kinds` it is the text of every node of the
+/// given kinds instead, which suits snippets embedded in the source, e.g.
+/// SQL in string literals.
+///
+/// A command that cannot be started, exits unsuccessfully, prints
+/// something that is not UTF-8, or outside of its timeout leaves its text
+/// unchanged and is reported as an error diagnostic of the pass.
+///
+/// # Examples
+/// ```ignore
+/// pipeline.add_pass(
+///     ExternalPass::new("SqlFormat", "sql-formatter")
+///         .with_args(["--language", "postgresql"])
+///         .with_node_kinds(&["sql_block"])
+///         .with_timeout(Duration::from_secs(5)),
+/// );
+/// ```
+pub struct ExternalPass<Config> {
+    name: &'static str,
+    description: Option<&'static str>,
+    program: String,
+    args: Vec<String>,
+    node_kinds: &'static [&'static str],
+    timeout: Option<Duration>,
+    /// Failures of the last runs, by hash of the source they ran on, until
+    /// `diagnose` reports them
+    failures: Mutex<HashMap<u64, Vec<Diagnostic>>>,
+    _marker: PhantomData<fn() -> Config>,
+}
+
+impl<Config> ExternalPass<Config> {
+    /// Create a pass piping the whole source through `program`.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the pass, see `Pass::name`
+    /// * `program` - The executable to run, looked up in `PATH` if not a path
+    pub fn new(name: &'static str, program: impl Into<String>) -> Self {
+        Self {
+            name,
+            description: None,
+            program: program.into(),
+            args: Vec::new(),
+            node_kinds: &[],
+            timeout: None,
+            failures: Mutex::new(HashMap::new()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the arguments passed to the command.
+    #[must_use]
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Pipe the text of every node of these kinds through the command
+    /// instead of the whole source.
+    ///
+    /// Nodes inside a node of these kinds are formatted with it.
+    #[must_use]
+    pub fn with_node_kinds(mut self, kinds: &'static [&'static str]) -> Self {
+        self.node_kinds = kinds;
+        self
+    }
+
+    /// Kill the command if it runs for longer than `timeout`.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the one-line description of the pass.
+    #[must_use]
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Byte ranges of the source to pipe through the command.
+    fn targets(&self, root: &Node, source: &str) -> Vec<(usize, usize)> {
+        if self.node_kinds.is_empty() {
+            return vec![(0, source.len())];
+        }
+        let mut ranges = Vec::new();
+        let mut cursor = root.walk();
+        loop {
+            let node = cursor.node();
+            let is_target = self.node_kinds.contains(&node.kind());
+            if is_target {
+                ranges.push((node.start_byte(), node.end_byte()));
+            }
+            if !is_target && cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return ranges;
+                }
+            }
+        }
+    }
+
+    /// Run the command on `input` and return what it printed.
+    fn pipe(&self, input: &str) -> Result<String, String> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("could not run `{}`: {err}", self.program))?;
+
+        // Write and read on separate threads, so a command filling one pipe
+        // while we block on another doesn't deadlock
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = input.to_string();
+        let writer = thread::spawn(move || {
+            // The command may exit without reading its input
+            let _ = stdin.write_all(input.as_bytes());
+        });
+        let stdout = read_to_end(child.stdout.take().expect("stdout is piped"));
+        let stderr = read_to_end(child.stderr.take().expect("stderr is piped"));
+
+        let started = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {}
+                Err(err) => return Err(format!("could not wait for `{}`: {err}", self.program)),
+            }
+            if self
+                .timeout
+                .is_some_and(|timeout| started.elapsed() >= timeout)
+            {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "`{}` timed out after {:?}",
+                    self.program,
+                    started.elapsed()
+                ));
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        let _ = writer.join();
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr);
+            let message = format!("`{}` failed with {status}", self.program);
+            return Err(match stderr.lines().next() {
+                Some(reason) => format!("{message}: {reason}"),
+                None => message,
+            });
+        }
+        String::from_utf8(stdout)
+            .map_err(|_| format!("`{}` printed output that is not UTF-8", self.program))
+    }
+}
+
+/// Read everything from `reader` on a separate thread.
+fn read_to_end(mut reader: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = reader.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+impl<Config> Pass for ExternalPass<Config>
+where
+    Config: Serialize + DeserializeOwned,
+{
+    type Config = Config;
+
+    fn run(&self, _config: &Config, root: &Node, source: &str) -> Vec<Edit> {
+        let mut edits = Vec::new();
+        let mut failures = Vec::new();
+        for (start, end) in self.targets(root, source) {
+            let text = &source[start..end];
+            match self.pipe(text) {
+                Ok(mut content) => {
+                    // Formatters end their output with a newline the snippet may not have
+                    if !text.ends_with('\n') && content.ends_with('\n') {
+                        content.pop();
+                    }
+                    if content != text {
+                        edits.push(Edit {
+                            range: (start, end),
+                            content,
+                        });
+                    }
+                }
+                Err(message) => {
+                    failures.push(Diagnostic::error(message).with_range((start, end)));
+                }
+            }
+        }
+        if !failures.is_empty() {
+            self.failures
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(fnv1a_64(source.as_bytes()))
+                .or_default()
+                .extend(failures);
+        }
+        edits
+    }
+
+    fn diagnose(&self, _config: &Config, _root: &Node, source: &str) -> Vec<Diagnostic> {
+        self.failures
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&fnv1a_64(source.as_bytes()))
+            .unwrap_or_default()
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        self.description
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::pipeline::WhitespaceConfig;
+    use tree_sitter::{Parser, Tree};
+
+    fn parse(source: &str) -> Tree {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    fn shell(script: &str) -> ExternalPass<WhitespaceConfig> {
+        ExternalPass::new("External", "sh").with_args(["-c", script])
+    }
+
+    fn run(pass: &ExternalPass<WhitespaceConfig>, source: &str) -> (Vec<Edit>, Vec<Diagnostic>) {
+        let tree = parse(source);
+        let config = WhitespaceConfig::default();
+        let edits = pass.run(&config, &tree.root_node(), source);
+        let diagnostics = pass.diagnose(&config, &tree.root_node(), source);
+        (edits, diagnostics)
+    }
+
+    #[test]
+    fn test_replaces_whole_source() {
+        let (edits, diagnostics) = run(&shell("tr -d ' '"), "{\"a\" : 1}\n");
+        assert_eq!(
+            edits,
+            [Edit {
+                range: (0, 10),
+                content: "{\"a\":1}\n".to_string(),
+            }]
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_replaces_nodes_of_kinds() {
+        let pass = shell("tr a-z A-Z").with_node_kinds(&["string"]);
+        let (edits, _) = run(&pass, "{\"a\": [\"b\", 1]}");
+        let contents: Vec<&str> = edits.iter().map(|edit| edit.content.as_str()).collect();
+        assert_eq!(contents, ["\"A\"", "\"B\""]);
+        assert_eq!(edits[1].range, (7, 10));
+    }
+
+    #[test]
+    fn test_unchanged_output_has_no_edits() {
+        let (edits, diagnostics) = run(&shell("cat"), "[1]");
+        assert!(edits.is_empty());
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_failure_is_diagnosed() {
+        let (edits, diagnostics) = run(&shell("echo broken >&2; exit 3"), "[1]");
+        assert!(edits.is_empty());
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert!(diagnostics[0].message.ends_with(": broken"));
+
+        let pass = ExternalPass::<WhitespaceConfig>::new("External", "no-such-program-here");
+        let (_, diagnostics) = run(&pass, "[1]");
+        assert!(diagnostics[0].message.starts_with("could not run"));
+    }
+
+    #[test]
+    fn test_timeout_is_diagnosed() {
+        let pass = shell("sleep 5").with_timeout(Duration::from_millis(50));
+        let started = Instant::now();
+        let (edits, diagnostics) = run(&pass, "[1]");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(edits.is_empty());
+        assert!(diagnostics[0].message.contains("timed out"));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::Pass;
+use crate::pipeline::whitespace_config::HasWhitespaceConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+use tree_sitter::Node;
+
+/// Built-in pass ending files with exactly one newline.
+///
+/// Works on the raw source of any language: blank lines at the end of the
+/// file are removed and a missing newline is added. Empty files and files
+/// holding only whitespace are left alone. Enabled by
+/// `whitespace.final_newline`.
+///
+/// # Examples
+/// ```ignore
+/// let mut pipeline = Pipeline::<MyConfig>::new();
+/// pipeline.add_pass(MyPass).add_pass(FinalNewlinePass::new());
+/// ```
+pub struct FinalNewlinePass<Config> {
+    _marker: PhantomData<fn() -> Config>,
+}
+
+impl<Config> FinalNewlinePass<Config> {
+    /// Create the pass.
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Config> Default for FinalNewlinePass<Config> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Config> Pass for FinalNewlinePass<Config>
+where
+    Config: Serialize + DeserializeOwned + HasWhitespaceConfig,
+{
+    type Config = Config;
+
+    fn description(&self) -> Option<&'static str> {
+        Some("End files with exactly one newline")
+    }
+
+    fn enabled(&self, config: &Config) -> bool {
+        config.whitespace().final_newline
+    }
+
+    fn config_keys(&self) -> &'static [&'static str] {
+        &["whitespace.final_newline"]
+    }
+
+    fn run(&self, config: &Config, _root: &Node, source: &str) -> Vec<Edit> {
+        if !config.whitespace().final_newline {
+            return Vec::new();
+        }
+
+        let content_end = source.trim_end().len();
+        if content_end == 0 {
+            return Vec::new();
+        }
+        // Trailing spaces of the last line are not this pass's business.
+        let line_end = source[content_end..]
+            .find('\n')
+            .map_or(source.len(), |i| content_end + i);
+        if &source[line_end..] == "\n" {
+            return Vec::new();
+        }
+
+        vec![Edit {
+            range: (line_end, source.len()),
+            content: "\n".to_string(),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::WhitespaceConfig;
+    use rstest::rstest;
+
+    fn run(config: &WhitespaceConfig, source: &str) -> Vec<Edit> {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        FinalNewlinePass::new().run(config, &tree.root_node(), source)
+    }
+
+    fn apply(source: &str, edits: &[Edit]) -> String {
+        let mut result = source.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(edit.range.0..edit.range.1, &edit.content);
+        }
+        result
+    }
+
+    #[rstest]
+    #[case("{}", "{}\n")]
+    #[case("{}\n", "{}\n")]
+    #[case("{}\n\n\n", "{}\n")]
+    #[case("{}\n \t\n", "{}\n")]
+    #[case("{}  ", "{}  \n")]
+    #[case("", "")]
+    #[case("\n\n", "\n\n")]
+    fn test_final_newline(#[case] source: &str, #[case] expected: &str) {
+        let edits = run(&WhitespaceConfig::default(), source);
+
+        assert_eq!(apply(source, &edits), expected);
+        assert!(edits.is_empty() || source != expected);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let config = WhitespaceConfig {
+            final_newline: false,
+            ..WhitespaceConfig::default()
+        };
+
+        assert!(run(&config, "{}\n\n").is_empty());
+    }
+}
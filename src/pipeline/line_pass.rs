@@ -0,0 +1,255 @@
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::{short_type_name, Pass};
+use crate::pipeline::rule::Rule;
+use crate::pipeline::runtime_options::RuntimeOptions;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+use tree_sitter::Node;
+
+/// An edit of a `LinePass`, positioned within a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEdit {
+    /// Index of the line, starting at `0`
+    pub line: usize,
+    /// The byte range to replace, relative to the start of the line
+    ///
+    /// The range may reach past the end of the line into the next ones,
+    /// e.g. `(0, line.len() + 1)` removes the line and its `\n`.
+    pub range: (usize, usize),
+    /// The new content to insert
+    pub content: String,
+}
+
+/// Trait for passes working on lines of text rather than on the tree.
+///
+/// Rules such as trailing whitespace or header banners only need the
+/// lines of a file. The pass gets the lines without their terminators
+/// (`\n` or `\r\n`), and the framework maps its line edits to byte edits.
+/// Add it to a pipeline with `Pipeline::add_line_pass`.
+///
+/// # Examples
+/// ```ignore
+/// struct TrimLines;
+///
+/// impl LinePass for TrimLines {
+///     type Config = MyConfig;
+///
+///     fn run(&self, _config: &MyConfig, lines: &[&str]) -> Vec<LineEdit> {
+///         lines
+///             .iter()
+///             .enumerate()
+///             .filter(|(_, line)| line.ends_with(' '))
+///             .map(|(i, line)| LineEdit {
+///                 line: i,
+///                 range: (line.trim_end().len(), line.len()),
+///                 content: String::new(),
+///             })
+///             .collect()
+///     }
+/// }
+/// ```
+pub trait LinePass {
+    /// The type of configuration for this pass
+    type Config: Serialize + DeserializeOwned;
+
+    /// Run the pass on the lines of the source.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration for this pass
+    /// * `lines` - The lines of the source, without line terminators
+    ///
+    /// # Returns
+    /// A vector of edits to apply, positioned within lines
+    fn run(&self, config: &Self::Config, lines: &[&str]) -> Vec<LineEdit>;
+
+    /// Decide whether the pass runs on a source at all.
+    ///
+    /// See `Pass::should_run`.
+    fn should_run(&self, _config: &Self::Config, _path: Option<&Path>, _source: &str) -> bool {
+        true
+    }
+
+    /// Decide from the config alone whether the pass runs.
+    ///
+    /// See `Pass::enabled`.
+    fn enabled(&self, _config: &Self::Config) -> bool {
+        true
+    }
+
+    /// Keys of the config the pass reads.
+    ///
+    /// See `Pass::config_keys`.
+    fn config_keys(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Receive the values of extra command line arguments.
+    ///
+    /// See `Pass::configure`.
+    fn configure(&mut self, _options: &RuntimeOptions) {}
+
+    /// Name of the pass.
+    ///
+    /// See `Pass::name`.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Names of the passes that must run before this one.
+    ///
+    /// See `Pass::depends_on`.
+    fn depends_on(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Rules whose codes the diagnostics of this pass are tagged with.
+    ///
+    /// See `Pass::rules`.
+    fn rules(&self) -> &'static [Rule] {
+        &[]
+    }
+
+    /// Version of the formatting rules of this pass.
+    ///
+    /// See `Pass::version`.
+    fn version(&self) -> u32 {
+        0
+    }
+}
+
+/// Runs a `LinePass` as a `Pass`, ignoring the tree.
+pub(crate) struct LinePassAdapter<P>(pub(crate) P);
+
+impl<P: LinePass> Pass for LinePassAdapter<P> {
+    type Config = P::Config;
+
+    fn run(&self, config: &Self::Config, _root: &Node, source: &str) -> Vec<Edit> {
+        let (lines, starts) = split_lines(source);
+        self.0
+            .run(config, &lines)
+            .into_iter()
+            .filter_map(|edit| {
+                let start = *starts.get(edit.line)?;
+                Some(Edit {
+                    range: (start + edit.range.0, start + edit.range.1),
+                    content: edit.content,
+                })
+            })
+            .collect()
+    }
+
+    fn should_run(&self, config: &Self::Config, path: Option<&Path>, source: &str) -> bool {
+        self.0.should_run(config, path, source)
+    }
+
+    fn enabled(&self, config: &Self::Config) -> bool {
+        self.0.enabled(config)
+    }
+
+    fn config_keys(&self) -> &'static [&'static str] {
+        self.0.config_keys()
+    }
+
+    fn configure(&mut self, options: &RuntimeOptions) {
+        self.0.configure(options);
+    }
+
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        self.0.description()
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        self.0.depends_on()
+    }
+
+    fn rules(&self) -> &'static [Rule] {
+        self.0.rules()
+    }
+
+    fn version(&self) -> u32 {
+        self.0.version()
+    }
+}
+
+/// Split `source` into lines without terminators, with the byte offset each starts at.
+///
+/// A source ending with a line terminator has an empty last line.
+fn split_lines(source: &str) -> (Vec<&str>, Vec<usize>) {
+    let mut lines = Vec::new();
+    let mut starts = Vec::new();
+    let mut start = 0;
+    for line in source.split('\n') {
+        lines.push(line.strip_suffix('\r').unwrap_or(line));
+        starts.push(start);
+        start += line.len() + 1;
+    }
+    (lines, starts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::WhitespaceConfig;
+
+    /// Trims trailing spaces and puts a banner above the first line.
+    struct BannerPass;
+
+    impl LinePass for BannerPass {
+        type Config = WhitespaceConfig;
+
+        fn run(&self, _config: &WhitespaceConfig, lines: &[&str]) -> Vec<LineEdit> {
+            let mut edits = vec![LineEdit {
+                line: 0,
+                range: (0, 0),
+                content: "// banner\n".to_string(),
+            }];
+            edits.extend(lines.iter().enumerate().map(|(i, line)| LineEdit {
+                line: i,
+                range: (line.trim_end().len(), line.len()),
+                content: String::new(),
+            }));
+            edits.retain(|edit| edit.range.0 != edit.range.1 || !edit.content.is_empty());
+            edits
+        }
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let (lines, starts) = split_lines("a\r\nbc\n\nd");
+        assert_eq!(lines, ["a", "bc", "", "d"]);
+        assert_eq!(starts, [0, 3, 6, 7]);
+
+        let (lines, starts) = split_lines("a\n");
+        assert_eq!(lines, ["a", ""]);
+        assert_eq!(starts, [0, 2]);
+    }
+
+    #[test]
+    fn test_line_edits_map_to_bytes() {
+        let source = "{\r\n  \"a\": 1  \r\n}  ";
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let adapter = LinePassAdapter(BannerPass);
+        let edits = adapter.run(&WhitespaceConfig::default(), &tree.root_node(), source);
+
+        let mut result = source.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(edit.range.0..edit.range.1, &edit.content);
+        }
+        assert_eq!(result, "// banner\n{\r\n  \"a\": 1\r\n}");
+        assert_eq!(adapter.name(), "BannerPass");
+    }
+}
@@ -1,7 +1,46 @@
+mod blank_lines_pass;
+mod build_cache;
+mod conflict_policy;
+mod consistency_pass;
 mod edit;
+mod external_pass;
+mod final_newline_pass;
+mod line_pass;
 mod pass;
+mod phase;
 mod pipeline_core;
+mod pipeline_error;
+mod project_pass;
+mod query_cache;
+mod query_pass;
+mod rule;
+mod runtime_options;
+mod source_map;
+mod stable_hash;
+mod structured_context;
+mod trailing_whitespace_pass;
+mod verbatim;
+mod whitespace_config;
 
+pub use blank_lines_pass::BlankLinesPass;
+pub use build_cache::BuildCache;
+pub use conflict_policy::ConflictPolicy;
+pub use consistency_pass::ConsistencyPass;
 pub use edit::{Edit, EditTarget};
+pub use external_pass::ExternalPass;
+pub use final_newline_pass::FinalNewlinePass;
+pub use line_pass::{LineEdit, LinePass};
 pub use pass::{Pass, StructuredPass};
+pub use phase::Phase;
 pub use pipeline_core::Pipeline;
+pub use pipeline_error::PipelineError;
+pub use project_pass::{ProjectEdit, ProjectFile, ProjectPass};
+pub use query_cache::QueryCache;
+pub use query_pass::{QueryCaptures, QueryPass};
+pub use rule::Rule;
+pub use runtime_options::RuntimeOptions;
+pub use source_map::{SourceMap, SourceMapSegment};
+pub use stable_hash::{serialized_hash, stable_hash, StableHasher};
+pub use structured_context::{Spanned, StructuredContext};
+pub use trailing_whitespace_pass::TrailingWhitespacePass;
+pub use whitespace_config::{HasWhitespaceConfig, WhitespaceConfig};
@@ -1,5 +1,15 @@
+use crate::comments::{comments_in, dropped_comments};
+use crate::core::Diagnostic;
+use crate::parser::LanguageCapabilities;
+use crate::pipeline::build_cache::{BuildCache, CachedBuild};
 use crate::pipeline::edit::{Edit, EditTarget};
+use crate::pipeline::rule::Rule;
+use crate::pipeline::runtime_options::RuntimeOptions;
+use crate::pipeline::stable_hash::{serialized_hash, stable_hash};
+use crate::pipeline::structured_context::StructuredContext;
+use log::warn;
 use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
 use tree_sitter::Node;
 
 /// Base trait for all formatting passes.
@@ -37,24 +47,199 @@ pub trait Pass {
     /// # Returns
     /// A vector of edits to apply to the source code
     fn run(&self, config: &Self::Config, root: &Node, source: &str) -> Vec<Edit>;
+
+    /// Report problems in the source that the pass does not fix.
+    ///
+    /// Called right after `run`, on the same tree and source, so ranges of
+    /// the diagnostics refer to the source `run` saw. Lint-only passes
+    /// return no edits from `run` and report everything here. Diagnostics
+    /// without a pass are attributed to this one. Defaults to none.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration for this pass
+    /// * `root` - The root node of the AST
+    /// * `source` - The source code
+    fn diagnose(&self, _config: &Self::Config, _root: &Node, _source: &str) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+
+    /// Decide whether the pass runs on a source at all.
+    ///
+    /// Called before `run` on every source, so a pass can skip files by
+    /// path, size, or config setting without short-circuiting in `run`.
+    /// Defaults to running on every source.
+    ///
+    /// `EngineCache` keys results by content rather than path, so a pass
+    /// that skips files by path should not be combined with a cache.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration for this pass
+    /// * `path` - Path of the file being formatted, if the source has one
+    /// * `source` - The source code
+    fn should_run(&self, _config: &Self::Config, _path: Option<&Path>, _source: &str) -> bool {
+        true
+    }
+
+    /// Decide from the config alone whether the pass runs.
+    ///
+    /// A pass turned off by a config setting should return false here
+    /// rather than in `should_run`, so `list-passes` can show it as
+    /// disabled. Defaults to enabled.
+    fn enabled(&self, _config: &Self::Config) -> bool {
+        true
+    }
+
+    /// Keys of the config the pass reads, shown by `list-passes`.
+    ///
+    /// Use dotted paths for nested keys, e.g. `whitespace.max_blank_lines`.
+    /// Defaults to none.
+    fn config_keys(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Receive the values of extra command line arguments.
+    ///
+    /// Called once before any file is formatted. The default
+    /// implementation ignores the options.
+    ///
+    /// # Arguments
+    /// * `options` - Values of the arguments registered with `CliBuilder::add_arg`
+    fn configure(&mut self, _options: &RuntimeOptions) {}
+
+    /// Name of the pass.
+    ///
+    /// Identifies the pass in edit traces, timing reports, diagnostics, and
+    /// the pipeline fingerprint. Defaults to the type name without its
+    /// module path and generic arguments; override it if several passes
+    /// share a type.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Names of the passes that must run before this one.
+    ///
+    /// `Pipeline::resolve_order` runs them first, whatever order the passes
+    /// were added in. Defaults to none.
+    fn depends_on(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Rules whose codes the diagnostics of this pass are tagged with.
+    ///
+    /// Listed by `explain`. Defaults to none.
+    fn rules(&self) -> &'static [Rule] {
+        &[]
+    }
+
+    /// Version of the formatting rules of this pass.
+    ///
+    /// Bump it whenever the pass starts producing different output for the
+    /// same input, so results cached by `EngineCache` under an older
+    /// version are discarded. Defaults to `0`.
+    fn version(&self) -> u32 {
+        0
+    }
 }
 
 /// Type-erased wrapper for passes to enable dynamic dispatch.
 ///
 /// This trait allows storing passes with different associated types
 /// in a single collection by erasing the associated type information.
-pub trait ErasedPass<Config> {
+/// Passes must be `Send + Sync` so files can be formatted in parallel.
+pub trait ErasedPass<Config>: Send + Sync {
     /// Run the pass with the given configuration.
     fn run(&self, config: &Config, root: &Node, source: &str) -> Vec<Edit>;
+
+    /// Report problems in the source that the pass does not fix.
+    fn diagnose(&self, config: &Config, root: &Node, source: &str) -> Vec<Diagnostic>;
+
+    /// Decide whether the pass runs on a source at all.
+    fn should_run(&self, config: &Config, path: Option<&Path>, source: &str) -> bool;
+
+    /// Decide from the config alone whether the pass runs.
+    fn enabled(&self, config: &Config) -> bool;
+
+    /// Keys of the config the pass reads.
+    fn config_keys(&self) -> &'static [&'static str];
+
+    /// Receive the values of extra command line arguments.
+    fn configure(&mut self, options: &RuntimeOptions);
+
+    /// Name of the pass, used in diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str>;
+
+    /// Names of the passes that must run before this one.
+    fn depends_on(&self) -> &'static [&'static str];
+
+    /// Rules whose codes the diagnostics of the pass are tagged with.
+    fn rules(&self) -> &'static [Rule];
+
+    /// Version of the formatting rules of the pass.
+    fn version(&self) -> u32;
 }
 
 impl<T> ErasedPass<<T as Pass>::Config> for T
 where
-    T: Pass,
+    T: Pass + Send + Sync,
 {
     fn run(&self, config: &<T as Pass>::Config, root: &Node, source: &str) -> Vec<Edit> {
         <T as Pass>::run(self, config, root, source)
     }
+
+    fn diagnose(&self, config: &<T as Pass>::Config, root: &Node, source: &str) -> Vec<Diagnostic> {
+        <T as Pass>::diagnose(self, config, root, source)
+    }
+
+    fn should_run(&self, config: &<T as Pass>::Config, path: Option<&Path>, source: &str) -> bool {
+        <T as Pass>::should_run(self, config, path, source)
+    }
+
+    fn enabled(&self, config: &<T as Pass>::Config) -> bool {
+        <T as Pass>::enabled(self, config)
+    }
+
+    fn config_keys(&self) -> &'static [&'static str] {
+        <T as Pass>::config_keys(self)
+    }
+
+    fn configure(&mut self, options: &RuntimeOptions) {
+        <T as Pass>::configure(self, options);
+    }
+
+    fn name(&self) -> &'static str {
+        <T as Pass>::name(self)
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        <T as Pass>::description(self)
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        <T as Pass>::depends_on(self)
+    }
+
+    fn rules(&self) -> &'static [Rule] {
+        <T as Pass>::rules(self)
+    }
+
+    fn version(&self) -> u32 {
+        <T as Pass>::version(self)
+    }
+}
+
+/// Get the name of a type without its module path or generic arguments.
+pub(crate) fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
 }
 
 /// Structured trait for passes that work with concrete items.
@@ -71,6 +256,16 @@ where
 /// 1. `extract` - Find all targets in the AST
 /// 2. `transform` - Modify items according to formatting rules
 /// 3. `build` - Generate the formatted text from items
+///
+/// A built text that drops a comment of its target range is not applied,
+/// so comments are never lost; `build` should carry them along with the
+/// items (see the `comments` module). Items of type `Spanned` keep their
+/// original byte range for that.
+///
+/// `transform` and `build` report diagnostics through their
+/// `StructuredContext`. A pass producing diagnostics runs its steps twice
+/// per source, once for the edits and once for the diagnostics, so they
+/// should not have side effects.
 pub trait StructuredPass {
     /// The type of configuration
     type Config: Serialize + DeserializeOwned;
@@ -100,15 +295,18 @@ pub trait StructuredPass {
     /// * `source` - The source code
     /// * `config` - The configuration
     /// * `items` - Mutable reference to the items to transform
+    /// * `context` - The target being formatted, and where to report diagnostics
     ///
     /// # Returns
-    /// `Ok(())` on success, or an error message
+    /// `Ok(())` on success, or an error message, reported as an error
+    /// diagnostic; the target is then left unchanged
     fn transform(
         &self,
         _root: &Node,
         _source: &str,
         _config: &Self::Config,
         _items: &mut Vec<Self::Item>,
+        _context: &mut StructuredContext,
     ) -> Result<(), String> {
         Ok(())
     }
@@ -121,10 +319,90 @@ pub trait StructuredPass {
     /// # Arguments
     /// * `config` - The configuration
     /// * `items` - The items to format
+    /// * `context` - The target being formatted, and where to report diagnostics
     ///
     /// # Returns
     /// The formatted text
-    fn build(&self, config: &Self::Config, items: &[Self::Item]) -> String;
+    fn build(
+        &self,
+        config: &Self::Config,
+        items: &[Self::Item],
+        context: &mut StructuredContext,
+    ) -> String;
+
+    /// Node kinds of the comments `build` must keep.
+    ///
+    /// Defaults to the common comment node kinds of tree-sitter grammars.
+    fn comment_kinds(&self) -> &'static [&'static str] {
+        LanguageCapabilities::new().comment_node_kinds()
+    }
+
+    /// Decide whether the pass runs on a source at all.
+    ///
+    /// See `Pass::should_run`.
+    fn should_run(&self, _config: &Self::Config, _path: Option<&Path>, _source: &str) -> bool {
+        true
+    }
+
+    /// Decide from the config alone whether the pass runs.
+    ///
+    /// See `Pass::enabled`.
+    fn enabled(&self, _config: &Self::Config) -> bool {
+        true
+    }
+
+    /// Keys of the config the pass reads.
+    ///
+    /// See `Pass::config_keys`.
+    fn config_keys(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Cache reusing the results of `transform` and `build` for unchanged
+    /// targets, if the pass keeps one.
+    ///
+    /// See `BuildCache`. Defaults to none.
+    fn build_cache(&self) -> Option<&BuildCache> {
+        None
+    }
+
+    /// Receive the values of extra command line arguments.
+    ///
+    /// See `Pass::configure`.
+    fn configure(&mut self, _options: &RuntimeOptions) {}
+
+    /// Name of the pass.
+    ///
+    /// See `Pass::name`.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Names of the passes that must run before this one.
+    ///
+    /// See `Pass::depends_on`.
+    fn depends_on(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Rules whose codes the diagnostics of this pass are tagged with.
+    ///
+    /// See `Pass::rules`.
+    fn rules(&self) -> &'static [Rule] {
+        &[]
+    }
+
+    /// Version of the formatting rules of this pass.
+    ///
+    /// See `Pass::version`.
+    fn version(&self) -> u32 {
+        0
+    }
 }
 
 impl<T> Pass for T
@@ -134,25 +412,387 @@ where
     type Config = <T as StructuredPass>::Config;
 
     fn run(&self, config: &Self::Config, root: &Node, source: &str) -> Vec<Edit> {
-        let mut edits = Vec::new();
+        format_targets(self, config, root, source).0
+    }
+
+    fn diagnose(&self, config: &Self::Config, root: &Node, source: &str) -> Vec<Diagnostic> {
+        format_targets(self, config, root, source).1
+    }
+
+    fn should_run(&self, config: &Self::Config, path: Option<&Path>, source: &str) -> bool {
+        <T as StructuredPass>::should_run(self, config, path, source)
+    }
+
+    fn enabled(&self, config: &Self::Config) -> bool {
+        <T as StructuredPass>::enabled(self, config)
+    }
+
+    fn config_keys(&self) -> &'static [&'static str] {
+        <T as StructuredPass>::config_keys(self)
+    }
 
-        for mut target in self.extract(root, source) {
-            if target.items.is_empty() {
-                continue;
+    fn configure(&mut self, options: &RuntimeOptions) {
+        <T as StructuredPass>::configure(self, options);
+    }
+
+    fn name(&self) -> &'static str {
+        <T as StructuredPass>::name(self)
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        <T as StructuredPass>::description(self)
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        <T as StructuredPass>::depends_on(self)
+    }
+
+    fn rules(&self) -> &'static [Rule] {
+        <T as StructuredPass>::rules(self)
+    }
+
+    fn version(&self) -> u32 {
+        <T as StructuredPass>::version(self)
+    }
+}
+
+/// Run the steps of a structured pass on every target it extracts.
+///
+/// # Returns
+/// The edits of the targets, and the diagnostics reported for them
+fn format_targets<T: StructuredPass + ?Sized>(
+    pass: &T,
+    config: &T::Config,
+    root: &Node,
+    source: &str,
+) -> (Vec<Edit>, Vec<Diagnostic>) {
+    let mut edits = Vec::new();
+    let mut diagnostics = Vec::new();
+    let cache = pass
+        .build_cache()
+        .and_then(|cache| Some((cache, serialized_hash(config)?)));
+
+    for target in pass.extract(root, source) {
+        if target.items.is_empty() {
+            continue;
+        }
+
+        let (start, end) = target.range;
+        let key = cache.map(|(_, config)| {
+            stable_hash(&(pass.name(), pass.version(), config, &source[start..end]))
+        });
+        let built = match cache
+            .zip(key)
+            .and_then(|((cache, _), key)| cache.get(key, &source[start..end]))
+        {
+            Some(built) => built,
+            None => {
+                let built = build_target(pass, config, root, source, target);
+                if let Some(((cache, _), key)) = cache.zip(key) {
+                    cache.insert(key, built.clone());
+                }
+                built
             }
+        };
+        // Ranges are cached relative to the target, which may have moved
+        diagnostics.extend(built.diagnostics.into_iter().map(|mut diagnostic| {
+            diagnostic.range = diagnostic
+                .range
+                .map(|(from, to)| (from.wrapping_add(start), to.wrapping_add(start)));
+            diagnostic
+        }));
+        let Some(content) = built.content else {
+            continue;
+        };
+
+        let comments = comments_in(*root, (start, end), pass.comment_kinds());
+        let dropped = dropped_comments(&comments, source, &content);
+        if !dropped.is_empty() {
+            warn!(
+                "{} would drop {} comment(s) in bytes {start}..{end}, leaving them unchanged",
+                pass.name(),
+                dropped.len(),
+            );
+            continue;
+        }
+
+        edits.push(Edit {
+            range: (start, end),
+            content,
+        });
+    }
+
+    (edits, diagnostics)
+}
 
-            if let Err(err) = self.transform(root, source, config, &mut target.items) {
-                eprintln!("Transform error in pass: {}", err);
-                continue;
+/// Run `transform` and `build` of a structured pass on one target.
+///
+/// # Returns
+/// The built text, unless `transform` failed, and the diagnostics reported,
+/// with ranges relative to the start of the target
+fn build_target<T: StructuredPass + ?Sized>(
+    pass: &T,
+    config: &T::Config,
+    root: &Node,
+    source: &str,
+    mut target: EditTarget<T::Item>,
+) -> CachedBuild {
+    let mut context = StructuredContext::new(source, target.range, root.language().clone());
+    let content = match pass.transform(root, source, config, &mut target.items, &mut context) {
+        Ok(()) => Some(pass.build(config, &target.items, &mut context)),
+        Err(err) => {
+            context.report(Diagnostic::error(format!("Transform error: {err}")));
+            None
+        }
+    };
+    let start = target.range.0;
+    let diagnostics = context
+        .into_diagnostics()
+        .into_iter()
+        .map(|mut diagnostic| {
+            diagnostic.range = diagnostic
+                .range
+                .map(|(from, to)| (from.wrapping_sub(start), to.wrapping_sub(start)));
+            diagnostic
+        })
+        .collect();
+    CachedBuild {
+        target: source[target.range.0..target.range.1].to_string(),
+        content,
+        diagnostics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{Spanned, WhitespaceConfig};
+    use std::sync::atomic::Ordering;
+
+    /// Rewrites the top-level array of numbers with single spaces, dropping anything else.
+    struct NumberListPass;
+
+    impl StructuredPass for NumberListPass {
+        type Config = WhitespaceConfig;
+        type Item = String;
+
+        fn extract(&self, root: &Node, source: &str) -> Vec<EditTarget<String>> {
+            let array = root.named_child(0).unwrap();
+            let mut cursor = array.walk();
+            let items = array
+                .named_children(&mut cursor)
+                .filter(|node| node.kind() == "number")
+                .map(|node| source[node.start_byte()..node.end_byte()].to_string())
+                .collect();
+            vec![EditTarget {
+                range: (array.start_byte(), array.end_byte()),
+                items,
+            }]
+        }
+
+        fn build(
+            &self,
+            _config: &WhitespaceConfig,
+            items: &[String],
+            _context: &mut StructuredContext,
+        ) -> String {
+            format!("[{}]", items.join(", "))
+        }
+    }
+
+    /// Sorts the numbers of the top-level array, reporting the ones that move.
+    struct SortedNumbersPass;
+
+    impl StructuredPass for SortedNumbersPass {
+        type Config = WhitespaceConfig;
+        type Item = Spanned<i64>;
+
+        fn extract(&self, root: &Node, source: &str) -> Vec<EditTarget<Spanned<i64>>> {
+            let array = root.named_child(0).unwrap();
+            let mut cursor = array.walk();
+            let items = array
+                .named_children(&mut cursor)
+                .map(|node| {
+                    let value = node.utf8_text(source.as_bytes()).unwrap().parse().unwrap();
+                    Spanned::from_node(&node, value)
+                })
+                .collect();
+            vec![EditTarget {
+                range: (array.start_byte(), array.end_byte()),
+                items,
+            }]
+        }
+
+        fn transform(
+            &self,
+            _root: &Node,
+            _source: &str,
+            _config: &WhitespaceConfig,
+            items: &mut Vec<Spanned<i64>>,
+            context: &mut StructuredContext,
+        ) -> Result<(), String> {
+            if items.iter().any(|item| item.value < 0) {
+                return Err("negative numbers are not supported".to_string());
+            }
+            let original = items.clone();
+            items.sort_by_key(|item| item.value);
+            for (before, after) in original.iter().zip(items.iter()) {
+                if before != after {
+                    let line = context.line_of(after.range.0);
+                    context.report(
+                        Diagnostic::warning(format!("{} moved from line {line}", after.value))
+                            .with_range(after.range),
+                    );
+                }
             }
+            Ok(())
+        }
 
-            let content = self.build(config, &target.items);
-            edits.push(Edit {
-                range: target.range,
-                content,
-            });
+        fn build(
+            &self,
+            _config: &WhitespaceConfig,
+            items: &[Spanned<i64>],
+            context: &mut StructuredContext,
+        ) -> String {
+            let lines: Vec<&str> = items
+                .iter()
+                .map(|item| item.text(context.source()))
+                .collect();
+            format!("[{}]", lines.join(", "))
         }
+    }
+
+    /// `SortedNumbersPass` with a build cache, counting the targets it builds.
+    #[derive(Default)]
+    struct CachedSortPass {
+        cache: BuildCache,
+        builds: std::sync::atomic::AtomicUsize,
+    }
+
+    impl StructuredPass for CachedSortPass {
+        type Config = WhitespaceConfig;
+        type Item = Spanned<i64>;
+
+        fn extract(&self, root: &Node, source: &str) -> Vec<EditTarget<Spanned<i64>>> {
+            SortedNumbersPass.extract(root, source)
+        }
+
+        fn transform(
+            &self,
+            root: &Node,
+            source: &str,
+            config: &WhitespaceConfig,
+            items: &mut Vec<Spanned<i64>>,
+            context: &mut StructuredContext,
+        ) -> Result<(), String> {
+            SortedNumbersPass.transform(root, source, config, items, context)
+        }
+
+        fn build(
+            &self,
+            config: &WhitespaceConfig,
+            items: &[Spanned<i64>],
+            context: &mut StructuredContext,
+        ) -> String {
+            self.builds
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            SortedNumbersPass.build(config, items, context)
+        }
+
+        fn build_cache(&self) -> Option<&BuildCache> {
+            Some(&self.cache)
+        }
+    }
+
+    fn parse(source: &str) -> tree_sitter::Tree {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    fn run(source: &str) -> Vec<Edit> {
+        let tree = parse(source);
+        Pass::run(
+            &NumberListPass,
+            &WhitespaceConfig::default(),
+            &tree.root_node(),
+            source,
+        )
+    }
+
+    #[test]
+    fn test_structured_pass_keeps_comments() {
+        assert_eq!(
+            run("[1,2,  3]"),
+            [Edit {
+                range: (0, 9),
+                content: "[1, 2, 3]".to_string(),
+            }]
+        );
+        assert!(run("[1, /* two */ 2]").is_empty());
+    }
+
+    #[test]
+    fn test_structured_pass_reports_diagnostics() {
+        let config = WhitespaceConfig::default();
+        let source = "[2,\n1]";
+        let tree = parse(source);
+        let root = tree.root_node();
+
+        let edits = Pass::run(&SortedNumbersPass, &config, &root, source);
+        assert_eq!(edits[0].content, "[1, 2]");
+        let diagnostics = Pass::diagnose(&SortedNumbersPass, &config, &root, source);
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.range))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("1 moved from line 2", Some((4, 5))),
+                ("2 moved from line 1", Some((1, 2)))
+            ]
+        );
+
+        let source = "[2, -1]";
+        let tree = parse(source);
+        assert!(Pass::run(&SortedNumbersPass, &config, &tree.root_node(), source).is_empty());
+        let diagnostics = Pass::diagnose(&SortedNumbersPass, &config, &tree.root_node(), source);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].range, Some((0, 7)));
+    }
+
+    #[test]
+    fn test_build_cache_reuses_unchanged_targets() {
+        let pass = CachedSortPass::default();
+        let config = WhitespaceConfig::default();
+        let run = |source: &str, config: &WhitespaceConfig| {
+            let tree = parse(source);
+            let edits = Pass::run(&pass, config, &tree.root_node(), source);
+            let diagnostics = Pass::diagnose(&pass, config, &tree.root_node(), source);
+            (edits, diagnostics)
+        };
+
+        let (edits, diagnostics) = run("[2, 1]", &config);
+        assert_eq!(edits[0].content, "[1, 2]");
+        assert_eq!(diagnostics[0].range, Some((4, 5)));
+        assert_eq!(pass.builds.load(Ordering::Relaxed), 1);
+        assert_eq!(pass.cache.hits(), 1);
+
+        // A target that moved keeps its result, with ranges moved along
+        let (edits, diagnostics) = run("  [2, 1]", &config);
+        assert_eq!(edits[0].range, (2, 8));
+        assert_eq!(edits[0].content, "[1, 2]");
+        assert_eq!(diagnostics[0].range, Some((6, 7)));
+        assert_eq!(pass.builds.load(Ordering::Relaxed), 1);
 
-        edits
+        let changed = WhitespaceConfig {
+            max_blank_lines: None,
+            ..WhitespaceConfig::default()
+        };
+        run("[2, 1]", &changed);
+        assert_eq!(pass.builds.load(Ordering::Relaxed), 2);
     }
 }
@@ -0,0 +1,32 @@
+use crate::pipeline::pass::ErasedPass;
+use crate::pipeline::ConflictPolicy;
+
+/// A group of consecutive passes of a pipeline that run as one step.
+///
+/// Every pass of a phase sees the output of all passes of the previous
+/// phases, whatever the conflict policy; how the edits of passes within the
+/// phase are combined is set by the policy of the phase. Passes added to a
+/// pipeline directly form unnamed phases between the named ones, which use
+/// the policy of the pipeline.
+pub struct Phase<'a, Config> {
+    pub(crate) name: Option<&'static str>,
+    pub(crate) passes: &'a [Box<dyn ErasedPass<Config>>],
+    pub(crate) conflict_policy: ConflictPolicy,
+}
+
+impl<'a, Config> Phase<'a, Config> {
+    /// Name of the phase, or `None` for passes added to the pipeline directly.
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// Get the passes of the phase, in the order they run.
+    pub fn passes(&self) -> &'a [Box<dyn ErasedPass<Config>>] {
+        self.passes
+    }
+
+    /// Get how the edits of the passes of the phase are combined.
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.conflict_policy
+    }
+}
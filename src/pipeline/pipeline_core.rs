@@ -1,11 +1,23 @@
+use crate::core::fnv1a_64;
+use crate::pipeline::consistency_pass::ErasedConsistencyPass;
+use crate::pipeline::line_pass::LinePassAdapter;
 use crate::pipeline::pass::ErasedPass;
-use crate::pipeline::Pass;
+use crate::pipeline::project_pass::ErasedProjectPass;
+use crate::pipeline::{
+    ConflictPolicy, ConsistencyPass, LinePass, Pass, Phase, PipelineError, ProjectPass, Rule,
+    RuntimeOptions,
+};
 
 /// A pipeline of formatting passes that are applied sequentially.
 ///
 /// The pipeline maintains an ordered collection of passes that will be
 /// executed in sequence to transform source code. Each pass receives
-/// the configuration and produces a set of edits.
+/// the configuration and produces a set of edits. How edits of different
+/// passes are combined is set by its `ConflictPolicy`.
+///
+/// Passes run in the order they are added unless `resolve_order` reorders
+/// them by priority and declared dependencies. Large pipelines can be
+/// composed of named phases with `add_phase`.
 ///
 /// # Type Parameters
 /// * `Config` - The configuration type shared by all passes in the pipeline
@@ -18,17 +30,35 @@ use crate::pipeline::Pass;
 /// ```
 pub struct Pipeline<Config> {
     passes: Vec<Box<dyn ErasedPass<Config>>>,
+    priorities: Vec<i32>,
+    /// Index in `phases` of the phase of each pass
+    phase_of: Vec<usize>,
+    phases: Vec<PhaseInfo>,
+    consistency_passes: Vec<Box<dyn ErasedConsistencyPass<Config>>>,
+    project_passes: Vec<Box<dyn ErasedProjectPass<Config>>>,
+    options: RuntimeOptions,
+    conflict_policy: ConflictPolicy,
 }
 
 impl<Config> Pipeline<Config> {
     /// Create a new empty pipeline.
     pub fn new() -> Self {
-        Self { passes: Vec::new() }
+        Self {
+            passes: Vec::new(),
+            priorities: Vec::new(),
+            phase_of: Vec::new(),
+            phases: Vec::new(),
+            consistency_passes: Vec::new(),
+            project_passes: Vec::new(),
+            options: RuntimeOptions::new(),
+            conflict_policy: ConflictPolicy::default(),
+        }
     }
 
     /// Add a pass to the pipeline.
     ///
-    /// Passes are executed in the order they are added.
+    /// Passes are executed in the order they are added. Passes must be
+    /// `Send + Sync`, since files may be formatted on several threads.
     ///
     /// # Arguments
     /// * `pass` - The pass to add to the pipeline
@@ -45,9 +75,205 @@ impl<Config> Pipeline<Config> {
     /// ```
     pub fn add_pass<P>(&mut self, pass: P) -> &mut Self
     where
-        P: Pass<Config = Config> + 'static,
+        P: Pass<Config = Config> + Send + Sync + 'static,
     {
+        self.add_pass_with_priority(pass, 0)
+    }
+
+    /// Add a line-based pass to the pipeline.
+    ///
+    /// The pass runs like any other pass, in the order it is added, but
+    /// gets the lines of the source instead of the tree.
+    ///
+    /// # Arguments
+    /// * `pass` - The line pass to add to the pipeline
+    ///
+    /// # Returns
+    /// A mutable reference to self for method chaining
+    pub fn add_line_pass<P>(&mut self, pass: P) -> &mut Self
+    where
+        P: LinePass<Config = Config> + Send + Sync + 'static,
+    {
+        self.add_pass(LinePassAdapter(pass))
+    }
+
+    /// Add a pass to the pipeline with a priority.
+    ///
+    /// Once `resolve_order` is called, passes with a lower priority run
+    /// first, unless that breaks a declared dependency. `add_pass` uses
+    /// priority `0`.
+    ///
+    /// # Arguments
+    /// * `pass` - The pass to add to the pipeline
+    /// * `priority` - Where the pass runs relative to other passes
+    ///
+    /// # Returns
+    /// A mutable reference to self for method chaining
+    pub fn add_pass_with_priority<P>(&mut self, pass: P, priority: i32) -> &mut Self
+    where
+        P: Pass<Config = Config> + Send + Sync + 'static,
+    {
+        if self.phases.last().is_none_or(|phase| phase.name.is_some()) {
+            self.phases.push(PhaseInfo {
+                name: None,
+                conflict_policy: None,
+            });
+        }
         self.passes.push(Box::new(pass));
+        self.priorities.push(priority);
+        self.phase_of.push(self.phases.len() - 1);
+        self
+    }
+
+    /// Add the passes of `phase` to the pipeline as a named phase.
+    ///
+    /// The passes of the phase run after every pass added before it and
+    /// see their output; passes added afterwards see the output of the
+    /// phase. Within the phase, edits are combined according to the
+    /// conflict policy of `phase`. Phases of `phase` itself stay separate
+    /// phases, and its consistency and project passes are added to the
+    /// run-level stage of this pipeline.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the phase, e.g. `whitespace`
+    /// * `phase` - The passes of the phase
+    ///
+    /// # Returns
+    /// A mutable reference to self for method chaining
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let mut normalize = Pipeline::new();
+    /// normalize.add_pass(QuoteStyle).add_pass(NumberStyle);
+    /// let mut whitespace = Pipeline::new();
+    /// whitespace.add_pass(TrailingWhitespacePass::new());
+    ///
+    /// let mut pipeline = Pipeline::new();
+    /// pipeline
+    ///     .add_phase("normalize", normalize)
+    ///     .add_phase("whitespace", whitespace);
+    /// ```
+    pub fn add_phase(&mut self, name: &'static str, phase: Pipeline<Config>) -> &mut Self {
+        let offset = self.phases.len();
+        self.phases
+            .extend(phase.phases.into_iter().map(|info| PhaseInfo {
+                name: Some(info.name.unwrap_or(name)),
+                conflict_policy: Some(info.conflict_policy.unwrap_or(phase.conflict_policy)),
+            }));
+        self.passes.extend(phase.passes);
+        self.priorities.extend(phase.priorities);
+        self.phase_of
+            .extend(phase.phase_of.into_iter().map(|index| offset + index));
+        self.consistency_passes.extend(phase.consistency_passes);
+        self.project_passes.extend(phase.project_passes);
+        self
+    }
+
+    /// Reorder the passes by their priority and declared dependencies.
+    ///
+    /// Every pass runs after the passes it depends on (`Pass::depends_on`);
+    /// among the passes whose dependencies already ran, the one with the
+    /// lowest priority runs next, and ties keep the order the passes were
+    /// added in. A dependency names every pass of that name in the same or
+    /// an earlier phase. Passes stay in their phase.
+    ///
+    /// # Errors
+    /// Returns `PipelineError::UnknownDependency` if a pass depends on a
+    /// pass that is not in the pipeline, `PipelineError::DependencyInLaterPhase`
+    /// if the pass it depends on only runs in a later phase, and
+    /// `PipelineError::DependencyCycle` if passes depend on each other.
+    pub fn resolve_order(&mut self) -> Result<(), PipelineError> {
+        let names: Vec<&'static str> = self.passes.iter().map(|pass| pass.name()).collect();
+        let mut dependencies = Vec::with_capacity(self.passes.len());
+        for (i, (pass, name)) in self.passes.iter().zip(&names).enumerate() {
+            let mut indices: Vec<usize> = Vec::new();
+            for &dependency in pass.depends_on() {
+                let named: Vec<usize> = (0..names.len())
+                    .filter(|&j| names[j] == dependency)
+                    .collect();
+                let before = indices.len();
+                indices.extend(
+                    named
+                        .iter()
+                        .filter(|&&j| self.phase_of[j] <= self.phase_of[i]),
+                );
+                if named.is_empty() {
+                    return Err(PipelineError::UnknownDependency {
+                        pass: name,
+                        dependency,
+                    });
+                }
+                if indices.len() == before {
+                    return Err(PipelineError::DependencyInLaterPhase {
+                        pass: name,
+                        dependency,
+                    });
+                }
+            }
+            dependencies.push(indices);
+        }
+
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut placed = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            let next = (0..self.passes.len())
+                .filter(|&i| !placed[i] && dependencies[i].iter().all(|&j| placed[j]))
+                .min_by_key(|&i| (self.phase_of[i], self.priorities[i], i));
+            let Some(next) = next else {
+                return Err(PipelineError::DependencyCycle {
+                    passes: (0..names.len())
+                        .filter(|&i| !placed[i])
+                        .map(|i| names[i])
+                        .collect(),
+                });
+            };
+            placed[next] = true;
+            order.push(next);
+        }
+
+        let mut passes: Vec<_> = std::mem::take(&mut self.passes)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.passes = order.iter().filter_map(|&i| passes[i].take()).collect();
+        self.priorities = order.iter().map(|&i| self.priorities[i]).collect();
+        self.phase_of = order.iter().map(|&i| self.phase_of[i]).collect();
+        Ok(())
+    }
+
+    /// Add a consistency pass to the run-level stage of the pipeline.
+    ///
+    /// Consistency passes run after all per-file passes, in the order
+    /// they are added, and observe summaries of every file in the run.
+    ///
+    /// # Arguments
+    /// * `pass` - The consistency pass to add
+    ///
+    /// # Returns
+    /// A mutable reference to self for method chaining
+    pub fn add_consistency_pass<P>(&mut self, pass: P) -> &mut Self
+    where
+        P: ConsistencyPass<Config = Config> + Send + Sync + 'static,
+    {
+        self.consistency_passes.push(Box::new(pass));
+        self
+    }
+
+    /// Add a project pass to the run-level stage of the pipeline.
+    ///
+    /// Project passes run after the consistency passes, in the order they
+    /// are added, and see every file of the run at once.
+    ///
+    /// # Arguments
+    /// * `pass` - The project pass to add
+    ///
+    /// # Returns
+    /// A mutable reference to self for method chaining
+    pub fn add_project_pass<P>(&mut self, pass: P) -> &mut Self
+    where
+        P: ProjectPass<Config = Config> + Send + Sync + 'static,
+    {
+        self.project_passes.push(Box::new(pass));
         self
     }
 
@@ -59,17 +285,150 @@ impl<Config> Pipeline<Config> {
         &self.passes
     }
 
-    /// Get the number of passes in the pipeline.
+    /// Get the phases of the pipeline, in the order they run.
+    ///
+    /// Consecutive passes added directly form one unnamed phase; see
+    /// `add_phase`.
+    pub fn phases(&self) -> impl Iterator<Item = Phase<'_, Config>> + '_ {
+        self.phases.iter().enumerate().filter_map(|(index, info)| {
+            let start = self.phase_of.partition_point(|&phase| phase < index);
+            let end = self.phase_of.partition_point(|&phase| phase <= index);
+            (start < end).then(|| Phase {
+                name: info.name,
+                passes: &self.passes[start..end],
+                conflict_policy: info.conflict_policy.unwrap_or(self.conflict_policy),
+            })
+        })
+    }
+
+    /// Get the rules of every pass, with the name of the pass checking them.
+    ///
+    /// # Returns
+    /// Pairs of pass name and rule, in pass order
+    pub fn rules(&self) -> impl Iterator<Item = (&'static str, &'static Rule)> + '_ {
+        self.passes
+            .iter()
+            .flat_map(|pass| pass.rules().iter().map(|rule| (pass.name(), rule)))
+    }
+
+    /// Find the rule with `code` among the rules of every pass.
+    ///
+    /// # Returns
+    /// The name of the pass checking the rule and the rule, if any pass has it
+    pub fn rule(&self, code: &str) -> Option<(&'static str, &'static Rule)> {
+        self.rules().find(|(_, rule)| rule.code == code)
+    }
+
+    /// Get a reference to the consistency passes in this pipeline.
+    ///
+    /// # Returns
+    /// A slice of boxed erased consistency passes
+    pub fn consistency_passes(&self) -> &[Box<dyn ErasedConsistencyPass<Config>>] {
+        &self.consistency_passes
+    }
+
+    /// Get a reference to the project passes in this pipeline.
+    ///
+    /// # Returns
+    /// A slice of boxed erased project passes
+    pub fn project_passes(&self) -> &[Box<dyn ErasedProjectPass<Config>>] {
+        &self.project_passes
+    }
+
+    /// Returns true if the pipeline has passes that need every file of a run.
+    pub fn has_run_level_passes(&self) -> bool {
+        !self.consistency_passes.is_empty() || !self.project_passes.is_empty()
+    }
+
+    /// Set how the edits of different passes are combined.
+    ///
+    /// # Arguments
+    /// * `policy` - The policy for edits of different passes that overlap
+    ///
+    /// # Returns
+    /// A mutable reference to self for method chaining
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) -> &mut Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Get how the edits of different passes are combined.
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.conflict_policy
+    }
+
+    /// Hand runtime options to every pass, in pipeline order.
+    ///
+    /// # Arguments
+    /// * `options` - Values of extra command line arguments
+    pub fn configure(&mut self, options: &RuntimeOptions) {
+        for pass in &mut self.passes {
+            pass.configure(options);
+        }
+        for pass in &mut self.consistency_passes {
+            pass.configure(options);
+        }
+        for pass in &mut self.project_passes {
+            pass.configure(options);
+        }
+        self.options = options.clone();
+    }
+
+    /// Get a fingerprint identifying the output of this pipeline.
+    ///
+    /// It changes whenever a pass is added, removed, reordered, or bumps its
+    /// version, when the conflict policy or runtime options differ, and
+    /// across crate versions.
+    ///
+    /// # Returns
+    /// A stable 64-bit hash of the pipeline
+    pub fn fingerprint(&self) -> u64 {
+        let mut identity = format!(
+            "fmt-runner {}\nconflicts {}\n",
+            env!("CARGO_PKG_VERSION"),
+            self.conflict_policy.as_str()
+        );
+        for phase in self.phases() {
+            identity.push_str(&format!(
+                "phase {} {}\n",
+                phase.name().unwrap_or_default(),
+                phase.conflict_policy().as_str()
+            ));
+            for pass in phase.passes() {
+                identity.push_str(&format!("pass {}@{}\n", pass.name(), pass.version()));
+            }
+        }
+        for pass in &self.consistency_passes {
+            identity.push_str(&format!("consistency {}@{}\n", pass.name(), pass.version()));
+        }
+        for pass in &self.project_passes {
+            identity.push_str(&format!("project {}@{}\n", pass.name(), pass.version()));
+        }
+        for (name, values) in self.options.sorted() {
+            identity.push_str(&format!("option {name}={values:?}\n"));
+        }
+        fnv1a_64(identity.as_bytes())
+    }
+
+    /// Get the number of passes in the pipeline, including consistency and project passes.
     pub fn len(&self) -> usize {
-        self.passes.len()
+        self.passes.len() + self.consistency_passes.len() + self.project_passes.len()
     }
 
     /// Check if the pipeline is empty.
     pub fn is_empty(&self) -> bool {
-        self.passes.is_empty()
+        self.passes.is_empty() && !self.has_run_level_passes()
     }
 }
 
+/// Name and conflict policy of a phase of a pipeline.
+struct PhaseInfo {
+    /// Name given to `add_phase`, or `None` for passes added directly
+    name: Option<&'static str>,
+    /// Policy of the phase, or `None` to use the policy of the pipeline
+    conflict_policy: Option<ConflictPolicy>,
+}
+
 impl<Config> Default for Pipeline<Config> {
     fn default() -> Self {
         Self::new()
@@ -125,9 +484,268 @@ mod tests {
         assert_eq!(pipeline.len(), 0);
     }
 
+    #[test]
+    fn test_configure_reaches_passes() {
+        use crate::pipeline::Edit;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use tree_sitter::Node;
+
+        struct FlagPass(Arc<AtomicBool>);
+
+        impl Pass for FlagPass {
+            type Config = DummyConfig;
+
+            fn run(&self, _config: &DummyConfig, _root: &Node, _source: &str) -> Vec<Edit> {
+                Vec::new()
+            }
+
+            fn configure(&mut self, options: &RuntimeOptions) {
+                self.0
+                    .store(options.is_set("experimental"), Ordering::SeqCst);
+            }
+        }
+
+        let seen = Arc::new(AtomicBool::new(false));
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(FlagPass(Arc::clone(&seen)));
+
+        let mut options = RuntimeOptions::new();
+        options.insert("experimental", vec!["true".to_string()]);
+        pipeline.configure(&options);
+
+        assert!(seen.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_pass_identity() {
+        use crate::pipeline::{Edit, FinalNewlinePass, WhitespaceConfig};
+        use tree_sitter::Node;
+
+        struct RenamedPass;
+
+        impl Pass for RenamedPass {
+            type Config = WhitespaceConfig;
+
+            fn run(&self, _config: &WhitespaceConfig, _root: &Node, _source: &str) -> Vec<Edit> {
+                Vec::new()
+            }
+
+            fn name(&self) -> &'static str {
+                "renamed"
+            }
+        }
+
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(RenamedPass)
+            .add_pass(FinalNewlinePass::new());
+        let passes = pipeline.passes();
+
+        assert_eq!(passes[0].name(), "renamed");
+        assert_eq!(passes[0].description(), None);
+        assert_eq!(passes[1].name(), "FinalNewlinePass");
+        assert!(passes[1].description().is_some());
+    }
+
+    struct OrderedPass {
+        name: &'static str,
+        depends_on: &'static [&'static str],
+    }
+
+    impl Pass for OrderedPass {
+        type Config = DummyConfig;
+
+        fn run(
+            &self,
+            _config: &DummyConfig,
+            _root: &tree_sitter::Node,
+            _source: &str,
+        ) -> Vec<crate::pipeline::Edit> {
+            Vec::new()
+        }
+
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn depends_on(&self) -> &'static [&'static str] {
+            self.depends_on
+        }
+    }
+
+    fn ordered(name: &'static str, depends_on: &'static [&'static str]) -> OrderedPass {
+        OrderedPass { name, depends_on }
+    }
+
+    fn pass_names(pipeline: &Pipeline<DummyConfig>) -> Vec<&'static str> {
+        pipeline.passes().iter().map(|pass| pass.name()).collect()
+    }
+
+    #[test]
+    fn test_resolve_order_by_priority() {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ordered("a", &[]))
+            .add_pass_with_priority(ordered("b", &[]), 10)
+            .add_pass_with_priority(ordered("c", &[]), -5)
+            .add_pass(ordered("d", &[]));
+
+        pipeline.resolve_order().unwrap();
+
+        assert_eq!(pass_names(&pipeline), ["c", "a", "d", "b"]);
+    }
+
+    #[test]
+    fn test_resolve_order_by_dependencies() {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass_with_priority(ordered("indent", &["wrap"]), -10)
+            .add_pass(ordered("wrap", &["sort"]))
+            .add_pass_with_priority(ordered("sort", &[]), 5)
+            .add_pass(ordered("spacing", &[]));
+
+        pipeline.resolve_order().unwrap();
+
+        assert_eq!(pass_names(&pipeline), ["spacing", "sort", "wrap", "indent"]);
+    }
+
+    #[test]
+    fn test_resolve_order_rejects_cycle() {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ordered("free", &[]))
+            .add_pass(ordered("a", &["b"]))
+            .add_pass(ordered("b", &["a"]));
+
+        assert_eq!(
+            pipeline.resolve_order(),
+            Err(PipelineError::DependencyCycle {
+                passes: vec!["a", "b"]
+            })
+        );
+        assert_eq!(pass_names(&pipeline), ["free", "a", "b"]);
+    }
+
+    #[test]
+    fn test_resolve_order_rejects_unknown_dependency() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_pass(ordered("a", &["missing"]));
+
+        assert_eq!(
+            pipeline.resolve_order(),
+            Err(PipelineError::UnknownDependency {
+                pass: "a",
+                dependency: "missing"
+            })
+        );
+    }
+
+    #[test]
+    fn test_phases_keep_their_passes_together() {
+        let mut normalize = Pipeline::new();
+        normalize
+            .add_pass(ordered("quotes", &[]))
+            .add_pass_with_priority(ordered("numbers", &[]), -5);
+        let mut whitespace = Pipeline::new();
+        whitespace
+            .add_pass_with_priority(ordered("indent", &["quotes"]), -10)
+            .set_conflict_policy(ConflictPolicy::FirstWins);
+
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass_with_priority(ordered("first", &[]), 10)
+            .add_phase("normalize", normalize)
+            .add_phase("whitespace", whitespace)
+            .add_pass(ordered("last", &[]));
+        pipeline.resolve_order().unwrap();
+
+        assert_eq!(
+            pass_names(&pipeline),
+            ["first", "numbers", "quotes", "indent", "last"]
+        );
+        let phases: Vec<_> = pipeline
+            .phases()
+            .map(|phase| (phase.name(), phase.passes().len(), phase.conflict_policy()))
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                (None, 1, ConflictPolicy::Sequential),
+                (Some("normalize"), 2, ConflictPolicy::Sequential),
+                (Some("whitespace"), 1, ConflictPolicy::FirstWins),
+                (None, 1, ConflictPolicy::Sequential),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_order_rejects_dependency_on_later_phase() {
+        let mut later = Pipeline::new();
+        later.add_pass(ordered("sort", &[]));
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .add_pass(ordered("wrap", &["sort"]))
+            .add_phase("later", later);
+
+        assert_eq!(
+            pipeline.resolve_order(),
+            Err(PipelineError::DependencyInLaterPhase {
+                pass: "wrap",
+                dependency: "sort"
+            })
+        );
+    }
+
     #[test]
     fn test_default_pipeline_is_empty() {
         let pipeline: Pipeline<DummyConfig> = Pipeline::default();
         assert!(pipeline.is_empty());
     }
+
+    #[test]
+    fn test_fingerprint_tracks_pass_versions_and_options() {
+        use crate::pipeline::Edit;
+        use tree_sitter::Node;
+
+        struct VersionedPass(u32);
+
+        impl Pass for VersionedPass {
+            type Config = DummyConfig;
+
+            fn run(&self, _config: &DummyConfig, _root: &Node, _source: &str) -> Vec<Edit> {
+                Vec::new()
+            }
+
+            fn version(&self) -> u32 {
+                self.0
+            }
+        }
+
+        let fingerprint = |version, options: &RuntimeOptions| {
+            let mut pipeline = Pipeline::new();
+            pipeline.add_pass(VersionedPass(version));
+            pipeline.configure(options);
+            pipeline.fingerprint()
+        };
+
+        let empty = RuntimeOptions::new();
+        let mut experimental = RuntimeOptions::new();
+        experimental.insert("experimental", vec!["true".to_string()]);
+
+        assert_eq!(fingerprint(1, &empty), fingerprint(1, &empty));
+        assert_ne!(fingerprint(1, &empty), fingerprint(2, &empty));
+        assert_ne!(fingerprint(1, &empty), fingerprint(1, &experimental));
+        assert_ne!(
+            fingerprint(1, &empty),
+            Pipeline::<DummyConfig>::new().fingerprint()
+        );
+
+        let mut first_wins = Pipeline::<DummyConfig>::new();
+        first_wins.set_conflict_policy(ConflictPolicy::FirstWins);
+        assert_ne!(
+            first_wins.fingerprint(),
+            Pipeline::<DummyConfig>::new().fingerprint()
+        );
+    }
 }
@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+/// Error in how the passes of a pipeline are declared.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PipelineError {
+    /// A pass depends on a pass that is not in the pipeline
+    #[error("Pass '{pass}' depends on '{dependency}', which is not in the pipeline")]
+    UnknownDependency {
+        /// Name of the dependent pass
+        pass: &'static str,
+        /// Name of the missing pass
+        dependency: &'static str,
+    },
+
+    /// A pass depends on a pass that only runs in a later phase
+    #[error("Pass '{pass}' depends on '{dependency}', which runs in a later phase")]
+    DependencyInLaterPhase {
+        /// Name of the dependent pass
+        pass: &'static str,
+        /// Name of the pass in the later phase
+        dependency: &'static str,
+    },
+
+    /// Passes depend on each other in a cycle
+    #[error("Passes {} depend on each other in a cycle", .passes.join(", "))]
+    DependencyCycle {
+        /// Names of the passes in the cycle and of the passes depending on them
+        passes: Vec<&'static str>,
+    },
+}
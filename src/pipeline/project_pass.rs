@@ -0,0 +1,171 @@
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::short_type_name;
+use crate::pipeline::runtime_options::RuntimeOptions;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+use std::path::Path;
+use tree_sitter::Node;
+
+/// A parsed file of the run, as seen by a `ProjectPass`.
+pub struct ProjectFile<'a, Config> {
+    /// Path of the file, if the source has one
+    pub path: Option<&'a Path>,
+    /// The configuration of the file, which differs from that of the run
+    /// for files under a nested config
+    pub config: &'a Config,
+    /// The root node of the file's AST
+    pub root: Node<'a>,
+    /// The file's source code
+    pub source: &'a str,
+}
+
+impl<Config> Clone for ProjectFile<'_, Config> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Config> Copy for ProjectFile<'_, Config> {}
+
+impl<Config> fmt::Debug for ProjectFile<'_, Config> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProjectFile")
+            .field("path", &self.path)
+            .field("root", &self.root)
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An edit of a `ProjectPass` to one file of the run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectEdit {
+    /// Index of the file in the slice the pass received
+    pub file: usize,
+    /// The edit to apply to the file
+    pub edit: Edit,
+}
+
+/// Trait for passes that see every file of a run at once.
+///
+/// A project pass runs after the per-file and consistency passes, on the
+/// calling thread, with the parsed files of the whole run. It may edit
+/// any of them, which enables rules such as "order modules the same way
+/// across a package" or removing definitions duplicated across files.
+/// When a single source is formatted, the project consists of that source
+/// only.
+///
+/// Through the CLI, the run holds every collected file at once, whatever
+/// the `runner.batch_size`, and each file comes with the config resolved
+/// for it, as for a `ConsistencyPass`.
+///
+/// Prefer a `ConsistencyPass` when edits to a file only need a summary of
+/// the others: it runs in parallel and keeps less in memory.
+///
+/// # Examples
+/// ```ignore
+/// struct DuplicateDefinitions;
+///
+/// impl ProjectPass for DuplicateDefinitions {
+///     type Config = MyConfig;
+///
+///     fn run(&self, _config: &MyConfig, files: &[ProjectFile<MyConfig>]) -> Vec<ProjectEdit> {
+///         let mut seen = HashSet::new();
+///         let mut edits = Vec::new();
+///         for (i, file) in files.iter().enumerate() {
+///             for definition in definitions(file.root, file.source) {
+///                 if !seen.insert(definition.name) {
+///                     edits.push(ProjectEdit { file: i, edit: remove(definition) });
+///                 }
+///             }
+///         }
+///         edits
+///     }
+/// }
+/// ```
+pub trait ProjectPass {
+    /// The type of configuration for this pass
+    type Config: Serialize + DeserializeOwned;
+
+    /// Produce edits for the files of the run.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration of the run
+    /// * `files` - Every file of the run, with its own configuration, in
+    ///   input order
+    ///
+    /// # Returns
+    /// Edits to apply, each to the file at its index in `files`
+    fn run(&self, config: &Self::Config, files: &[ProjectFile<Self::Config>]) -> Vec<ProjectEdit>;
+
+    /// Receive the values of extra command line arguments.
+    ///
+    /// See `Pass::configure`.
+    fn configure(&mut self, _options: &RuntimeOptions) {}
+
+    /// Name of the pass.
+    ///
+    /// See `Pass::name`.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Version of the formatting rules of this pass.
+    ///
+    /// See `Pass::version`.
+    fn version(&self) -> u32 {
+        0
+    }
+}
+
+/// Type-erased wrapper for project passes to enable dynamic dispatch.
+pub trait ErasedProjectPass<Config>: Send + Sync {
+    /// Produce edits for the files of the run.
+    fn run(&self, config: &Config, files: &[ProjectFile<Config>]) -> Vec<ProjectEdit>;
+
+    /// Receive the values of extra command line arguments.
+    fn configure(&mut self, options: &RuntimeOptions);
+
+    /// Name of the pass, used in diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// One-line description of what the pass does, if any.
+    fn description(&self) -> Option<&'static str>;
+
+    /// Version of the formatting rules of the pass.
+    fn version(&self) -> u32;
+}
+
+impl<T> ErasedProjectPass<<T as ProjectPass>::Config> for T
+where
+    T: ProjectPass + Send + Sync,
+{
+    fn run(
+        &self,
+        config: &<T as ProjectPass>::Config,
+        files: &[ProjectFile<<T as ProjectPass>::Config>],
+    ) -> Vec<ProjectEdit> {
+        <T as ProjectPass>::run(self, config, files)
+    }
+
+    fn configure(&mut self, options: &RuntimeOptions) {
+        <T as ProjectPass>::configure(self, options);
+    }
+
+    fn name(&self) -> &'static str {
+        <T as ProjectPass>::name(self)
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        <T as ProjectPass>::description(self)
+    }
+
+    fn version(&self) -> u32 {
+        <T as ProjectPass>::version(self)
+    }
+}
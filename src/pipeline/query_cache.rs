@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use tree_sitter::{Language, Query, QueryError};
+
+/// Compiled tree-sitter queries, kept per language and shared across files and threads.
+///
+/// Compiling a query is expensive, so passes look their queries up here
+/// instead of compiling them in `run` for every file. The same query text
+/// is compiled once for each language it is used with, so a pass shared by
+/// the engines of a `LanguageRegistry` works with all of them.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::QueryCache;
+///
+/// let json = tree_sitter_json::LANGUAGE.into();
+/// let cache = QueryCache::new();
+/// let pairs = cache.get(&json, "(pair) @pair").unwrap();
+/// assert!(std::sync::Arc::ptr_eq(&pairs, &cache.get(&json, "(pair) @pair").unwrap()));
+/// assert!(cache.get(&json, "(not_a_node)").is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    queries: Mutex<HashMap<Language, HashMap<String, Arc<Query>>>>,
+}
+
+impl QueryCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the cache shared by the whole process.
+    pub fn global() -> &'static QueryCache {
+        static GLOBAL: OnceLock<QueryCache> = OnceLock::new();
+        GLOBAL.get_or_init(QueryCache::new)
+    }
+
+    /// Get `source` compiled for `language`, compiling it on first use.
+    ///
+    /// # Returns
+    /// The compiled query, or the error compiling it; errors are not cached
+    pub fn get(&self, language: &Language, source: &str) -> Result<Arc<Query>, QueryError> {
+        if let Some(query) = self
+            .queries()
+            .get(language)
+            .and_then(|queries| queries.get(source))
+        {
+            return Ok(Arc::clone(query));
+        }
+
+        // Compile without holding the lock; a query compiled meanwhile by
+        // another thread wins.
+        let query = Arc::new(Query::new(language, source)?);
+        let mut queries = self.queries();
+        let query = queries
+            .entry(language.clone())
+            .or_default()
+            .entry(source.to_string())
+            .or_insert(query);
+        Ok(Arc::clone(query))
+    }
+
+    /// Get the number of compiled queries, over all languages.
+    pub fn len(&self) -> usize {
+        self.queries().values().map(HashMap::len).sum()
+    }
+
+    /// Returns true if no query is compiled.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every compiled query.
+    pub fn clear(&self) {
+        self.queries().clear();
+    }
+
+    fn queries(&self) -> std::sync::MutexGuard<'_, HashMap<Language, HashMap<String, Arc<Query>>>> {
+        self.queries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries_are_compiled_once_per_text() {
+        let json: Language = tree_sitter_json::LANGUAGE.into();
+        let cache = QueryCache::new();
+
+        let first = cache.get(&json, "(number) @n").unwrap();
+        let again = cache.get(&json, "(number) @n").unwrap();
+        cache.get(&json, "(string) @s").unwrap();
+
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&json, "(number").is_err());
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_queries_are_shared_across_threads() {
+        let json: Language = tree_sitter_json::LANGUAGE.into();
+        let cache = QueryCache::new();
+        let queries: Vec<Arc<Query>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| cache.get(&json, "(array) @a").unwrap()))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(cache.len(), 1);
+        let stored = cache.get(&json, "(array) @a").unwrap();
+        assert!(queries.iter().any(|query| Arc::ptr_eq(query, &stored)));
+    }
+}
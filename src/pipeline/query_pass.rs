@@ -0,0 +1,251 @@
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::Pass;
+use crate::pipeline::query_cache::QueryCache;
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tree_sitter::{Node, Query, QueryCapture, QueryCursor, StreamingIterator};
+
+/// Callback mapping one match of a `QueryPass` to edits.
+type MatchCallback<Config> =
+    dyn Fn(&Config, &QueryCaptures<'_, '_>, &str) -> Vec<Edit> + Send + Sync;
+
+/// A pass driven by a tree-sitter query.
+///
+/// The pass runs its query over the whole tree and hands every match to a
+/// callback, which looks up the named captures and returns edits. The
+/// query is compiled through `QueryCache::global` the first time the pass
+/// runs for a language, and shared by every file and thread afterwards.
+///
+/// Edits of all matches are collected into one set: edits that would
+/// not change the source are dropped, and so are edits overlapping an
+/// edit of an earlier match, so nested matches don't reject the pass.
+///
+/// # Panics
+/// Running the pass panics if the query does not compile for the language
+/// of the tree; the engine reports it as a panic of this pass.
+///
+/// # Examples
+/// ```ignore
+/// let query = "(pair key: (_) @key value: (_) @value)";
+/// pipeline.add_pass(QueryPass::new("ColonSpacing", query, |_config, captures, _source| {
+///     let (key, value) = (captures.get("key").unwrap(), captures.get("value").unwrap());
+///     vec![Edit {
+///         range: (key.end_byte(), value.start_byte()),
+///         content: ": ".to_string(),
+///     }]
+/// }));
+/// ```
+pub struct QueryPass<Config> {
+    name: &'static str,
+    description: Option<&'static str>,
+    source: &'static str,
+    on_match: Box<MatchCallback<Config>>,
+    _marker: PhantomData<fn() -> Config>,
+}
+
+impl<Config> QueryPass<Config> {
+    /// Create a pass running `query` and mapping each match to edits with `on_match`.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the pass, see `Pass::name`
+    /// * `query` - Tree-sitter query with the named captures `on_match` reads
+    /// * `on_match` - Maps the config, the captures of one match, and the source to edits
+    pub fn new<F>(name: &'static str, query: &'static str, on_match: F) -> Self
+    where
+        F: Fn(&Config, &QueryCaptures<'_, '_>, &str) -> Vec<Edit> + Send + Sync + 'static,
+    {
+        Self {
+            name,
+            description: None,
+            source: query,
+            on_match: Box::new(on_match),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the one-line description of the pass.
+    #[must_use]
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Get the query, compiling it for the language of `root` on first use.
+    fn query(&self, root: &Node) -> Arc<Query> {
+        match QueryCache::global().get(&root.language(), self.source) {
+            Ok(query) => query,
+            Err(err) => panic!("Invalid query in pass '{}': {err}", self.name),
+        }
+    }
+}
+
+impl<Config> Pass for QueryPass<Config>
+where
+    Config: Serialize + DeserializeOwned,
+{
+    type Config = Config;
+
+    fn run(&self, config: &Config, root: &Node, source: &str) -> Vec<Edit> {
+        let query = self.query(root);
+        let names = query.capture_names();
+        let mut edits = Vec::new();
+
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(&query, *root, source.as_bytes());
+        while let Some(found) = matches.next() {
+            let captures = QueryCaptures {
+                names,
+                captures: found.captures,
+                pattern_index: found.pattern_index,
+            };
+            edits.extend((self.on_match)(config, &captures, source));
+        }
+
+        keep_disjoint(edits, source)
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        self.description
+    }
+}
+
+/// Drop edits that change nothing or overlap an edit kept before them.
+fn keep_disjoint(edits: Vec<Edit>, source: &str) -> Vec<Edit> {
+    let mut kept: Vec<Edit> = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let (start, end) = edit.range;
+        if source.get(start..end) == Some(edit.content.as_str()) {
+            continue;
+        }
+        let overlaps = kept.iter().any(|other| {
+            let (other_start, other_end) = other.range;
+            // Insertions at the same offset conflict too
+            (start < other_end && other_start < end) || (start == other_start && end == other_end)
+        });
+        if !overlaps {
+            kept.push(edit);
+        }
+    }
+    kept
+}
+
+/// The captures of one query match, looked up by capture name.
+pub struct QueryCaptures<'query, 'tree> {
+    names: &'query [&'query str],
+    captures: &'query [QueryCapture<'tree>],
+    pattern_index: usize,
+}
+
+impl<'tree> QueryCaptures<'_, 'tree> {
+    /// Get the first node captured under `name`, if any.
+    pub fn get(&self, name: &str) -> Option<Node<'tree>> {
+        self.nodes(name).next()
+    }
+
+    /// Get every node captured under `name`, in capture order.
+    pub fn nodes<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Node<'tree>> + 'a {
+        self.captures
+            .iter()
+            .filter(move |capture| self.names[capture.index as usize] == name)
+            .map(|capture| capture.node)
+    }
+
+    /// Get the source text of the first node captured under `name`, if any.
+    pub fn text<'s>(&self, name: &str, source: &'s str) -> Option<&'s str> {
+        self.get(name)
+            .and_then(|node| source.get(node.start_byte()..node.end_byte()))
+    }
+
+    /// Index of the query pattern that matched.
+    pub fn pattern_index(&self) -> usize {
+        self.pattern_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::WhitespaceConfig;
+    use tree_sitter::Parser;
+
+    fn run(pass: &QueryPass<WhitespaceConfig>, source: &str) -> String {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let mut edits = pass.run(&WhitespaceConfig::default(), &tree.root_node(), source);
+        edits.sort_by_key(|edit| edit.range);
+        let mut result = source.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(edit.range.0..edit.range.1, &edit.content);
+        }
+        result
+    }
+
+    fn colon_spacing() -> QueryPass<WhitespaceConfig> {
+        QueryPass::new(
+            "ColonSpacing",
+            "(pair key: (_) @key value: (_) @value)",
+            |_config, captures, _source| {
+                let key = captures.get("key").unwrap();
+                let value = captures.get("value").unwrap();
+                vec![Edit {
+                    range: (key.end_byte(), value.start_byte()),
+                    content: ": ".to_string(),
+                }]
+            },
+        )
+    }
+
+    #[test]
+    fn test_edits_every_match() {
+        let pass = colon_spacing();
+        assert_eq!(
+            run(&pass, "{\"a\" :1, \"b\":{\"c\"  :  2}}"),
+            "{\"a\": 1, \"b\": {\"c\": 2}}"
+        );
+        assert_eq!(pass.name(), "ColonSpacing");
+    }
+
+    #[test]
+    fn test_captures_by_name() {
+        let pass = QueryPass::new(
+            "UppercaseTrue",
+            "((true) @literal (#eq? @literal \"true\"))",
+            |_config, captures, source| {
+                let node = captures.get("literal").unwrap();
+                assert_eq!(captures.text("literal", source), Some("true"));
+                assert_eq!(captures.nodes("missing").count(), 0);
+                vec![Edit::replace_node(&node, "TRUE")]
+            },
+        );
+        assert_eq!(run(&pass, "[true, false, true]"), "[TRUE, false, TRUE]");
+    }
+
+    #[test]
+    fn test_overlapping_edits_keep_the_first() {
+        let pass = QueryPass::new(
+            "ReplaceObjects",
+            "(object) @object",
+            |_config, captures, _source| {
+                let node = captures.get("object").unwrap();
+                vec![Edit::replace_node(&node, "{}")]
+            },
+        );
+        assert_eq!(run(&pass, "[{\"a\": {\"b\": 1}}, {}]"), "[{}, {}]");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid query in pass 'Broken'")]
+    fn test_invalid_query_panics() {
+        let pass = QueryPass::new("Broken", "(no_such_kind) @x", |_, _, _| Vec::new());
+        run(&pass, "{}");
+    }
+}
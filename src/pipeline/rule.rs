@@ -0,0 +1,29 @@
+/// A rule a pass checks, identified by a stable code.
+///
+/// Passes list their rules with `Pass::rules` and tag the diagnostics they
+/// report with the code of the rule (`Diagnostic::with_code`). Codes let
+/// users look up the rule with `explain` and change its severity in the
+/// `runner.rules` section of the config.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::Rule;
+///
+/// static RULES: &[Rule] = &[Rule::new("FMT001", "Lines must not exceed the maximum line length.")];
+///
+/// assert_eq!(RULES[0].code, "FMT001");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    /// Stable code of the rule, e.g. `FMT001`
+    pub code: &'static str,
+    /// What the rule checks and why
+    pub description: &'static str,
+}
+
+impl Rule {
+    /// Create a rule.
+    pub const fn new(code: &'static str, description: &'static str) -> Self {
+        Self { code, description }
+    }
+}
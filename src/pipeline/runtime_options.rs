@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Values of extra command line arguments, handed to passes before formatting.
+///
+/// Formatter binaries register extra arguments with `CliBuilder::add_arg`;
+/// their raw values are collected here under the argument id. Flags are
+/// stored as `"true"` or `"false"`.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::RuntimeOptions;
+///
+/// let mut options = RuntimeOptions::new();
+/// options.insert("experimental", vec!["true".to_string()]);
+/// options.insert("width", vec!["100".to_string()]);
+///
+/// assert!(options.is_set("experimental"));
+/// assert_eq!(options.parse::<usize>("width"), Some(100));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeOptions {
+    values: HashMap<String, Vec<String>>,
+}
+
+impl RuntimeOptions {
+    /// Create an empty set of options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the values of an option, replacing any previous values.
+    pub fn insert(&mut self, name: impl Into<String>, values: Vec<String>) {
+        self.values.insert(name.into(), values);
+    }
+
+    /// Get the first value of an option.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).first().map(String::as_str)
+    }
+
+    /// Get all values of an option, in command line order.
+    pub fn get_all(&self, name: &str) -> &[String] {
+        self.values.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns true if a flag option was passed.
+    pub fn is_set(&self, name: &str) -> bool {
+        self.get(name) == Some("true")
+    }
+
+    /// Parse the first value of an option.
+    ///
+    /// # Returns
+    /// `None` if the option has no value or the value does not parse
+    pub fn parse<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.get(name)?.parse().ok()
+    }
+
+    /// Returns true if no options are set.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Get all options with their values, sorted by name.
+    pub(crate) fn sorted(&self) -> Vec<(&str, &[String])> {
+        let mut entries: Vec<_> = self
+            .values
+            .iter()
+            .map(|(name, values)| (name.as_str(), values.as_slice()))
+            .collect();
+        entries.sort_unstable();
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_option() {
+        let options = RuntimeOptions::new();
+        assert!(options.is_empty());
+        assert_eq!(options.get("width"), None);
+        assert!(options.get_all("width").is_empty());
+        assert!(!options.is_set("experimental"));
+    }
+
+    #[test]
+    fn test_values_and_flags() {
+        let mut options = RuntimeOptions::new();
+        options.insert("tag", vec!["a".to_string(), "b".to_string()]);
+        options.insert("experimental", vec!["false".to_string()]);
+        options.insert("width", vec!["wide".to_string()]);
+
+        assert_eq!(options.get("tag"), Some("a"));
+        assert_eq!(options.get_all("tag"), ["a", "b"]);
+        assert!(!options.is_set("experimental"));
+        assert_eq!(options.parse::<usize>("width"), None);
+    }
+}
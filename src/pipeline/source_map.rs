@@ -0,0 +1,289 @@
+use serde::Serialize;
+
+/// A contiguous region of the original text and its counterpart in the formatted text.
+///
+/// Unchanged segments map byte-for-byte; changed segments map the whole
+/// original range onto the whole replacement range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceMapSegment {
+    /// Byte range in the original text
+    pub original: (usize, usize),
+    /// Byte range in the formatted text
+    pub formatted: (usize, usize),
+    /// Whether the text of this segment was replaced by an edit
+    pub changed: bool,
+}
+
+impl SourceMapSegment {
+    fn identity(original: (usize, usize), formatted: (usize, usize)) -> Self {
+        Self {
+            original,
+            formatted,
+            changed: false,
+        }
+    }
+
+    /// Split an unchanged segment at a formatted offset strictly inside it.
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let original_mid = self.original.0 + (offset - self.formatted.0);
+        (
+            Self::identity((self.original.0, original_mid), (self.formatted.0, offset)),
+            Self::identity((original_mid, self.original.1), (offset, self.formatted.1)),
+        )
+    }
+}
+
+/// Mapping from original byte ranges to formatted byte ranges.
+///
+/// The map is built by recording every edit in the order it is applied,
+/// with ranges expressed in the coordinates of the text at that moment.
+/// Segments are ordered and together cover both texts completely.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::SourceMap;
+///
+/// // "a = 1" -> "a=1"
+/// let mut map = SourceMap::new(5);
+/// map.record_edit(3, 4, 0);
+/// map.record_edit(1, 2, 0);
+///
+/// assert_eq!(map.map_offset(4), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceMap {
+    segments: Vec<SourceMapSegment>,
+}
+
+impl SourceMap {
+    /// Create an identity map for a text of `len` bytes.
+    pub fn new(len: usize) -> Self {
+        let segments = if len == 0 {
+            Vec::new()
+        } else {
+            vec![SourceMapSegment::identity((0, len), (0, len))]
+        };
+        Self { segments }
+    }
+
+    /// Get the ordered segments of this map.
+    pub fn segments(&self) -> &[SourceMapSegment] {
+        &self.segments
+    }
+
+    /// Check whether any edit changed the text.
+    pub fn has_changes(&self) -> bool {
+        self.segments.iter().any(|segment| segment.changed)
+    }
+
+    /// Record an edit replacing `start..old_end` of the current text with `new_len` bytes.
+    ///
+    /// Edits overlapping an earlier replacement are merged into it, since
+    /// the original position of text inside a replacement is unknown.
+    pub fn record_edit(&mut self, start: usize, old_end: usize, new_len: usize) {
+        self.split_unchanged_at(start);
+        self.split_unchanged_at(old_end);
+
+        let affected: Vec<usize> = self
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| {
+                let (seg_start, seg_end) = segment.formatted;
+                (seg_start < old_end && seg_end > start)
+                    || (segment.changed && seg_start < start && seg_end > start)
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        let (insert_at, original, merged_start, merged_end) =
+            match (affected.first(), affected.last()) {
+                (Some(&first), Some(&last)) => {
+                    let first_seg = &self.segments[first];
+                    let last_seg = &self.segments[last];
+                    (
+                        first,
+                        (first_seg.original.0, last_seg.original.1),
+                        first_seg.formatted.0.min(start),
+                        last_seg.formatted.1.max(old_end),
+                    )
+                }
+                _ => {
+                    let insert_at = self
+                        .segments
+                        .iter()
+                        .position(|segment| segment.formatted.0 >= start)
+                        .unwrap_or(self.segments.len());
+                    let original_pos = self.original_position_at(insert_at);
+                    (insert_at, (original_pos, original_pos), start, old_end)
+                }
+            };
+
+        let new_end = start + new_len + (merged_end - old_end);
+        let removed = affected.len();
+        self.segments.drain(insert_at..insert_at + removed);
+
+        let delta = new_len as isize - (old_end - start) as isize;
+        for segment in &mut self.segments[insert_at..] {
+            segment.formatted.0 = segment.formatted.0.wrapping_add_signed(delta);
+            segment.formatted.1 = segment.formatted.1.wrapping_add_signed(delta);
+        }
+
+        if original.0 != original.1 || merged_start != new_end {
+            self.segments.insert(
+                insert_at,
+                SourceMapSegment {
+                    original,
+                    formatted: (merged_start, new_end),
+                    changed: true,
+                },
+            );
+        }
+    }
+
+    /// Map an offset in the original text to the formatted text.
+    ///
+    /// Offsets inside a changed segment map to the start of its replacement.
+    pub fn map_offset(&self, original: usize) -> usize {
+        let containing = self
+            .segments
+            .iter()
+            .find(|segment| segment.original.0 <= original && original < segment.original.1);
+
+        match containing {
+            Some(segment) if segment.changed => segment.formatted.0,
+            Some(segment) => segment.formatted.0 + (original - segment.original.0),
+            None => self
+                .segments
+                .last()
+                .map_or(original, |segment| segment.formatted.1),
+        }
+    }
+
+    /// Map a byte range in the original text to the formatted text.
+    pub fn map_range(&self, range: (usize, usize)) -> (usize, usize) {
+        let start = self.map_offset(range.0);
+        let end = self.map_offset(range.1).max(start);
+        (start, end)
+    }
+
+    /// Split the unchanged segment containing `offset` strictly inside it.
+    fn split_unchanged_at(&mut self, offset: usize) {
+        let found = self.segments.iter().position(|segment| {
+            !segment.changed && segment.formatted.0 < offset && offset < segment.formatted.1
+        });
+        if let Some(index) = found {
+            let (left, right) = self.segments[index].split_at(offset);
+            self.segments[index] = left;
+            self.segments.insert(index + 1, right);
+        }
+    }
+
+    /// Original offset at the boundary before the segment at `index`.
+    fn original_position_at(&self, index: usize) -> usize {
+        if index > 0 {
+            self.segments[index - 1].original.1
+        } else {
+            self.segments
+                .get(index)
+                .map_or(0, |segment| segment.original.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply an edit to the text and record it in the map.
+    fn apply(text: &mut String, map: &mut SourceMap, start: usize, end: usize, new: &str) {
+        text.replace_range(start..end, new);
+        map.record_edit(start, end, new.len());
+    }
+
+    fn assert_covers(map: &SourceMap, original_len: usize, formatted_len: usize) {
+        let segments = map.segments();
+        let mut original = 0;
+        let mut formatted = 0;
+        for segment in segments {
+            assert_eq!(segment.original.0, original);
+            assert_eq!(segment.formatted.0, formatted);
+            original = segment.original.1;
+            formatted = segment.formatted.1;
+        }
+        assert_eq!(original, original_len);
+        assert_eq!(formatted, formatted_len);
+    }
+
+    #[test]
+    fn test_identity_map() {
+        let map = SourceMap::new(10);
+        assert!(!map.has_changes());
+        assert_eq!(map.map_offset(7), 7);
+        assert_covers(&map, 10, 10);
+    }
+
+    #[test]
+    fn test_empty_text_insert() {
+        let mut text = String::new();
+        let mut map = SourceMap::new(0);
+        apply(&mut text, &mut map, 0, 0, "abc");
+
+        assert_eq!(map.segments().len(), 1);
+        assert_eq!(map.segments()[0].original, (0, 0));
+        assert_eq!(map.segments()[0].formatted, (0, 3));
+        assert_covers(&map, 0, 3);
+    }
+
+    #[test]
+    fn test_reverse_ordered_edits() {
+        let original = "a = 1; b = 2;";
+        let mut text = original.to_string();
+        let mut map = SourceMap::new(text.len());
+
+        apply(&mut text, &mut map, 8, 11, "=");
+        apply(&mut text, &mut map, 1, 4, "=");
+
+        assert_eq!(text, "a=1; b=2;");
+        assert_covers(&map, original.len(), text.len());
+        assert_eq!(
+            map.map_offset(original.find('b').unwrap()),
+            text.find('b').unwrap()
+        );
+        assert_eq!(
+            map.map_offset(original.find('2').unwrap()),
+            text.find('2').unwrap()
+        );
+    }
+
+    #[test]
+    fn test_later_edit_overlapping_earlier_replacement_merges() {
+        let original = "abcdef";
+        let mut text = original.to_string();
+        let mut map = SourceMap::new(text.len());
+
+        apply(&mut text, &mut map, 2, 4, "XYZ");
+        apply(&mut text, &mut map, 3, 6, "Q");
+
+        assert_eq!(text, "abXQf");
+        assert_covers(&map, original.len(), text.len());
+        let changed: Vec<_> = map.segments().iter().filter(|s| s.changed).collect();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].original, (2, 5));
+        assert_eq!(changed[0].formatted, (2, 4));
+    }
+
+    #[test]
+    fn test_insertion_between_segments() {
+        let original = "ab";
+        let mut text = original.to_string();
+        let mut map = SourceMap::new(text.len());
+
+        apply(&mut text, &mut map, 1, 1, "--");
+
+        assert_eq!(text, "a--b");
+        assert_covers(&map, 2, 4);
+        assert_eq!(map.map_offset(1), 3);
+        assert_eq!(map.map_range((0, 2)), (0, 4));
+    }
+}
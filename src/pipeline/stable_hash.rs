@@ -0,0 +1,76 @@
+use crate::core::{fnv1a_64, fnv1a_64_extend, FNV_OFFSET_BASIS};
+use serde::Serialize;
+use std::hash::{Hash, Hasher};
+
+/// A `Hasher` computing 64-bit FNV-1a.
+///
+/// Unlike `DefaultHasher`, it is not seeded per process, so equal values
+/// hash equally across runs of the same build, as caches kept by a watch
+/// mode or daemon need. Integers are hashed in native byte order, so
+/// hashes should not be shared between machines.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    /// Create a hasher in its initial state.
+    pub fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = fnv1a_64_extend(self.0, bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hash a value with `StableHasher`.
+pub fn stable_hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = StableHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash the JSON serialization of a value, such as a formatter config.
+///
+/// # Returns
+/// The hash, or `None` if the value cannot be serialized
+pub fn serialized_hash<T: Serialize + ?Sized>(value: &T) -> Option<u64> {
+    serde_json::to_vec(value).ok().map(|bytes| fnv1a_64(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::WhitespaceConfig;
+
+    #[test]
+    fn test_stable_hash_is_fnv() {
+        let mut hasher = StableHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), fnv1a_64(b"a"));
+        assert_eq!(stable_hash("abc"), stable_hash("abc"));
+        assert_ne!(stable_hash(&("a", "bc")), stable_hash(&("ab", "c")));
+    }
+
+    #[test]
+    fn test_serialized_hash_follows_values() {
+        let config = WhitespaceConfig::default();
+        let changed = WhitespaceConfig {
+            max_blank_lines: None,
+            ..WhitespaceConfig::default()
+        };
+        assert_eq!(serialized_hash(&config), serialized_hash(&config.clone()));
+        assert_ne!(serialized_hash(&config), serialized_hash(&changed));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::core::Diagnostic;
+use crate::pipeline::query_cache::QueryCache;
+use std::sync::Arc;
+use tree_sitter::{Language, Node, Query, QueryError};
+
+/// What `StructuredPass::transform` and `StructuredPass::build` know about
+/// the target they format, and where they report diagnostics.
+pub struct StructuredContext<'a> {
+    source: &'a str,
+    range: (usize, usize),
+    language: Language,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> StructuredContext<'a> {
+    /// Create a context for the target at `range` of `source`, parsed as `language`.
+    pub(crate) fn new(source: &'a str, range: (usize, usize), language: Language) -> Self {
+        Self {
+            source,
+            range,
+            language,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Get the source the target was extracted from.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Get the byte range of the target.
+    pub fn range(&self) -> (usize, usize) {
+        self.range
+    }
+
+    /// Get the 1-based line of the source the byte `offset` is on.
+    pub fn line_of(&self, offset: usize) -> usize {
+        let offset = offset.min(self.source.len());
+        self.source.as_bytes()[..offset]
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count()
+            + 1
+    }
+
+    /// Get `query` compiled for the language of the source.
+    ///
+    /// Queries are compiled once per language through `QueryCache::global`
+    /// and shared by every file, so calling this for each target is cheap.
+    pub fn query(&self, query: &str) -> Result<Arc<Query>, QueryError> {
+        QueryCache::global().get(&self.language, query)
+    }
+
+    /// Report a diagnostic about the target.
+    ///
+    /// A diagnostic without a range is pointed at the range of the target.
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        let diagnostic = match diagnostic.range {
+            Some(_) => diagnostic,
+            None => diagnostic.with_range(self.range),
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Take the diagnostics reported so far.
+    pub(crate) fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+/// An item of a `StructuredPass` with the byte range it was extracted from.
+///
+/// Using it as the item type keeps the original position of each item
+/// through `transform`, e.g. to report where a sorted item moved from or
+/// to copy the comments around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned<T> {
+    /// The byte range of the source the item was extracted from
+    pub range: (usize, usize),
+    /// The item itself
+    pub value: T,
+}
+
+impl<T> Spanned<T> {
+    /// Create an item extracted from `range`.
+    pub fn new(range: (usize, usize), value: T) -> Self {
+        Self { range, value }
+    }
+
+    /// Create an item extracted from the text of `node`.
+    pub fn from_node(node: &Node, value: T) -> Self {
+        Self::new((node.start_byte(), node.end_byte()), value)
+    }
+
+    /// Get the original source text of the item.
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.range.0..self.range.1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json() -> Language {
+        tree_sitter_json::LANGUAGE.into()
+    }
+
+    #[test]
+    fn test_line_of() {
+        let context = StructuredContext::new("a\nb\nc", (2, 5), json());
+        assert_eq!(context.line_of(0), 1);
+        assert_eq!(context.line_of(2), 2);
+        assert_eq!(context.line_of(4), 3);
+        assert_eq!(context.line_of(100), 3);
+    }
+
+    #[test]
+    fn test_report_defaults_to_target_range() {
+        let mut context = StructuredContext::new("[1, 2]", (0, 6), json());
+        context.report(Diagnostic::warning("whole target"));
+        context.report(Diagnostic::warning("one item").with_range((4, 5)));
+
+        let ranges: Vec<_> = context
+            .into_diagnostics()
+            .into_iter()
+            .map(|diagnostic| diagnostic.range)
+            .collect();
+        assert_eq!(ranges, [Some((0, 6)), Some((4, 5))]);
+    }
+
+    #[test]
+    fn test_query_is_shared_between_contexts() {
+        let first = StructuredContext::new("[1]", (0, 3), json());
+        let second = StructuredContext::new("[2]", (0, 3), json());
+
+        let query = first.query("(number) @number").unwrap();
+        assert_eq!(query.capture_names(), ["number"]);
+        assert!(Arc::ptr_eq(
+            &query,
+            &second.query("(number) @number").unwrap()
+        ));
+        assert!(first.query("(number").is_err());
+    }
+}
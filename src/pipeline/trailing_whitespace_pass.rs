@@ -0,0 +1,149 @@
+use crate::parser::{LanguageCapabilities, LanguageProvider};
+use crate::pipeline::edit::Edit;
+use crate::pipeline::pass::Pass;
+use crate::pipeline::verbatim::{overlaps_any, verbatim_ranges};
+use crate::pipeline::whitespace_config::HasWhitespaceConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+use tree_sitter::Node;
+
+/// Built-in pass removing spaces and tabs at the end of lines.
+///
+/// Trailing whitespace inside verbatim nodes, such as multiline strings,
+/// is kept. Verbatim nodes are the string node kinds of the language's
+/// `LanguageCapabilities`, or the kinds given with `with_verbatim_kinds`.
+/// Enabled by `whitespace.trim_trailing_whitespace`.
+///
+/// # Examples
+/// ```ignore
+/// let mut pipeline = Pipeline::<MyConfig>::new();
+/// pipeline.add_pass(TrailingWhitespacePass::for_language::<MyLanguage>());
+/// ```
+pub struct TrailingWhitespacePass<Config> {
+    verbatim_kinds: &'static [&'static str],
+    _marker: PhantomData<fn() -> Config>,
+}
+
+impl<Config> TrailingWhitespacePass<Config> {
+    /// Create the pass, keeping nodes of the common string node kinds verbatim.
+    pub fn new() -> Self {
+        Self {
+            verbatim_kinds: LanguageCapabilities::new().string_node_kinds(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create the pass, keeping nodes of the string node kinds of `Language` verbatim.
+    pub fn for_language<Language: LanguageProvider>() -> Self {
+        Self::new().with_verbatim_kinds(Language::capabilities().string_node_kinds())
+    }
+
+    /// Set the node kinds whose trailing whitespace is kept.
+    #[must_use]
+    pub fn with_verbatim_kinds(mut self, kinds: &'static [&'static str]) -> Self {
+        self.verbatim_kinds = kinds;
+        self
+    }
+}
+
+impl<Config> Default for TrailingWhitespacePass<Config> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Config> Pass for TrailingWhitespacePass<Config>
+where
+    Config: Serialize + DeserializeOwned + HasWhitespaceConfig,
+{
+    type Config = Config;
+
+    fn description(&self) -> Option<&'static str> {
+        Some("Remove spaces and tabs at the end of lines")
+    }
+
+    fn enabled(&self, config: &Config) -> bool {
+        config.whitespace().trim_trailing_whitespace
+    }
+
+    fn config_keys(&self) -> &'static [&'static str] {
+        &["whitespace.trim_trailing_whitespace"]
+    }
+
+    fn run(&self, config: &Config, root: &Node, source: &str) -> Vec<Edit> {
+        if !config.whitespace().trim_trailing_whitespace {
+            return Vec::new();
+        }
+
+        let verbatim = verbatim_ranges(root, self.verbatim_kinds);
+        let mut edits = Vec::new();
+        let mut line_start = 0;
+        for line in source.split_inclusive('\n') {
+            let text = line.strip_suffix('\n').unwrap_or(line);
+            let end = line_start + text.len();
+            let start = line_start + text.trim_end_matches([' ', '\t']).len();
+            if start < end && !overlaps_any(&verbatim, start, end) {
+                edits.push(Edit {
+                    range: (start, end),
+                    content: String::new(),
+                });
+            }
+            line_start += line.len();
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::WhitespaceConfig;
+    use rstest::rstest;
+
+    fn format(pass: &TrailingWhitespacePass<WhitespaceConfig>, source: &str) -> String {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let edits = pass.run(&WhitespaceConfig::default(), &tree.root_node(), source);
+
+        let mut result = source.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(edit.range.0..edit.range.1, &edit.content);
+        }
+        result
+    }
+
+    #[rstest]
+    #[case("{ \n  \"a\": 1\t \n}  ", "{\n  \"a\": 1\n}")]
+    #[case("{}\n   \n", "{}\n\n")]
+    #[case("{\"a\": \"b  \"}  \n", "{\"a\": \"b  \"}\n")]
+    fn test_trailing_whitespace(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(format(&TrailingWhitespacePass::new(), source), expected);
+    }
+
+    #[test]
+    fn test_verbatim_kinds() {
+        let pass = TrailingWhitespacePass::new().with_verbatim_kinds(&["array"]);
+        let source = "{\"a\": [1,  \n 2]}  \n";
+
+        assert_eq!(format(&pass, source), "{\"a\": [1,  \n 2]}\n");
+    }
+
+    #[test]
+    fn test_disabled() {
+        let config = WhitespaceConfig {
+            trim_trailing_whitespace: false,
+            ..WhitespaceConfig::default()
+        };
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse("{}  ", None).unwrap();
+
+        let pass = TrailingWhitespacePass::new();
+        assert!(pass.run(&config, &tree.root_node(), "{}  ").is_empty());
+    }
+}
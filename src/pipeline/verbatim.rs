@@ -0,0 +1,47 @@
+use crate::walk::{walk, Visit};
+use tree_sitter::Node;
+
+/// Get the byte ranges of the nodes of `kinds`, whose text must be kept verbatim, in source order.
+///
+/// Nodes inside a verbatim node are not visited.
+pub(crate) fn verbatim_ranges(root: &Node, kinds: &[&str]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    walk(*root, |node, _| {
+        if kinds.contains(&node.kind()) {
+            ranges.push((node.start_byte(), node.end_byte()));
+            return Visit::SkipChildren;
+        }
+        Visit::Continue
+    });
+    ranges
+}
+
+/// Returns true if `start..end` overlaps one of the sorted `ranges`.
+pub(crate) fn overlaps_any(ranges: &[(usize, usize)], start: usize, end: usize) -> bool {
+    let after = ranges.partition_point(|&(range_start, _)| range_start < end);
+    ranges[..after]
+        .iter()
+        .any(|&(_, range_end)| start < range_end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbatim_ranges() {
+        let source = r#"{"a": ["b", 1]}"#;
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let ranges = verbatim_ranges(&tree.root_node(), &["string"]);
+
+        assert_eq!(ranges, vec![(1, 4), (7, 10)]);
+        assert!(overlaps_any(&ranges, 3, 5));
+        assert!(!overlaps_any(&ranges, 4, 7));
+        assert!(!overlaps_any(&ranges, 10, 15));
+    }
+}
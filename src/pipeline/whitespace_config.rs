@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings of the built-in whitespace passes.
+///
+/// Formatter configs embed it as a `whitespace` field and implement
+/// `HasWhitespaceConfig`, so every formatter built on this crate reads the
+/// same section.
+///
+/// # Examples
+/// ```yaml
+/// whitespace:
+///   final_newline: true
+///   trim_trailing_whitespace: true
+///   max_blank_lines: 1
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WhitespaceConfig {
+    /// End files with exactly one newline
+    pub final_newline: bool,
+    /// Remove spaces and tabs at the end of lines, outside verbatim nodes
+    pub trim_trailing_whitespace: bool,
+    /// Longest run of blank lines kept outside verbatim nodes; `null` keeps all of them
+    pub max_blank_lines: Option<usize>,
+}
+
+impl Default for WhitespaceConfig {
+    fn default() -> Self {
+        Self {
+            final_newline: true,
+            trim_trailing_whitespace: true,
+            max_blank_lines: Some(1),
+        }
+    }
+}
+
+/// Formatter configs that hold a `WhitespaceConfig`, as needed by the built-in whitespace passes.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::{HasWhitespaceConfig, WhitespaceConfig};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Default, Serialize, Deserialize)]
+/// struct MyConfig {
+///     indent_width: usize,
+///     #[serde(default)]
+///     whitespace: WhitespaceConfig,
+/// }
+///
+/// impl HasWhitespaceConfig for MyConfig {
+///     fn whitespace(&self) -> &WhitespaceConfig {
+///         &self.whitespace
+///     }
+/// }
+/// ```
+pub trait HasWhitespaceConfig {
+    /// Get the settings of the built-in whitespace passes.
+    fn whitespace(&self) -> &WhitespaceConfig;
+}
+
+impl HasWhitespaceConfig for WhitespaceConfig {
+    fn whitespace(&self) -> &WhitespaceConfig {
+        self
+    }
+}
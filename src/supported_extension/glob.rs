@@ -0,0 +1,55 @@
+/// Returns true if `text` matches the glob `pattern`.
+///
+/// `?` matches one character and `*` any run of characters except `/`;
+/// `**` also matches across `/`. Every other character matches itself.
+pub(crate) fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches_from(&pattern, &text)
+}
+
+fn matches_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directory at all
+            let rest_after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            matches_from(rest_after_slash, text)
+                || (0..=text.len()).any(|skip| matches_from(rest, &text[skip..]))
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&skip| skip == 0 || text[skip - 1] != '/')
+            .any(|skip| matches_from(rest, &text[skip..])),
+        ['?', rest @ ..] => match text {
+            [first, text_rest @ ..] if *first != '/' => matches_from(rest, text_rest),
+            _ => false,
+        },
+        [expected, rest @ ..] => match text {
+            [first, text_rest @ ..] if first == expected => matches_from(rest, text_rest),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("*.tpl", "page.tpl", true)]
+    #[case("*.tpl", "dir/page.tpl", false)]
+    #[case("*.tpl", "page.tpl.bak", false)]
+    #[case("Jenkinsfile*", "Jenkinsfile.prod", true)]
+    #[case("?.json", "a.json", true)]
+    #[case("?.json", "ab.json", false)]
+    #[case("**/*.json", "a.json", true)]
+    #[case("**/*.json", "a/b/c.json", true)]
+    #[case("config/**/*.json", "config/a/b.json", true)]
+    #[case("config/**/*.json", "config/b.json", true)]
+    #[case("config/*.json", "config/a/b.json", false)]
+    #[case("", "", true)]
+    fn test_glob_matches(#[case] pattern: &str, #[case] text: &str, #[case] expected: bool) {
+        assert_eq!(glob_matches(pattern, text), expected);
+    }
+}
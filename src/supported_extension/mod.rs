@@ -1,17 +1,90 @@
+mod glob;
+
+use glob::glob_matches;
+use std::fs;
+use std::io::Read;
 use std::path::Path;
 
-pub static CONFIG_EXTENSIONS: SupportedExtension = SupportedExtension::new(&["yml", "yaml"]);
+pub static CONFIG_EXTENSIONS: SupportedExtension =
+    SupportedExtension::new(&["yml", "yaml", "toml", "json", "json5"]);
+
+/// Number of leading bytes of a file read to find its shebang line.
+const SHEBANG_SNIFF_LEN: u64 = 256;
+
+/// The former name of `FileMatcher`, which only matched extensions.
+pub type SupportedExtension = FileMatcher;
 
-/// A wrapper type for a collection of supported file extensions.
+/// Decides which files a language formats.
+///
+/// Files match by extension, by exact file name, by glob pattern, or, for
+/// files without an extension, by the interpreter named in their shebang
+/// line. Every set can be given in a `const` or `static`.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::FileMatcher;
+/// use std::path::Path;
+///
+/// static SHELL: FileMatcher = FileMatcher::new(&["sh", "bash"])
+///     .with_filenames(&[".bashrc"])
+///     .with_patterns(&["*.sh.in", "scripts/**/*.cmd"])
+///     .with_interpreters(&["sh", "bash"]);
+///
+/// assert!(SHELL.matches(Path::new("build.SH")));
+/// assert!(SHELL.matches(Path::new("home/.bashrc")));
+/// assert!(SHELL.matches(Path::new("repo/scripts/ci/run.cmd")));
+/// assert!(SHELL.matches_source(Path::new("bin/deploy"), "#!/usr/bin/env bash\n"));
+/// ```
 #[derive(Debug)]
-pub struct SupportedExtension {
+pub struct FileMatcher {
     extensions: &'static [&'static str],
+    filenames: &'static [&'static str],
+    patterns: &'static [&'static str],
+    interpreters: &'static [&'static str],
 }
 
-impl SupportedExtension {
+impl FileMatcher {
     /// Creates a new instance with the given extensions (should be in lower case, without dots).
+    ///
+    /// Extensions may have several parts, such as `d.ts`.
     pub const fn new(extensions: &'static [&'static str]) -> Self {
-        Self { extensions }
+        Self {
+            extensions,
+            filenames: &[],
+            patterns: &[],
+            interpreters: &[],
+        }
+    }
+
+    /// Also match files with exactly one of these names, such as `Makefile`.
+    #[must_use]
+    pub const fn with_filenames(mut self, filenames: &'static [&'static str]) -> Self {
+        self.filenames = filenames;
+        self
+    }
+
+    /// Also match files by glob pattern.
+    ///
+    /// Patterns without `/` are matched against the file name, others
+    /// against the end of the path. `*` and `?` do not match `/`; `**` does.
+    #[must_use]
+    pub const fn with_patterns(mut self, patterns: &'static [&'static str]) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Also match files without extension whose shebang line runs one of these interpreters.
+    ///
+    /// Versioned interpreters match too, so `python` matches `python3.12`.
+    #[must_use]
+    pub const fn with_interpreters(mut self, interpreters: &'static [&'static str]) -> Self {
+        self.interpreters = interpreters;
+        self
+    }
+
+    /// Returns the extensions matched, in lower case and without dots.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
     }
 
     /// Returns true if the given extension (case-insensitive, without dot) is supported.
@@ -21,13 +94,105 @@ impl SupportedExtension {
         self.extensions.contains(&extension.to_lowercase().as_str())
     }
 
-    /// Returns true if the path's extension matches one of this set (case-insensitive).
+    /// Returns true if the path matches by extension (case-insensitive), file name or pattern.
+    ///
+    /// The content of the file is not looked at; see `matches_file`.
     pub fn matches(&self, path: &Path) -> bool {
-        match path.extension().and_then(|e| e.to_str()) {
-            Some(ext) => self.contains(ext),
-            None => false,
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        self.matches_extension(name)
+            || self.filenames.contains(&name)
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, path))
+    }
+
+    /// Returns true if the path matches, or has no extension and `source` starts
+    /// with a shebang line running one of the interpreters.
+    pub fn matches_source(&self, path: &Path, source: &str) -> bool {
+        self.matches(path) || (path.extension().is_none() && self.matches_shebang(source))
+    }
+
+    /// Returns true if the path matches, reading the start of the file if
+    /// needed to find its shebang line.
+    ///
+    /// Files that cannot be read only match by name.
+    pub fn matches_file(&self, path: &Path) -> bool {
+        if self.matches(path) {
+            return true;
         }
+        if self.interpreters.is_empty() || path.extension().is_some() {
+            return false;
+        }
+        let mut head = Vec::new();
+        let read = fs::File::open(path)
+            .and_then(|file| file.take(SHEBANG_SNIFF_LEN).read_to_end(&mut head));
+        read.is_ok() && self.matches_shebang(&String::from_utf8_lossy(&head))
     }
+
+    /// Returns true if `source` starts with a shebang line running one of the interpreters.
+    ///
+    /// `#!/usr/bin/env` is looked through, so `#!/usr/bin/env -S node`
+    /// runs `node`.
+    pub fn matches_shebang(&self, source: &str) -> bool {
+        let Some(line) = source.strip_prefix("#!") else {
+            return false;
+        };
+        let line = line.lines().next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(mut program) = words.next().map(base_name) else {
+            return false;
+        };
+        if program == "env" {
+            match words.find(|word| !word.starts_with('-') && !word.contains('=')) {
+                Some(word) => program = base_name(word),
+                None => return false,
+            }
+        }
+        self.interpreters.iter().any(|interpreter| {
+            program
+                .strip_prefix(interpreter)
+                .is_some_and(|version| version.chars().all(|ch| ch.is_ascii_digit() || ch == '.'))
+        })
+    }
+
+    /// Returns true if the file name ends with a dot and one of the extensions.
+    fn matches_extension(&self, name: &str) -> bool {
+        let Some((_, last)) = name.rsplit_once('.') else {
+            return false;
+        };
+        if self.contains(last) {
+            return true;
+        }
+        let name = name.to_lowercase();
+        self.extensions.iter().any(|extension| {
+            extension.contains('.')
+                && name
+                    .strip_suffix(extension)
+                    .is_some_and(|stem| stem.len() > 1 && stem.ends_with('.'))
+        })
+    }
+}
+
+/// Returns true if the glob `pattern` matches the file name of `path`, or,
+/// if the pattern has a `/`, the end of `path`.
+pub(crate) fn matches_pattern(pattern: &str, path: &Path) -> bool {
+    if !pattern.contains('/') {
+        return path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| glob_matches(pattern, name));
+    }
+    let path = path.to_string_lossy().replace('\\', "/");
+    let pattern = pattern.trim_start_matches('/');
+    glob_matches(pattern, &path) || glob_matches(&format!("**/{pattern}"), &path)
+}
+
+/// Get the last component of a program path such as `/usr/bin/env`.
+fn base_name(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
 }
 
 #[cfg(test)]
@@ -56,4 +221,53 @@ mod tests {
         assert!(custom.matches(Path::new("data.xml")));
         assert!(!custom.matches(Path::new("data.txt")));
     }
+
+    const TYPESCRIPT: FileMatcher = FileMatcher::new(&["d.ts"])
+        .with_filenames(&["Makefile", "Dockerfile"])
+        .with_patterns(&["*.tpl", "ci/**/*.yml"]);
+
+    #[rstest]
+    #[case("types/index.d.ts", true)]
+    #[case("index.D.TS", true)]
+    #[case("index.ts", false)]
+    #[case(".d.ts", false)]
+    #[case("Makefile", true)]
+    #[case("src/Dockerfile", true)]
+    #[case("makefile", false)]
+    #[case("views/page.tpl", true)]
+    #[case("repo/ci/jobs/build.yml", true)]
+    #[case("ci/build.yml", true)]
+    #[case("other/build.yml", false)]
+    fn test_matches_names_and_patterns(#[case] path: &str, #[case] expected: bool) {
+        assert_eq!(TYPESCRIPT.matches(Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case("#!/bin/sh\necho", true)]
+    #[case("#!/usr/bin/env python3.12\n", true)]
+    #[case("#!/usr/bin/env -S PYTHONPATH=. python -u\n", true)]
+    #[case("#! /usr/local/bin/python2", true)]
+    #[case("#!/usr/bin/env node\n", false)]
+    #[case("#!/usr/bin/pythonista\n", false)]
+    #[case("#!/usr/bin/env\n", false)]
+    #[case("echo\n#!/bin/sh", false)]
+    fn test_matches_shebang(#[case] source: &str, #[case] expected: bool) {
+        let scripts = FileMatcher::new(&["py"]).with_interpreters(&["python", "sh"]);
+        assert_eq!(scripts.matches_shebang(source), expected);
+    }
+
+    #[test]
+    fn test_matches_file_reads_shebang() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let script = dir.path().join("deploy");
+        fs::write(&script, "#!/bin/sh\nexit 0\n").unwrap();
+        let with_extension = dir.path().join("deploy.txt");
+        fs::write(&with_extension, "#!/bin/sh\nexit 0\n").unwrap();
+        let scripts = FileMatcher::new(&[]).with_interpreters(&["sh"]);
+
+        assert!(scripts.matches_file(&script));
+        assert!(!scripts.matches_file(&with_extension));
+        assert!(!scripts.matches_file(&dir.path().join("missing")));
+        assert!(!scripts.matches_source(Path::new("deploy.txt"), "#!/bin/sh\n"));
+    }
 }
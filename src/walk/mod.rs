@@ -0,0 +1,491 @@
+//! Tree traversal helpers for passes.
+//!
+//! Passes visit nodes through these helpers instead of hand-rolling
+//! `TreeCursor` loops: a visitor is called when a node is entered and when
+//! it is left, and decides whether to descend into the children of a node
+//! or stop the walk. Passes that only filter or map nodes can use the
+//! preorder iterators `iter_nodes` and `iter_named` instead.
+
+use tree_sitter::{Node, TreeCursor};
+
+/// What a walk does after entering a node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Visit {
+    /// Visit the children of the node, then continue
+    #[default]
+    Continue,
+    /// Leave the node without visiting its children
+    SkipChildren,
+    /// End the walk
+    Stop,
+}
+
+impl Visit {
+    const CONTINUE: &'static str = "continue";
+    const SKIP_CHILDREN: &'static str = "skip_children";
+    const STOP: &'static str = "stop";
+
+    /// Get the string representation of the visit
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Visit::Continue => Self::CONTINUE,
+            Visit::SkipChildren => Self::SKIP_CHILDREN,
+            Visit::Stop => Self::STOP,
+        }
+    }
+}
+
+/// Hooks called by `walk_with` around every visited node.
+///
+/// `depth` is `0` for the node the walk starts at and grows by one per
+/// level of children.
+pub trait Visitor<'tree> {
+    /// Called before the children of `node` are visited.
+    fn enter(&mut self, _node: Node<'tree>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called after the children of `node` were visited or skipped.
+    ///
+    /// Not called for nodes still open when the walk is stopped.
+    fn leave(&mut self, _node: Node<'tree>, _depth: usize) {}
+}
+
+/// Walk the tree under `root` in preorder, calling `visit` on every node.
+///
+/// # Arguments
+/// * `root` - Node to start at, visited first
+/// * `visit` - Called with each node and its depth below `root`
+///
+/// # Examples
+/// ```ignore
+/// let mut strings = Vec::new();
+/// walk(root, |node, _depth| {
+///     if node.kind() == "string" {
+///         strings.push(node);
+///         return Visit::SkipChildren;
+///     }
+///     Visit::Continue
+/// });
+/// ```
+pub fn walk<'tree>(root: Node<'tree>, visit: impl FnMut(Node<'tree>, usize) -> Visit) {
+    struct Preorder<F>(F);
+
+    impl<'tree, F: FnMut(Node<'tree>, usize) -> Visit> Visitor<'tree> for Preorder<F> {
+        fn enter(&mut self, node: Node<'tree>, depth: usize) -> Visit {
+            (self.0)(node, depth)
+        }
+    }
+
+    walk_with(root, &mut Preorder(visit));
+}
+
+/// Walk the tree under `root` in postorder, calling `visit` on every node after its children.
+///
+/// # Arguments
+/// * `root` - Node to start at, visited last
+/// * `visit` - Called with each node and its depth below `root`
+pub fn walk_postorder<'tree>(root: Node<'tree>, visit: impl FnMut(Node<'tree>, usize)) {
+    struct Postorder<F>(F);
+
+    impl<'tree, F: FnMut(Node<'tree>, usize)> Visitor<'tree> for Postorder<F> {
+        fn leave(&mut self, node: Node<'tree>, depth: usize) {
+            (self.0)(node, depth);
+        }
+    }
+
+    walk_with(root, &mut Postorder(visit));
+}
+
+/// Walk the tree under `root`, calling `visit` only on nodes of `kinds`.
+///
+/// Nodes of other kinds are walked through without being visited, so the
+/// depth still counts every level below `root`.
+///
+/// # Arguments
+/// * `root` - Node to start at
+/// * `kinds` - Node kinds to visit
+/// * `visit` - Called with each node of `kinds` and its depth below `root`
+pub fn walk_kinds<'tree>(
+    root: Node<'tree>,
+    kinds: &[&str],
+    mut visit: impl FnMut(Node<'tree>, usize) -> Visit,
+) {
+    walk(root, |node, depth| {
+        if kinds.contains(&node.kind()) {
+            visit(node, depth)
+        } else {
+            Visit::Continue
+        }
+    });
+}
+
+/// Walk the tree under `root` depth-first, calling the hooks of `visitor`.
+///
+/// # Arguments
+/// * `root` - Node to start at
+/// * `visitor` - Entered and left for every visited node
+pub fn walk_with<'tree, V: Visitor<'tree> + ?Sized>(root: Node<'tree>, visitor: &mut V) {
+    let mut cursor = root.walk();
+    let mut depth = 0;
+    loop {
+        let node = cursor.node();
+        match visitor.enter(node, depth) {
+            Visit::Stop => return,
+            Visit::Continue if cursor.goto_first_child() => {
+                depth += 1;
+                continue;
+            }
+            Visit::Continue | Visit::SkipChildren => visitor.leave(node, depth),
+        }
+
+        // Move to the next sibling, leaving every parent that has none
+        loop {
+            if depth == 0 {
+                return;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            cursor.goto_parent();
+            depth -= 1;
+            visitor.leave(cursor.node(), depth);
+        }
+    }
+}
+
+/// Iterator over the nodes under a root in preorder, created by `iter_nodes` and its variants.
+pub struct Nodes<'tree> {
+    cursor: TreeCursor<'tree>,
+    depth: usize,
+    max_depth: Option<usize>,
+    named: bool,
+    started: bool,
+    done: bool,
+}
+
+impl<'tree> Nodes<'tree> {
+    fn new(root: Node<'tree>, max_depth: Option<usize>, named: bool) -> Self {
+        Self {
+            cursor: root.walk(),
+            depth: 0,
+            max_depth,
+            named,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Get the depth below the root of the node returned last.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Move the cursor to the next node in preorder, staying under the root.
+    fn advance(&mut self) -> bool {
+        let below_limit = !matches!(self.max_depth, Some(max) if self.depth >= max);
+        if below_limit && self.cursor.goto_first_child() {
+            self.depth += 1;
+            return true;
+        }
+        loop {
+            if self.depth == 0 {
+                return false;
+            }
+            if self.cursor.goto_next_sibling() {
+                return true;
+            }
+            self.cursor.goto_parent();
+            self.depth -= 1;
+        }
+    }
+}
+
+impl<'tree> Iterator for Nodes<'tree> {
+    type Item = Node<'tree>;
+
+    fn next(&mut self) -> Option<Node<'tree>> {
+        while !self.done {
+            if self.started && !self.advance() {
+                self.done = true;
+                break;
+            }
+            self.started = true;
+            let node = self.cursor.node();
+            if !self.named || node.is_named() {
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+/// Iterate over `root` and every node under it, in preorder.
+///
+/// # Examples
+/// ```ignore
+/// let strings: Vec<Node> = iter_nodes(root)
+///     .filter(|node| node.kind() == "string")
+///     .collect();
+/// ```
+pub fn iter_nodes(root: Node<'_>) -> Nodes<'_> {
+    Nodes::new(root, None, false)
+}
+
+/// Iterate over `root` and the nodes under it at most `max_depth` levels below it, in preorder.
+pub fn iter_nodes_to_depth(root: Node<'_>, max_depth: usize) -> Nodes<'_> {
+    Nodes::new(root, Some(max_depth), false)
+}
+
+/// Iterate over the named nodes among `root` and every node under it, in preorder.
+///
+/// Anonymous nodes are walked through, so named nodes under them are
+/// still returned.
+pub fn iter_named(root: Node<'_>) -> Nodes<'_> {
+    Nodes::new(root, None, true)
+}
+
+/// Iterate over the named nodes at most `max_depth` levels below `root`, in preorder.
+pub fn iter_named_to_depth(root: Node<'_>, max_depth: usize) -> Nodes<'_> {
+    Nodes::new(root, Some(max_depth), true)
+}
+
+/// Find the smallest named node under `root` covering the byte `range`.
+///
+/// # Returns
+/// The node, `root` itself if no named node under it covers `range`, or
+/// `None` if `range` is not inside `root`
+///
+/// # Examples
+/// ```ignore
+/// // The statement an inline suppression or a diagnostic applies to
+/// let node = node_at_range(root, diagnostic.range?)?;
+/// ```
+pub fn node_at_range(root: Node<'_>, (start, end): (usize, usize)) -> Option<Node<'_>> {
+    if start > end || start < root.start_byte() || end > root.end_byte() {
+        return None;
+    }
+    root.named_descendant_for_byte_range(start, end)
+}
+
+/// Find the nodes under `root` intersecting the byte `range`, in preorder.
+///
+/// A node intersects `range` if they share at least one byte. An empty
+/// range intersects the nodes containing the byte at its offset.
+///
+/// # Arguments
+/// * `root` - Node to search, returned too if it intersects `range`
+/// * `range` - Byte range of the source, e.g. the range to format
+pub fn nodes_in_range(root: Node<'_>, (start, end): (usize, usize)) -> Vec<Node<'_>> {
+    let intersects =
+        |node: &Node| node.start_byte() < end.max(start + 1) && start < node.end_byte();
+    let mut nodes = Vec::new();
+    walk(root, |node, _| {
+        if !intersects(&node) {
+            // Children lie inside their parent
+            return Visit::SkipChildren;
+        }
+        nodes.push(node);
+        Visit::Continue
+    });
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn parse(source: &str) -> tree_sitter::Tree {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_json::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    /// Records enters and leaves of named nodes.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        skip: Option<&'static str>,
+        stop: Option<&'static str>,
+    }
+
+    impl<'tree> Visitor<'tree> for Recorder {
+        fn enter(&mut self, node: Node<'tree>, depth: usize) -> Visit {
+            if node.is_named() {
+                self.events.push(format!("+{}@{depth}", node.kind()));
+            }
+            if self.stop == Some(node.kind()) {
+                Visit::Stop
+            } else if self.skip == Some(node.kind()) {
+                Visit::SkipChildren
+            } else {
+                Visit::Continue
+            }
+        }
+
+        fn leave(&mut self, node: Node<'tree>, depth: usize) {
+            if node.is_named() {
+                self.events.push(format!("-{}@{depth}", node.kind()));
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(None, None, "+document@0 +array@1 +number@2 -number@2 +array@2 +true@3 -true@3 -array@2 -array@1 -document@0")]
+    #[case(Some("array"), None, "+document@0 +array@1 -array@1 -document@0")]
+    #[case(
+        None,
+        Some("true"),
+        "+document@0 +array@1 +number@2 -number@2 +array@2 +true@3"
+    )]
+    fn test_walk_with(
+        #[case] skip: Option<&'static str>,
+        #[case] stop: Option<&'static str>,
+        #[case] expected: &str,
+    ) {
+        let tree = parse("[1, [true]]");
+        let mut recorder = Recorder {
+            skip,
+            stop,
+            ..Recorder::default()
+        };
+
+        walk_with(tree.root_node(), &mut recorder);
+
+        assert_eq!(recorder.events.join(" "), expected);
+    }
+
+    #[test]
+    fn test_walk_orders() {
+        let tree = parse("[1, [true]]");
+        let named = |node: Node| node.is_named().then(|| node.kind().to_string());
+
+        let mut preorder = Vec::new();
+        walk(tree.root_node(), |node, _| {
+            preorder.extend(named(node));
+            Visit::Continue
+        });
+        let mut postorder = Vec::new();
+        walk_postorder(tree.root_node(), |node, _| postorder.extend(named(node)));
+
+        assert_eq!(preorder, ["document", "array", "number", "array", "true"]);
+        assert_eq!(postorder, ["number", "true", "array", "array", "document"]);
+    }
+
+    #[test]
+    fn test_walk_kinds() {
+        let tree = parse(r#"{"a": [1, {"b": 2}]}"#);
+        let mut visited = Vec::new();
+
+        walk_kinds(tree.root_node(), &["pair", "number"], |node, depth| {
+            visited.push((node.kind(), depth));
+            Visit::Continue
+        });
+
+        assert_eq!(
+            visited,
+            [("pair", 2), ("number", 4), ("pair", 5), ("number", 6)]
+        );
+    }
+
+    #[test]
+    fn test_walk_from_inner_node_stays_inside() {
+        let tree = parse("[[1], 2]");
+        let inner = tree.root_node().child(0).unwrap().child(1).unwrap();
+        let mut kinds = Vec::new();
+
+        walk(inner, |node, _| {
+            kinds.push(node.kind());
+            Visit::Continue
+        });
+
+        assert_eq!(kinds, ["array", "[", "number", "]"]);
+    }
+
+    #[rstest]
+    #[case(None, false, "document array [ number , array [ true ] ]")]
+    #[case(None, true, "document array number array true")]
+    #[case(Some(2), false, "document array [ number , array ]")]
+    #[case(Some(2), true, "document array number array")]
+    #[case(Some(0), true, "document")]
+    fn test_iter_nodes(
+        #[case] max_depth: Option<usize>,
+        #[case] named: bool,
+        #[case] expected: &str,
+    ) {
+        let tree = parse("[1, [true]]");
+        let nodes = Nodes::new(tree.root_node(), max_depth, named);
+
+        let kinds: Vec<_> = nodes.map(|node| node.kind()).collect();
+
+        assert_eq!(kinds.join(" "), expected);
+    }
+
+    #[test]
+    fn test_iter_nodes_matches_walk() {
+        let tree = parse(r#"{"a": [1, {"b": 2}], "c": null}"#);
+        let mut walked = Vec::new();
+        walk(tree.root_node(), |node, depth| {
+            walked.push((node.id(), depth));
+            Visit::Continue
+        });
+
+        let mut nodes = iter_nodes(tree.root_node());
+        let mut iterated = Vec::new();
+        while let Some(node) = nodes.next() {
+            iterated.push((node.id(), nodes.depth()));
+        }
+
+        assert_eq!(iterated, walked);
+    }
+
+    #[test]
+    fn test_iter_named_from_inner_node_stays_inside() {
+        let tree = parse("[[1, 2], 3]");
+        let inner = tree.root_node().child(0).unwrap().child(1).unwrap();
+
+        let numbers = iter_named(inner)
+            .filter(|node| node.kind() == "number")
+            .count();
+
+        assert_eq!(numbers, 2);
+    }
+
+    #[rstest]
+    #[case((9, 13), Some("string"))]
+    #[case((10, 12), Some("string_content"))]
+    #[case((9, 17), Some("pair"))]
+    #[case((16, 16), Some("number"))]
+    #[case((0, 31), Some("object"))]
+    #[case((0, 40), None)]
+    #[case((5, 3), None)]
+    fn test_node_at_range(#[case] range: (usize, usize), #[case] expected: Option<&str>) {
+        let source = r#"{"a": 1, "bc": 23, "d": [true]}"#;
+        let tree = parse(source);
+
+        let node = node_at_range(tree.root_node(), range);
+
+        assert_eq!(node.map(|node| node.kind()), expected);
+    }
+
+    #[rstest]
+    #[case((1, 2), "document object pair string \"")]
+    #[case((6, 7), "document object pair number")]
+    #[case((6, 6), "document object pair number")]
+    #[case((7, 10), "document object , pair string \"")]
+    #[case((31, 31), "")]
+    fn test_nodes_in_range(#[case] range: (usize, usize), #[case] expected: &str) {
+        let source = r#"{"a": 1, "bc": 23, "d": [true]}"#;
+        let tree = parse(source);
+
+        let kinds: Vec<_> = nodes_in_range(tree.root_node(), range)
+            .into_iter()
+            .map(|node| node.kind())
+            .collect();
+
+        assert_eq!(kinds.join(" "), expected);
+    }
+}
@@ -0,0 +1,222 @@
+//! Line wrapping for delimited lists, the core of argument, parameter, and import wrapping passes.
+
+use crate::indent::IndentStyle;
+
+/// How a list is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// All items on the line of the opening delimiter
+    SingleLine,
+    /// One item per line, indented one level deeper than the list
+    OnePerLine,
+}
+
+impl Layout {
+    const SINGLE_LINE: &'static str = "single-line";
+    const ONE_PER_LINE: &'static str = "one-per-line";
+
+    /// Get the string representation of the layout
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Layout::SingleLine => Self::SINGLE_LINE,
+            Layout::OnePerLine => Self::ONE_PER_LINE,
+        }
+    }
+}
+
+/// Lays out a delimited list of items within a maximum line length.
+///
+/// A list stays on one line if it fits and no item spans several lines;
+/// otherwise every item goes on its own line, followed by the separator,
+/// including the last one if `with_trailing_separator` is set.
+///
+/// # Examples
+/// ```
+/// use fmt_runner::indent::IndentStyle;
+/// use fmt_runner::wrap::ListLayout;
+///
+/// let args = ["first_argument", "second_argument"];
+/// let layout = ListLayout::new("(", ")", 30).with_trailing_separator(true);
+///
+/// // `call` starts at column 4, so the list starts at column 8
+/// assert_eq!(layout.render(&args, 8, 1), "(\n        first_argument,\n        second_argument,\n    )");
+/// assert_eq!(layout.render(&args[..1], 8, 1), "(first_argument)");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListLayout {
+    open: String,
+    close: String,
+    separator: String,
+    max_line_length: usize,
+    trailing_separator: bool,
+    bracket_spacing: bool,
+    style: IndentStyle,
+}
+
+impl ListLayout {
+    /// Create a layout for lists between `open` and `close`, separated by commas.
+    ///
+    /// # Arguments
+    /// * `open` - Opening delimiter, such as `(`
+    /// * `close` - Closing delimiter, such as `)`
+    /// * `max_line_length` - Longest line, in characters, a single-line list may produce
+    pub fn new(open: &str, close: &str, max_line_length: usize) -> Self {
+        Self {
+            open: open.to_string(),
+            close: close.to_string(),
+            separator: ",".to_string(),
+            max_line_length,
+            trailing_separator: false,
+            bracket_spacing: false,
+            style: IndentStyle::default(),
+        }
+    }
+
+    /// Set the separator between items; single-line lists follow it with a space.
+    #[must_use]
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Put the separator after the last item of one-per-line lists.
+    #[must_use]
+    pub fn with_trailing_separator(mut self, trailing: bool) -> Self {
+        self.trailing_separator = trailing;
+        self
+    }
+
+    /// Put spaces inside the delimiters of non-empty single-line lists, as in `{ a, b }`.
+    #[must_use]
+    pub fn with_bracket_spacing(mut self, spacing: bool) -> Self {
+        self.bracket_spacing = spacing;
+        self
+    }
+
+    /// Set the indentation of one-per-line lists.
+    #[must_use]
+    pub fn with_indent_style(mut self, style: IndentStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Choose the layout of `items`.
+    ///
+    /// # Arguments
+    /// * `items` - Text of the items, without separators
+    /// * `column` - Column, in characters, of the opening delimiter
+    pub fn choose<S: AsRef<str>>(&self, items: &[S], column: usize) -> Layout {
+        let multiline = items.iter().any(|item| item.as_ref().contains('\n'));
+        let width = self.single_line(items).chars().count();
+        if multiline || column + width > self.max_line_length {
+            Layout::OnePerLine
+        } else {
+            Layout::SingleLine
+        }
+    }
+
+    /// Render `items` in the layout chosen by `choose`.
+    ///
+    /// # Arguments
+    /// * `items` - Text of the items, without separators
+    /// * `column` - Column, in characters, of the opening delimiter
+    /// * `level` - Indentation level of the line holding the opening delimiter
+    ///
+    /// # Returns
+    /// The list from its opening to its closing delimiter
+    pub fn render<S: AsRef<str>>(&self, items: &[S], column: usize, level: usize) -> String {
+        match self.choose(items, column) {
+            Layout::SingleLine => self.single_line(items),
+            Layout::OnePerLine => self.one_per_line(items, level),
+        }
+    }
+
+    /// Render `items` on a single line.
+    pub fn single_line<S: AsRef<str>>(&self, items: &[S]) -> String {
+        if items.is_empty() {
+            return format!("{}{}", self.open, self.close);
+        }
+        let padding = if self.bracket_spacing { " " } else { "" };
+        let items: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+        format!(
+            "{}{padding}{}{padding}{}",
+            self.open,
+            items.join(&format!("{} ", self.separator)),
+            self.close
+        )
+    }
+
+    /// Render `items` one per line, the list being indented `level` levels.
+    pub fn one_per_line<S: AsRef<str>>(&self, items: &[S], level: usize) -> String {
+        if items.is_empty() {
+            return format!("{}{}", self.open, self.close);
+        }
+        let item_indentation = self.style.indentation(level + 1);
+        let mut result = self.open.clone();
+        for (i, item) in items.iter().enumerate() {
+            result.push('\n');
+            result.push_str(&item_indentation);
+            result.push_str(item.as_ref());
+            if i + 1 < items.len() || self.trailing_separator {
+                result.push_str(&self.separator);
+            }
+        }
+        result.push('\n');
+        result.push_str(&self.style.indentation(level));
+        result.push_str(&self.close);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const ITEMS: [&str; 3] = ["a", "bb", "ccc"];
+
+    #[rstest]
+    #[case(0, 14, Layout::SingleLine)]
+    #[case(1, 14, Layout::OnePerLine)]
+    #[case(0, 13, Layout::OnePerLine)]
+    fn test_choose(#[case] column: usize, #[case] max: usize, #[case] expected: Layout) {
+        // "(a, bb, ccc)" is 12 characters; bracket spacing adds 2
+        let layout = ListLayout::new("(", ")", max).with_bracket_spacing(true);
+
+        assert_eq!(layout.choose(&ITEMS, column), expected);
+    }
+
+    #[test]
+    fn test_multiline_items_force_one_per_line() {
+        let layout = ListLayout::new("[", "]", 100);
+
+        assert_eq!(layout.choose(&["a", "{\n}"], 0), Layout::OnePerLine);
+    }
+
+    #[test]
+    fn test_single_line() {
+        let layout = ListLayout::new("{", "}", 80).with_separator(";");
+
+        assert_eq!(layout.single_line(&ITEMS), "{a; bb; ccc}");
+        assert_eq!(layout.single_line::<&str>(&[]), "{}");
+    }
+
+    #[rstest]
+    #[case(false, "(\n\t\ta,\n\t\tbb,\n\t\tccc\n\t)")]
+    #[case(true, "(\n\t\ta,\n\t\tbb,\n\t\tccc,\n\t)")]
+    fn test_one_per_line(#[case] trailing: bool, #[case] expected: &str) {
+        let layout = ListLayout::new("(", ")", 5)
+            .with_trailing_separator(trailing)
+            .with_indent_style(IndentStyle::tabs(4));
+
+        assert_eq!(layout.render(&ITEMS, 0, 1), expected);
+    }
+
+    #[test]
+    fn test_width_counts_characters() {
+        // "(éé, ü)" is 7 characters and 10 bytes
+        let layout = ListLayout::new("(", ")", 7);
+
+        assert_eq!(layout.choose(&["éé", "ü"], 0), Layout::SingleLine);
+    }
+}